@@ -0,0 +1,156 @@
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use data_tree::branch_page::BranchPage;
+use data_tree::leaf_page::{LeafPage, LeafPageView};
+use data_tree::rle_leaf_page::RLELeafPage;
+use data_tree::slotted_leaf_page::SlottedLeafPage;
+use rand::prelude::*;
+
+const PAGE_SIZE: usize = 64 * 1024;
+
+// Adds keys until the page is full, returning the ones that went in. Keys
+// are spaced out so the run-length page can't join them into runs.
+fn fill(mut add: impl FnMut(u64) -> bool) -> Vec<u64> {
+    (0..).step_by(3).take_while(|&key| add(key)).collect()
+}
+
+fn full_leaf_page() -> (LeafPage, Vec<u64>) {
+    let mut page = LeafPage::empty(PAGE_SIZE);
+    let keys = fill(|key| page.put(key, &key.to_le_bytes()));
+    (page, keys)
+}
+
+fn full_rle_page() -> (RLELeafPage, Vec<u64>) {
+    let mut page = RLELeafPage::new_empty(PAGE_SIZE);
+    let keys = fill(|key| page.put(key, &key.to_le_bytes()));
+    (page, keys)
+}
+
+fn full_branch_page() -> (BranchPage, Vec<u64>) {
+    let mut page = BranchPage::new_empty(PAGE_SIZE);
+    let keys = fill(|key| page.insert(key + 1, key));
+    (page, keys)
+}
+
+// The lookups the pages did before binary search, as a baseline
+fn linear_leaf_get(page: &LeafPage, key: u64) -> Option<&[u8]> {
+    page.metadata().iter()
+        .find(|meta| meta.key == key)
+        .map(|meta| &page.data()[meta.value_offset..meta.value_offset + meta.value_length])
+}
+
+fn linear_rle_get(page: &RLELeafPage, key: u64) -> Option<&[u8]> {
+    page.metadata().iter()
+        .find(|meta| key >= meta.start_key && key <= meta.end_key)
+        .map(|meta| &page.data()[meta.value_offset..meta.value_offset + meta.value_length])
+}
+
+fn linear_find_page_id(page: &BranchPage, key: u64) -> Option<u64> {
+    let entries = page.entries();
+    let index = entries.iter().rposition(|e| e.first_key <= key).unwrap_or(0);
+    entries.get(index).map(|e| e.page_id)
+}
+
+fn shuffled(keys: &[u64]) -> Vec<u64> {
+    let mut keys = keys.to_vec();
+    keys.shuffle(&mut StdRng::seed_from_u64(18));
+    keys
+}
+
+fn bench_leaf_page(c: &mut Criterion) {
+    let (page, keys) = full_leaf_page();
+    let lookups = shuffled(&keys);
+    let mut group = c.benchmark_group(format!("leaf_page_get_{}_entries", keys.len()));
+    group.bench_function("binary", |b| b.iter(|| {
+        lookups.iter().filter(|&&key| page.get(black_box(key)).is_some()).count()
+    }));
+    group.bench_function("linear", |b| b.iter(|| {
+        lookups.iter().filter(|&&key| linear_leaf_get(&page, black_box(key)).is_some()).count()
+    }));
+    group.finish();
+
+    // A lookup starting from the page's bytes, as a tree read does
+    let bytes = page.serialize();
+    let mut group = c.benchmark_group("leaf_page_get_from_bytes");
+    group.bench_function("view", |b| b.iter(|| {
+        lookups.iter().take(100).filter(|&&key| LeafPageView::<u64>::new(&bytes).unwrap().get(black_box(key)).is_some()).count()
+    }));
+    group.bench_function("deserialize", |b| b.iter(|| {
+        lookups.iter().take(100).filter(|&&key| LeafPage::deserialize(&bytes).get(black_box(key)).is_some()).count()
+    }));
+    group.finish();
+
+    // Filling a page in random key order, each put finding its slot
+    c.bench_function("leaf_page_fill_random_order", |b| b.iter_batched(
+        || LeafPage::empty(PAGE_SIZE),
+        |mut page| {
+            for &key in &lookups {
+                page.put(key, &key.to_le_bytes());
+            }
+            page
+        },
+        BatchSize::SmallInput,
+    ));
+}
+
+fn bench_leaf_page_updates(c: &mut Criterion) {
+    // Updates of existing keys to values one byte shorter or longer: a plain
+    // page rebuilds its data for each, a slotted page mostly writes in place.
+    // The pages are half full so that every update fits.
+    let (_, mut keys) = full_leaf_page();
+    keys.truncate(keys.len() / 2);
+    let updates: Vec<(u64, Vec<u8>)> = shuffled(&keys).into_iter()
+        .take(1000)
+        .enumerate()
+        .map(|(i, key)| (key, vec![key as u8; 7 + i % 3]))
+        .collect();
+
+    let mut group = c.benchmark_group(format!("leaf_page_update_{}_entries", keys.len()));
+    group.bench_function("plain", |b| b.iter_batched(
+        || {
+            let mut page = LeafPage::empty(PAGE_SIZE);
+            keys.iter().for_each(|&key| assert!(page.put(key, &key.to_le_bytes())));
+            page
+        },
+        |mut page| updates.iter().filter(|(key, value)| page.put(black_box(*key), value)).count(),
+        BatchSize::SmallInput,
+    ));
+    group.bench_function("slotted", |b| b.iter_batched(
+        || {
+            let mut page = SlottedLeafPage::new_empty(PAGE_SIZE);
+            keys.iter().for_each(|&key| assert!(page.put(key, &key.to_le_bytes())));
+            page
+        },
+        |mut page| updates.iter().filter(|(key, value)| page.put(black_box(*key), value)).count(),
+        BatchSize::SmallInput,
+    ));
+    group.finish();
+}
+
+fn bench_rle_leaf_page(c: &mut Criterion) {
+    let (page, keys) = full_rle_page();
+    let lookups = shuffled(&keys);
+    let mut group = c.benchmark_group(format!("rle_leaf_page_get_{}_runs", page.metadata().len()));
+    group.bench_function("binary", |b| b.iter(|| {
+        lookups.iter().filter(|&&key| page.get(black_box(key)).is_some()).count()
+    }));
+    group.bench_function("linear", |b| b.iter(|| {
+        lookups.iter().filter(|&&key| linear_rle_get(&page, black_box(key)).is_some()).count()
+    }));
+    group.finish();
+}
+
+fn bench_branch_page(c: &mut Criterion) {
+    let (page, keys) = full_branch_page();
+    let lookups = shuffled(&keys);
+    let mut group = c.benchmark_group(format!("branch_page_find_{}_entries", keys.len()));
+    group.bench_function("binary", |b| b.iter(|| {
+        lookups.iter().filter_map(|&key| page.find_page_id(black_box(key))).sum::<u64>()
+    }));
+    group.bench_function("linear", |b| b.iter(|| {
+        lookups.iter().filter_map(|&key| linear_find_page_id(&page, black_box(key))).sum::<u64>()
+    }));
+    group.finish();
+}
+
+criterion_group!(benches, bench_leaf_page, bench_leaf_page_updates, bench_rle_leaf_page, bench_branch_page);
+criterion_main!(benches);
@@ -1,43 +1,63 @@
+use std::error::Error;
+use std::marker::PhantomData;
 use crate::data_tree::PageType;
+use crate::leaf_page::read_u64;
+use crate::page_key::PageKey;
 
 #[derive(Debug, Clone, Copy)]
-pub struct BranchEntry {
+pub struct BranchEntry<K = u64> {
     pub page_id: u64,
-    pub first_key: u64,
+    pub first_key: K,
 }
 
-impl BranchEntry {
-    pub fn serialize(&self) -> [u8; 16] {
-        let mut bytes = [0u8; 16];
-        bytes[0..8].copy_from_slice(&self.page_id.to_le_bytes());
-        bytes[8..16].copy_from_slice(&self.first_key.to_le_bytes());
-        bytes
+impl<K: PageKey> BranchEntry<K> {
+    /// Returns the number of bytes the entry takes when serialized
+    pub fn size(&self) -> usize {
+        PAGE_ID_SIZE + self.first_key.encoded_size()
     }
 
-    pub fn deserialize(bytes: &[u8]) -> Self {
-        let page_id = u64::from_le_bytes(bytes[0..8].try_into().unwrap());
-        let first_key = u64::from_le_bytes(bytes[8..16].try_into().unwrap());
-        BranchEntry { page_id, first_key }
+    pub fn serialize(&self, bytes: &mut Vec<u8>) {
+        bytes.extend_from_slice(&self.page_id.to_le_bytes());
+        self.first_key.encode(bytes);
+    }
+
+    /// Reads an entry from the start of `bytes`, returning it with the number
+    /// of bytes it took
+    pub fn deserialize(bytes: &[u8]) -> (Self, usize) {
+        let page_id = u64::from_le_bytes(bytes[0..PAGE_ID_SIZE].try_into().unwrap());
+        let (first_key, key_size) = K::decode(&bytes[PAGE_ID_SIZE..])
+            .expect("Cannot deserialize BranchEntry: key runs past the page");
+        (BranchEntry { page_id, first_key }, PAGE_ID_SIZE + key_size)
     }
 }
 
+// Size of an entry's page ID
+pub const PAGE_ID_SIZE: usize = 8;
+
+// Size of a serialized BranchEntry with a u64 key
+pub const ENTRY_SIZE: usize = 16;
+
+// A branch of keys of type K. The u64 form is the BranchPage used by most
+// trees; byte-string trees use KeyedBranchPage<Vec<u8>>.
 #[derive(Debug)]
-pub struct BranchPage {
+pub struct KeyedBranchPage<K: PageKey> {
     pub page_type: PageType,
     pub page_size: usize,
-    pub entries: Vec<BranchEntry>,
+    pub entries: Vec<BranchEntry<K>>,
     pub prev_page_id: u64,
     pub next_page_id: u64,
 }
 
-impl BranchPage {
+pub type BranchPage = KeyedBranchPage<u64>;
+
+impl<K: PageKey> KeyedBranchPage<K> {
     pub fn new(bytes: &[u8]) -> Self {
         Self::deserialize(bytes)
     }
 
     pub fn new_empty(page_size: usize) -> Self {
-        BranchPage {
-            page_type: PageType::BranchPage,
+        KeyedBranchPage {
+            page_type: K::BRANCH_PAGE_TYPE,
             page_size,
             entries: Vec::new(),
             prev_page_id: 0,
@@ -45,43 +65,93 @@ impl BranchPage {
         }
     }
 
-    pub fn insert(&mut self, page_id: u64, first_key: u64) -> bool {
-        let entry = BranchEntry { page_id, first_key };
+    /// Inserts an entry in key order. Returns false if the page has no room for it.
+    pub fn insert(&mut self, page_id: u64, first_key: K) -> bool {
+        if !self.has_room_for(&first_key) {
+            return false;
+        }
 
         // Find insertion point to maintain sorted order
-        let pos = self.entries.binary_search_by_key(&first_key, |e| e.first_key)
+        let pos = self.entries.binary_search_by(|e| e.first_key.cmp(&first_key))
             .unwrap_or_else(|pos| pos);
+        let entry = BranchEntry { page_id, first_key };
 
         self.entries.insert(pos, entry);
         true
     }
 
-    pub fn find_page_id(&self, key: u64) -> Option<u64> {
-        if self.entries.is_empty() {
-            return None;
+    /// Removes the entry for `page_id`, returning whether it was present
+    pub fn remove(&mut self, page_id: u64) -> bool {
+        if let Some(pos) = self.entries.iter().position(|e| e.page_id == page_id) {
+            let removed = self.entries.remove(pos);
+
+            // The first entry carries the page's lower bound, so the entry that
+            // takes its place inherits it
+            if pos == 0 && !self.entries.is_empty() {
+                self.entries[0].first_key = removed.first_key;
+            }
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Sets the first key of the entry for `page_id`, returning whether it was present.
+    /// The new key must keep the entries in order.
+    pub fn set_first_key(&mut self, page_id: u64, first_key: K) -> bool {
+        match self.entries.iter_mut().find(|e| e.page_id == page_id) {
+            Some(entry) => {
+                entry.first_key = first_key;
+                true
+            }
+            None => false,
         }
+    }
+
+    /// Returns the number of bytes the page takes when serialized
+    pub fn used_space(&self) -> usize {
+        Self::HEADER_SIZE + self.entries.iter().map(|e| e.size()).sum::<usize>()
+    }
+
+    /// Checks whether an entry starting at `first_key` fits in the serialized page
+    pub fn has_room_for(&self, first_key: &K) -> bool {
+        self.used_space() + PAGE_ID_SIZE + first_key.encoded_size() <= self.page_size
+    }
 
-        // If key is less than first entry's key, return first page
-        if key < self.entries[0].first_key {
-            return Some(self.entries[0].page_id);
+    /// Moves the upper half of the entries, by size, to a new page. The new
+    /// page's first key is the separator to insert into the parent. Returns
+    /// None if there are fewer than two entries.
+    pub fn split(&mut self) -> Option<KeyedBranchPage<K>> {
+        if self.entries.len() < 2 {
+            return None;
         }
 
-        // Find the entry whose range contains this key
-        for i in 0..self.entries.len() {
-            let current_key = self.entries[i].first_key;
-            let next_key = if i + 1 < self.entries.len() {
-                self.entries[i + 1].first_key
-            } else {
-                u64::MAX
-            };
-
-            if key >= current_key && key < next_key {
-                return Some(self.entries[i].page_id);
+        // Split where the halves' sizes are closest, which is the middle
+        // entry when all keys have the same size
+        let total: usize = self.entries.iter().map(|e| e.size()).sum();
+        let mut left = 0;
+        let mut best = (usize::MAX, 1);
+        for (i, entry) in self.entries.iter().enumerate().take(self.entries.len() - 1) {
+            left += entry.size();
+            if left.abs_diff(total - left) < best.0 {
+                best = (left.abs_diff(total - left), i + 1);
             }
         }
 
-        // If we get here, the key is in the last page
-        Some(self.entries.last().unwrap().page_id)
+        let mut new_page = KeyedBranchPage::new_empty(self.page_size);
+        new_page.entries = self.entries.split_off(best.1);
+        Some(new_page)
+    }
+
+    pub fn find_page_id(&self, key: K::Ref<'_>) -> Option<u64> {
+        if self.entries.is_empty() {
+            return None;
+        }
+
+        // The last entry whose first key is at or below the key. A key below
+        // every first key belongs to the first page.
+        let above = self.entries.partition_point(|e| e.first_key.cmp_ref(key).is_le());
+        Some(self.entries[above.saturating_sub(1)].page_id)
     }
 
     pub fn serialize(&self) -> Vec<u8> {
@@ -101,7 +171,7 @@ impl BranchPage {
 
         // Write entries
         for entry in &self.entries {
-            bytes.extend_from_slice(&entry.serialize());
+            entry.serialize(&mut bytes);
         }
 
         bytes
@@ -112,7 +182,7 @@ impl BranchPage {
     const COUNT_SIZE: usize = 8;     // 8 bytes for entry count
     const PREV_PAGE_ID_SIZE: usize = 8; // 8 bytes for previous page ID
     const NEXT_PAGE_ID_SIZE: usize = 8; // 8 bytes for next page ID
-    const HEADER_SIZE: usize = Self::PAGE_TYPE_SIZE + Self::COUNT_SIZE + Self::PREV_PAGE_ID_SIZE + Self::NEXT_PAGE_ID_SIZE;
+    pub const HEADER_SIZE: usize = Self::PAGE_TYPE_SIZE + Self::COUNT_SIZE + Self::PREV_PAGE_ID_SIZE + Self::NEXT_PAGE_ID_SIZE;
 
     pub fn deserialize(bytes: &[u8]) -> Self {
         // Check if the bytes array is long enough for the header
@@ -124,7 +194,7 @@ impl BranchPage {
         let mut offset = 0;
 
         // Read page type (1 byte)
-        let page_type = PageType::from_u8(bytes[offset]).unwrap_or(PageType::BranchPage);
+        let page_type = PageType::from_u8(bytes[offset]).unwrap_or(K::BRANCH_PAGE_TYPE);
         offset += 1;
 
         // Read number of entries (8 bytes)
@@ -142,12 +212,12 @@ impl BranchPage {
         // Read entries
         let mut entries = Vec::with_capacity(count as usize);
         for _ in 0..count {
-            let entry_bytes = &bytes[offset..offset + 16];
-            entries.push(BranchEntry::deserialize(entry_bytes));
-            offset += 16;
+            let (entry, entry_size) = BranchEntry::deserialize(&bytes[offset..]);
+            entries.push(entry);
+            offset += entry_size;
         }
 
-        BranchPage {
+        KeyedBranchPage {
             page_type,
             page_size: bytes.len(),
             entries,
@@ -160,7 +230,7 @@ impl BranchPage {
         self.page_type
     }
 
-    pub fn entries(&self) -> &[BranchEntry] {
+    pub fn entries(&self) -> &[BranchEntry<K>] {
         &self.entries
     }
 
@@ -181,6 +251,104 @@ impl BranchPage {
     }
 }
 
+impl BranchPage {
+    /// Checks whether one more entry fits in the serialized page
+    pub fn has_room_for_entry(&self) -> bool {
+        self.used_space() + ENTRY_SIZE <= self.page_size
+    }
+}
+
+/// A branch page read in place from its serialized bytes, the counterpart
+/// of `LeafPageView`. Fixed-size entries are binary searched; byte-string
+/// keys vary in size, so their entries are walked.
+#[derive(Debug, Clone, Copy)]
+pub struct BranchPageView<'a, K: PageKey = u64> {
+    bytes: &'a [u8],
+    count: usize,
+    _key: PhantomData<K>,
+}
+
+impl<'a, K: PageKey> BranchPageView<'a, K> {
+    const PREV_PAGE_ID_OFFSET: usize = KeyedBranchPage::<K>::PAGE_TYPE_SIZE + KeyedBranchPage::<K>::COUNT_SIZE;
+    const NEXT_PAGE_ID_OFFSET: usize = Self::PREV_PAGE_ID_OFFSET + KeyedBranchPage::<K>::PREV_PAGE_ID_SIZE;
+
+    pub fn new(bytes: &'a [u8]) -> Result<Self, Box<dyn Error>> {
+        let header_size = KeyedBranchPage::<K>::HEADER_SIZE;
+        if bytes.len() < header_size {
+            return Err(format!("Branch page of {} bytes is shorter than its header", bytes.len()).into());
+        }
+        if PageType::from_u8(bytes[0]) != Some(K::BRANCH_PAGE_TYPE) {
+            return Err(format!("Page type {} is not a {:?}", bytes[0], K::BRANCH_PAGE_TYPE).into());
+        }
+
+        let count = read_u64(bytes, KeyedBranchPage::<K>::PAGE_TYPE_SIZE).unwrap() as usize;
+        if let Some(key_size) = K::ENCODED_SIZE {
+            let entries_end = count.checked_mul(PAGE_ID_SIZE + key_size).and_then(|size| size.checked_add(header_size));
+            if entries_end.is_none_or(|end| end > bytes.len()) {
+                return Err(format!("Branch page entries run past its {} bytes", bytes.len()).into());
+            }
+        }
+        Ok(BranchPageView { bytes, count, _key: PhantomData })
+    }
+
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn prev_page_id(&self) -> u64 {
+        read_u64(self.bytes, Self::PREV_PAGE_ID_OFFSET).unwrap()
+    }
+
+    pub fn next_page_id(&self) -> u64 {
+        read_u64(self.bytes, Self::NEXT_PAGE_ID_OFFSET).unwrap()
+    }
+
+    /// The child that owns `key`, as `KeyedBranchPage::find_page_id` finds it
+    pub fn find_page_id(&self, key: K::Ref<'_>) -> Option<u64> {
+        let header_size = KeyedBranchPage::<K>::HEADER_SIZE;
+        if self.count == 0 {
+            return None;
+        }
+
+        // The offset of the last entry whose first key is at or below the
+        // key, or of the first entry for a key below them all
+        let entry_offset = match K::ENCODED_SIZE {
+            Some(key_size) => {
+                let entry_size = PAGE_ID_SIZE + key_size;
+                let first_key = |index: usize| K::decode_ref(&self.bytes[header_size + index * entry_size + PAGE_ID_SIZE..]).map(|(key, _)| key);
+                let (mut low, mut high) = (0, self.count);
+                while low < high {
+                    let middle = low + (high - low) / 2;
+                    if K::cmp_refs(first_key(middle)?, key).is_le() {
+                        low = middle + 1;
+                    } else {
+                        high = middle;
+                    }
+                }
+                header_size + low.saturating_sub(1) * entry_size
+            }
+            None => {
+                let mut offset = header_size;
+                let mut found = offset;
+                for _ in 0..self.count {
+                    let (first_key, key_size) = K::decode_ref(self.bytes.get(offset + PAGE_ID_SIZE..)?)?;
+                    if K::cmp_refs(first_key, key).is_gt() {
+                        break;
+                    }
+                    found = offset;
+                    offset += PAGE_ID_SIZE + key_size;
+                }
+                found
+            }
+        };
+        read_u64(self.bytes, entry_offset)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
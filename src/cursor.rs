@@ -0,0 +1,266 @@
+use std::error::Error;
+use crate::data_tree::DataTree;
+use crate::data_tree::EncodedRef;
+use crate::key_codec::KeyCodec;
+use crate::leaf::{Leaf, LeafRun};
+use crate::leaf_page::LeafPageItem;
+use crate::page_key::PageKey;
+use crate::page_store::PageStore;
+
+/// A position in a DataTree that can move forwards and backwards through the
+/// keys and update or delete the entry it is on, created by `DataTree::cursor`.
+///
+/// The cursor keeps the leaf it is on, so moving along a leaf, or through the
+/// keys of a run in a run-length leaf, reads no pages
+/// and moving off its end follows the sibling links. `put_current` and
+/// `delete_current` change that leaf in place; they only descend from the root
+/// when the leaf has to split or rebalance, which needs its parent pages.
+pub struct Cursor<'a, S: PageStore, K: KeyCodec = u64> {
+    tree: &'a mut DataTree<S, K>,
+    page_id: u64,
+    leaf_page: Option<Leaf<K::Encoded>>,
+    runs: Vec<LeafRun<K::Encoded>>,
+    // The index of the current run and the current key within it
+    position: Option<(usize, K::Encoded)>,
+}
+
+impl<'a, S: PageStore, K: KeyCodec> Cursor<'a, S, K> {
+    pub(crate) fn new(tree: &'a mut DataTree<S, K>) -> Self {
+        Cursor {
+            tree,
+            page_id: 0,
+            leaf_page: None,
+            runs: Vec::new(),
+            position: None,
+        }
+    }
+
+    /// Whether the cursor is on an entry
+    pub fn is_valid(&self) -> bool {
+        self.position.is_some()
+    }
+
+    /// The key of the current entry
+    pub fn key(&self) -> Option<K> {
+        self.position.as_ref().map(|(_, key)| K::decode(key))
+    }
+
+    // The current entry's key as stored in the leaf
+    fn stored_key(&self) -> Option<EncodedRef<'_, K>> {
+        self.position.as_ref().map(|(_, key)| key.to_ref())
+    }
+
+    /// The value of the current entry. A value kept in overflow pages is
+    /// read from them.
+    pub fn value(&self) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
+        let (key, leaf_page) = match (self.stored_key(), self.leaf_page.as_ref()) {
+            (Some(key), Some(leaf_page)) => (key, leaf_page),
+            _ => return Ok(None),
+        };
+        match leaf_page.get(key) {
+            Some(record) if leaf_page.is_overflow(key) => Ok(Some(self.tree.read_overflow_value(record)?)),
+            Some(value) => Ok(Some(value.to_vec())),
+            None => Ok(None),
+        }
+    }
+
+    /// Moves to the first key greater than or equal to `key`.
+    /// Returns whether there is such a key.
+    pub fn seek(&mut self, key: K::Ref<'_>) -> Result<bool, Box<dyn Error>> {
+        self.seek_encoded(K::encode(key).to_ref())
+    }
+
+    fn seek_encoded(&mut self, key: EncodedRef<'_, K>) -> Result<bool, Box<dyn Error>> {
+        let (_, page_id) = self.tree.find_leaf(key)?;
+        self.load(page_id)?;
+        if self.locate(key) {
+            return Ok(true);
+        }
+        self.next_leaf()
+    }
+
+    /// Moves to the last key less than or equal to `key`.
+    /// Returns whether there is such a key.
+    pub fn seek_for_prev(&mut self, key: K::Ref<'_>) -> Result<bool, Box<dyn Error>> {
+        let key = K::encode(key);
+        let key = key.to_ref();
+        let (_, page_id) = self.tree.find_leaf(key)?;
+        self.load(page_id)?;
+        let pos = self.runs.partition_point(|run| run.start.cmp_ref(key).is_le());
+        if pos > 0 {
+            let run = &self.runs[pos - 1];
+            let current = if run.end.cmp_ref(key).is_gt() { K::Encoded::from_ref(key) } else { run.end.clone() };
+            self.position = Some((pos - 1, current));
+            return Ok(true);
+        }
+        self.prev_leaf()
+    }
+
+    /// Moves to the next key. Returns false, leaving the cursor invalid,
+    /// once it moves past the last key.
+    // Not Iterator::next: moving can fail, and the cursor stays in place to
+    // be read and updated
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Result<bool, Box<dyn Error>> {
+        let (pos, key) = match &self.position {
+            Some(position) => position,
+            None => return Ok(false),
+        };
+        if let Some(next_key) = self.runs[*pos].key_after(key) {
+            self.position = Some((*pos, next_key));
+            return Ok(true);
+        }
+        if pos + 1 < self.runs.len() {
+            self.position = Some((pos + 1, self.runs[pos + 1].start.clone()));
+            return Ok(true);
+        }
+        self.next_leaf()
+    }
+
+    /// Moves to the previous key. Returns false, leaving the cursor invalid,
+    /// once it moves past the first key.
+    pub fn prev(&mut self) -> Result<bool, Box<dyn Error>> {
+        let (pos, key) = match &self.position {
+            Some(position) => position,
+            None => return Ok(false),
+        };
+        if let Some(prev_key) = self.runs[*pos].key_before(key) {
+            self.position = Some((*pos, prev_key));
+            return Ok(true);
+        }
+        if *pos > 0 {
+            self.position = Some((pos - 1, self.runs[pos - 1].end.clone()));
+            return Ok(true);
+        }
+        self.prev_leaf()
+    }
+
+    /// Replaces the value of the current entry
+    pub fn put_current(&mut self, value: &[u8]) -> Result<(), Box<dyn Error>> {
+        let key = self.current_key()?;
+        if self.tree.preserving_pages() {
+            // The tree keeps the pages live snapshots see as it writes
+            self.tree.atomically(|tree| tree.put_encoded(key.clone(), value))?;
+            self.seek_encoded(key.to_ref())?;
+            return Ok(());
+        }
+        let inline = value.len() <= DataTree::<S, K>::max_inline_value_size(self.tree.usable_page_size(), &key);
+        let leaf_page = self.leaf_page.as_mut().unwrap();
+        let replaced = self.tree.overflow_record(leaf_page, key.to_ref());
+        let item = LeafPageItem { key: key.clone(), bytes: value.to_vec(), overflow: false };
+        if inline && self.tree.put_into_leaf(leaf_page, &item) {
+            self.tree.write_leaf(self.page_id, leaf_page)?;
+            self.tree.free_overflow_value(replaced)?;
+
+            // Putting into a run can split it
+            self.runs = leaf_page.runs();
+            self.locate(key.to_ref());
+            return Ok(());
+        }
+
+        // The new value doesn't fit, or needs overflow pages: let the tree
+        // store it and find the entry again
+        self.tree.put_encoded(key.clone(), value)?;
+        self.seek_encoded(key.to_ref())?;
+        Ok(())
+    }
+
+    /// Deletes the current entry and moves to the entry after it
+    pub fn delete_current(&mut self) -> Result<(), Box<dyn Error>> {
+        let key = self.current_key()?;
+        if self.tree.preserving_pages() {
+            self.tree.atomically(|tree| tree.delete_encoded(key.to_ref()))?;
+            self.seek_encoded(key.to_ref())?;
+            return Ok(());
+        }
+        let leaf_page = self.leaf_page.as_mut().unwrap();
+        let removed = self.tree.overflow_record(leaf_page, key.to_ref());
+        leaf_page.delete(key.to_ref());
+
+        if leaf_page.used_space() > self.tree.usable_page_size() {
+            // Deleting from the middle of a run overfilled the leaf, which
+            // the tree splits; find the following entry again afterwards
+            self.tree.delete_encoded(key.to_ref())?;
+            self.seek_encoded(key.to_ref())?;
+            return Ok(());
+        }
+        self.tree.write_leaf(self.page_id, leaf_page)?;
+        self.tree.free_overflow_value(removed)?;
+
+        if self.tree.leaf_underflows(leaf_page) {
+            // Rebalancing may move entries between leaves, so find the
+            // following entry again afterwards
+            let (path, page_id) = self.tree.find_leaf(key.to_ref())?;
+            self.tree.rebalance_leaf(path, page_id)?;
+            self.seek_encoded(key.to_ref())?;
+            return Ok(());
+        }
+
+        self.runs = leaf_page.runs();
+        if !self.locate(key.to_ref()) {
+            self.next_leaf()?;
+        }
+        Ok(())
+    }
+
+    // The key of the current entry, or an error if there is none
+    fn current_key(&self) -> Result<K::Encoded, Box<dyn Error>> {
+        match &self.position {
+            Some((_, key)) => Ok(key.clone()),
+            None => Err("Cursor is not on an entry".into()),
+        }
+    }
+
+    // Move to the first key of the current leaf at or after `key`. Returns
+    // false, leaving the cursor invalid, if there is none.
+    fn locate(&mut self, key: EncodedRef<'_, K>) -> bool {
+        let pos = self.runs.partition_point(|run| run.end.cmp_ref(key).is_lt());
+        self.position = self.runs.get(pos).map(|run| {
+            let current = if run.start.cmp_ref(key).is_lt() { K::Encoded::from_ref(key) } else { run.start.clone() };
+            (pos, current)
+        });
+        self.position.is_some()
+    }
+
+    // Make `page_id` the current leaf, with no entry selected
+    fn load(&mut self, page_id: u64) -> Result<(), Box<dyn Error>> {
+        let leaf_page = self.tree.read_leaf(page_id)?;
+        self.runs = leaf_page.runs();
+        self.page_id = page_id;
+        self.leaf_page = Some(leaf_page);
+        self.position = None;
+        Ok(())
+    }
+
+    // Follow the next links to the first entry of the next non-empty leaf
+    fn next_leaf(&mut self) -> Result<bool, Box<dyn Error>> {
+        loop {
+            let next_page_id = self.leaf_page.as_ref().map_or(0, |leaf| leaf.next_page_id());
+            if next_page_id == 0 {
+                self.position = None;
+                return Ok(false);
+            }
+            self.load(next_page_id)?;
+            if let Some(run) = self.runs.first() {
+                self.position = Some((0, run.start.clone()));
+                return Ok(true);
+            }
+        }
+    }
+
+    // Follow the prev links to the last entry of the previous non-empty leaf
+    fn prev_leaf(&mut self) -> Result<bool, Box<dyn Error>> {
+        loop {
+            let prev_page_id = self.leaf_page.as_ref().map_or(0, |leaf| leaf.prev_page_id());
+            if prev_page_id == 0 {
+                self.position = None;
+                return Ok(false);
+            }
+            self.load(prev_page_id)?;
+            if let Some(run) = self.runs.last() {
+                self.position = Some((self.runs.len() - 1, run.end.clone()));
+                return Ok(true);
+            }
+        }
+    }
+}
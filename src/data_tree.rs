@@ -1,9 +1,23 @@
 use std::collections::HashSet;
 use std::error::Error;
 use std::fmt;
-use crate::leaf_page::LeafPage;
-use crate::branch_page::BranchPage;
-use crate::page_store::PageStore;
+use std::marker::PhantomData;
+use std::ops::{Bound, RangeBounds};
+use std::sync::{Arc, Mutex};
+use crate::leaf::{Leaf, LeafEncoding, LeafRun, LeafView};
+use crate::leaf_page::{KeyedLeafPage, LeafPage, LeafPageItem};
+use crate::overflow_page::{OverflowPage, OverflowPointer};
+use crate::branch_page::{BranchPageView, KeyedBranchPage, PAGE_ID_SIZE};
+use crate::key_codec::KeyCodec;
+use crate::page_key::PageKey;
+use crate::page_store::{PageStore, CRC_SIZE};
+use crate::range::{KeyRun, Range, Runs};
+use crate::cursor::Cursor;
+use crate::rle_leaf_page::RLELeafPage;
+use crate::snapshot::{CopyOnWritePageStore, Snapshot, SnapshotView, Snapshots};
+use crate::superblock::{Superblock, SUPERBLOCK_PAGE_ID};
+use crate::transaction::{Transaction, TransactionPageStore};
+use crate::write_batch::{UndoPageStore, WriteBatch};
 
 // Define a custom error type for when a key is not found
 #[derive(Debug)]
@@ -23,6 +37,12 @@ pub enum PageType {
     LeafPage = 1,
     BranchPage = 2,
     RLELeafPage = 3,
+    Superblock = 4,
+    OverflowPage = 5,
+    ByteLeafPage = 6,
+    ByteBranchPage = 7,
+    SlottedLeafPage = 8,
+    ByteSlottedLeafPage = 9,
     // Future page types will be added here
 }
 
@@ -33,6 +53,12 @@ impl PageType {
             1 => Some(PageType::LeafPage),
             2 => Some(PageType::BranchPage),
             3 => Some(PageType::RLELeafPage),
+            4 => Some(PageType::Superblock),
+            5 => Some(PageType::OverflowPage),
+            6 => Some(PageType::ByteLeafPage),
+            7 => Some(PageType::ByteBranchPage),
+            8 => Some(PageType::SlottedLeafPage),
+            9 => Some(PageType::ByteSlottedLeafPage),
             _ => None,
         }
     }
@@ -42,37 +68,470 @@ impl PageType {
     }
 }
 
-pub struct DataTree<S: PageStore> {
+// Branch pages visited on the way from the root to a leaf, with their page IDs
+pub(crate) type BranchPath<K = u64> = Vec<(u64, KeyedBranchPage<K>)>;
+
+// The branch path down to a leaf, with the leaf's page ID
+pub(crate) type LeafPath<K> = (BranchPath<K>, u64);
+
+// A stored key in the form pages take it: a u64, or a slice of a byte string
+pub(crate) type EncodedRef<'a, K> = <<K as KeyCodec>::Encoded as PageKey>::Ref<'a>;
+
+/// Settings that change how a DataTree maintains its pages but not the
+/// on-disk format, so they are not stored with the tree
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DataTreeConfig {
+    /// Fraction of a page a leaf or branch must fill after a delete before it
+    /// borrows from or merges with a sibling. At most 0.5, so that two
+    /// underfull siblings always fit in one page.
+    pub min_fill: f64,
+    /// Fraction of each page `bulk_load` fills before starting the next one.
+    /// Less than 1.0 leaves room for later puts without immediate splits.
+    pub fill_factor: f64,
+    /// Encoding of the leaves the tree writes. Leaves of either encoding are
+    /// read whatever the setting, and one written in the other encoding is
+    /// converted when it is next written, if its entries fit. Adaptive
+    /// writes each leaf in whichever encoding takes fewer bytes.
+    pub leaf_encoding: LeafEncoding,
+}
+
+impl Default for DataTreeConfig {
+    fn default() -> Self {
+        DataTreeConfig {
+            min_fill: 0.25,
+            fill_factor: 1.0,
+            leaf_encoding: LeafEncoding::Plain,
+        }
+    }
+}
+
+impl DataTreeConfig {
+    pub fn validate(&self) -> Result<(), Box<dyn Error>> {
+        if !(0.0..=0.5).contains(&self.min_fill) {
+            return Err(format!("Minimum fill {} is not between 0 and 0.5", self.min_fill).into());
+        }
+        if !(self.fill_factor > 0.0 && self.fill_factor <= 1.0) {
+            return Err(format!("Fill factor {} is not above 0 and at most 1", self.fill_factor).into());
+        }
+        Ok(())
+    }
+}
+
+/// Counts of the pages making up a tree, from `DataTree::page_stats`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PageStats {
+    pub branch_pages: usize,
+    pub plain_leaf_pages: usize,
+    pub run_length_leaf_pages: usize,
+    pub slotted_leaf_pages: usize,
+    pub overflow_pages: usize,
+    /// Bytes the run-length leaves save over plain leaves holding the same
+    /// entries. Negative when they take more.
+    pub bytes_saved: i64,
+}
+
+pub struct DataTree<S: PageStore, K: KeyCodec = u64> {
     store: S,
     root_page_id: u64,
+    config: DataTreeConfig,
+    // Whether the superblock records this tree's root. The trees of a
+    // DataTree2 catalog are recorded in the catalog instead.
+    in_superblock: bool,
+    // The live snapshots of the tree, shared with their handles
+    snapshots: Arc<Mutex<Snapshots>>,
+    key_type: PhantomData<K>,
 }
 
+// Constructors for the usual u64-keyed tree. Trees of other key types are
+// made with the `_keyed` constructors.
 impl<S: PageStore> DataTree<S> {
     // This method creates a DataTree with a BranchPage as the root
-    pub fn new(mut store: S) -> Self {
+    pub fn new(store: S) -> Self {
+        Self::new_keyed(store)
+    }
+
+    /// Builds a tree in an empty store from entries sorted by key, packing
+    /// pages to the default fill factor
+    pub fn bulk_load<I>(store: S, entries: I) -> Result<Self, Box<dyn Error>>
+    where
+        I: IntoIterator<Item = (u64, Vec<u8>)>,
+    {
+        Self::bulk_load_keyed(store, DataTreeConfig::default(), entries)
+    }
+
+    /// Builds a tree in an empty store from entries sorted by key, packing
+    /// pages to `config.fill_factor`
+    pub fn bulk_load_with_config<I>(store: S, config: DataTreeConfig, entries: I) -> Result<Self, Box<dyn Error>>
+    where
+        I: IntoIterator<Item = (u64, Vec<u8>)>,
+    {
+        Self::bulk_load_keyed(store, config, entries)
+    }
+
+    /// Opens the tree recorded in the store's superblock
+    pub fn open(store: S) -> Result<Self, Box<dyn Error>> {
+        Self::open_keyed(store)
+    }
+
+    /// Creates a DataTree from an existing store and root page ID
+    pub fn from_existing(store: S, root_page_id: u64) -> Self {
+        Self::at_root(store, root_page_id, true)
+    }
+
+    /// Folds overflow chains left by older versions into the branch.
+    ///
+    /// Older trees never split leaves: a full leaf got a new page linked
+    /// after it through next_page_id, and those pages were never added to the
+    /// root BranchPage. Lookups now route by the branch alone, so such stores
+    /// must be migrated once before use. The entries of every chain page are
+    /// put back through the normal split path and the chain pages are freed.
+    /// Returns the number of chain pages that were migrated.
+    pub fn migrate_overflow_chains(&mut self) -> Result<usize, Box<dyn Error>> {
+        if self.snapshots.lock().unwrap().any_live() {
+            return Err("Overflow chains can't be migrated while snapshots are live".into());
+        }
+        let branch_page = self.read_root_branch()?;
+        let leaf_page_ids: Vec<u64> = branch_page.entries().iter().map(|entry| entry.page_id).collect();
+
+        // Older versions only wrote a single branch level; a tree with deeper
+        // levels was written by this one and has no chains
+        for page_id in &leaf_page_ids {
+            let page_bytes = self.store.get_page_bytes(*page_id)?;
+            if PageType::from_u8(page_bytes[0]) != Some(PageType::LeafPage) {
+                return Ok(0);
+            }
+        }
+        let branch_leaves: HashSet<u64> = leaf_page_ids.iter().copied().collect();
+
+        // The old lookup searched a leaf before its chain, so the first copy
+        // of a key along the chain is the visible one
+        let mut seen = HashSet::new();
+        for page_id in &leaf_page_ids {
+            for run in self.read_leaf(*page_id)?.runs() {
+                seen.insert(run.start);
+            }
+        }
+
+        // Collect the entries of every chain page and free it
+        let mut orphaned = Vec::new();
+        let mut chain_page_count = 0;
+        for page_id in &leaf_page_ids {
+            let mut current_page_id = self.read_leaf(*page_id)?.next_page_id();
+            while current_page_id != 0 && !branch_leaves.contains(&current_page_id) {
+                let chain_page = self.read_leaf(current_page_id)?;
+                for run in chain_page.runs() {
+                    if seen.insert(run.start) {
+                        orphaned.push((run.start, run.bytes));
+                    }
+                }
+
+                self.store.free_page(current_page_id)?;
+                chain_page_count += 1;
+                current_page_id = chain_page.next_page_id();
+            }
+        }
+
+        if chain_page_count == 0 {
+            return Ok(0);
+        }
+
+        // Relink the leaves the branch knows about in key order
+        for (i, page_id) in leaf_page_ids.iter().enumerate() {
+            let mut leaf_page = self.read_leaf(*page_id)?;
+            leaf_page.set_prev_page_id(if i > 0 { leaf_page_ids[i - 1] } else { 0 });
+            leaf_page.set_next_page_id(leaf_page_ids.get(i + 1).copied().unwrap_or(0));
+            self.write_leaf(*page_id, &leaf_page)?;
+        }
+
+        // Put the orphaned entries back; put splits leaves as needed
+        for (key, value) in orphaned {
+            self.put(key, &value)?;
+        }
+
+        Ok(chain_page_count)
+    }
+
+    /// Convert a byte array to a u64. Only the first 8 bytes are used, so
+    /// longer keys collide; use a tree keyed by `Vec<u8>` for byte strings.
+    pub fn bytes_to_u64(key: &[u8]) -> u64 {
+        if key.len() >= 8 {
+            u64::from_le_bytes(key[0..8].try_into().unwrap())
+        } else {
+            // Pad with zeros if key is shorter than 8 bytes
+            let mut padded = [0u8; 8];
+            for (i, &b) in key.iter().enumerate() {
+                padded[i] = b;
+            }
+            u64::from_le_bytes(padded)
+        }
+    }
+
+    /// Convert a u64 to a byte array
+    pub fn u64_to_bytes(key: u64) -> [u8; 8] {
+        key.to_le_bytes()
+    }
+}
+
+impl<S: PageStore, K: KeyCodec> DataTree<S, K> {
+    /// Creates a tree of K keys with a BranchPage as the root
+    pub fn new_keyed(store: S) -> Self {
+        let mut tree = Self::create(store, true);
+
+        // Record the root in the superblock so the store can be reopened with `open`
+        tree.write_superblock().unwrap();
+        tree
+    }
+
+    // Allocate an empty leaf under a new root branch
+    fn create(mut store: S, in_superblock: bool) -> Self {
         // Allocate a page for the leaf page
         let leaf_page_id = store.allocate_page();
-        let leaf_page = LeafPage::new_empty(store.page_size());
+        let leaf_page = KeyedLeafPage::<K::Encoded>::empty(store.page_size());
         store.put_page_bytes(leaf_page_id, &leaf_page.serialize()).unwrap();
 
         // Allocate a page for the branch page (root)
         let root_page_id = store.allocate_page();
-        let mut branch_page = BranchPage::new_empty(store.page_size());
+        let mut branch_page = KeyedBranchPage::new_empty(store.page_size());
 
         // Add the leaf page as the first entry in the branch page
-        // Use 0 as the first_key since it's an empty leaf page
-        branch_page.insert(leaf_page_id, 0);
+        // Use the smallest key as the first_key since it's an empty leaf page
+        branch_page.insert(leaf_page_id, K::Encoded::min_key());
 
         // Save the branch page
         store.put_page_bytes(root_page_id, &branch_page.serialize()).unwrap();
 
+        Self::at_root(store, root_page_id, in_superblock)
+    }
+
+    // A tree over the pages already under `root_page_id`
+    pub(crate) fn at_root(store: S, root_page_id: u64, in_superblock: bool) -> Self {
         DataTree {
             store,
             root_page_id,
+            config: DataTreeConfig::default(),
+            in_superblock,
+            snapshots: Arc::default(),
+            key_type: PhantomData,
         }
     }
 
+    /// Creates an empty tree whose root is kept by a DataTree2 catalog
+    /// rather than the superblock
+    pub(crate) fn new_in_catalog(store: S) -> Self {
+        Self::create(store, false)
+    }
+
+    /// Opens a tree whose root is kept by a DataTree2 catalog
+    pub(crate) fn open_in_catalog(store: S, root_page_id: u64) -> Result<Self, Box<dyn Error>> {
+        let tree = Self::at_root(store, root_page_id, false);
+
+        // A tree of another key type has other page types
+        tree.read_root_branch()?;
+        Ok(tree)
+    }
+
+    /// Builds a tree in an empty store from entries sorted by key.
+    ///
+    /// Leaves are filled to `config.fill_factor` one after another and
+    /// linked as they are written, then each level of branch pages is built
+    /// over the one below until a single root remains. Keys must be strictly
+    /// increasing; the load stops with an error at the first one that isn't.
+    pub fn bulk_load_keyed<I>(mut store: S, config: DataTreeConfig, entries: I) -> Result<Self, Box<dyn Error>>
+    where
+        I: IntoIterator<Item = (K, Vec<u8>)>,
+    {
+        Self::check_config(&config)?;
+        let usable_page_size = store.page_size() - CRC_SIZE;
+        let fill_bytes = (usable_page_size as f64 * config.fill_factor) as usize;
+
+        // Step 1: write the leaves, remembering each one's ID and first key.
+        // The first leaf starts at the smallest key, like the first leaf of
+        // `new`, and each later one at a separator from the leaf before it.
+        let mut level: Vec<(u64, K::Encoded)> = Vec::new();
+        let mut leaf_page_id = store.allocate_page();
+        let mut leaf_page = Leaf::new_empty(config.leaf_encoding, usable_page_size);
+        let mut first_key = K::Encoded::min_key();
+        let mut last_key: Option<K::Encoded> = None;
+        for (key, value) in entries {
+            let key = K::encode(key.to_ref());
+            if let Some(last_key) = &last_key {
+                if key <= *last_key {
+                    return Err(format!("Bulk load keys must be strictly increasing: {:?} after {:?}", key, last_key).into());
+                }
+            }
+            Self::check_key_size(usable_page_size, &key)?;
+
+            // Values too large for a leaf go to overflow pages
+            let overflow = value.len() > Self::max_inline_value_size(usable_page_size, &key);
+            let value = if overflow {
+                Self::write_overflow_value(&mut store, &value)?
+            } else {
+                value
+            };
+            let item = LeafPageItem { key: key.clone(), bytes: value, overflow };
+
+            // Start a new leaf once this one reaches the fill factor, or when
+            // the entry doesn't fit the leaf's encoding
+            let mut added = leaf_page.is_empty() || Self::append_to_leaf(&mut leaf_page, &item, fill_bytes);
+            if !added && config.leaf_encoding == LeafEncoding::Adaptive {
+                // The entry may fit once the leaf is in the other encoding
+                if let Some(mut converted) = leaf_page.to_encoding(leaf_page.other_encoding()) {
+                    if Self::append_to_leaf(&mut converted, &item, fill_bytes) {
+                        leaf_page = converted;
+                        added = true;
+                    }
+                }
+            }
+            if !added {
+                let next_page_id = store.allocate_page();
+                leaf_page.set_next_page_id(next_page_id);
+                store.put_page_bytes(leaf_page_id, &Self::serialize_leaf(config.leaf_encoding, &leaf_page))?;
+                level.push((leaf_page_id, first_key));
+                first_key = K::Encoded::separator(last_key.as_ref().unwrap(), &key);
+
+                leaf_page = Leaf::new_empty(config.leaf_encoding, usable_page_size);
+                leaf_page.set_prev_page_id(leaf_page_id);
+                leaf_page_id = next_page_id;
+            }
+            if leaf_page.is_empty() && !leaf_page.put_item(&item) {
+                return Err(format!("Entry for key {:?} does not fit in a leaf", key).into());
+            }
+            last_key = Some(key);
+        }
+        store.put_page_bytes(leaf_page_id, &Self::serialize_leaf(config.leaf_encoding, &leaf_page))?;
+        level.push((leaf_page_id, first_key));
+
+        // Step 2: build branch levels until one page holds the whole level.
+        // The root is always a branch, even over a single leaf.
+        loop {
+            // Size branches for the level's largest entry, so every branch fits
+            let entry_size = level.iter().map(|(_, key)| PAGE_ID_SIZE + key.encoded_size()).max().unwrap();
+            let header_size = KeyedBranchPage::<K::Encoded>::HEADER_SIZE;
+            let fill_entries = fill_bytes.saturating_sub(header_size) / entry_size;
+            let max_entries = (usable_page_size - header_size) / entry_size;
+            let per_branch = fill_entries.clamp(2, max_entries);
+
+            // Spread the level evenly over as few branches as fit, so the
+            // last branch isn't left with a single child
+            let branch_count = level.len().div_ceil(per_branch);
+            let mut chunks = Vec::with_capacity(branch_count);
+            let mut start = 0;
+            for i in 0..branch_count {
+                let end = start + (level.len() - start) / (branch_count - i);
+                chunks.push(&level[start..end]);
+                start = end;
+            }
+
+            let mut next_level = Vec::with_capacity(chunks.len());
+            for chunk in chunks {
+                let mut branch_page = KeyedBranchPage::new_empty(usable_page_size);
+                for (page_id, first_key) in chunk {
+                    branch_page.insert(*page_id, first_key.clone());
+                }
+                let branch_page_id = store.allocate_page();
+                store.put_page_bytes(branch_page_id, &branch_page.serialize())?;
+                next_level.push((branch_page_id, chunk[0].1.clone()));
+            }
+
+            level = next_level;
+            if level.len() == 1 {
+                break;
+            }
+        }
+
+        let mut tree = Self::at_root(store, level[0].0, true);
+        tree.config = config;
+        tree.write_superblock()?;
+        Ok(tree)
+    }
+
+    // Append an entry to a leaf being bulk loaded if it stays within `fill_bytes`
+    fn append_to_leaf(leaf_page: &mut Leaf<K::Encoded>, item: &LeafPageItem<K::Encoded>, fill_bytes: usize) -> bool {
+        leaf_page.used_space() + leaf_page.appended_size(item) <= fill_bytes && leaf_page.put_item(item)
+    }
+
+    /// Opens the tree of K keys recorded in the store's superblock
+    pub fn open_keyed(mut store: S) -> Result<Self, Box<dyn Error>> {
+        let superblock_bytes = store.get_page_bytes(SUPERBLOCK_PAGE_ID)?;
+        let superblock = Superblock::deserialize(&superblock_bytes)?;
+        superblock.validate(store.page_size())?;
+        store.set_free_list_head(superblock.free_list_head);
+
+        let mut tree = Self::at_root(store, superblock.root_page_id, true);
+
+        // A tree of another key type has other page types
+        tree.read_root_branch()?;
+
+        // No snapshot outlives the tree, so the copies kept for the ones
+        // live at the last flush are free
+        if let Some(pointer) = superblock.copy_list() {
+            let record = pointer.serialize().to_vec();
+            for page_id in tree.read_overflow_value(&record)?.chunks_exact(8) {
+                tree.store.free_page(u64::from_le_bytes(page_id.try_into().unwrap()))?;
+            }
+            tree.free_overflow_value(Some(record))?;
+            tree.write_copy_list(None)?;
+        }
+        Ok(tree)
+    }
+
+    /// Reads the superblock from page 0
+    pub fn superblock(&self) -> Result<Superblock, Box<dyn Error>> {
+        let superblock_bytes = self.store.get_page_bytes(SUPERBLOCK_PAGE_ID)?;
+        Superblock::deserialize(&superblock_bytes)
+    }
+
+    // Rewrite the superblock with the current root, page count and free list
+    // head. The copy list is kept as it is; only a flush or open changes it.
+    fn write_superblock(&mut self) -> Result<(), Box<dyn Error>> {
+        if !self.in_superblock {
+            return Ok(());
+        }
+        let mut superblock = Superblock::new(self.store.page_size(), self.root_page_id);
+        superblock.page_count = self.store.get_page_count() as u64;
+        superblock.free_list_head = self.store.free_list_head();
+        if let Ok(current) = self.superblock() {
+            superblock.copy_list_page_id = current.copy_list_page_id;
+            superblock.copy_list_length = current.copy_list_length;
+        }
+        self.store.put_page_bytes(SUPERBLOCK_PAGE_ID, &superblock.serialize())
+    }
+
+    // Record the copy list the superblock points to
+    fn write_copy_list(&mut self, pointer: Option<OverflowPointer>) -> Result<(), Box<dyn Error>> {
+        let mut superblock = self.superblock()?;
+        superblock.copy_list_page_id = pointer.map_or(0, |pointer| pointer.first_page_id);
+        superblock.copy_list_length = pointer.map_or(0, |pointer| pointer.value_length);
+        self.store.put_page_bytes(SUPERBLOCK_PAGE_ID, &superblock.serialize())
+    }
+
+    // Replace the copy list with the copies live snapshots keep now, so that
+    // reopening the flushed store frees them
+    fn update_copy_list(&mut self) -> Result<(), Box<dyn Error>> {
+        if !self.in_superblock {
+            return Ok(());
+        }
+        let old_pointer = self.superblock()?.copy_list();
+        let copies = self.snapshots.lock().unwrap().copies();
+        if old_pointer.is_none() && copies.is_empty() {
+            return Ok(());
+        }
+
+        self.free_overflow_value(old_pointer.map(|pointer| pointer.serialize().to_vec()))?;
+        let pointer = if copies.is_empty() {
+            None
+        } else {
+            let bytes: Vec<u8> = copies.iter().flat_map(|page_id| page_id.to_le_bytes()).collect();
+            let record = Self::write_overflow_value(&mut self.store, &bytes)?;
+            Some(OverflowPointer::deserialize(&record)?)
+        };
+        self.write_copy_list(pointer)
+    }
+
     pub fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        self.reclaim_snapshot_pages()?;
+        self.update_copy_list()?;
+        self.write_superblock()?;
         self.store.flush()
     }
 
@@ -100,238 +559,1102 @@ impl<S: PageStore> DataTree<S> {
         self.store
     }
 
-    /// Creates a DataTree from an existing store and root page ID
-    pub fn from_existing(store: S, root_page_id: u64) -> Self {
-        DataTree {
-            store,
-            root_page_id,
+    /// Returns the tree's settings
+    pub fn config(&self) -> DataTreeConfig {
+        self.config
+    }
+
+    /// Replaces the tree's settings. They apply from the next operation on.
+    pub fn set_config(&mut self, config: DataTreeConfig) -> Result<(), Box<dyn Error>> {
+        Self::check_config(&config)?;
+        self.config = config;
+        Ok(())
+    }
+
+    // Validate settings, including those that depend on the key type
+    fn check_config(config: &DataTreeConfig) -> Result<(), Box<dyn Error>> {
+        config.validate()?;
+        if config.leaf_encoding == LeafEncoding::RunLength && !K::Encoded::supports_runs() {
+            return Err("Run-length leaves need keys stored as u64".into());
         }
+        Ok(())
     }
 
-    /// Get a value by its u64 key
-    pub fn get(&self, key: u64) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
-        // Start with the root page (which is a BranchPage)
-        let root_page_bytes = self.store.get_page_bytes(self.root_page_id)?;
+    /// Returns the largest key, in encoded bytes, the tree accepts. Keys are
+    /// limited so that a branch page always holds at least four of them, and
+    /// so that a byte-string key's length fits its 2 byte prefix.
+    pub fn max_key_size(&self) -> usize {
+        Self::max_key_size_for(self.usable_page_size())
+    }
 
-        // Check the page type
-        let page_type = PageType::from_u8(root_page_bytes[0]).unwrap_or(PageType::LeafPage);
+    fn max_key_size_for(usable_page_size: usize) -> usize {
+        let branch_limit = (usable_page_size - KeyedBranchPage::<K::Encoded>::HEADER_SIZE) / 4 - PAGE_ID_SIZE;
+        branch_limit.min(u16::MAX as usize)
+    }
 
-        // Guard clause: ensure root page is a BranchPage
-        if page_type != PageType::BranchPage {
-            // Root is not a BranchPage, which is unexpected
-            return Err("Root page is not a BranchPage".into());
+    // The largest value stored in a leaf rather than in overflow pages
+    pub(crate) fn max_inline_value_size(usable_page_size: usize, key: &K::Encoded) -> usize {
+        KeyedLeafPage::<K::Encoded>::empty(usable_page_size).max_value_size_for(key)
+    }
+
+    fn check_key_size(usable_page_size: usize, key: &K::Encoded) -> Result<(), Box<dyn Error>> {
+        let max_key_size = Self::max_key_size_for(usable_page_size);
+        if key.encoded_size() > max_key_size {
+            return Err(format!("Key of {} bytes is larger than the maximum of {}", key.encoded_size(), max_key_size).into());
         }
+        Ok(())
+    }
 
-        // It's a branch page, find the appropriate leaf page
-        let branch_page = BranchPage::deserialize(&root_page_bytes);
+    /// Get a value by its key
+    pub fn get(&self, key: K::Ref<'_>) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
+        let key = K::encode(key);
+        let key = key.to_ref();
+
+        // Descend from the root to the only leaf that can hold the key. Pages
+        // are read in place through views rather than deserialized.
+        let mut page_bytes = self.store.get_page_bytes(self.root_page_id)?;
+        while page_bytes.first().and_then(|b| PageType::from_u8(*b)) == Some(K::Encoded::BRANCH_PAGE_TYPE) {
+            let child_page_id = BranchPageView::<K::Encoded>::new(&page_bytes)?.find_page_id(key)
+                .ok_or("Could not find a leaf page for the key")?;
+            page_bytes = self.store.get_page_bytes(child_page_id)?;
+        }
 
-        // Find the leaf page ID using the branch page
-        let leaf_page_id = match branch_page.find_page_id(key) {
-            Some(id) => id,
-            None => {
-                // Key not found in branch page
-                return Ok(None);
-            }
+        match LeafView::<K::Encoded>::new(&page_bytes)?.get_entry(key) {
+            Some((record, true)) => Ok(Some(self.read_overflow_value(record)?)),
+            Some((value, false)) => Ok(Some(value.to_vec())),
+            None => Ok(None),
+        }
+    }
+
+    /// Put a value with a key
+    pub fn put(&mut self, key: K::Ref<'_>, value: &[u8]) -> Result<(), Box<dyn Error>> {
+        let key = K::encode(key);
+        if self.preserving_pages() {
+            return self.atomically(|tree| tree.put_encoded(key, value));
+        }
+        self.put_encoded(key, value)
+    }
+
+    // Put a value with a key already in its stored form
+    pub(crate) fn put_encoded(&mut self, key: K::Encoded, value: &[u8]) -> Result<(), Box<dyn Error>> {
+        Self::check_key_size(self.usable_page_size(), &key)?;
+
+        // A value too large for a page is written to overflow pages and the
+        // leaf holds a pointer to them
+        let item = if value.len() > Self::max_inline_value_size(self.usable_page_size(), &key) {
+            LeafPageItem { key: key.clone(), bytes: Self::write_overflow_value(&mut self.store, value)?, overflow: true }
+        } else {
+            LeafPageItem { key: key.clone(), bytes: value.to_vec(), overflow: false }
         };
 
-        // Now get the leaf page
-        let leaf_page_bytes = self.store.get_page_bytes(leaf_page_id)?;
-        let leaf_page = LeafPage::deserialize(&leaf_page_bytes);
+        loop {
+            let (path, leaf_page_id) = self.find_leaf(key.to_ref())?;
+
+            // Try to insert into the leaf page
+            let mut leaf_page = self.read_leaf(leaf_page_id)?;
+            let replaced = self.overflow_record(&leaf_page, key.to_ref());
+            if self.put_into_leaf(&mut leaf_page, &item) {
+                // Page is automatically marked as dirty in put_page_bytes
+                self.write_leaf(leaf_page_id, &leaf_page)?;
+                return self.free_overflow_value(replaced);
+            }
+
+            // The leaf is full: split it, register the new right sibling in the
+            // parent and route the key again
+            let (new_page_id, first_key) = self.split_leaf(leaf_page_id, leaf_page, &key)?;
+            self.insert_into_parent(path, new_page_id, first_key)?;
+        }
+    }
+
+    /// Delete a value by its key
+    pub fn delete(&mut self, key: K::Ref<'_>) -> Result<bool, Box<dyn Error>> {
+        let key = K::encode(key);
+        if self.preserving_pages() {
+            return self.atomically(|tree| tree.delete_encoded(key.to_ref()));
+        }
+        self.delete_encoded(key.to_ref())
+    }
+
+    // Delete a value by its key in stored form
+    pub(crate) fn delete_encoded(&mut self, key: EncodedRef<'_, K>) -> Result<bool, Box<dyn Error>> {
+        let (path, leaf_page_id) = self.find_leaf(key)?;
 
-        // Look for the key in the leaf page
-        if let Some(value) = leaf_page.get(key) {
-            return Ok(Some(value.to_vec()));
+        let mut leaf_page = self.read_leaf(leaf_page_id)?;
+        let removed = self.overflow_record(&leaf_page, key);
+        if !leaf_page.delete(key) {
+            return Ok(false);
         }
+        self.free_overflow_value(removed)?;
+
+        // Deleting from the middle of a run splits it, which can overfill
+        // a run-length leaf
+        if leaf_page.used_space() > self.usable_page_size() {
+            let (new_page_id, first_key) = self.split_leaf(leaf_page_id, leaf_page, &K::Encoded::from_ref(key))?;
+            self.insert_into_parent(path, new_page_id, first_key)?;
+            return Ok(true);
+        }
+        self.write_leaf(leaf_page_id, &leaf_page)?;
 
-        // Check if there are more leaf pages to search
-        let mut current_page_id = leaf_page_id;
-        while let Some(next_page_id) = self.store.get_next_page_id(current_page_id) {
-            current_page_id = next_page_id;
-            let page_bytes = self.store.get_page_bytes(current_page_id)?;
-            let page = LeafPage::deserialize(&page_bytes);
+        // An underfull leaf borrows from or merges with a sibling
+        if self.leaf_underflows(&leaf_page) {
+            self.rebalance_leaf(path, leaf_page_id)?;
+        }
 
-            if let Some(value) = page.get(key) {
-                return Ok(Some(value.to_vec()));
+        Ok(true)
+    }
+
+    /// Puts `value` at every key in `range`, as a single run per leaf the
+    /// range reaches rather than an entry per key. Runs already in the range
+    /// are clipped or split around it. Only trees whose keys are stored as
+    /// u64 can hold runs, and the value must fit in a leaf.
+    pub fn put_range<'k, R: RangeBounds<K::Ref<'k>>>(&mut self, range: R, value: &[u8]) -> Result<(), Box<dyn Error>> {
+        let lower = range.start_bound().map(|key| K::encode(*key));
+        let upper = range.end_bound().map(|key| K::encode(*key));
+        if self.preserving_pages() {
+            return self.atomically(|tree| tree.put_range_encoded(lower, upper, value));
+        }
+        self.put_range_encoded(lower, upper, value)
+    }
+
+    // Put a value at every key between bounds already in stored form
+    pub(crate) fn put_range_encoded(&mut self, lower: Bound<K::Encoded>, upper: Bound<K::Encoded>, value: &[u8]) -> Result<(), Box<dyn Error>> {
+        if !K::Encoded::supports_runs() {
+            return Err("Range puts need keys stored as u64".into());
+        }
+        let max_value_size = RLELeafPage::new_empty(self.usable_page_size()).max_value_size();
+        if value.len() > max_value_size {
+            return Err(format!("Value of {} bytes is too large for a run; at most {}", value.len(), max_value_size).into());
+        }
+
+        // Runs are stored with inclusive bounds
+        let run_key = |key: &K::Encoded| K::Encoded::run_key(key.to_ref()).unwrap();
+        let start = match &lower {
+            Bound::Included(key) => Some(run_key(key)),
+            Bound::Excluded(key) => run_key(key).checked_add(1),
+            Bound::Unbounded => Some(0),
+        };
+        let end = match &upper {
+            Bound::Included(key) => Some(run_key(key)),
+            Bound::Excluded(key) => run_key(key).checked_sub(1),
+            Bound::Unbounded => Some(u64::MAX),
+        };
+        match (start, end) {
+            (Some(start), Some(end)) if start <= end => {
+                // Clearing the range first merges away the leaves it covers,
+                // so the run is split over as few leaves as possible
+                let start = K::Encoded::from_run_key(start).unwrap();
+                let end = K::Encoded::from_run_key(end).unwrap();
+                self.update_range(Bound::Included(start.clone()), Bound::Included(end.clone()), None)?;
+                self.update_range(Bound::Included(start), Bound::Included(end), Some(value))?;
+                Ok(())
             }
+            _ => Ok(()),
         }
+    }
 
-        // Key not found in any leaf page
-        Ok(None)
+    /// Deletes every key in `range`, clipping or splitting the runs that
+    /// reach into it. Returns the number of keys deleted.
+    pub fn delete_range<'k, R: RangeBounds<K::Ref<'k>>>(&mut self, range: R) -> Result<u64, Box<dyn Error>> {
+        let lower = range.start_bound().map(|key| K::encode(*key));
+        let upper = range.end_bound().map(|key| K::encode(*key));
+        let empty = match (&lower, &upper) {
+            (Bound::Included(start), Bound::Included(end)) => start > end,
+            (Bound::Included(start) | Bound::Excluded(start), Bound::Included(end) | Bound::Excluded(end)) => start >= end,
+            _ => false,
+        };
+        if empty {
+            return Ok(0);
+        }
+        if self.preserving_pages() {
+            return self.atomically(|tree| tree.update_range(lower, upper, None));
+        }
+        self.update_range(lower, upper, None)
     }
 
+    /// Applies the operations in `batch` in order, all of them or none. If
+    /// one fails, including a page write failing part way through a split,
+    /// every page the batch wrote is put back as it was and the pages it
+    /// allocated are freed before the error is returned.
+    pub fn write(&mut self, batch: WriteBatch<K>) -> Result<(), Box<dyn Error>> {
+        self.atomically(|tree| batch.apply(tree))
+    }
 
+    // Run `operation` on a tree over an undo log of the pages it writes,
+    // which has live snapshots keep each page before it is first changed. If
+    // the operation fails, every page is put back as it was, the pages
+    // allocated are freed and the snapshots read the tree's pages again.
+    pub(crate) fn atomically<T, F>(&mut self, operation: F) -> Result<T, Box<dyn Error>>
+    where
+        F: FnOnce(&mut DataTree<CopyOnWritePageStore<'_, UndoPageStore<&mut S>>, K>) -> Result<T, Box<dyn Error>>,
+    {
+        self.reclaim_snapshot_pages()?;
+        let mut snapshots = self.snapshots.lock().unwrap();
+        let store = CopyOnWritePageStore::new(UndoPageStore::new(&mut self.store), &mut snapshots);
+        let mut tree = DataTree::<_, K>::at_root(store, self.root_page_id, self.in_superblock);
+        tree.config = self.config;
+        let result = operation(&mut tree);
+        let root_page_id = tree.root_page_id;
+
+        match result {
+            Ok(value) => {
+                self.root_page_id = root_page_id;
+                Ok(value)
+            }
+            Err(error) => {
+                let (undo, copies) = tree.into_store().into_parts();
+                match undo.roll_back() {
+                    Ok(()) => {
+                        snapshots.forget(&copies);
+                        Err(error)
+                    }
+                    Err(rollback_error) => Err(format!("{}; rolling back the write also failed: {}", error, rollback_error).into()),
+                }
+            }
+        }
+    }
 
-    /// Put a value with a u64 key
-    pub fn put(&mut self, key: u64, value: &[u8]) -> Result<(), Box<dyn Error>> {
-        // Check if value is too large for a page
-        let page = LeafPage::new_empty(self.store.page_size());
-        if page.is_value_too_large(value) {
-            return Err("Value too large for page size".into());
+    /// Starts a transaction on the tree. Its writes are buffered and reach
+    /// the store only when it commits.
+    pub fn begin(&mut self) -> Transaction<'_, S, K> {
+        let mut tree = DataTree::<_, K>::at_root(TransactionPageStore::new(&mut self.store), self.root_page_id, self.in_superblock);
+        tree.config = self.config;
+        Transaction::new(tree, &mut self.root_page_id, Arc::clone(&self.snapshots))
+    }
+
+    /// Pins the tree as it is now for reading through `view`, while writes
+    /// go on. Until the snapshot is dropped, the writer copies each page the
+    /// snapshot can see before changing or freeing it; the copies are freed
+    /// by the first write or flush after the last snapshot reading them is
+    /// dropped.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot::new(&self.snapshots, self.root_page_id)
+    }
+
+    /// Reads `snapshot` as the tree was when it was taken. The view borrows
+    /// the tree, so a scan that runs across writes takes a new view after
+    /// each write and resumes from the last key it read.
+    pub fn view(&self, snapshot: &Snapshot) -> Result<SnapshotView<'_, S, K>, Box<dyn Error>> {
+        if !snapshot.belongs_to(&self.snapshots) {
+            return Err("The snapshot was taken of another tree".into());
         }
+        Ok(SnapshotView::new(&self.store, snapshot))
+    }
 
-        // Start with the root page (which is a BranchPage)
-        let root_page_bytes = self.store.get_page_bytes(self.root_page_id)?;
+    // Whether writes must go through `atomically` to keep the pages live
+    // snapshots see, or to free the copies no snapshot reads any more
+    pub(crate) fn preserving_pages(&self) -> bool {
+        self.snapshots.lock().unwrap().preserving_pages()
+    }
 
-        // Check the page type
-        let page_type = PageType::from_u8(root_page_bytes[0]).unwrap_or(PageType::LeafPage);
+    // Free the page copies no live snapshot reads and the pages dropped
+    // transactions left allocated, and the copy list with the last of them
+    fn reclaim_snapshot_pages(&mut self) -> Result<(), Box<dyn Error>> {
+        let pages = self.snapshots.lock().unwrap().take_reclaimable();
+        for page_id in &pages {
+            self.store.free_page(*page_id)?;
+        }
+        if !pages.is_empty() && self.snapshots.lock().unwrap().copies().is_empty() {
+            self.update_copy_list()?;
+        }
+        Ok(())
+    }
 
-        // Guard clause: ensure root page is a BranchPage
-        if page_type != PageType::BranchPage {
-            // Root is not a BranchPage, which is unexpected
-            return Err("Root page is not a BranchPage".into());
+    // Clear the keys between `lower` and `upper` leaf by leaf and, for a range
+    // put, add a run of `value` over them to each leaf. Range puts pass
+    // inclusive bounds. Returns the number of keys cleared.
+    fn update_range(&mut self, mut lower: Bound<K::Encoded>, upper: Bound<K::Encoded>, value: Option<&[u8]>) -> Result<u64, Box<dyn Error>> {
+        let mut cleared = 0;
+        loop {
+            let key = match &lower {
+                Bound::Included(key) | Bound::Excluded(key) => key.clone(),
+                Bound::Unbounded => K::Encoded::min_key(),
+            };
+            let (path, page_id) = self.find_leaf(key.to_ref())?;
+            let leaf_page = self.read_leaf(page_id)?;
+
+            // Keys from the next leaf's first key on belong to later leaves
+            let next_first_key = Self::next_first_key(&path, page_id);
+
+            let mut runs = Vec::new();
+            let mut removed = Vec::new();
+            for run in leaf_page.runs() {
+                let (below, above) = run.clip(lower.as_ref(), upper.as_ref());
+                let kept = below.iter().chain(above.iter()).map(LeafRun::key_count).sum::<u64>();
+                cleared += run.key_count() - kept;
+                if below.is_none() && above.is_none() && run.overflow {
+                    removed.push(run.bytes.clone());
+                }
+                runs.extend(below);
+                runs.extend(above);
+            }
+
+            if let (Some(value), Bound::Included(end)) = (value, &upper) {
+                // This leaf's part of the run stops before the next leaf's keys
+                let end = K::Encoded::run_key(end.to_ref()).unwrap();
+                let end = match &next_first_key {
+                    Some(first_key) => end.min(K::Encoded::run_key(first_key.to_ref()).unwrap() - 1),
+                    None => end,
+                };
+                let run = LeafRun { start: key.clone(), end: K::Encoded::from_run_key(end).unwrap(), bytes: value.to_vec(), overflow: false };
+                Self::insert_run(&mut runs, run);
+            }
+
+            if cleared > 0 || value.is_some() {
+                self.write_runs(path, page_id, &leaf_page, &runs)?;
+            }
+            for record in removed {
+                self.free_overflow_value(Some(record))?;
+            }
+
+            // Go on to the next leaf if the range reaches it
+            let next_first_key = match next_first_key {
+                Some(first_key) => first_key,
+                None => return Ok(cleared),
+            };
+            let reaches = match &upper {
+                Bound::Included(end) => next_first_key <= *end,
+                Bound::Excluded(end) => next_first_key < *end,
+                Bound::Unbounded => true,
+            };
+            if !reaches {
+                return Ok(cleared);
+            }
+            lower = Bound::Included(next_first_key);
+        }
+    }
+
+    // Insert a run into runs in key order that don't overlap it, joining it
+    // with the runs on either side when they hold the same value
+    fn insert_run(runs: &mut Vec<LeafRun<K::Encoded>>, mut run: LeafRun<K::Encoded>) {
+        let mut pos = runs.partition_point(|other| other.end < run.start);
+        if pos < runs.len() && run.joins(&runs[pos]) {
+            run.end = runs.remove(pos).end;
         }
+        if pos > 0 && runs[pos - 1].joins(&run) {
+            pos -= 1;
+            run.start = runs.remove(pos).start;
+        }
+        runs.insert(pos, run);
+    }
 
-        // It's a branch page, find the appropriate leaf page
-        let branch_page = BranchPage::deserialize(&root_page_bytes);
+    // The first key of the leaf after `page_id`, as routed by the branches
+    // on `path`. None for the last leaf.
+    fn next_first_key(path: &BranchPath<K::Encoded>, page_id: u64) -> Option<K::Encoded> {
+        let mut child_page_id = page_id;
+        for (branch_page_id, branch_page) in path.iter().rev() {
+            let entries = branch_page.entries();
+            let pos = entries.iter().position(|entry| entry.page_id == child_page_id)?;
+            if let Some(next) = entries.get(pos + 1) {
+                return Some(next.first_key.clone());
+            }
+            child_page_id = *branch_page_id;
+        }
+        None
+    }
 
-        // Find the leaf page ID using the branch page
-        let leaf_page_id = match branch_page.find_page_id(key) {
-            Some(id) => id,
-            None => {
-                // This should not happen with our implementation, but handle it anyway
-                return Err("Could not find a leaf page for the key".into());
+    // Replace the contents of a leaf with `runs`. Runs that no longer fit in
+    // one leaf are packed into new leaves linked in after it; a leaf left
+    // underfull is rebalanced.
+    fn write_runs(&mut self, path: BranchPath<K::Encoded>, page_id: u64, leaf_page: &Leaf<K::Encoded>, runs: &[LeafRun<K::Encoded>]) -> Result<(), Box<dyn Error>> {
+        if let Some(new_page) = self.build_leaf(leaf_page.prev_page_id(), leaf_page.next_page_id(), runs) {
+            self.write_leaf(page_id, &new_page)?;
+            if self.leaf_underflows(&new_page) {
+                self.rebalance_leaf(path, page_id)?;
             }
-        };
+            return Ok(());
+        }
 
-        // Now try to insert into the leaf page
-        let mut current_page_id = leaf_page_id;
-        loop {
-            let page_bytes = self.store.get_page_bytes(current_page_id)?;
-            let mut page = LeafPage::deserialize(&page_bytes);
+        let mut new_pages = self.pack_runs(runs)?;
+        let mut page_ids = vec![page_id];
+        page_ids.extend((1..new_pages.len()).map(|_| self.store.allocate_page()));
+        let next_page_id = leaf_page.next_page_id();
+        for (i, new_page) in new_pages.iter_mut().enumerate() {
+            new_page.set_prev_page_id(if i == 0 { leaf_page.prev_page_id() } else { page_ids[i - 1] });
+            new_page.set_next_page_id(page_ids.get(i + 1).copied().unwrap_or(next_page_id));
+            self.write_leaf(page_ids[i], new_page)?;
+        }
+        if next_page_id != 0 {
+            let mut next_page = self.read_leaf(next_page_id)?;
+            next_page.set_prev_page_id(*page_ids.last().unwrap());
+            self.write_leaf(next_page_id, &next_page)?;
+        }
 
-            // Try to insert into this page
-            if page.put(key, value) {
-                // Successfully inserted, update the page
-                self.store.put_page_bytes(current_page_id, &page.serialize())?;
-                // Page is automatically marked as dirty in put_page_bytes
-                return Ok(());
+        // Register each new leaf with the branch that routes its keys, which
+        // changes as branches split
+        for i in 1..new_pages.len() {
+            let left_key = new_pages[i - 1].last_key().unwrap();
+            let first_key = K::Encoded::separator(&left_key, &new_pages[i].first_key().unwrap());
+            let (path, _) = self.find_leaf(first_key.to_ref())?;
+            self.insert_into_parent(path, page_ids[i], first_key)?;
+        }
+        Ok(())
+    }
+
+    // Pack runs in key order into as few leaves as hold them, each in
+    // whichever encoding takes the most runs
+    fn pack_runs(&self, mut runs: &[LeafRun<K::Encoded>]) -> Result<Vec<Leaf<K::Encoded>>, Box<dyn Error>> {
+        let mut leaves = Vec::new();
+        while !runs.is_empty() {
+            let count = [LeafEncoding::Plain, LeafEncoding::RunLength].iter()
+                .map(|encoding| {
+                    let mut leaf_page = Leaf::new_empty(*encoding, self.usable_page_size());
+                    runs.iter().take_while(|run| leaf_page.push_run(run)).count()
+                })
+                .max()
+                .unwrap();
+            let leaf_page = match self.build_leaf(0, 0, &runs[..count]) {
+                Some(leaf_page) if count > 0 => leaf_page,
+                _ => return Err("Run does not fit in a leaf".into()),
+            };
+            leaves.push(leaf_page);
+            runs = &runs[count..];
+        }
+        Ok(leaves)
+    }
+
+    /// Counts the tree's pages by type, walking it from the root, along with
+    /// the bytes its run-length leaves save
+    pub fn page_stats(&self) -> Result<PageStats, Box<dyn Error>> {
+        let chunk_size = LeafPage::empty(self.usable_page_size()).get_value_chunk_size() as u64;
+        let mut stats = PageStats::default();
+        let mut page_ids = vec![self.root_page_id];
+        while let Some(page_id) = page_ids.pop() {
+            let page_bytes = self.store.get_page_bytes(page_id)?;
+            if PageType::from_u8(page_bytes[0]) == Some(K::Encoded::BRANCH_PAGE_TYPE) {
+                stats.branch_pages += 1;
+                let branch_page = KeyedBranchPage::<K::Encoded>::deserialize(&page_bytes);
+                page_ids.extend(branch_page.entries().iter().map(|entry| entry.page_id));
+                continue;
             }
 
-            // Page is full, check if there's a next page
-            if let Some(next_page_id) = self.store.get_next_page_id(current_page_id) {
-                current_page_id = next_page_id;
+            let leaf_page = Leaf::<K::Encoded>::deserialize(&page_bytes)?;
+            match leaf_page.encoding() {
+                LeafEncoding::RunLength => {
+                    stats.run_length_leaf_pages += 1;
+                    let plain_size = i64::try_from(leaf_page.plain_size()).unwrap_or(i64::MAX);
+                    stats.bytes_saved = stats.bytes_saved.saturating_add(plain_size - leaf_page.used_space() as i64);
+                }
+                LeafEncoding::Slotted => stats.slotted_leaf_pages += 1,
+                _ => stats.plain_leaf_pages += 1,
+            }
+
+            // Overflow chains are counted from their values' lengths rather
+            // than read
+            for run in leaf_page.runs().iter().filter(|run| run.overflow) {
+                let pointer = OverflowPointer::deserialize(&run.bytes)?;
+                stats.overflow_pages += pointer.value_length.div_ceil(chunk_size) as usize;
+            }
+        }
+        Ok(stats)
+    }
+
+    /// Frees every page of the tree, overflow pages included, walking it
+    /// from the root
+    pub(crate) fn destroy(mut self) -> Result<(), Box<dyn Error>> {
+        let mut page_ids = vec![self.root_page_id];
+        while let Some(page_id) = page_ids.pop() {
+            let page_bytes = self.store.get_page_bytes(page_id)?;
+            if PageType::from_u8(page_bytes[0]) == Some(K::Encoded::BRANCH_PAGE_TYPE) {
+                let branch_page = KeyedBranchPage::<K::Encoded>::deserialize(&page_bytes);
+                page_ids.extend(branch_page.entries().iter().map(|entry| entry.page_id));
             } else {
-                // No next page, create a new one
-                let new_page_id = self.store.allocate_page();
-                let mut new_page = LeafPage::new_empty(self.store.page_size());
+                let leaf_page = Leaf::<K::Encoded>::deserialize(&page_bytes)?;
+                for run in leaf_page.runs().into_iter().filter(|run| run.overflow) {
+                    self.free_overflow_value(Some(run.bytes))?;
+                }
+            }
+            self.store.free_page(page_id)?;
+        }
+        Ok(())
+    }
 
-                // Update the current page to point to the new page
-                page.set_next_page_id(new_page_id);
-                self.store.put_page_bytes(current_page_id, &page.serialize())?;
-                // Page is automatically marked as dirty in put_page_bytes
+    /// Iterates over the entries with keys in `range`, in key order.
+    /// The iterator can also be consumed from the back.
+    pub fn range<'k, R: RangeBounds<K::Ref<'k>>>(&self, range: R) -> Range<'_, S, K> {
+        let lower = range.start_bound().map(|key| K::encode(*key));
+        let upper = range.end_bound().map(|key| K::encode(*key));
+        Range::new(self, lower, upper)
+    }
+
+    /// Iterates over the runs of keys in `range` holding the same value, as
+    /// `(start, end, value)` in key order, without visiting their keys one by
+    /// one. Runs are clipped to the range.
+    pub fn runs<'k, R: RangeBounds<K::Ref<'k>>>(&self, range: R) -> Runs<'_, S, K> {
+        let lower = range.start_bound().map(|key| K::encode(*key));
+        let upper = range.end_bound().map(|key| K::encode(*key));
+        Runs::new(self, lower, upper)
+    }
 
-                // Update the new page to point back to the current page
-                new_page.set_prev_page_id(current_page_id);
+    /// Returns the whole run holding `key`, as `(start, end, value)`, or None
+    /// if the key isn't in the tree. The run extends as far as consecutive
+    /// keys hold the same value, across leaves.
+    pub fn run_containing(&self, key: K::Ref<'_>) -> Result<Option<KeyRun<K>>, Box<dyn Error>> {
+        let key = K::encode(key);
+        let (_, page_id) = self.find_leaf(key.to_ref())?;
+        let leaf_page = self.read_leaf(page_id)?;
+        let mut before = leaf_page.runs();
+        let pos = before.partition_point(|run| run.end < key);
+        let run = match before.get(pos) {
+            Some(run) if run.start <= key => run.clone(),
+            _ => return Ok(None),
+        };
+        let mut run = self.read_run_value(run)?;
+        let mut after: Vec<_> = before.split_off(pos).into_iter().skip(1).rev().collect();
 
-                // Insert the key-value pair into the new page
-                if !new_page.put(key, value) {
-                    return Err("Failed to insert into new page".into());
+        // Join the runs before it, from this leaf and then the ones before
+        // it, as long as they continue it
+        let mut prev_page_id = leaf_page.prev_page_id();
+        loop {
+            let prev = match before.pop() {
+                Some(prev) => self.read_run_value(prev)?,
+                None if prev_page_id != 0 => {
+                    let prev_page = self.read_leaf(prev_page_id)?;
+                    prev_page_id = prev_page.prev_page_id();
+                    before = prev_page.runs();
+                    continue;
                 }
+                None => break,
+            };
+            if !prev.joins(&run) {
+                break;
+            }
+            run.start = prev.start;
+        }
 
-                // Save the new page
-                self.store.put_page_bytes(new_page_id, &new_page.serialize())?;
-                // Page is automatically marked as dirty in put_page_bytes
-                return Ok(());
+        // And the runs after it the same way
+        let mut next_page_id = leaf_page.next_page_id();
+        loop {
+            let next = match after.pop() {
+                Some(next) => self.read_run_value(next)?,
+                None if next_page_id != 0 => {
+                    let next_page = self.read_leaf(next_page_id)?;
+                    next_page_id = next_page.next_page_id();
+                    after = next_page.runs().into_iter().rev().collect();
+                    continue;
+                }
+                None => break,
+            };
+            if !run.joins(&next) {
+                break;
             }
+            run.end = next.end;
         }
+
+        Ok(Some((K::decode(&run.start), K::decode(&run.end), run.bytes)))
     }
 
+    /// Iterates over all entries in key order
+    pub fn iter(&self) -> Range<'_, S, K> {
+        Range::new(self, Bound::Unbounded, Bound::Unbounded)
+    }
 
+    /// Iterates over all keys in order
+    pub fn keys(&self) -> impl DoubleEndedIterator<Item = K> + '_ {
+        self.iter().map(|(key, _)| key)
+    }
 
-    /// Delete a value by its u64 key
-    pub fn delete(&mut self, key: u64) -> Result<bool, Box<dyn Error>> {
-        // Start with the root page (which is a BranchPage)
-        let root_page_bytes = self.store.get_page_bytes(self.root_page_id)?;
+    /// Iterates over all values in key order
+    pub fn values(&self) -> impl DoubleEndedIterator<Item = Vec<u8>> + '_ {
+        self.iter().map(|(_, value)| value)
+    }
 
-        // Check the page type
-        let page_type = PageType::from_u8(root_page_bytes[0]).unwrap_or(PageType::LeafPage);
+    /// Returns a cursor over the tree. It is not on an entry until one of
+    /// its seek methods is called.
+    pub fn cursor(&mut self) -> Cursor<'_, S, K> {
+        Cursor::new(self)
+    }
+
+    // Space available to a page's serialized bytes once the store adds its CRC
+    pub(crate) fn usable_page_size(&self) -> usize {
+        self.store.page_size() - CRC_SIZE
+    }
+
+    // Read the root page, which must be a BranchPage for K keys
+    fn read_root_branch(&self) -> Result<KeyedBranchPage<K::Encoded>, Box<dyn Error>> {
+        let root_page_bytes = self.store.get_page_bytes(self.root_page_id)?;
 
         // Guard clause: ensure root page is a BranchPage
-        if page_type != PageType::BranchPage {
+        let page_type = PageType::from_u8(root_page_bytes[0]).unwrap_or(PageType::LeafPage);
+        if page_type != K::Encoded::BRANCH_PAGE_TYPE {
             // Root is not a BranchPage, which is unexpected
             return Err("Root page is not a BranchPage".into());
         }
 
-        // It's a branch page, find the appropriate leaf page
-        let branch_page = BranchPage::deserialize(&root_page_bytes);
+        let mut branch_page = KeyedBranchPage::deserialize(&root_page_bytes);
+        branch_page.page_size = self.usable_page_size();
+        Ok(branch_page)
+    }
 
-        // Find the leaf page ID using the branch page
-        let leaf_page_id = match branch_page.find_page_id(key) {
-            Some(id) => id,
-            None => {
-                // This should not happen with our implementation, but handle it anyway
-                return Ok(false);
+    // Descend from the root to the leaf that owns `key`. Returns the branch
+    // pages on the way down, root first, along with the leaf's page ID.
+    pub(crate) fn find_leaf(&self, key: EncodedRef<'_, K>) -> Result<LeafPath<K::Encoded>, Box<dyn Error>> {
+        self.descend(|branch_page| branch_page.find_page_id(key))
+    }
+
+    // Descend from the root to the last leaf
+    pub(crate) fn find_last_leaf(&self) -> Result<u64, Box<dyn Error>> {
+        let (_, page_id) = self.descend(|branch_page| branch_page.entries().last().map(|entry| entry.page_id))?;
+        Ok(page_id)
+    }
+
+    // Descend from the root, choosing the child to follow in each branch
+    // with `choose`, until a leaf is reached
+    fn descend<F>(&self, choose: F) -> Result<LeafPath<K::Encoded>, Box<dyn Error>>
+    where
+        F: Fn(&KeyedBranchPage<K::Encoded>) -> Option<u64>,
+    {
+        let mut path = vec![(self.root_page_id, self.read_root_branch()?)];
+
+        loop {
+            let (_, branch_page) = path.last().unwrap();
+            let child_page_id = match choose(branch_page) {
+                Some(id) => id,
+                None => {
+                    // This should not happen with our implementation, but handle it anyway
+                    return Err("Could not find a leaf page for the key".into());
+                }
+            };
+
+            let child_bytes = self.store.get_page_bytes(child_page_id)?;
+            let child_type = PageType::from_u8(child_bytes[0]);
+            if child_type == Some(K::Encoded::BRANCH_PAGE_TYPE) {
+                let mut child_page = KeyedBranchPage::deserialize(&child_bytes);
+                child_page.page_size = self.usable_page_size();
+                path.push((child_page_id, child_page));
+            } else if Leaf::<K::Encoded>::is_leaf_page_type(child_type) {
+                return Ok((path, child_page_id));
+            } else {
+                return Err(format!("Page {} is neither a branch nor a leaf", child_page_id).into());
+            }
+        }
+    }
+
+    // Register a new page in the parent at the end of `path`. A full parent is
+    // split and its upper half is registered one level up in turn; when the
+    // root itself splits the tree grows a new root above it.
+    fn insert_into_parent(&mut self, mut path: BranchPath<K::Encoded>, mut page_id: u64, mut first_key: K::Encoded) -> Result<(), Box<dyn Error>> {
+        while let Some((branch_page_id, mut branch_page)) = path.pop() {
+            if branch_page.insert(page_id, first_key.clone()) {
+                return self.store.put_page_bytes(branch_page_id, &branch_page.serialize());
             }
+
+            // Split the branch and insert the entry into whichever half owns it
+            let mut new_branch_page = match branch_page.split() {
+                Some(page) => page,
+                None => return Err("Page size too small for a branch page".into()),
+            };
+            if first_key >= new_branch_page.entries()[0].first_key {
+                new_branch_page.insert(page_id, first_key);
+            } else {
+                branch_page.insert(page_id, first_key);
+            }
+
+            let new_branch_page_id = self.store.allocate_page();
+            self.store.put_page_bytes(branch_page_id, &branch_page.serialize())?;
+            self.store.put_page_bytes(new_branch_page_id, &new_branch_page.serialize())?;
+
+            // The new branch's first key separates it from its left sibling
+            page_id = new_branch_page_id;
+            first_key = new_branch_page.entries()[0].first_key.clone();
+        }
+
+        // The root split: the old root becomes the first child of a new root,
+        // keeping its lower bound
+        let old_root = self.read_root_branch()?;
+        let mut new_root = KeyedBranchPage::new_empty(self.usable_page_size());
+        new_root.insert(self.root_page_id, old_root.entries()[0].first_key.clone());
+        new_root.insert(page_id, first_key);
+
+        let new_root_page_id = self.store.allocate_page();
+        self.store.put_page_bytes(new_root_page_id, &new_root.serialize())?;
+        self.root_page_id = new_root_page_id;
+        self.write_superblock()
+    }
+
+    // Smallest number of bytes a non-root page may use before it is rebalanced
+    fn min_fill_bytes(&self) -> usize {
+        (self.usable_page_size() as f64 * self.config.min_fill) as usize
+    }
+
+    // Whether a leaf is empty or below the minimum fill after a delete
+    pub(crate) fn leaf_underflows(&self, leaf_page: &Leaf<K::Encoded>) -> bool {
+        leaf_page.is_empty() || leaf_page.used_space() < self.min_fill_bytes()
+    }
+
+    // Pair an underfull leaf with a sibling under the same parent. The two are
+    // merged into the left one if their entries fit in one page, otherwise the
+    // entries are split evenly between them. The parent is rebalanced in turn.
+    pub(crate) fn rebalance_leaf(&mut self, mut path: BranchPath<K::Encoded>, page_id: u64) -> Result<(), Box<dyn Error>> {
+        let (parent_page_id, mut parent_page) = path.pop().unwrap();
+
+        // The only leaf under the root has no sibling to pair with
+        let (left_page_id, right_page_id) = match Self::sibling_pair(&parent_page, page_id) {
+            Some(pair) => pair,
+            None => return Ok(()),
         };
+        let left_page = self.read_leaf(left_page_id)?;
+        let right_page = self.read_leaf(right_page_id)?;
+
+        // Keys in the left page are all below those in the right one. Sizes
+        // are estimated for a run-length leaf if any run needs one.
+        let mut runs = left_page.runs();
+        runs.extend(right_page.runs());
+        let encoding = if runs.iter().any(|run| run.is_wide()) { LeafEncoding::RunLength } else { self.config.leaf_encoding };
+        let sizes: Vec<usize> = runs.iter().map(|run| Leaf::run_size(run, encoding)).collect();
+        let capacity = self.usable_page_size() - Leaf::<K::Encoded>::header_size(encoding);
+
+        // The estimate is for one encoding, but the runs are merged when they
+        // fit a page of any encoding build_leaf picks
+        if let Some(merged_page) = self.build_leaf(left_page.prev_page_id(), right_page.next_page_id(), &runs) {
+            // Merge the right page into the left one and unlink it
+            let next_page_id = right_page.next_page_id();
+            self.write_leaf(left_page_id, &merged_page)?;
+            if next_page_id != 0 {
+                let mut next_page = self.read_leaf(next_page_id)?;
+                next_page.set_prev_page_id(left_page_id);
+                self.write_leaf(next_page_id, &next_page)?;
+            }
 
-        // Now try to delete from the leaf page
-        let mut current_page_id = leaf_page_id;
-        loop {
-            let page_bytes = self.store.get_page_bytes(current_page_id)?;
-            let mut page = LeafPage::deserialize(&page_bytes);
+            parent_page.remove(right_page_id);
+            self.store.free_page(right_page_id)?;
+        } else if runs.len() < 2 {
+            // A single run that doesn't fit the estimate has nothing to
+            // spread; it stays where it is
+            return Ok(());
+        } else {
+            let split_point = Self::balanced_split_point(&sizes, capacity);
+            let (left_runs, right_runs) = runs.split_at(split_point);
+            let new_left_page = self.build_leaf(left_page.prev_page_id(), right_page_id, left_runs);
+            let new_right_page = self.build_leaf(left_page_id, right_page.next_page_id(), right_runs);
+            let (new_left_page, new_right_page) = match (new_left_page, new_right_page) {
+                (Some(left), Some(right)) => (left, right),
+                // Runs that need different encodings can't always be spread
+                // over two pages; they stay as they are
+                _ => return Ok(()),
+            };
+            self.write_leaf(left_page_id, &new_left_page)?;
+            self.write_leaf(right_page_id, &new_right_page)?;
+            let separator = K::Encoded::separator(&left_runs.last().unwrap().end, &right_runs[0].start);
+            parent_page.set_first_key(right_page_id, separator);
+        }
 
-            if page.delete(key) {
-                self.store.put_page_bytes(current_page_id, &page.serialize())?;
-                // Page is automatically marked as dirty in put_page_bytes
+        self.update_parent(path, parent_page_id, parent_page)
+    }
 
-                // Check if page is empty and not root
-                if page.metadata().is_empty() && current_page_id != self.root_page_id {
-                    // Get previous and next page IDs
-                    let prev_page_id = page.prev_page_id();
-                    let next_page_id = page.next_page_id();
-
-                    // Update links
-                    if prev_page_id != 0 {
-                        let prev_bytes = self.store.get_page_bytes(prev_page_id)?;
-                        let mut prev_page = LeafPage::deserialize(&prev_bytes);
-                        prev_page.set_next_page_id(next_page_id);
-                        self.store.put_page_bytes(prev_page_id, &prev_page.serialize())?;
-                        // Page is automatically marked as dirty in put_page_bytes
-                    }
+    // Rebalance a branch whose entries changed, the same way as a leaf. When
+    // it is the root and is left with a single child branch, that child
+    // becomes the new root and the tree shrinks by one level.
+    fn rebalance_branch(&mut self, mut path: BranchPath<K::Encoded>, page_id: u64, branch_page: KeyedBranchPage<K::Encoded>) -> Result<(), Box<dyn Error>> {
+        let (parent_page_id, mut parent_page) = match path.pop() {
+            Some(parent) => parent,
+            None => return self.collapse_root(branch_page),
+        };
+        if branch_page.entries().len() >= 2 && branch_page.used_space() >= self.min_fill_bytes() {
+            return Ok(());
+        }
 
-                    if next_page_id != 0 {
-                        let next_bytes = self.store.get_page_bytes(next_page_id)?;
-                        let mut next_page = LeafPage::deserialize(&next_bytes);
-                        next_page.set_prev_page_id(prev_page_id);
-                        self.store.put_page_bytes(next_page_id, &next_page.serialize())?;
-                        // Page is automatically marked as dirty in put_page_bytes
-                    }
+        // Branches are always paired with a sibling unless the parent is a
+        // root with a single child, which collapsing removes
+        let (left_page_id, right_page_id) = match Self::sibling_pair(&parent_page, page_id) {
+            Some(pair) => pair,
+            None => return self.rebalance_branch(path, parent_page_id, parent_page),
+        };
+        let mut left_page = self.read_branch(left_page_id)?;
+        let mut right_page = self.read_branch(right_page_id)?;
+
+        let mut entries = std::mem::take(&mut left_page.entries);
+        entries.append(&mut right_page.entries);
+
+        let sizes: Vec<usize> = entries.iter().map(|entry| entry.size()).collect();
+        let capacity = self.usable_page_size() - KeyedBranchPage::<K::Encoded>::HEADER_SIZE;
+        if sizes.iter().sum::<usize>() <= capacity {
+            // Merge the right page into the left one
+            left_page.entries = entries;
+            self.store.put_page_bytes(left_page_id, &left_page.serialize())?;
+            parent_page.remove(right_page_id);
+            self.store.free_page(right_page_id)?;
+        } else {
+            right_page.entries = entries.split_off(Self::balanced_split_point(&sizes, capacity));
+            left_page.entries = entries;
+            self.store.put_page_bytes(left_page_id, &left_page.serialize())?;
+            self.store.put_page_bytes(right_page_id, &right_page.serialize())?;
+            parent_page.set_first_key(right_page_id, right_page.entries()[0].first_key.clone());
+        }
 
-                    // Free the empty page
-                    self.store.free_page(current_page_id)?;
-                    // Page is automatically removed from dirty pages in free_page
-                }
+        self.update_parent(path, parent_page_id, parent_page)
+    }
 
-                return Ok(true);
+    // Write a parent whose entries changed while rebalancing its children and
+    // rebalance it in turn. A longer separator key can leave it too large for
+    // its page, in which case it is split as if it had gained an entry.
+    fn update_parent(&mut self, path: BranchPath<K::Encoded>, page_id: u64, mut branch_page: KeyedBranchPage<K::Encoded>) -> Result<(), Box<dyn Error>> {
+        if branch_page.used_space() <= self.usable_page_size() {
+            self.store.put_page_bytes(page_id, &branch_page.serialize())?;
+            return self.rebalance_branch(path, page_id, branch_page);
+        }
+
+        let new_branch_page = match branch_page.split() {
+            Some(page) => page,
+            None => return Err("Page size too small for a branch page".into()),
+        };
+        let new_branch_page_id = self.store.allocate_page();
+        self.store.put_page_bytes(page_id, &branch_page.serialize())?;
+        self.store.put_page_bytes(new_branch_page_id, &new_branch_page.serialize())?;
+        self.insert_into_parent(path, new_branch_page_id, new_branch_page.entries()[0].first_key.clone())
+    }
+
+    // Replace a root that has a single child branch with that child
+    fn collapse_root(&mut self, mut root_page: KeyedBranchPage<K::Encoded>) -> Result<(), Box<dyn Error>> {
+        let mut collapsed = false;
+        while root_page.entries().len() == 1 {
+            let child_page_id = root_page.entries()[0].page_id;
+            let child_bytes = self.store.get_page_bytes(child_page_id)?;
+            if PageType::from_u8(child_bytes[0]) != Some(K::Encoded::BRANCH_PAGE_TYPE) {
+                break;
             }
 
-            if let Some(next_page_id) = self.store.get_next_page_id(current_page_id) {
-                current_page_id = next_page_id;
-            } else {
-                return Ok(false);
+            self.store.free_page(self.root_page_id)?;
+            self.root_page_id = child_page_id;
+            root_page = self.read_branch(child_page_id)?;
+            collapsed = true;
+        }
+
+        if collapsed {
+            self.write_superblock()?;
+        }
+        Ok(())
+    }
+
+    // The IDs of `page_id` and its right sibling in `parent_page`, or its left
+    // sibling if it is the last child. None if it is the only child.
+    fn sibling_pair(parent_page: &KeyedBranchPage<K::Encoded>, page_id: u64) -> Option<(u64, u64)> {
+        let entries = parent_page.entries();
+        let pos = entries.iter().position(|e| e.page_id == page_id)?;
+        if pos + 1 < entries.len() {
+            Some((page_id, entries[pos + 1].page_id))
+        } else if pos > 0 {
+            Some((entries[pos - 1].page_id, page_id))
+        } else {
+            None
+        }
+    }
+
+    // The index that divides entries of the given sizes most evenly while
+    // keeping both halves within `capacity`
+    fn balanced_split_point(sizes: &[usize], capacity: usize) -> usize {
+        let total: usize = sizes.iter().sum();
+        let mut best = (usize::MAX, sizes.len() / 2);
+        let mut left = 0;
+        for (i, size) in sizes.iter().enumerate().take(sizes.len() - 1) {
+            left += size;
+            let right = total - left;
+            if left <= capacity && right <= capacity && left.abs_diff(right) < best.0 {
+                best = (left.abs_diff(right), i + 1);
             }
         }
+        best.1
     }
 
+    // Build a leaf holding `runs`, in the configured encoding or else a
+    // plain or run-length one. None if they fit in none. Adaptive trees start
+    // from a plain leaf and pick the smaller encoding when it is written.
+    fn build_leaf(&self, prev_page_id: u64, next_page_id: u64, runs: &[LeafRun<K::Encoded>]) -> Option<Leaf<K::Encoded>> {
+        let encoding = match self.config.leaf_encoding {
+            LeafEncoding::Adaptive => LeafEncoding::Plain,
+            encoding => encoding,
+        };
+        let mut leaf_page = [encoding, LeafEncoding::Plain, LeafEncoding::RunLength].into_iter()
+            .find_map(|encoding| Leaf::from_runs(encoding, self.usable_page_size(), runs))?;
+        leaf_page.set_prev_page_id(prev_page_id);
+        leaf_page.set_next_page_id(next_page_id);
+        Some(leaf_page)
+    }
 
+    // Write a value too large for a leaf to a chain of overflow pages, each
+    // holding one chunk, and return the pointer record for its leaf entry
+    fn write_overflow_value(store: &mut S, value: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+        let chunk_size = LeafPage::empty(store.page_size() - CRC_SIZE).get_value_chunk_size();
+        let chunks: Vec<&[u8]> = value.chunks(chunk_size).collect();
+        let page_ids: Vec<u64> = chunks.iter().map(|_| store.allocate_page()).collect();
 
-    /// Convert a byte array to a u64
-    pub fn bytes_to_u64(key: &[u8]) -> u64 {
-        if key.len() >= 8 {
-            u64::from_le_bytes(key[0..8].try_into().unwrap())
-        } else {
-            // Pad with zeros if key is shorter than 8 bytes
-            let mut padded = [0u8; 8];
-            for (i, &b) in key.iter().enumerate() {
-                padded[i] = b;
+        for (i, chunk) in chunks.iter().enumerate() {
+            let next_page_id = page_ids.get(i + 1).copied().unwrap_or(0);
+            store.put_page_bytes(page_ids[i], &OverflowPage::new(chunk, next_page_id).serialize())?;
+        }
+
+        let pointer = OverflowPointer {
+            first_page_id: page_ids[0],
+            value_length: value.len() as u64,
+        };
+        Ok(pointer.serialize().to_vec())
+    }
+
+    // Reassemble a value from the overflow pages its pointer record names
+    pub(crate) fn read_overflow_value(&self, record: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+        let pointer = OverflowPointer::deserialize(record)?;
+
+        // Walk the chain before trusting the declared length, so a damaged
+        // record or chain fails here instead of sizing a huge allocation
+        let chunk_size = LeafPage::empty(self.store.page_size() - CRC_SIZE).get_value_chunk_size();
+        let page_count = pointer.value_length.div_ceil(chunk_size as u64);
+        let mut chunks = Vec::new();
+        let mut page_id = pointer.first_page_id;
+        while page_id != 0 {
+            if chunks.len() as u64 >= page_count {
+                return Err(format!("Overflow chain is longer than the {} pages a {} byte value takes", page_count, pointer.value_length).into());
             }
-            u64::from_le_bytes(padded)
+            let page = OverflowPage::deserialize(&self.store.get_page_bytes(page_id)?)?;
+            page_id = page.next_page_id;
+            chunks.push(page.data);
+        }
+
+        let value = chunks.concat();
+        if value.len() as u64 != pointer.value_length {
+            return Err(format!("Overflow chain holds {} bytes, expected {}", value.len(), pointer.value_length).into());
         }
+        Ok(value)
     }
 
-    /// Convert a u64 to a byte array
-    pub fn u64_to_bytes(key: u64) -> [u8; 8] {
-        key.to_le_bytes()
+    // A run with its value read from its overflow pages if it has them, so
+    // that runs can be compared by value
+    pub(crate) fn read_run_value(&self, run: LeafRun<K::Encoded>) -> Result<LeafRun<K::Encoded>, Box<dyn Error>> {
+        if !run.overflow {
+            return Ok(run);
+        }
+        let bytes = self.read_overflow_value(&run.bytes)?;
+        Ok(LeafRun { bytes, overflow: false, ..run })
+    }
+
+    // The overflow pointer record stored for `key`, if its value is in overflow pages
+    pub(crate) fn overflow_record(&self, leaf_page: &Leaf<K::Encoded>, key: EncodedRef<'_, K>) -> Option<Vec<u8>> {
+        if leaf_page.is_overflow(key) {
+            leaf_page.get(key).map(|record| record.to_vec())
+        } else {
+            None
+        }
+    }
+
+    // Free the overflow pages of a value that was replaced or deleted
+    pub(crate) fn free_overflow_value(&mut self, record: Option<Vec<u8>>) -> Result<(), Box<dyn Error>> {
+        let record = match record {
+            Some(record) => record,
+            None => return Ok(()),
+        };
+
+        let mut page_id = OverflowPointer::deserialize(&record)?.first_page_id;
+        while page_id != 0 {
+            let page = OverflowPage::deserialize(&self.store.get_page_bytes(page_id)?)?;
+            self.store.free_page(page_id)?;
+            page_id = page.next_page_id;
+        }
+        Ok(())
+    }
+
+    fn read_branch(&self, page_id: u64) -> Result<KeyedBranchPage<K::Encoded>, Box<dyn Error>> {
+        let page_bytes = self.store.get_page_bytes(page_id)?;
+        let mut branch_page = KeyedBranchPage::deserialize(&page_bytes);
+        branch_page.page_size = self.usable_page_size();
+        Ok(branch_page)
     }
 
+    // Read a leaf page of either encoding. Deserialization sizes the page to
+    // its serialized bytes, so restore the real capacity before the page is
+    // modified.
+    pub(crate) fn read_leaf(&self, page_id: u64) -> Result<Leaf<K::Encoded>, Box<dyn Error>> {
+        let page_bytes = self.store.get_page_bytes(page_id)?;
+        let mut leaf_page = Leaf::deserialize(&page_bytes)?;
+        leaf_page.set_page_size(self.usable_page_size());
+        Ok(leaf_page)
+    }
+
+    // Write a leaf page in the configured encoding, or in its own if its
+    // entries don't fit the configured one
+    pub(crate) fn write_leaf(&mut self, page_id: u64, leaf_page: &Leaf<K::Encoded>) -> Result<(), Box<dyn Error>> {
+        let page_bytes = Self::serialize_leaf(self.config.leaf_encoding, leaf_page);
+        self.store.put_page_bytes(page_id, &page_bytes)
+    }
+
+    // Serialize a leaf in `encoding` if its entries fit it, otherwise in its
+    // own. Adaptive leaves are serialized in whichever encoding is smaller.
+    fn serialize_leaf(encoding: LeafEncoding, leaf_page: &Leaf<K::Encoded>) -> Vec<u8> {
+        let converted = match encoding {
+            LeafEncoding::Adaptive => leaf_page.smaller_form(),
+            encoding if encoding != leaf_page.encoding() => leaf_page.to_encoding(encoding),
+            _ => None,
+        };
+        converted.as_ref().unwrap_or(leaf_page).serialize()
+    }
+
+    // Put an entry into a leaf, returning false if it doesn't fit. Adaptive
+    // trees try the leaf in the other encoding before giving up, so a leaf
+    // is only split once its entries fit in neither.
+    pub(crate) fn put_into_leaf(&self, leaf_page: &mut Leaf<K::Encoded>, item: &LeafPageItem<K::Encoded>) -> bool {
+        if leaf_page.put_item(item) {
+            return true;
+        }
+        if self.config.leaf_encoding != LeafEncoding::Adaptive {
+            return false;
+        }
+        if let Some(mut converted) = leaf_page.to_encoding(leaf_page.other_encoding()) {
+            if converted.put_item(item) {
+                *leaf_page = converted;
+                return true;
+            }
+        }
+        false
+    }
+
+    // Split a full leaf into itself and a new right sibling, without inserting
+    // `key`. Returns the new page's id and the key separating it from the
+    // page that was split.
+    fn split_leaf(&mut self, page_id: u64, mut leaf_page: Leaf<K::Encoded>, key: &K::Encoded) -> Result<(u64, K::Encoded), Box<dyn Error>> {
+        let mut new_page = match leaf_page.split() {
+            Some(new_page) => new_page,
+            None => {
+                // A single entry can't be split in two. Cut its run around
+                // the key, so the key ends up apart from the rest of the run,
+                // or put an empty page on the side of the entry where the
+                // key belongs.
+                let encoding = leaf_page.encoding();
+                let runs = leaf_page.runs();
+                let (left, right) = match runs.into_iter().next() {
+                    Some(run) => run.cut(key),
+                    None => (None, None),
+                };
+                let mut new_page = Leaf::new_empty(encoding, self.usable_page_size());
+                let mut kept_page = Leaf::new_empty(encoding, self.usable_page_size());
+                kept_page.set_prev_page_id(leaf_page.prev_page_id());
+                kept_page.set_next_page_id(leaf_page.next_page_id());
+                for (page, run) in [(&mut kept_page, left), (&mut new_page, right)] {
+                    if let Some(run) = run {
+                        page.push_run(&run);
+                    }
+                }
+                leaf_page = kept_page;
+                new_page
+            }
+        };
+
+        // The separator lies between the two pages' keys, with `key` standing
+        // in for the keys of a page left empty
+        let left_key = leaf_page.last_key().unwrap_or_else(|| key.clone());
+        let right_key = new_page.first_key().unwrap_or_else(|| key.clone());
+        let first_key = if left_key < right_key { K::Encoded::separator(&left_key, &right_key) } else { right_key };
+
+        // Link the new page in after the one being split
+        let new_page_id = self.store.allocate_page();
+        let next_page_id = leaf_page.next_page_id();
+        new_page.set_prev_page_id(page_id);
+        new_page.set_next_page_id(next_page_id);
+        leaf_page.set_next_page_id(new_page_id);
+        if next_page_id != 0 {
+            let mut next_page = self.read_leaf(next_page_id)?;
+            next_page.set_prev_page_id(new_page_id);
+            self.write_leaf(next_page_id, &next_page)?;
+        }
+
+        self.write_leaf(page_id, &leaf_page)?;
+        self.write_leaf(new_page_id, &new_page)?;
+        Ok((new_page_id, first_key))
+    }
 }
\ No newline at end of file
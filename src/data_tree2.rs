@@ -1,55 +1,109 @@
+use std::collections::{BTreeMap, HashSet};
 use std::error::Error;
-use crate::leaf_page::LeafPage;
+use std::ops::RangeBounds;
+use crate::data_tree::DataTree;
+use crate::formatter::{formatter_for, Formatter, Value, IDENTITY_FORMATTER_ID};
 use crate::page_store::PageStore;
+use crate::range::Range;
 
-// Formatter for DataTree2
-pub struct IdentityFormatter {
+// Constants for catalog entry sizes
+pub const ROOT_PAGE_ID_SIZE: usize = 8; // 8 bytes for the tree's root page ID
+pub const FORMATTER_ID_SIZE: usize = 1; // 1 byte for the tree's formatter ID
+pub const CATALOG_ENTRY_SIZE: usize = ROOT_PAGE_ID_SIZE + FORMATTER_ID_SIZE;
+
+// What the catalog records for a named tree
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CatalogEntry {
     pub root_page_id: u64,
+    // The formatter the tree's values are written with
+    pub formatter_id: u8,
 }
 
-impl IdentityFormatter {
-    pub fn new(page_id: u64) -> Self {
-        IdentityFormatter { root_page_id: page_id }
+impl CatalogEntry {
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(CATALOG_ENTRY_SIZE);
+
+        // Write root page ID (8 bytes)
+        bytes.extend_from_slice(&self.root_page_id.to_le_bytes());
+
+        // Write formatter ID (1 byte)
+        bytes.push(self.formatter_id);
+
+        bytes
     }
-}
 
-// Collection of formatters
-pub struct ResultFormatter {
-    pub formatters: Vec<IdentityFormatter>,
-}
+    pub fn deserialize(bytes: &[u8]) -> Result<Self, Box<dyn Error>> {
+        if bytes.len() < CATALOG_ENTRY_SIZE {
+            return Err(format!("Catalog entry is {} bytes, expected at least {}", bytes.len(), CATALOG_ENTRY_SIZE).into());
+        }
+
+        // Read root page ID (8 bytes)
+        let root_page_id = u64::from_le_bytes(bytes[0..ROOT_PAGE_ID_SIZE].try_into().unwrap());
+
+        // Read formatter ID (1 byte)
+        let formatter_id = bytes[ROOT_PAGE_ID_SIZE];
 
-impl ResultFormatter {
-    pub fn new(formatters: Vec<IdentityFormatter>) -> Self {
-        ResultFormatter { formatters }
+        Ok(CatalogEntry { root_page_id, formatter_id })
     }
 }
 
-// DataTree2 implementation that uses formatters
+// Named trees sharing one store. The catalog is itself a tree, keyed by
+// name and recorded in the superblock, and each of its entries holds the
+// root of a full B+tree of u64 keys and the id of the formatter its values
+// pass through.
 pub struct DataTree2<S: PageStore> {
     store: S,
-    formatter: ResultFormatter,
+    catalog_root_page_id: u64,
+    // The catalog's entries by name, read when the store is opened and
+    // written through on every change
+    trees: BTreeMap<String, CatalogEntry>,
 }
 
 impl<S: PageStore> DataTree2<S> {
     pub fn get_page_count(&self) -> usize {
-        return self.store.get_page_count();
+        self.store.get_page_count()
     }
 }
 
 impl<S: PageStore> DataTree2<S> {
-    pub fn dirty_pages(&self) -> &std::collections::HashSet<u64> {
-        return self.store.dirty_pages();
+    pub fn dirty_pages(&self) -> &HashSet<u64> {
+        self.store.dirty_pages()
     }
 }
 
 impl<S: PageStore> DataTree2<S> {
-    pub fn new(store: S, formatter: ResultFormatter) -> Self {
+    /// Creates an empty catalog in an empty store
+    pub fn new(mut store: S) -> Self {
+        let catalog_root_page_id = DataTree::<_, Vec<u8>>::new_keyed(&mut store).root_page_id();
         DataTree2 {
             store,
-            formatter,
+            catalog_root_page_id,
+            trees: BTreeMap::new(),
         }
     }
 
+    /// Opens the catalog recorded in the store's superblock
+    pub fn open(mut store: S) -> Result<Self, Box<dyn Error>> {
+        let catalog = DataTree::<_, Vec<u8>>::open_keyed(&mut store)?;
+        let catalog_root_page_id = catalog.root_page_id();
+
+        let mut trees = BTreeMap::new();
+        let mut entries = catalog.iter();
+        for (name, bytes) in entries.by_ref() {
+            let name = String::from_utf8(name).map_err(|_| "Catalog holds a tree name that is not UTF-8")?;
+            trees.insert(name, CatalogEntry::deserialize(&bytes)?);
+        }
+        if let Some(error) = entries.take_error() {
+            return Err(error);
+        }
+
+        Ok(DataTree2 {
+            store,
+            catalog_root_page_id,
+            trees,
+        })
+    }
+
     /// Returns a reference to the store
     pub fn store(&self) -> &S {
         &self.store
@@ -60,35 +114,209 @@ impl<S: PageStore> DataTree2<S> {
         &mut self.store
     }
 
-    pub fn put(&mut self, key: u64, value: &[u8]) -> Result<(), Box<dyn Error>> {
-        // For simplicity, just use the first formatter's page_id
-        let page_id = self.formatter.formatters[0].root_page_id;
+    /// Consumes the catalog and returns the underlying store
+    pub fn into_store(self) -> S {
+        self.store
+    }
+
+    pub fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        DataTree::<_, Vec<u8>>::at_root(&mut self.store, self.catalog_root_page_id, true).flush()
+    }
 
-        // Create a leaf page
-        let mut page = LeafPage::new_empty(self.store.page_size());
+    /// Returns the names of the trees in the catalog, in order
+    pub fn list_trees(&self) -> Vec<&str> {
+        self.trees.keys().map(String::as_str).collect()
+    }
 
-        // Put the key-value pair in the page
-        page.put(key, value);
+    /// Creates an empty tree of bytes under `name`. Fails if the name is
+    /// taken.
+    pub fn create_tree(&mut self, name: &str) -> Result<(), Box<dyn Error>> {
+        self.create_tree_with_formatter(name, IDENTITY_FORMATTER_ID)
+    }
 
-        // Save the page
-        self.store.put_page_bytes(page_id, &page.serialize())?;
+    /// Creates an empty tree under `name` whose values pass through the
+    /// built-in formatter with `formatter_id`. The catalog records only the
+    /// id, and every open finds the formatter again with `formatter_for`.
+    pub fn create_tree_with_formatter(&mut self, name: &str, formatter_id: u8) -> Result<(), Box<dyn Error>> {
+        if self.trees.contains_key(name) {
+            return Err(format!("A tree named {} already exists", name).into());
+        }
+        if formatter_for(formatter_id).is_none() {
+            return Err(format!("Formatter {:#04x} is not a built-in formatter", formatter_id).into());
+        }
 
+        let tree = DataTree::<_, u64>::new_in_catalog(&mut self.store);
+        let entry = CatalogEntry { root_page_id: tree.root_page_id(), formatter_id };
+        if let Err(error) = write_entry(&mut self.store, &mut self.catalog_root_page_id, name, entry) {
+            // A name the catalog can't hold; give back the tree's pages
+            DataTree::<_, u64>::open_in_catalog(&mut self.store, entry.root_page_id)?.destroy()?;
+            return Err(error);
+        }
+        self.trees.insert(name.to_string(), entry);
         Ok(())
     }
 
-    pub fn get(&self, key: u64) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
-        // For simplicity, just use the first formatter's page_id
-        let page_id = self.formatter.formatters[0].root_page_id;
+    /// Opens the tree under `name`
+    pub fn open_tree(&mut self, name: &str) -> Result<NamedTree<'_, S>, Box<dyn Error>> {
+        let entry = self.trees.get_mut(name).ok_or_else(|| format!("No tree named {}", name))?;
+        let formatter = formatter_for(entry.formatter_id)
+            .ok_or_else(|| format!("Tree {} uses unknown formatter {:#04x}", name, entry.formatter_id))?;
+        let tree = DataTree::open_in_catalog(&mut self.store, entry.root_page_id)?;
+        Ok(NamedTree {
+            name: name.to_string(),
+            tree,
+            formatter,
+            entry,
+            catalog_root_page_id: &mut self.catalog_root_page_id,
+        })
+    }
+
+    /// Removes the tree under `name` and frees its pages. Returns false if
+    /// there is no such tree.
+    pub fn drop_tree(&mut self, name: &str) -> Result<bool, Box<dyn Error>> {
+        let entry = match self.trees.get(name) {
+            Some(entry) => *entry,
+            None => return Ok(false),
+        };
+
+        // Take the tree out of the catalog before freeing its pages, so a
+        // failure part way leaks pages rather than leaving a broken tree
+        let mut catalog = DataTree::<_, Vec<u8>>::at_root(&mut self.store, self.catalog_root_page_id, true);
+        let deleted = catalog.delete(name.as_bytes());
+        self.catalog_root_page_id = catalog.root_page_id();
+        deleted?;
+        self.trees.remove(name);
+
+        DataTree::<_, u64>::open_in_catalog(&mut self.store, entry.root_page_id)?.destroy()?;
+        Ok(true)
+    }
+}
+
+// Write `name`'s entry to the catalog, following the catalog's root if the
+// write moves it
+fn write_entry<S: PageStore>(store: S, catalog_root_page_id: &mut u64, name: &str, entry: CatalogEntry) -> Result<(), Box<dyn Error>> {
+    let mut catalog = DataTree::<_, Vec<u8>>::at_root(store, *catalog_root_page_id, true);
+    let written = catalog.put(name.as_bytes(), &entry.serialize());
+    *catalog_root_page_id = catalog.root_page_id();
+    written
+}
+
+/// A tree opened by name from a `DataTree2` catalog. Values are formatted
+/// on the way in and parsed on the way out by the tree's formatter. When a
+/// change gives the tree a new root, the catalog entry is updated with it.
+pub struct NamedTree<'a, S: PageStore> {
+    name: String,
+    tree: DataTree<&'a mut S>,
+    formatter: Box<dyn Formatter>,
+    entry: &'a mut CatalogEntry,
+    catalog_root_page_id: &'a mut u64,
+}
 
-        // Get the page
-        let page_bytes = self.store.get_page_bytes(page_id)?;
-        let page = LeafPage::deserialize(&page_bytes);
+impl<'a, S: PageStore> NamedTree<'a, S> {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the root page ID
+    pub fn root_page_id(&self) -> u64 {
+        self.tree.root_page_id()
+    }
+
+    /// Returns the formatter the tree's values pass through
+    pub fn formatter(&self) -> &dyn Formatter {
+        self.formatter.as_ref()
+    }
+
+    pub fn get(&self, key: u64) -> Result<Option<Value>, Box<dyn Error>> {
+        match self.tree.get(key)? {
+            Some(bytes) => Ok(Some(self.formatter.parse(&bytes)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn put(&mut self, key: u64, value: impl Into<Value>) -> Result<(), Box<dyn Error>> {
+        let bytes = self.formatter.format(&value.into())?;
+        let put = self.tree.put(key, &bytes);
+        self.record_root()?;
+        put
+    }
+
+    pub fn delete(&mut self, key: u64) -> Result<bool, Box<dyn Error>> {
+        let deleted = self.tree.delete(key);
+        self.record_root()?;
+        deleted
+    }
+
+    /// Iterates over the entries with keys in `range`, in key order
+    pub fn range<R: RangeBounds<u64>>(&self, range: R) -> NamedRange<'_, &'a mut S> {
+        NamedRange { range: self.tree.range(range), formatter: self.formatter.as_ref(), finished: false, error: None }
+    }
+
+    /// Iterates over all entries in key order
+    pub fn iter(&self) -> NamedRange<'_, &'a mut S> {
+        self.range(..)
+    }
+
+    // Write the tree's root to its catalog entry if the last change moved it
+    fn record_root(&mut self) -> Result<(), Box<dyn Error>> {
+        if self.tree.root_page_id() == self.entry.root_page_id {
+            return Ok(());
+        }
+
+        let entry = CatalogEntry { root_page_id: self.tree.root_page_id(), ..*self.entry };
+        write_entry(self.tree.store_mut(), self.catalog_root_page_id, &self.name, entry)?;
+        *self.entry = entry;
+        Ok(())
+    }
+}
+
+/// Iterator over a named tree's entries, with each value parsed by the
+/// tree's formatter. Iteration ends at the first value that fails to parse
+/// or page that fails to read; `take_error` returns why.
+pub struct NamedRange<'r, S: PageStore> {
+    range: Range<'r, S>,
+    formatter: &'r dyn Formatter,
+    finished: bool,
+    error: Option<Box<dyn Error>>,
+}
+
+impl<S: PageStore> NamedRange<'_, S> {
+    /// Returns the error that ended iteration early, if there was one
+    pub fn take_error(&mut self) -> Option<Box<dyn Error>> {
+        self.error.take().or_else(|| self.range.take_error())
+    }
+
+    fn parse(&mut self, entry: Option<(u64, Vec<u8>)>) -> Option<(u64, Value)> {
+        let (key, bytes) = entry?;
+        match self.formatter.parse(&bytes) {
+            Ok(value) => Some((key, value)),
+            Err(error) => {
+                self.error = Some(error);
+                self.finished = true;
+                None
+            }
+        }
+    }
+}
+
+impl<S: PageStore> Iterator for NamedRange<'_, S> {
+    type Item = (u64, Value);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        let entry = self.range.next();
+        self.parse(entry)
+    }
+}
 
-        // Get the value
-        if let Some(value) = page.get(key) {
-            Ok(Some(value.to_vec()))
-        } else {
-            Ok(None)
+impl<S: PageStore> DoubleEndedIterator for NamedRange<'_, S> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
         }
+        let entry = self.range.next_back();
+        self.parse(entry)
     }
 }
@@ -0,0 +1,296 @@
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use crate::data_tree::PageType;
+use crate::leaf_page::LeafPage;
+use crate::page_store::{add_crc, extract_and_verify_crc, pop_free_page, push_free_page, PageStore, CRC_SIZE};
+use crate::superblock::SUPERBLOCK_PAGE_ID;
+
+// File-backed implementation of PageStore
+//
+// Page `n` lives at byte offset `n * page_size`. Every slot is exactly
+// `page_size` bytes: the serialized page padded with zeros, followed by the
+// CRC trailer in the last 4 bytes. Page 0 holds the superblock and is never
+// handed out by `allocate_page`.
+pub struct FilePageStore {
+    file: File,
+    path: PathBuf,
+    page_size: usize,
+    next_page_id: u64,
+    // Page images (padded, with CRC) written since the last flush
+    pages: HashMap<u64, Vec<u8>>,
+    free_pages: HashSet<u64>,
+    free_list_head: u64,
+    dirty_pages: HashSet<u64>,
+}
+
+impl FilePageStore {
+    /// Creates a new, empty store at `path`, truncating any existing file
+    pub fn create<P: AsRef<Path>>(path: P, page_size: usize) -> Result<Self, Box<dyn Error>> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path.as_ref())?;
+
+        Ok(FilePageStore {
+            file,
+            path: path.as_ref().to_path_buf(),
+            page_size,
+            next_page_id: 1,
+            pages: HashMap::new(),
+            free_pages: HashSet::new(),
+            free_list_head: 0,
+            dirty_pages: HashSet::new(),
+        })
+    }
+
+    /// Opens an existing store at `path` that was written with the same page size
+    pub fn open<P: AsRef<Path>>(path: P, page_size: usize) -> Result<Self, Box<dyn Error>> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(path.as_ref())?;
+
+        // A partial slot at the end is a page append torn by a crash. It is
+        // left out of the store, and overwritten in full when that page is
+        // written again, for instance by a log replay.
+        let file_len = file.metadata()?.len();
+
+        let mut store = FilePageStore {
+            file,
+            path: path.as_ref().to_path_buf(),
+            page_size,
+            next_page_id: std::cmp::max(1, file_len / page_size as u64),
+            pages: HashMap::new(),
+            free_pages: HashSet::new(),
+            free_list_head: 0,
+            dirty_pages: HashSet::new(),
+        };
+
+        // Slots that were freed before the last flush are written as FREE pages.
+        // The head of their free list is restored from the superblock by the tree.
+        // A slot that fails its CRC check is left for the reader (or a log
+        // replay) to deal with rather than failing the open.
+        for page_id in 1..store.next_page_id {
+            if let Ok(bytes) = store.get_page_bytes(page_id) {
+                if bytes.first() == Some(&PageType::FREE.to_u8()) {
+                    store.free_pages.insert(page_id);
+                }
+            }
+        }
+
+        Ok(store)
+    }
+
+    /// Returns the path of the backing file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn page_offset(&self, page_id: u64) -> u64 {
+        page_id * self.page_size as u64
+    }
+
+    // Pad the serialized page to fill its slot and append the CRC trailer
+    fn page_image(&self, bytes: &[u8]) -> Vec<u8> {
+        let mut padded = bytes.to_vec();
+        padded.resize(self.page_size - CRC_SIZE, 0);
+        add_crc(padded)
+    }
+
+    fn read_page_image(&self, page_id: u64) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut image = vec![0u8; self.page_size];
+        let mut file = &self.file;
+        file.seek(SeekFrom::Start(self.page_offset(page_id)))?;
+        file.read_exact(&mut image)?;
+        Ok(image)
+    }
+
+    fn page_not_found() -> Box<dyn Error> {
+        Box::new(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "Page not found",
+        ))
+    }
+}
+
+impl PageStore for FilePageStore {
+    fn get_page_bytes(&self, page_id: u64) -> Result<Vec<u8>, Box<dyn Error>> {
+        if page_id != SUPERBLOCK_PAGE_ID && page_id >= self.next_page_id {
+            return Err(Self::page_not_found());
+        }
+
+        let image = match self.pages.get(&page_id) {
+            Some(image) => image.clone(),
+            None => self.read_page_image(page_id)?,
+        };
+
+        // Extract data and verify CRC
+        let data = extract_and_verify_crc(&image)?;
+        Ok(data.to_vec())
+    }
+
+    fn put_page_bytes(&mut self, page_id: u64, bytes: &[u8]) -> Result<(), Box<dyn Error>> {
+        if bytes.len() + CRC_SIZE > self.page_size {
+            return Err(Box::new(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "Page too large",
+            )));
+        }
+
+        // Writing past the end leaves a gap of never-allocated slots, which
+        // are recorded as FREE so that every slot in the file has a valid CRC
+        while page_id != SUPERBLOCK_PAGE_ID && self.next_page_id < page_id {
+            let gap_page_id = self.next_page_id;
+            self.next_page_id += 1;
+            self.free_page(gap_page_id)?;
+        }
+
+        let image = self.page_image(bytes);
+        self.pages.insert(page_id, image);
+        if page_id != SUPERBLOCK_PAGE_ID && page_id >= self.next_page_id {
+            self.next_page_id = page_id + 1;
+        }
+
+        // Track FREE pages by their type byte, so pages written back from a
+        // log are classified the same way as pages freed through free_page
+        if bytes.first() == Some(&PageType::FREE.to_u8()) {
+            self.free_pages.insert(page_id);
+        } else {
+            self.free_pages.remove(&page_id);
+        }
+
+        // Mark the page as dirty
+        self.mark_page_dirty(page_id);
+
+        Ok(())
+    }
+
+    fn allocate_page(&mut self) -> u64 {
+        // Reuse a freed page before growing the file. A free list that can't be read
+        // is kept for later, and a new page is used this time.
+        let page_id = match pop_free_page(self) {
+            Ok(Some(page_id)) => page_id,
+            _ => {
+                let page_id = self.next_page_id;
+                self.next_page_id += 1;
+                page_id
+            }
+        };
+
+        // Initialize the page with an empty LeafPage
+        let page = LeafPage::empty(self.page_size);
+        self.put_page_bytes(page_id, &page.serialize()).unwrap();
+
+        page_id
+    }
+
+    fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        // Write the dirty set in page order, then make it durable
+        let mut dirty: Vec<u64> = self.dirty_pages.iter().copied().collect();
+        dirty.sort_unstable();
+
+        for page_id in dirty {
+            if let Some(image) = self.pages.get(&page_id) {
+                let offset = self.page_offset(page_id);
+                self.file.seek(SeekFrom::Start(offset))?;
+                self.file.write_all(image)?;
+            }
+        }
+
+        self.file.sync_all()?;
+        self.pages.clear();
+        self.clear_dirty_pages();
+        Ok(())
+    }
+
+    fn page_size(&self) -> usize {
+        self.page_size
+    }
+
+    fn get_next_page_id(&self, page_id: u64) -> Option<u64> {
+        let bytes = self.get_page_bytes(page_id).ok()?;
+        let page = LeafPage::new(&bytes);
+        let next_id = page.next_page_id();
+        if next_id == 0 {
+            None
+        } else {
+            Some(next_id)
+        }
+    }
+
+    fn get_prev_page_id(&self, page_id: u64) -> Option<u64> {
+        let bytes = self.get_page_bytes(page_id).ok()?;
+        let page = LeafPage::new(&bytes);
+        let prev_id = page.prev_page_id();
+        if prev_id == 0 {
+            None
+        } else {
+            Some(prev_id)
+        }
+    }
+
+    fn link_pages(&mut self, prev_page_id: u64, next_page_id: u64) -> Result<(), Box<dyn Error>> {
+        // Get and update previous page
+        let prev_bytes = self.get_page_bytes(prev_page_id)?;
+        let mut prev_page = LeafPage::new(&prev_bytes);
+        prev_page.set_next_page_id(next_page_id);
+        self.put_page_bytes(prev_page_id, &prev_page.serialize())?;
+
+        // Get and update next page
+        let next_bytes = self.get_page_bytes(next_page_id)?;
+        let mut next_page = LeafPage::new(&next_bytes);
+        next_page.set_prev_page_id(prev_page_id);
+        self.put_page_bytes(next_page_id, &next_page.serialize())?;
+
+        Ok(())
+    }
+
+    fn page_exists(&self, page_id: u64) -> bool {
+        if page_id == SUPERBLOCK_PAGE_ID {
+            return self.pages.contains_key(&page_id) || self.get_page_bytes(page_id).is_ok();
+        }
+        page_id < self.next_page_id && !self.free_pages.contains(&page_id)
+    }
+
+    fn free_page(&mut self, page_id: u64) -> Result<(), Box<dyn Error>> {
+        if page_id == SUPERBLOCK_PAGE_ID {
+            return Err("The superblock page cannot be freed".into());
+        }
+        if !self.page_exists(page_id) {
+            return Err(Self::page_not_found());
+        }
+
+        // Overwrite the slot with a FREE page so the page stays free after reopening
+        push_free_page(self, page_id)
+    }
+
+    fn get_page_count(&self) -> usize {
+        // The superblock in page 0 is not counted as a tree page
+        (self.next_page_id - 1) as usize - self.free_pages.len()
+    }
+
+    fn mark_page_dirty(&mut self, page_id: u64) {
+        self.dirty_pages.insert(page_id);
+    }
+
+    fn dirty_pages(&self) -> &HashSet<u64> {
+        &self.dirty_pages
+    }
+
+    fn clear_dirty_pages(&mut self) {
+        self.dirty_pages.clear();
+    }
+
+    fn free_list_head(&self) -> u64 {
+        self.free_list_head
+    }
+
+    fn set_free_list_head(&mut self, page_id: u64) {
+        self.free_list_head = page_id;
+    }
+}
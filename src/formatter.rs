@@ -0,0 +1,318 @@
+use std::error::Error;
+use std::io::{Read, Write};
+use flate2::Compression;
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+
+// Formatter ids recorded in the DataTree2 catalog. Integer formatters add
+// their width in bytes to INTEGER_FORMATTER_ID, and a compressed formatter
+// sets COMPRESSED_FORMATTER_FLAG on the id of the one it wraps.
+pub const IDENTITY_FORMATTER_ID: u8 = 0x00;
+pub const INTEGER_FORMATTER_ID: u8 = 0x10;
+pub const UTF8_FORMATTER_ID: u8 = 0x20;
+pub const RECORD_FORMATTER_ID: u8 = 0x30;
+pub const JSON_FORMATTER_ID: u8 = 0x40;
+pub const COMPRESSED_FORMATTER_FLAG: u8 = 0x80;
+
+// Largest value a compressed formatter inflates, so a small corrupt or
+// hostile stream can't exhaust memory
+pub const MAX_INFLATED_SIZE: usize = 16 << 20;
+
+// Constants for record field sizes
+pub const FIELD_LENGTH_SIZE: usize = 4; // 4 bytes for each field's length
+
+/// A value as a formatter writes it to a tree and reads it back
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Bytes(Vec<u8>),
+    Integer(i64),
+    Text(String),
+    Record(Vec<Vec<u8>>),
+    Json(serde_json::Value),
+}
+
+impl From<&[u8]> for Value {
+    fn from(bytes: &[u8]) -> Self {
+        Value::Bytes(bytes.to_vec())
+    }
+}
+
+impl<const N: usize> From<&[u8; N]> for Value {
+    fn from(bytes: &[u8; N]) -> Self {
+        Value::Bytes(bytes.to_vec())
+    }
+}
+
+impl From<Vec<u8>> for Value {
+    fn from(bytes: Vec<u8>) -> Self {
+        Value::Bytes(bytes)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Value::Integer(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(text: &str) -> Self {
+        Value::Text(text.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(text: String) -> Self {
+        Value::Text(text)
+    }
+}
+
+impl From<serde_json::Value> for Value {
+    fn from(json: serde_json::Value) -> Self {
+        Value::Json(json)
+    }
+}
+
+/// Turns values into the bytes a tree stores and back. DataTree2 applies a
+/// tree's formatter on every write and reverses it on every read.
+pub trait Formatter {
+    /// The id the catalog records for trees using this formatter
+    fn id(&self) -> u8;
+
+    /// Encodes `value` for storage. Fails for values of another kind.
+    fn format(&self, value: &Value) -> Result<Vec<u8>, Box<dyn Error>>;
+
+    /// Decodes bytes written by `format`
+    fn parse(&self, bytes: &[u8]) -> Result<Value, Box<dyn Error>>;
+}
+
+/// Returns the built-in formatter with `id`, as a reopened store finds a
+/// tree's formatter from its catalog entry
+pub fn formatter_for(id: u8) -> Option<Box<dyn Formatter>> {
+    if id & COMPRESSED_FORMATTER_FLAG != 0 {
+        let inner = formatter_for(id & !COMPRESSED_FORMATTER_FLAG)?;
+        return Some(Box::new(CompressedFormatter { inner }));
+    }
+
+    match id {
+        IDENTITY_FORMATTER_ID => Some(Box::new(IdentityFormatter)),
+        UTF8_FORMATTER_ID => Some(Box::new(Utf8Formatter)),
+        RECORD_FORMATTER_ID => Some(Box::new(RecordFormatter)),
+        JSON_FORMATTER_ID => Some(Box::new(JsonFormatter)),
+        _ => {
+            let width = id.checked_sub(INTEGER_FORMATTER_ID)?;
+            IntegerFormatter::new(width as usize).ok().map(|formatter| Box::new(formatter) as Box<dyn Formatter>)
+        }
+    }
+}
+
+// Error for a value of a kind the formatter doesn't write
+fn wrong_kind(formatter: &str, value: &Value) -> Box<dyn Error> {
+    format!("{} cannot format {:?}", formatter, value).into()
+}
+
+/// Stores bytes as they are
+#[derive(Debug, Clone, Copy, Default)]
+pub struct IdentityFormatter;
+
+impl Formatter for IdentityFormatter {
+    fn id(&self) -> u8 {
+        IDENTITY_FORMATTER_ID
+    }
+
+    fn format(&self, value: &Value) -> Result<Vec<u8>, Box<dyn Error>> {
+        match value {
+            Value::Bytes(bytes) => Ok(bytes.clone()),
+            _ => Err(wrong_kind("IdentityFormatter", value)),
+        }
+    }
+
+    fn parse(&self, bytes: &[u8]) -> Result<Value, Box<dyn Error>> {
+        Ok(Value::Bytes(bytes.to_vec()))
+    }
+}
+
+/// Stores signed integers in a fixed width of 1, 2, 4 or 8 bytes,
+/// little-endian
+#[derive(Debug, Clone, Copy)]
+pub struct IntegerFormatter {
+    width: usize,
+}
+
+impl IntegerFormatter {
+    pub fn new(width: usize) -> Result<Self, Box<dyn Error>> {
+        if !matches!(width, 1 | 2 | 4 | 8) {
+            return Err(format!("Integer width {} is not 1, 2, 4 or 8 bytes", width).into());
+        }
+        Ok(IntegerFormatter { width })
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+}
+
+impl Formatter for IntegerFormatter {
+    fn id(&self) -> u8 {
+        INTEGER_FORMATTER_ID + self.width as u8
+    }
+
+    fn format(&self, value: &Value) -> Result<Vec<u8>, Box<dyn Error>> {
+        let integer = match value {
+            Value::Integer(integer) => *integer,
+            _ => return Err(wrong_kind("IntegerFormatter", value)),
+        };
+
+        // The value must survive being cut to the width and sign-extended back
+        let shift = 64 - self.width * 8;
+        if (integer << shift) >> shift != integer {
+            return Err(format!("{} does not fit in {} bytes", integer, self.width).into());
+        }
+        Ok(integer.to_le_bytes()[..self.width].to_vec())
+    }
+
+    fn parse(&self, bytes: &[u8]) -> Result<Value, Box<dyn Error>> {
+        if bytes.len() != self.width {
+            return Err(format!("Integer is {} bytes, expected {}", bytes.len(), self.width).into());
+        }
+
+        // Sign-extend from the top byte
+        let fill = if bytes[self.width - 1] & 0x80 != 0 { 0xFF } else { 0 };
+        let mut le_bytes = [fill; 8];
+        le_bytes[..self.width].copy_from_slice(bytes);
+        Ok(Value::Integer(i64::from_le_bytes(le_bytes)))
+    }
+}
+
+/// Stores text as UTF-8, checking it on the way back
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Utf8Formatter;
+
+impl Formatter for Utf8Formatter {
+    fn id(&self) -> u8 {
+        UTF8_FORMATTER_ID
+    }
+
+    fn format(&self, value: &Value) -> Result<Vec<u8>, Box<dyn Error>> {
+        match value {
+            Value::Text(text) => Ok(text.as_bytes().to_vec()),
+            _ => Err(wrong_kind("Utf8Formatter", value)),
+        }
+    }
+
+    fn parse(&self, bytes: &[u8]) -> Result<Value, Box<dyn Error>> {
+        Ok(Value::Text(String::from_utf8(bytes.to_vec())?))
+    }
+}
+
+/// Stores a record of byte fields, each after its length
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RecordFormatter;
+
+impl Formatter for RecordFormatter {
+    fn id(&self) -> u8 {
+        RECORD_FORMATTER_ID
+    }
+
+    fn format(&self, value: &Value) -> Result<Vec<u8>, Box<dyn Error>> {
+        let fields = match value {
+            Value::Record(fields) => fields,
+            _ => return Err(wrong_kind("RecordFormatter", value)),
+        };
+
+        let mut bytes = Vec::with_capacity(fields.iter().map(|field| FIELD_LENGTH_SIZE + field.len()).sum());
+        for field in fields {
+            // Write field length (4 bytes)
+            let length = u32::try_from(field.len()).map_err(|_| format!("Record field of {} bytes is too long", field.len()))?;
+            bytes.extend_from_slice(&length.to_le_bytes());
+
+            // Write field
+            bytes.extend_from_slice(field);
+        }
+        Ok(bytes)
+    }
+
+    fn parse(&self, mut bytes: &[u8]) -> Result<Value, Box<dyn Error>> {
+        let mut fields = Vec::new();
+        while !bytes.is_empty() {
+            // Read field length (4 bytes)
+            if bytes.len() < FIELD_LENGTH_SIZE {
+                return Err("Record ends inside a field length".into());
+            }
+            let length = u32::from_le_bytes(bytes[..FIELD_LENGTH_SIZE].try_into().unwrap()) as usize;
+            bytes = &bytes[FIELD_LENGTH_SIZE..];
+
+            // Read field
+            if bytes.len() < length {
+                return Err(format!("Record field of {} bytes runs past the last {}", length, bytes.len()).into());
+            }
+            fields.push(bytes[..length].to_vec());
+            bytes = &bytes[length..];
+        }
+        Ok(Value::Record(fields))
+    }
+}
+
+/// Stores JSON documents as compact text
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonFormatter;
+
+impl Formatter for JsonFormatter {
+    fn id(&self) -> u8 {
+        JSON_FORMATTER_ID
+    }
+
+    fn format(&self, value: &Value) -> Result<Vec<u8>, Box<dyn Error>> {
+        match value {
+            Value::Json(json) => Ok(serde_json::to_vec(json)?),
+            _ => Err(wrong_kind("JsonFormatter", value)),
+        }
+    }
+
+    fn parse(&self, bytes: &[u8]) -> Result<Value, Box<dyn Error>> {
+        Ok(Value::Json(serde_json::from_slice(bytes)?))
+    }
+}
+
+/// Deflates what another formatter writes, the last stage of the pipeline
+/// on write and the first on read
+pub struct CompressedFormatter {
+    inner: Box<dyn Formatter>,
+}
+
+impl CompressedFormatter {
+    /// Wraps `inner`, which must not be compressed itself
+    pub fn new(inner: Box<dyn Formatter>) -> Result<Self, Box<dyn Error>> {
+        if inner.id() & COMPRESSED_FORMATTER_FLAG != 0 {
+            return Err("A compressed formatter cannot wrap another".into());
+        }
+        Ok(CompressedFormatter { inner })
+    }
+}
+
+impl Formatter for CompressedFormatter {
+    fn id(&self) -> u8 {
+        self.inner.id() | COMPRESSED_FORMATTER_FLAG
+    }
+
+    fn format(&self, value: &Value) -> Result<Vec<u8>, Box<dyn Error>> {
+        // A value parse would turn away is never written
+        let bytes = self.inner.format(value)?;
+        if bytes.len() > MAX_INFLATED_SIZE {
+            return Err(format!("Compressed value inflates past {} bytes", MAX_INFLATED_SIZE).into());
+        }
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&bytes)?;
+        Ok(encoder.finish()?)
+    }
+
+    fn parse(&self, bytes: &[u8]) -> Result<Value, Box<dyn Error>> {
+        // Read one byte past the limit to tell a value at the limit from one over it
+        let mut inflated = Vec::new();
+        DeflateDecoder::new(bytes).take(MAX_INFLATED_SIZE as u64 + 1).read_to_end(&mut inflated)?;
+        if inflated.len() > MAX_INFLATED_SIZE {
+            return Err(format!("Compressed value inflates past {} bytes", MAX_INFLATED_SIZE).into());
+        }
+        self.inner.parse(&inflated)
+    }
+}
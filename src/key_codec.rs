@@ -0,0 +1,220 @@
+use uuid::Uuid;
+use crate::page_key::PageKey;
+
+/// A key type a DataTree can be keyed by.
+///
+/// Pages only store u64 and byte-string keys, so every other key type is
+/// encoded into one of those before it reaches a page. The encoding must
+/// preserve order: `a < b` exactly when `encode(a) < encode(b)`, so that
+/// branches route keys correctly and ranges come back in key order.
+pub trait KeyCodec: Sized {
+    /// The form the key is stored in: u64 or `Vec<u8>`
+    type Encoded: PageKey;
+
+    /// The form callers pass keys in, such as `&str` for String keys
+    type Ref<'a>: Copy;
+
+    fn to_ref(&self) -> Self::Ref<'_>;
+
+    fn encode(key: Self::Ref<'_>) -> Self::Encoded;
+
+    /// Turns a stored key back into the key it was encoded from. Stored keys
+    /// always come from `encode`; malformed ones decode to a fallback value
+    /// rather than failing.
+    fn decode(encoded: &Self::Encoded) -> Self;
+}
+
+impl KeyCodec for u64 {
+    type Encoded = u64;
+    type Ref<'a> = u64;
+
+    fn to_ref(&self) -> u64 {
+        *self
+    }
+
+    fn encode(key: u64) -> u64 {
+        key
+    }
+
+    fn decode(encoded: &u64) -> Self {
+        *encoded
+    }
+}
+
+impl KeyCodec for Vec<u8> {
+    type Encoded = Vec<u8>;
+    type Ref<'a> = &'a [u8];
+
+    fn to_ref(&self) -> &[u8] {
+        self
+    }
+
+    fn encode(key: &[u8]) -> Vec<u8> {
+        key.to_vec()
+    }
+
+    fn decode(encoded: &Vec<u8>) -> Self {
+        encoded.clone()
+    }
+}
+
+impl KeyCodec for u32 {
+    type Encoded = u64;
+    type Ref<'a> = u32;
+
+    fn to_ref(&self) -> u32 {
+        *self
+    }
+
+    fn encode(key: u32) -> u64 {
+        key as u64
+    }
+
+    fn decode(encoded: &u64) -> Self {
+        *encoded as u32
+    }
+}
+
+impl KeyCodec for i64 {
+    type Encoded = u64;
+    type Ref<'a> = i64;
+
+    fn to_ref(&self) -> i64 {
+        *self
+    }
+
+    // Flipping the sign bit moves negative numbers below positive ones
+    fn encode(key: i64) -> u64 {
+        (key as u64) ^ (1 << 63)
+    }
+
+    fn decode(encoded: &u64) -> Self {
+        (*encoded ^ (1 << 63)) as i64
+    }
+}
+
+impl KeyCodec for f64 {
+    type Encoded = u64;
+    type Ref<'a> = f64;
+
+    fn to_ref(&self) -> f64 {
+        *self
+    }
+
+    // Positive floats already compare like their bits once the sign bit is
+    // set; negative ones have all bits flipped so larger magnitudes sort
+    // first. This is the IEEE 754 total order: -0.0 sorts just below 0.0 and
+    // NaNs sort beyond the infinities.
+    fn encode(key: f64) -> u64 {
+        let bits = key.to_bits();
+        if bits & (1 << 63) != 0 {
+            !bits
+        } else {
+            bits | (1 << 63)
+        }
+    }
+
+    fn decode(encoded: &u64) -> Self {
+        if encoded & (1 << 63) != 0 {
+            f64::from_bits(encoded ^ (1 << 63))
+        } else {
+            f64::from_bits(!encoded)
+        }
+    }
+}
+
+impl KeyCodec for u128 {
+    type Encoded = Vec<u8>;
+    type Ref<'a> = u128;
+
+    fn to_ref(&self) -> u128 {
+        *self
+    }
+
+    // Too wide for a u64; big-endian bytes compare like the numbers
+    fn encode(key: u128) -> Vec<u8> {
+        key.to_be_bytes().to_vec()
+    }
+
+    fn decode(encoded: &Vec<u8>) -> Self {
+        encoded.as_slice().try_into().map_or(0, u128::from_be_bytes)
+    }
+}
+
+impl KeyCodec for String {
+    type Encoded = Vec<u8>;
+    type Ref<'a> = &'a str;
+
+    fn to_ref(&self) -> &str {
+        self
+    }
+
+    // UTF-8 bytes compare in code point order, the same as str
+    fn encode(key: &str) -> Vec<u8> {
+        key.as_bytes().to_vec()
+    }
+
+    fn decode(encoded: &Vec<u8>) -> Self {
+        String::from_utf8_lossy(encoded).into_owned()
+    }
+}
+
+impl KeyCodec for Uuid {
+    type Encoded = Vec<u8>;
+    type Ref<'a> = Uuid;
+
+    fn to_ref(&self) -> Uuid {
+        *self
+    }
+
+    fn encode(key: Uuid) -> Vec<u8> {
+        key.as_bytes().to_vec()
+    }
+
+    fn decode(encoded: &Vec<u8>) -> Self {
+        Uuid::from_slice(encoded).unwrap_or(Uuid::nil())
+    }
+}
+
+// Read the next component of a composite key, advancing `bytes` past it
+fn decode_component<T: KeyCodec>(bytes: &mut &[u8]) -> T {
+    match T::Encoded::decode_ordered(bytes) {
+        Some((encoded, size)) => {
+            *bytes = &bytes[size..];
+            T::decode(&encoded)
+        }
+        None => {
+            *bytes = &[];
+            T::decode(&T::Encoded::min_key())
+        }
+    }
+}
+
+// Tuples are stored as their components' ordered encodings one after
+// another, so they compare component by component
+macro_rules! tuple_key_codec {
+    ($($name:ident $index:tt),+) => {
+        impl<$($name: KeyCodec),+> KeyCodec for ($($name,)+) {
+            type Encoded = Vec<u8>;
+            type Ref<'a> = ($($name::Ref<'a>,)+);
+
+            fn to_ref(&self) -> Self::Ref<'_> {
+                ($(self.$index.to_ref(),)+)
+            }
+
+            fn encode(key: Self::Ref<'_>) -> Vec<u8> {
+                let mut bytes = Vec::new();
+                $($name::encode(key.$index).encode_ordered(&mut bytes);)+
+                bytes
+            }
+
+            fn decode(encoded: &Vec<u8>) -> Self {
+                let mut bytes = encoded.as_slice();
+                ($(decode_component::<$name>(&mut bytes),)+)
+            }
+        }
+    };
+}
+
+tuple_key_codec!(A 0, B 1);
+tuple_key_codec!(A 0, B 1, C 2);
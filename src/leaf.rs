@@ -0,0 +1,588 @@
+use std::error::Error;
+use std::ops::Bound;
+use crate::data_tree::PageType;
+use crate::leaf_page::{KeyedLeafPage, LeafPageItem, LeafPageView, HEADER_SIZE as LEAF_HEADER_SIZE, VALUE_LENGTH_SIZE};
+use crate::page_key::PageKey;
+use crate::rle_leaf_page::{RLELeafPage, RLELeafPageView, HEADER_SIZE as RLE_HEADER_SIZE, METADATA_ENTRY_SIZE as RUN_ENTRY_SIZE};
+use crate::slotted_leaf_page::{KeyedSlottedLeafPage, SlottedLeafPageView, HEADER_SIZE as SLOTTED_HEADER_SIZE, VALUE_OFFSET_SIZE};
+
+/// How a DataTree encodes the leaves it writes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LeafEncoding {
+    /// A LeafPage entry for every key
+    #[default]
+    Plain,
+    /// An RLELeafPage entry for every run of consecutive keys holding the
+    /// same value. Only trees whose keys are stored as u64 can use it.
+    RunLength,
+    /// Whichever of the two serializes smaller, chosen again each time a
+    /// leaf is written. Leaves of keys that can't form runs are plain.
+    Adaptive,
+    /// A SlottedLeafPage slot for every key, pointing at a value that is
+    /// updated in place where it fits
+    Slotted,
+}
+
+/// Consecutive keys `start..=end` holding the same bytes. Leaves of either
+/// encoding are read and rebuilt as runs; in a LeafPage every run holds a
+/// single key.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LeafRun<K = u64> {
+    pub start: K,
+    pub end: K,
+    pub bytes: Vec<u8>,
+    pub overflow: bool,
+}
+
+impl<K: PageKey> LeafRun<K> {
+    pub fn single(item: LeafPageItem<K>) -> Self {
+        LeafRun {
+            start: item.key.clone(),
+            end: item.key,
+            bytes: item.bytes,
+            overflow: item.overflow,
+        }
+    }
+
+    /// Whether the run holds more than one key
+    pub fn is_wide(&self) -> bool {
+        self.start != self.end
+    }
+
+    /// The number of keys in the run
+    pub fn key_count(&self) -> u64 {
+        if !self.is_wide() {
+            return 1;
+        }
+        (run_key::<K>(self.end.to_ref()) - run_key::<K>(self.start.to_ref())).saturating_add(1)
+    }
+
+    /// Takes the first key off the run, returning its entry and the rest of
+    /// the run, if any
+    pub fn pop_first(mut self) -> (LeafPageItem<K>, Option<Self>) {
+        let item = LeafPageItem { key: self.start.clone(), bytes: self.bytes.clone(), overflow: self.overflow };
+        if !self.is_wide() {
+            return (item, None);
+        }
+        self.start = step(&self.start, true);
+        (item, Some(self))
+    }
+
+    /// Takes the last key off the run, returning its entry and the rest of
+    /// the run, if any
+    pub fn pop_last(mut self) -> (LeafPageItem<K>, Option<Self>) {
+        let item = LeafPageItem { key: self.end.clone(), bytes: self.bytes.clone(), overflow: self.overflow };
+        if !self.is_wide() {
+            return (item, None);
+        }
+        self.end = step(&self.end, false);
+        (item, Some(self))
+    }
+
+    /// The key after `key` in the run, if `key` isn't its last
+    pub fn key_after(&self, key: &K) -> Option<K> {
+        if *key < self.end { Some(step(key, true)) } else { None }
+    }
+
+    /// The key before `key` in the run, if `key` isn't its first
+    pub fn key_before(&self, key: &K) -> Option<K> {
+        if *key > self.start { Some(step(key, false)) } else { None }
+    }
+
+    /// Removes the keys within `lower..upper` from the run, returning the
+    /// parts of it below and above them
+    pub fn clip(&self, lower: Bound<&K>, upper: Bound<&K>) -> (Option<Self>, Option<Self>) {
+        let below = match lower {
+            Bound::Included(key) => self.part_below(key, false),
+            Bound::Excluded(key) => self.part_below(key, true),
+            Bound::Unbounded => None,
+        };
+        let above = match upper {
+            Bound::Included(key) => self.part_above(key, false),
+            Bound::Excluded(key) => self.part_above(key, true),
+            Bound::Unbounded => None,
+        };
+        (below, above)
+    }
+
+    /// The part of the run within `lower..upper`, if any
+    pub fn within(&self, lower: Bound<&K>, upper: Bound<&K>) -> Option<Self> {
+        let run = match lower {
+            Bound::Included(key) => self.part_above(key, true)?,
+            Bound::Excluded(key) => self.part_above(key, false)?,
+            Bound::Unbounded => self.clone(),
+        };
+        match upper {
+            Bound::Included(key) => run.part_below(key, true),
+            Bound::Excluded(key) => run.part_below(key, false),
+            Bound::Unbounded => Some(run),
+        }
+    }
+
+    // The keys of the run below `key`, or up to and including it
+    fn part_below(&self, key: &K, inclusive: bool) -> Option<Self> {
+        if self.start > *key || (self.start == *key && !inclusive) {
+            return None;
+        }
+        if self.end < *key || (self.end == *key && inclusive) {
+            return Some(self.clone());
+        }
+        // The key lies inside the run, which is therefore wide
+        let end = if inclusive { key.clone() } else { step(key, false) };
+        Some(LeafRun { end, ..self.clone() })
+    }
+
+    // The keys of the run above `key`, or from it on
+    fn part_above(&self, key: &K, inclusive: bool) -> Option<Self> {
+        if self.end < *key || (self.end == *key && !inclusive) {
+            return None;
+        }
+        if self.start > *key || (self.start == *key && inclusive) {
+            return Some(self.clone());
+        }
+        let start = if inclusive { key.clone() } else { step(key, true) };
+        Some(LeafRun { start, ..self.clone() })
+    }
+
+    /// Whether `next` continues the run: it starts right after it with the
+    /// same inline value
+    pub fn joins(&self, next: &Self) -> bool {
+        !self.overflow && !next.overflow && self.bytes == next.bytes
+            && K::run_key(self.end.to_ref()).and_then(|end| end.checked_add(1)).is_some_and(|key| K::run_key(next.start.to_ref()) == Some(key))
+    }
+
+    /// Cuts the run in two around `key`. Keys below `key` go to the left
+    /// part; when `key` starts the run it is cut off from the keys above it
+    /// instead. A run of the single key `key` stays whole on the left.
+    pub fn cut(self, key: &K) -> (Option<Self>, Option<Self>) {
+        if *key < self.start {
+            (None, Some(self))
+        } else if *key > self.end || !self.is_wide() {
+            (Some(self), None)
+        } else {
+            let split_key = if *key == self.start { step(key, true) } else { key.clone() };
+            let left = LeafRun { start: self.start.clone(), end: step(&split_key, false), ..self.clone() };
+            let right = LeafRun { start: split_key, ..self };
+            (Some(left), Some(right))
+        }
+    }
+}
+
+// The key after or before `key`. Only keys of wide runs are stepped, and
+// only keys stored as u64 form wide runs.
+fn step<K: PageKey>(key: &K, forward: bool) -> K {
+    let key = K::run_key(key.to_ref()).expect("Only u64 keys form runs");
+    K::from_run_key(if forward { key + 1 } else { key - 1 }).unwrap()
+}
+
+// A key of a run-length leaf as stored there
+fn run_key<K: PageKey>(key: K::Ref<'_>) -> u64 {
+    K::run_key(key).expect("Run-length leaves only hold u64 keys")
+}
+
+/// A leaf page of any encoding, as told apart by its page-type byte
+#[derive(Debug)]
+pub enum Leaf<K: PageKey = u64> {
+    Plain(KeyedLeafPage<K>),
+    RunLength(RLELeafPage),
+    Slotted(KeyedSlottedLeafPage<K>),
+}
+
+impl<K: PageKey> Leaf<K> {
+    /// Creates an empty leaf of the given encoding. Keys that can't form
+    /// runs always get a plain leaf.
+    pub fn new_empty(encoding: LeafEncoding, page_size: usize) -> Self {
+        match encoding {
+            LeafEncoding::RunLength if K::supports_runs() => Leaf::RunLength(RLELeafPage::new_empty(page_size)),
+            LeafEncoding::Slotted => Leaf::Slotted(KeyedSlottedLeafPage::new_empty(page_size)),
+            _ => Leaf::Plain(KeyedLeafPage::empty(page_size)),
+        }
+    }
+
+    /// Whether a page of this type is a leaf holding K keys
+    pub fn is_leaf_page_type(page_type: Option<PageType>) -> bool {
+        page_type == Some(K::LEAF_PAGE_TYPE) || page_type == Some(K::SLOTTED_LEAF_PAGE_TYPE) ||
+            (page_type == Some(PageType::RLELeafPage) && K::supports_runs())
+    }
+
+    /// Reads a leaf of any encoding, going by its page-type byte
+    pub fn deserialize(bytes: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let page_type = bytes.first().and_then(|b| PageType::from_u8(*b));
+        if page_type == Some(K::LEAF_PAGE_TYPE) {
+            Ok(Leaf::Plain(KeyedLeafPage::deserialize(bytes)))
+        } else if page_type == Some(K::SLOTTED_LEAF_PAGE_TYPE) {
+            Ok(Leaf::Slotted(KeyedSlottedLeafPage::deserialize(bytes)))
+        } else if Self::is_leaf_page_type(page_type) {
+            Ok(Leaf::RunLength(RLELeafPage::deserialize(bytes)))
+        } else {
+            Err(format!("Page of type {:?} is not a leaf", page_type).into())
+        }
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        match self {
+            Leaf::Plain(page) => page.serialize(),
+            Leaf::RunLength(page) => page.serialize(),
+            Leaf::Slotted(page) => page.serialize(),
+        }
+    }
+
+    pub fn encoding(&self) -> LeafEncoding {
+        match self {
+            Leaf::Plain(_) => LeafEncoding::Plain,
+            Leaf::RunLength(_) => LeafEncoding::RunLength,
+            Leaf::Slotted(_) => LeafEncoding::Slotted,
+        }
+    }
+
+    /// The encoding an adaptive tree would convert the page to. Slotted
+    /// pages, like plain ones, hold a key per entry.
+    pub fn other_encoding(&self) -> LeafEncoding {
+        match self {
+            Leaf::Plain(_) | Leaf::Slotted(_) => LeafEncoding::RunLength,
+            Leaf::RunLength(_) => LeafEncoding::Plain,
+        }
+    }
+
+    pub fn page_size(&self) -> usize {
+        match self {
+            Leaf::Plain(page) => page.page_size,
+            Leaf::RunLength(page) => page.page_size,
+            Leaf::Slotted(page) => page.page_size,
+        }
+    }
+
+    pub fn set_page_size(&mut self, page_size: usize) {
+        match self {
+            Leaf::Plain(page) => page.page_size = page_size,
+            Leaf::RunLength(page) => page.page_size = page_size,
+            Leaf::Slotted(page) => page.page_size = page_size,
+        }
+    }
+
+    pub fn prev_page_id(&self) -> u64 {
+        match self {
+            Leaf::Plain(page) => page.prev_page_id(),
+            Leaf::RunLength(page) => page.prev_page_id(),
+            Leaf::Slotted(page) => page.prev_page_id(),
+        }
+    }
+
+    pub fn next_page_id(&self) -> u64 {
+        match self {
+            Leaf::Plain(page) => page.next_page_id(),
+            Leaf::RunLength(page) => page.next_page_id(),
+            Leaf::Slotted(page) => page.next_page_id(),
+        }
+    }
+
+    pub fn set_prev_page_id(&mut self, page_id: u64) {
+        match self {
+            Leaf::Plain(page) => page.set_prev_page_id(page_id),
+            Leaf::RunLength(page) => page.set_prev_page_id(page_id),
+            Leaf::Slotted(page) => page.set_prev_page_id(page_id),
+        }
+    }
+
+    pub fn set_next_page_id(&mut self, page_id: u64) {
+        match self {
+            Leaf::Plain(page) => page.set_next_page_id(page_id),
+            Leaf::RunLength(page) => page.set_next_page_id(page_id),
+            Leaf::Slotted(page) => page.set_next_page_id(page_id),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Leaf::Plain(page) => page.metadata().is_empty(),
+            Leaf::RunLength(page) => page.metadata().is_empty(),
+            Leaf::Slotted(page) => page.slots().is_empty(),
+        }
+    }
+
+    /// Returns the number of bytes the page's contents take when serialized
+    pub fn used_space(&self) -> usize {
+        match self {
+            Leaf::Plain(page) => page.used_space(),
+            Leaf::RunLength(page) => page.used_space(),
+            Leaf::Slotted(page) => page.used_space(),
+        }
+    }
+
+    pub fn get(&self, key: K::Ref<'_>) -> Option<&[u8]> {
+        match self {
+            Leaf::Plain(page) => page.get(key),
+            Leaf::RunLength(page) => page.get(run_key::<K>(key)),
+            Leaf::Slotted(page) => page.get(key),
+        }
+    }
+
+    /// Whether the entry for `key` holds an overflow pointer record
+    pub fn is_overflow(&self, key: K::Ref<'_>) -> bool {
+        match self {
+            Leaf::Plain(page) => page.is_overflow(key),
+            Leaf::RunLength(page) => page.is_overflow(run_key::<K>(key)),
+            Leaf::Slotted(page) => page.is_overflow(key),
+        }
+    }
+
+    /// Stores an entry, returning false if the page has no room for it
+    pub fn put_item(&mut self, item: &LeafPageItem<K>) -> bool {
+        let single = match self {
+            Leaf::Plain(page) => return page.put_item(item),
+            Leaf::Slotted(page) => {
+                if page.put_item(item) {
+                    return true;
+                }
+                page.slots().len() <= 1
+            }
+            Leaf::RunLength(page) => {
+                let key = run_key::<K>(item.key.to_ref());
+                let put = |page: &mut RLELeafPage| if item.overflow {
+                    page.put_overflow(key, &item.bytes)
+                } else {
+                    page.put(key, &item.bytes)
+                };
+                if put(page) {
+                    return true;
+                }
+
+                // Replaced values leave unused bytes behind until compacted
+                page.compact_data();
+                if put(page) {
+                    return true;
+                }
+                page.metadata().len() <= 1
+            }
+        };
+
+        // A value too large for a run entry or a slot, both larger than a
+        // plain entry, goes in a plain leaf. Splitting leaves it alone in one.
+        if single {
+            if let Some(Leaf::Plain(mut plain)) = self.to_encoding(LeafEncoding::Plain) {
+                if plain.put_item(item) {
+                    *self = Leaf::Plain(plain);
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    /// Deletes the entry for `key`. Deleting from the middle of a run splits
+    /// it, which can leave a run-length page larger than its page size.
+    pub fn delete(&mut self, key: K::Ref<'_>) -> bool {
+        match self {
+            Leaf::Plain(page) => page.delete(key),
+            Leaf::RunLength(page) => page.delete(run_key::<K>(key)),
+            Leaf::Slotted(page) => page.delete(key),
+        }
+    }
+
+    /// Returns the page's runs in key order
+    pub fn runs(&self) -> Vec<LeafRun<K>> {
+        match self {
+            Leaf::Plain(page) => page.sorted_items().into_iter().map(LeafRun::single).collect(),
+            Leaf::Slotted(page) => page.sorted_items().into_iter().map(LeafRun::single).collect(),
+            Leaf::RunLength(page) => page.metadata().iter()
+                .map(|meta| LeafRun {
+                    start: K::from_run_key(meta.start_key).unwrap(),
+                    end: K::from_run_key(meta.end_key).unwrap(),
+                    bytes: page.data()[meta.value_offset..meta.value_offset + meta.value_length].to_vec(),
+                    overflow: meta.overflow,
+                })
+                .collect(),
+        }
+    }
+
+    /// The smallest key in the page
+    pub fn first_key(&self) -> Option<K> {
+        match self {
+            Leaf::Plain(page) => page.metadata().first().map(|meta| meta.key.clone()),
+            Leaf::RunLength(page) => page.metadata().first().map(|meta| K::from_run_key(meta.start_key).unwrap()),
+            Leaf::Slotted(page) => page.slots().first().map(|slot| slot.key.clone()),
+        }
+    }
+
+    /// The largest key in the page
+    pub fn last_key(&self) -> Option<K> {
+        match self {
+            Leaf::Plain(page) => page.metadata().last().map(|meta| meta.key.clone()),
+            Leaf::RunLength(page) => page.metadata().last().map(|meta| K::from_run_key(meta.end_key).unwrap()),
+            Leaf::Slotted(page) => page.slots().last().map(|slot| slot.key.clone()),
+        }
+    }
+
+    /// Moves the upper half of the page's entries to a new page of the same
+    /// encoding. None if there are fewer than two entries.
+    pub fn split(&mut self) -> Option<Self> {
+        match self {
+            Leaf::Plain(page) => page.split().map(Leaf::Plain),
+            Leaf::RunLength(page) => page.split().map(Leaf::RunLength),
+            Leaf::Slotted(page) => page.split().map(Leaf::Slotted),
+        }
+    }
+
+    /// Appends a run above every key in the page. Plain and slotted pages
+    /// only take runs of a single key.
+    pub fn push_run(&mut self, run: &LeafRun<K>) -> bool {
+        let item = || LeafPageItem { key: run.start.clone(), bytes: run.bytes.clone(), overflow: run.overflow };
+        match self {
+            Leaf::Plain(page) => !run.is_wide() && page.put_item(&item()),
+            Leaf::Slotted(page) => !run.is_wide() && page.put_item(&item()),
+            Leaf::RunLength(page) => {
+                page.push_run(run_key::<K>(run.start.to_ref()), run_key::<K>(run.end.to_ref()), &run.bytes, run.overflow)
+            }
+        }
+    }
+
+    /// Builds a page of the given encoding holding `runs`, which must be in
+    /// key order. None if they don't fit.
+    pub fn from_runs(encoding: LeafEncoding, page_size: usize, runs: &[LeafRun<K>]) -> Option<Self> {
+        let mut leaf = Self::new_empty(encoding, page_size);
+        for run in runs {
+            if !leaf.push_run(run) {
+                return None;
+            }
+        }
+        Some(leaf)
+    }
+
+    /// The same page in another encoding, keeping its links. None if its
+    /// entries don't fit that encoding.
+    pub fn to_encoding(&self, encoding: LeafEncoding) -> Option<Self> {
+        let mut leaf = Self::from_runs(encoding, self.page_size(), &self.runs())?;
+        if leaf.encoding() != encoding {
+            return None;
+        }
+        leaf.set_prev_page_id(self.prev_page_id());
+        leaf.set_next_page_id(self.next_page_id());
+        Some(leaf)
+    }
+
+    /// The same page in the other encoding, if that serializes smaller.
+    /// Plain pages are kept on a tie.
+    pub fn smaller_form(&self) -> Option<Self> {
+        let other = self.to_encoding(self.other_encoding())?;
+        let smaller = match other {
+            Leaf::Plain(_) => other.used_space() <= self.used_space(),
+            Leaf::RunLength(_) | Leaf::Slotted(_) => other.used_space() < self.used_space(),
+        };
+        if smaller { Some(other) } else { None }
+    }
+
+    /// The bytes the page's entries would take as a plain page, which for
+    /// long runs can be far more than a page holds
+    pub fn plain_size(&self) -> u64 {
+        match self {
+            Leaf::Plain(page) => page.used_space() as u64,
+            Leaf::RunLength(_) | Leaf::Slotted(_) => self.runs().iter().fold(LEAF_HEADER_SIZE as u64, |size, run| {
+                let entry_size = (run.start.encoded_size() + VALUE_LENGTH_SIZE + run.bytes.len()) as u64;
+                size.saturating_add(run.key_count().saturating_mul(entry_size))
+            }),
+        }
+    }
+
+    /// Bytes a page of the given encoding takes before its first entry.
+    /// Adaptive leaves are estimated as plain ones.
+    pub fn header_size(encoding: LeafEncoding) -> usize {
+        match encoding {
+            LeafEncoding::RunLength => RLE_HEADER_SIZE,
+            LeafEncoding::Slotted => SLOTTED_HEADER_SIZE,
+            LeafEncoding::Plain | LeafEncoding::Adaptive => LEAF_HEADER_SIZE,
+        }
+    }
+
+    /// Upper bound on the bytes a run adds to a page of the given encoding.
+    /// Adaptive leaves are estimated as plain ones.
+    pub fn run_size(run: &LeafRun<K>, encoding: LeafEncoding) -> usize {
+        match encoding {
+            LeafEncoding::RunLength => RUN_ENTRY_SIZE + run.bytes.len(),
+            LeafEncoding::Slotted => run.start.encoded_size() + VALUE_OFFSET_SIZE + VALUE_LENGTH_SIZE + run.bytes.len(),
+            LeafEncoding::Plain | LeafEncoding::Adaptive => run.start.encoded_size() + VALUE_LENGTH_SIZE + run.bytes.len(),
+        }
+    }
+
+    /// Upper bound on the bytes putting `item` after every key in the page
+    /// adds to it
+    pub fn appended_size(&self, item: &LeafPageItem<K>) -> usize {
+        if let Leaf::RunLength(page) = self {
+            // Extending the last run takes no space
+            if let Some(last) = page.metadata().last() {
+                let last_value = &page.data()[last.value_offset..last.value_offset + last.value_length];
+                let key = run_key::<K>(item.key.to_ref());
+                if !item.overflow && !last.overflow && last.end_key.checked_add(1) == Some(key) && last_value == item.bytes {
+                    return 0;
+                }
+            }
+        }
+        match self {
+            Leaf::Plain(_) => item.key.encoded_size() + VALUE_LENGTH_SIZE + item.bytes.len(),
+            Leaf::RunLength(_) => RUN_ENTRY_SIZE + item.bytes.len(),
+            Leaf::Slotted(_) => item.key.encoded_size() + VALUE_OFFSET_SIZE + VALUE_LENGTH_SIZE + item.bytes.len(),
+        }
+    }
+}
+
+/// A leaf page of any encoding read in place, the borrowed counterpart of
+/// `Leaf` for lookups that don't modify the page
+#[derive(Debug, Clone, Copy)]
+pub enum LeafView<'a, K: PageKey = u64> {
+    Plain(LeafPageView<'a, K>),
+    RunLength(RLELeafPageView<'a>),
+    Slotted(SlottedLeafPageView<'a, K>),
+}
+
+impl<'a, K: PageKey> LeafView<'a, K> {
+    /// Views a leaf of any encoding, going by its page-type byte
+    pub fn new(bytes: &'a [u8]) -> Result<Self, Box<dyn Error>> {
+        let page_type = bytes.first().and_then(|b| PageType::from_u8(*b));
+        if page_type == Some(K::LEAF_PAGE_TYPE) {
+            Ok(LeafView::Plain(LeafPageView::new(bytes)?))
+        } else if page_type == Some(K::SLOTTED_LEAF_PAGE_TYPE) {
+            Ok(LeafView::Slotted(SlottedLeafPageView::new(bytes)?))
+        } else if Leaf::<K>::is_leaf_page_type(page_type) {
+            Ok(LeafView::RunLength(RLELeafPageView::new(bytes)?))
+        } else {
+            Err(format!("Page of type {:?} is not a leaf", page_type).into())
+        }
+    }
+
+    pub fn encoding(&self) -> LeafEncoding {
+        match self {
+            LeafView::Plain(_) => LeafEncoding::Plain,
+            LeafView::RunLength(_) => LeafEncoding::RunLength,
+            LeafView::Slotted(_) => LeafEncoding::Slotted,
+        }
+    }
+
+    pub fn prev_page_id(&self) -> u64 {
+        match self {
+            LeafView::Plain(page) => page.prev_page_id(),
+            LeafView::RunLength(page) => page.prev_page_id(),
+            LeafView::Slotted(page) => page.prev_page_id(),
+        }
+    }
+
+    pub fn next_page_id(&self) -> u64 {
+        match self {
+            LeafView::Plain(page) => page.next_page_id(),
+            LeafView::RunLength(page) => page.next_page_id(),
+            LeafView::Slotted(page) => page.next_page_id(),
+        }
+    }
+
+    pub fn get(&self, key: K::Ref<'_>) -> Option<&'a [u8]> {
+        self.get_entry(key).map(|(value, _)| value)
+    }
+
+    /// The bytes stored for `key`, with whether they are an overflow pointer
+    /// record rather than the value
+    pub fn get_entry(&self, key: K::Ref<'_>) -> Option<(&'a [u8], bool)> {
+        match self {
+            LeafView::Plain(page) => page.get_entry(key),
+            LeafView::RunLength(page) => page.get_entry(run_key::<K>(key)),
+            LeafView::Slotted(page) => page.get_entry(key),
+        }
+    }
+}
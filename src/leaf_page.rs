@@ -1,11 +1,26 @@
+use std::cmp::Ordering;
+use std::error::Error;
+use std::marker::PhantomData;
 use crate::data_tree::PageType;
+use crate::overflow_page::HEADER_SIZE as OVERFLOW_HEADER_SIZE;
+use crate::page_key::PageKey;
 
 // Metadata for each key-value pair
 #[derive(Debug, Clone, Copy)]
-pub struct LeafPageEntry {
-    pub key: u64,
+pub struct LeafPageEntry<K = u64> {
+    pub key: K,
     pub value_offset: usize,
     pub value_length: usize,
+    // The stored bytes are an overflow pointer record rather than the value
+    pub overflow: bool,
+}
+
+// A key with the bytes stored for it, as moved between pages
+#[derive(Debug, Clone, PartialEq)]
+pub struct LeafPageItem<K = u64> {
+    pub key: K,
+    pub bytes: Vec<u8>,
+    pub overflow: bool,
 }
 
 // Constants for page header sizes
@@ -18,21 +33,30 @@ pub const NEXT_PAGE_ID_SIZE: usize = 8; // 8 bytes for next page ID
 pub const HEADER_SIZE: usize = PAGE_TYPE_SIZE + COUNT_SIZE + DATA_START_SIZE +
                               USED_BYTES_SIZE + PREV_PAGE_ID_SIZE + NEXT_PAGE_ID_SIZE;
 
-// Constants for metadata entry sizes
+// Constants for metadata entry sizes. Byte-string keys take their encoded
+// size in place of KEY_SIZE.
 pub const KEY_SIZE: usize = 8; // 8 bytes for u64 key
 pub const VALUE_LENGTH_SIZE: usize = 8; // 8 bytes for value length
 pub const METADATA_ENTRY_SIZE: usize = KEY_SIZE + VALUE_LENGTH_SIZE;
 
+// Set in a serialized value length when the entry holds an overflow pointer
+pub const OVERFLOW_FLAG: u64 = 1 << 63;
+
+// A leaf of keys of type K. The u64 form is the LeafPage used by most trees;
+// byte-string trees use KeyedLeafPage<Vec<u8>>.
 #[derive(Debug)]
-pub struct LeafPage {
+pub struct KeyedLeafPage<K: PageKey> {
+    pub page_type: PageType,
     pub page_size: usize,
-    pub metadata: Vec<LeafPageEntry>,
+    pub metadata: Vec<LeafPageEntry<K>>,
     pub data: Vec<u8>,
     pub prev_page_id: u64,
     pub next_page_id: u64,
 }
 
-impl LeafPage {
+pub type LeafPage = KeyedLeafPage<u64>;
+
+impl<K: PageKey> KeyedLeafPage<K> {
     pub fn new(bytes: &[u8]) -> Self {
         if bytes.is_empty() {
             panic!("Cannot create LeafPage from empty bytes");
@@ -42,7 +66,13 @@ impl LeafPage {
 
     #[deprecated(since = "0.2.0", note = "Use `new` method instead")]
     pub fn new_empty(page_size: usize) -> Self {
-        LeafPage {
+        Self::empty(page_size)
+    }
+
+    /// Creates a leaf with no entries that fills a page of `page_size` bytes
+    pub fn empty(page_size: usize) -> Self {
+        KeyedLeafPage {
+            page_type: K::LEAF_PAGE_TYPE,
             page_size,
             metadata: Vec::new(),
             data: Vec::new(),
@@ -62,14 +92,14 @@ impl LeafPage {
 
         // Calculate data start offset
         // Using HEADER_SIZE constant instead of magic numbers
-        let metadata_size = self.metadata.len() * METADATA_ENTRY_SIZE;
-        let data_start = HEADER_SIZE + metadata_size;
+        let data_start = HEADER_SIZE + self.metadata_size();
 
         // Write data start offset (8 bytes)
         bytes.extend_from_slice(&(data_start as u64).to_le_bytes());
 
         // Write used bytes (8 bytes)
-        bytes.extend_from_slice(&(self.data.len() as u64).to_le_bytes());
+        let used_bytes: usize = self.metadata.iter().map(|meta| meta.value_length).sum();
+        bytes.extend_from_slice(&(used_bytes as u64).to_le_bytes());
 
         // Write prev_page_id (8 bytes)
         bytes.extend_from_slice(&self.prev_page_id.to_le_bytes());
@@ -79,12 +109,15 @@ impl LeafPage {
 
         // Write metadata entries
         for meta in &self.metadata {
-            bytes.extend_from_slice(&meta.key.to_le_bytes());
-            bytes.extend_from_slice(&(meta.value_length as u64).to_le_bytes());
+            let flag = if meta.overflow { OVERFLOW_FLAG } else { 0 };
+            meta.key.encode(&mut bytes);
+            bytes.extend_from_slice(&(meta.value_length as u64 | flag).to_le_bytes());
         }
 
-        // Write data
-        bytes.extend_from_slice(&self.data);
+        // Write data in metadata order, which is how deserialize lays it out
+        for meta in &self.metadata {
+            bytes.extend_from_slice(&self.data[meta.value_offset..meta.value_offset + meta.value_length]);
+        }
 
         bytes
     }
@@ -102,17 +135,16 @@ impl LeafPage {
         let mut offset = 0;
 
         // Read page type (1 byte)
-        let page_type = PageType::from_u8(bytes[offset]).unwrap_or(PageType::LeafPage);
+        let page_type = PageType::from_u8(bytes[offset]).unwrap_or(K::LEAF_PAGE_TYPE);
 
-        // If this is a FREE page, return an empty LeafPage
+        // If this is a FREE page, return an empty page that keeps its free list link
         if page_type == PageType::FREE {
-            return LeafPage {
-                page_size: bytes.len(),
-                metadata: Vec::new(),
-                data: Vec::new(),
-                prev_page_id: 0,
-                next_page_id: 0,
-            };
+            let next_offset = HEADER_SIZE - NEXT_PAGE_ID_SIZE;
+            let next_page_id = u64::from_le_bytes(bytes[next_offset..HEADER_SIZE].try_into().unwrap());
+            let mut free_page = KeyedLeafPage::empty(bytes.len());
+            free_page.page_type = PageType::FREE;
+            free_page.set_next_page_id(next_page_id);
+            return free_page;
         }
 
         offset += 1;
@@ -142,22 +174,34 @@ impl LeafPage {
         let mut current_offset = 0;
         for _ in 0..count {
             // Check if there's enough data for the metadata entry
-            if offset + 16 > bytes.len() {
+            let (key, key_size) = match K::decode(&bytes[offset..]) {
+                Some(decoded) => decoded,
+                None => break,
+            };
+            if offset + key_size + VALUE_LENGTH_SIZE > bytes.len() {
                 break;
             }
 
-            let key = u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap());
-            offset += 8;
-            let value_length = u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap()) as usize;
+            offset += key_size;
+            let stored_length = u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap());
+            let value_length = (stored_length & !OVERFLOW_FLAG) as usize;
             offset += 8;
             metadata.push(LeafPageEntry {
                 key,
                 value_offset: current_offset,
                 value_length,
+                overflow: stored_length & OVERFLOW_FLAG != 0,
             });
             current_offset += value_length;
         }
 
+        // Pages written before metadata was kept sorted may hold it in
+        // insertion order. Each entry carries its own data offset, so
+        // sorting here leaves the values where they are.
+        if !metadata.is_sorted_by(|a, b| a.key <= b.key) {
+            metadata.sort_by(|a, b| a.key.cmp(&b.key));
+        }
+
         // Read data
         let data_start_usize = data_start as usize;
         let used_bytes_usize = used_bytes as usize;
@@ -168,7 +212,8 @@ impl LeafPage {
             Vec::new()
         };
 
-        LeafPage {
+        KeyedLeafPage {
+            page_type: K::LEAF_PAGE_TYPE,
             page_size: bytes.len(),
             metadata,
             data,
@@ -178,10 +223,10 @@ impl LeafPage {
     }
 
     pub fn page_type(&self) -> PageType {
-        PageType::LeafPage // Always return LeafPage type
+        self.page_type
     }
 
-    pub fn metadata(&self) -> &[LeafPageEntry] {
+    pub fn metadata(&self) -> &[LeafPageEntry<K>] {
         &self.metadata
     }
 
@@ -205,58 +250,83 @@ impl LeafPage {
         self.next_page_id = page_id;
     }
 
-    // New method that takes a u64 key
-    pub fn get(&self, key: u64) -> Option<&[u8]> {
-        // Find the metadata for the key
-        for meta in &self.metadata {
-            if meta.key == key {
-                return Some(&self.data[meta.value_offset..meta.value_offset + meta.value_length]);
-            }
-        }
-        None
+    // Takes a u64 key, or a byte slice in a byte-key page
+    pub fn get(&self, key: K::Ref<'_>) -> Option<&[u8]> {
+        let meta = &self.metadata[self.position(key).ok()?];
+        Some(&self.data[meta.value_offset..meta.value_offset + meta.value_length])
     }
 
-    // New method that takes a u64 key
-    pub fn put(&mut self, key: u64, value: &[u8]) -> bool {
+    // Binary search of the metadata, which is kept sorted by key: the index
+    // of the entry for `key`, or where it would be inserted
+    fn position(&self, key: K::Ref<'_>) -> Result<usize, usize> {
+        self.metadata.binary_search_by(|meta| meta.key.cmp_ref(key))
+    }
+
+    // Takes a u64 key, or a byte slice in a byte-key page
+    pub fn put(&mut self, key: K::Ref<'_>, value: &[u8]) -> bool {
+        self.put_entry(K::from_ref(key), value, false)
+    }
+
+    /// Stores an overflow pointer record for `key` in place of its value
+    pub fn put_overflow(&mut self, key: K::Ref<'_>, record: &[u8]) -> bool {
+        self.put_entry(K::from_ref(key), record, true)
+    }
+
+    /// Stores an item taken from another page, keeping its overflow flag
+    pub fn put_item(&mut self, item: &LeafPageItem<K>) -> bool {
+        self.put_entry(item.key.clone(), &item.bytes, item.overflow)
+    }
+
+    /// Whether the entry for `key` holds an overflow pointer record
+    pub fn is_overflow(&self, key: K::Ref<'_>) -> bool {
+        self.position(key).is_ok_and(|pos| self.metadata[pos].overflow)
+    }
+
+    /// Returns the page's entries in key order
+    pub fn sorted_items(&self) -> Vec<LeafPageItem<K>> {
+        self.metadata.iter()
+            .map(|meta| LeafPageItem {
+                key: meta.key.clone(),
+                bytes: self.data[meta.value_offset..meta.value_offset + meta.value_length].to_vec(),
+                overflow: meta.overflow,
+            })
+            .collect()
+    }
+
+    fn put_entry(&mut self, key: K, value: &[u8], overflow: bool) -> bool {
         // Check if key already exists
-        if let Some(pos) = self.metadata.iter().position(|meta| meta.key == key) {
+        let pos = self.metadata.binary_search_by(|meta| meta.key.cmp(&key));
+        if let Ok(pos) = pos {
             // Key exists, update the value
-            let old_meta = self.metadata[pos];
-            let required_space = if value.len() > old_meta.value_length {
-                // Only count the additional space needed
-                value.len() - old_meta.value_length
-            } else {
-                0 // No additional space needed if new value is smaller
-            };
+            let old_value_length = self.metadata[pos].value_length;
 
-            // Calculate total space after update
-            let total_space = self.data.len() + required_space;
+            // Calculate total space after replacing the old value
+            let total_space = HEADER_SIZE + self.metadata_size() + self.data.len() - old_value_length + value.len();
 
             // Check if we have enough space
             if total_space > self.page_size {
                 return false; // Not enough space
             }
 
-            // Remove old data and metadata
-            self.metadata.remove(pos);
-            self.compact_data();
-
-            // Add new data and metadata
-            let new_meta = LeafPageEntry {
-                key,
-                value_offset: self.data.len(),
-                value_length: value.len(),
-            };
-
-            self.data.extend_from_slice(value);
-            self.metadata.push(new_meta);
+            let meta = &mut self.metadata[pos];
+            meta.overflow = overflow;
+            if old_value_length == value.len() {
+                // Overwrite the value where it is
+                self.data[meta.value_offset..meta.value_offset + old_value_length].copy_from_slice(value);
+            } else {
+                // Point the entry at the new value, then drop the old bytes
+                meta.value_offset = self.data.len();
+                meta.value_length = value.len();
+                self.data.extend_from_slice(value);
+                self.compact_data();
+            }
 
             return true;
         }
 
         // Calculate total space needed for new entry
         let required_space = value.len();
-        let metadata_size = (self.metadata.len() + 1) * METADATA_ENTRY_SIZE;
+        let metadata_size = self.metadata_size() + key.encoded_size() + VALUE_LENGTH_SIZE;
         // Using HEADER_SIZE constant instead of magic numbers
         let total_space = self.data.len() + required_space + metadata_size + HEADER_SIZE;
 
@@ -270,21 +340,22 @@ impl LeafPage {
             key,
             value_offset: self.data.len(),
             value_length: value.len(),
+            overflow,
         };
 
         // Add the new data
         self.data.extend_from_slice(value);
 
-        // Add the new metadata
-        self.metadata.push(new_meta);
+        // Add the new metadata at its place in key order
+        self.metadata.insert(pos.unwrap_err(), new_meta);
 
         true
     }
 
-    // New method that takes a u64 key
-    pub fn delete(&mut self, key: u64) -> bool {
+    // Takes a u64 key, or a byte slice in a byte-key page
+    pub fn delete(&mut self, key: K::Ref<'_>) -> bool {
         // Find and remove the metadata
-        if let Some(pos) = self.metadata.iter().position(|meta| meta.key == key) {
+        if let Ok(pos) = self.position(key) {
             self.metadata.remove(pos);
             self.compact_data();
             true
@@ -293,13 +364,23 @@ impl LeafPage {
         }
     }
 
+    /// Returns the number of bytes the page takes when serialized
+    pub fn used_space(&self) -> usize {
+        HEADER_SIZE + self.metadata_size() + self.data.len()
+    }
+
+    // Bytes taken by the serialized metadata entries
+    fn metadata_size(&self) -> usize {
+        self.metadata.iter().map(|meta| meta.key.encoded_size() + VALUE_LENGTH_SIZE).sum()
+    }
+
     pub fn is_full(&self, value: &[u8]) -> bool {
         // Calculate space needed for new entry
-        let new_metadata_size = std::mem::size_of::<LeafPageEntry>();
+        let new_metadata_size = std::mem::size_of::<LeafPageEntry<K>>();
         let new_data_size = value.len();
 
         // Calculate current space used
-        let current_metadata_size = self.metadata.len() * std::mem::size_of::<LeafPageEntry>();
+        let current_metadata_size = self.metadata.len() * std::mem::size_of::<LeafPageEntry<K>>();
         let current_data_size = self.data.len();
 
         // Using HEADER_SIZE constant instead of calculating it again
@@ -308,26 +389,23 @@ impl LeafPage {
         current_metadata_size + current_data_size + new_metadata_size + new_data_size + HEADER_SIZE > self.page_size
     }
 
-    pub fn split(&mut self) -> Option<LeafPage> {
+    pub fn split(&mut self) -> Option<KeyedLeafPage<K>> {
         if self.metadata.len() < 2 {
             return None;
         }
 
-        // Sort metadata by key for consistent splitting
-        self.metadata.sort_by(|a, b| a.key.cmp(&b.key));
-
         // Calculate split point
         let split_point = self.metadata.len() / 2;
 
         // Create new page with same size
-        let mut new_page = LeafPage::new_empty(self.page_size);
+        let mut new_page = KeyedLeafPage::empty(self.page_size);
 
         // First pass: collect all data
         let mut all_data = Vec::new();
         for meta in &self.metadata {
-            let key = meta.key;
+            let key = meta.key.clone();
             let value = self.data[meta.value_offset..meta.value_offset + meta.value_length].to_vec();
-            all_data.push((key, value));
+            all_data.push((key, value, meta.overflow));
         }
 
         // Clear current data and metadata
@@ -337,13 +415,14 @@ impl LeafPage {
         let mut new_metadata = Vec::new();
 
         // Second pass: split data
-        for (i, (key, value)) in all_data.into_iter().enumerate() {
+        for (i, (key, value, overflow)) in all_data.into_iter().enumerate() {
             if i < split_point {
                 // Keep in current page
                 let new_meta = LeafPageEntry {
                     key,
                     value_offset: self.data.len(),
                     value_length: value.len(),
+                    overflow,
                 };
                 self.data.extend_from_slice(&value);
                 self.metadata.push(new_meta);
@@ -353,6 +432,7 @@ impl LeafPage {
                     key,
                     value_offset: new_data.len(),
                     value_length: value.len(),
+                    overflow,
                 };
                 new_data.extend_from_slice(&value);
                 new_metadata.push(new_meta);
@@ -366,39 +446,13 @@ impl LeafPage {
         Some(new_page)
     }
 
-    // Kept for backward compatibility
-    #[deprecated(since = "0.2.0", note = "Use `new` method instead")]
-    pub fn new_with_size(page_size: usize) -> Self {
-        LeafPage {
-            page_size,
-            metadata: Vec::new(),
-            data: Vec::new(),
-            prev_page_id: 0,
-            next_page_id: 0,
-        }
-    }
-
-    /// Creates a new FREE page with the given size
-    pub fn new_free_page(page_size: usize) -> Self {
-        LeafPage {
-            page_size,
-            metadata: Vec::new(),
-            data: Vec::new(),
-            prev_page_id: 0,
-            next_page_id: 0,
-        }
-    }
-
     fn compact_data(&mut self) {
         if self.metadata.is_empty() {
             self.data.clear();
             return;
         }
 
-        // Sort metadata by key
-        self.metadata.sort_by_key(|m| m.key);
-
-        // Rebuild data
+        // Rebuild data in key order
         let mut new_data = Vec::new();
         let mut new_metadata = Vec::new();
 
@@ -406,9 +460,10 @@ impl LeafPage {
             let value = &self.data[meta.value_offset..meta.value_offset + meta.value_length];
 
             let new_meta = LeafPageEntry {
-                key: meta.key,
+                key: meta.key.clone(),
                 value_offset: new_data.len(),
                 value_length: meta.value_length,
+                overflow: meta.overflow,
             };
 
             new_data.extend_from_slice(value);
@@ -419,18 +474,174 @@ impl LeafPage {
         self.metadata = new_metadata;
     }
 
+    /// Returns the largest value that fits under `key` in an otherwise
+    /// empty page
+    pub fn max_value_size_for(&self, key: &K) -> usize {
+        self.page_size.saturating_sub(HEADER_SIZE + key.encoded_size() + VALUE_LENGTH_SIZE)
+    }
+
+    /// Returns how much of a value too large for this page each overflow
+    /// page holds
+    pub fn get_value_chunk_size(&self) -> usize {
+        self.page_size.saturating_sub(OVERFLOW_HEADER_SIZE)
+    }
+}
+
+impl LeafPage {
+    // Kept for backward compatibility
+    #[deprecated(since = "0.2.0", note = "Use `new` method instead")]
+    pub fn new_with_size(page_size: usize) -> Self {
+        Self::empty(page_size)
+    }
+
+    /// Creates a new FREE page with the given size
+    ///
+    /// A FREE page uses the LeafPage layout with no entries; its next_page_id
+    /// links it to the next page in the store's free list.
+    pub fn new_free_page(page_size: usize) -> Self {
+        LeafPage {
+            page_type: PageType::FREE,
+            page_size,
+            metadata: Vec::new(),
+            data: Vec::new(),
+            prev_page_id: 0,
+            next_page_id: 0,
+        }
+    }
+
     pub fn max_value_size(&self) -> usize {
-        // Reserve space for metadata
-        let metadata_overhead = 32; // 16 bytes for metadata entry + buffer
-        self.page_size - metadata_overhead
+        // A value must fit in an otherwise empty page alongside its metadata entry
+        self.page_size.saturating_sub(HEADER_SIZE + METADATA_ENTRY_SIZE)
     }
 
     pub fn is_value_too_large(&self, value: &[u8]) -> bool {
         value.len() > self.max_value_size()
     }
+}
 
-    pub fn get_value_chunk_size(&self) -> usize {
-        self.max_value_size()
-    }
+
+// Reads a little-endian u64 at `offset`, or None if it runs past `bytes`
+pub(crate) fn read_u64(bytes: &[u8], offset: usize) -> Option<u64> {
+    let field = bytes.get(offset..offset.checked_add(8)?)?;
+    Some(u64::from_le_bytes(field.try_into().unwrap()))
+}
+
+/// A leaf page read in place from its serialized bytes.
+///
+/// Header fields are read as they're asked for and lookups search the
+/// entries where they lie, so nothing is copied out of the page. The entries
+/// must be sorted by key, as every page is written since metadata was kept
+/// sorted.
+#[derive(Debug, Clone, Copy)]
+pub struct LeafPageView<'a, K: PageKey = u64> {
+    bytes: &'a [u8],
+    count: usize,
+    _key: PhantomData<K>,
 }
 
+impl<'a, K: PageKey> LeafPageView<'a, K> {
+    pub fn new(bytes: &'a [u8]) -> Result<Self, Box<dyn Error>> {
+        if bytes.len() < HEADER_SIZE {
+            return Err(format!("Leaf page of {} bytes is shorter than its header", bytes.len()).into());
+        }
+        if PageType::from_u8(bytes[0]) != Some(K::LEAF_PAGE_TYPE) {
+            return Err(format!("Page type {} is not a {:?}", bytes[0], K::LEAF_PAGE_TYPE).into());
+        }
+
+        let count = read_u64(bytes, PAGE_TYPE_SIZE).unwrap() as usize;
+        let view = LeafPageView { bytes, count, _key: PhantomData };
+        // Fixed-size entries are reached by index, so they must all be there
+        if let Some(key_size) = K::ENCODED_SIZE {
+            let entries_end = count.checked_mul(key_size + VALUE_LENGTH_SIZE).and_then(|size| size.checked_add(HEADER_SIZE));
+            if entries_end.is_none_or(|end| end > bytes.len()) {
+                return Err(format!("Leaf page entries run past its {} bytes", bytes.len()).into());
+            }
+        }
+        Ok(view)
+    }
+
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn prev_page_id(&self) -> u64 {
+        read_u64(self.bytes, HEADER_SIZE - NEXT_PAGE_ID_SIZE - PREV_PAGE_ID_SIZE).unwrap()
+    }
+
+    pub fn next_page_id(&self) -> u64 {
+        read_u64(self.bytes, HEADER_SIZE - NEXT_PAGE_ID_SIZE).unwrap()
+    }
+
+    pub fn get(&self, key: K::Ref<'_>) -> Option<&'a [u8]> {
+        self.get_entry(key).map(|(value, _)| value)
+    }
+
+    /// Whether the entry for `key` holds an overflow pointer record
+    pub fn is_overflow(&self, key: K::Ref<'_>) -> bool {
+        self.get_entry(key).is_some_and(|(_, overflow)| overflow)
+    }
+
+    /// The bytes stored for `key`, with whether they are an overflow pointer
+    /// record rather than the value
+    pub fn get_entry(&self, key: K::Ref<'_>) -> Option<(&'a [u8], bool)> {
+        let (entry_offset, value_offset) = match K::ENCODED_SIZE {
+            Some(key_size) => self.search(key, key_size)?,
+            None => self.scan(key)?,
+        };
+
+        let stored_length = read_u64(self.bytes, entry_offset)?;
+        let value_length = (stored_length & !OVERFLOW_FLAG) as usize;
+        let data_start = read_u64(self.bytes, PAGE_TYPE_SIZE + COUNT_SIZE)? as usize;
+        let start = data_start.checked_add(value_offset)?;
+        let value = self.bytes.get(start..start.checked_add(value_length)?)?;
+        Some((value, stored_length & OVERFLOW_FLAG != 0))
+    }
+
+    // Binary search of fixed-size entries. Returns the offset of the found
+    // entry's value length and of its value within the data. Entries hold
+    // lengths rather than offsets, so the value is found by adding up the
+    // lengths of the entries before it.
+    fn search(&self, key: K::Ref<'_>, key_size: usize) -> Option<(usize, usize)> {
+        let entry_size = key_size + VALUE_LENGTH_SIZE;
+        let entry_key = |index: usize| K::decode_ref(&self.bytes[HEADER_SIZE + index * entry_size..]).map(|(key, _)| key);
+
+        let (mut low, mut high) = (0, self.count);
+        while low < high {
+            let middle = low + (high - low) / 2;
+            match K::cmp_refs(entry_key(middle)?, key) {
+                Ordering::Less => low = middle + 1,
+                Ordering::Greater => high = middle,
+                Ordering::Equal => {
+                    let value_offset = (0..middle)
+                        .map(|index| read_u64(self.bytes, HEADER_SIZE + index * entry_size + key_size).unwrap() & !OVERFLOW_FLAG)
+                        .sum::<u64>();
+                    return Some((HEADER_SIZE + middle * entry_size + key_size, value_offset as usize));
+                }
+            }
+        }
+        None
+    }
+
+    // Walks entries whose keys vary in size, stopping at the first key above
+    // `key`. Returns the same offsets as `search`.
+    fn scan(&self, key: K::Ref<'_>) -> Option<(usize, usize)> {
+        let mut offset = HEADER_SIZE;
+        let mut value_offset = 0;
+        for _ in 0..self.count {
+            let (entry_key, key_size) = K::decode_ref(self.bytes.get(offset..)?)?;
+            let length_offset = offset + key_size;
+            match K::cmp_refs(entry_key, key) {
+                Ordering::Less => {}
+                Ordering::Equal => return Some((length_offset, value_offset)),
+                Ordering::Greater => return None,
+            }
+            value_offset += (read_u64(self.bytes, length_offset)? & !OVERFLOW_FLAG) as usize;
+            offset = length_offset + VALUE_LENGTH_SIZE;
+        }
+        None
+    }
+}
@@ -1,9 +1,24 @@
 // Modules
 pub mod leaf_page;
+pub mod leaf;
+pub mod overflow_page;
+pub mod page_key;
+pub mod key_codec;
 pub mod page_store;
+pub mod file_page_store;
+pub mod superblock;
+pub mod wal;
 pub mod data_tree;
+pub mod range;
+pub mod cursor;
+pub mod write_batch;
+pub mod transaction;
+pub mod snapshot;
 pub mod branch_page;
 pub mod rle_leaf_page;
+pub mod slotted_leaf_page;
+pub mod formatter;
 pub mod data_tree2;
 
-pub use data_tree::{DataTree, KeyNotFoundError};
\ No newline at end of file
+pub use data_tree::{DataTree, KeyNotFoundError};
+pub use key_codec::KeyCodec;
\ No newline at end of file
@@ -0,0 +1,99 @@
+use std::error::Error;
+use crate::data_tree::PageType;
+
+// Constants for page header sizes
+pub const PAGE_TYPE_SIZE: usize = 1; // 1 byte for page type
+pub const NEXT_PAGE_ID_SIZE: usize = 8; // 8 bytes for next page ID
+pub const DATA_LENGTH_SIZE: usize = 8; // 8 bytes for chunk length
+pub const HEADER_SIZE: usize = PAGE_TYPE_SIZE + NEXT_PAGE_ID_SIZE + DATA_LENGTH_SIZE;
+
+// Constants for pointer record sizes
+pub const FIRST_PAGE_ID_SIZE: usize = 8; // 8 bytes for first overflow page ID
+pub const VALUE_LENGTH_SIZE: usize = 8;  // 8 bytes for total value length
+pub const POINTER_SIZE: usize = FIRST_PAGE_ID_SIZE + VALUE_LENGTH_SIZE;
+
+// One chunk of a value too large for a leaf. The chunks of a value are
+// chained through next_page_id, and the last one links to 0.
+#[derive(Debug)]
+pub struct OverflowPage {
+    pub next_page_id: u64,
+    pub data: Vec<u8>,
+}
+
+impl OverflowPage {
+    pub fn new(data: &[u8], next_page_id: u64) -> Self {
+        OverflowPage {
+            next_page_id,
+            data: data.to_vec(),
+        }
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_SIZE + self.data.len());
+
+        // Write page type (1 byte)
+        bytes.push(PageType::OverflowPage.to_u8());
+
+        // Write next_page_id (8 bytes)
+        bytes.extend_from_slice(&self.next_page_id.to_le_bytes());
+
+        // Write chunk length (8 bytes)
+        bytes.extend_from_slice(&(self.data.len() as u64).to_le_bytes());
+
+        // Write data
+        bytes.extend_from_slice(&self.data);
+
+        bytes
+    }
+
+    pub fn deserialize(bytes: &[u8]) -> Result<Self, Box<dyn Error>> {
+        if bytes.len() < HEADER_SIZE || PageType::from_u8(bytes[0]) != Some(PageType::OverflowPage) {
+            return Err("Page is not an overflow page".into());
+        }
+
+        let mut offset = PAGE_TYPE_SIZE;
+
+        // Read next_page_id (8 bytes)
+        let next_page_id = u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap());
+        offset += 8;
+
+        // Read chunk length (8 bytes)
+        let length = u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap()) as usize;
+        offset += 8;
+
+        if offset + length > bytes.len() {
+            return Err(format!("Overflow page chunk of {} bytes runs past the page", length).into());
+        }
+
+        Ok(OverflowPage {
+            next_page_id,
+            data: bytes[offset..offset + length].to_vec(),
+        })
+    }
+}
+
+// The record a leaf stores in place of a value that lives in overflow pages
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OverflowPointer {
+    pub first_page_id: u64,
+    pub value_length: u64,
+}
+
+impl OverflowPointer {
+    pub fn serialize(&self) -> [u8; POINTER_SIZE] {
+        let mut bytes = [0u8; POINTER_SIZE];
+        bytes[0..8].copy_from_slice(&self.first_page_id.to_le_bytes());
+        bytes[8..16].copy_from_slice(&self.value_length.to_le_bytes());
+        bytes
+    }
+
+    pub fn deserialize(bytes: &[u8]) -> Result<Self, Box<dyn Error>> {
+        if bytes.len() != POINTER_SIZE {
+            return Err(format!("Overflow pointer is {} bytes, expected {}", bytes.len(), POINTER_SIZE).into());
+        }
+        Ok(OverflowPointer {
+            first_page_id: u64::from_le_bytes(bytes[0..8].try_into().unwrap()),
+            value_length: u64::from_le_bytes(bytes[8..16].try_into().unwrap()),
+        })
+    }
+}
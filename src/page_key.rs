@@ -0,0 +1,243 @@
+use std::cmp::Ordering;
+use std::fmt::Debug;
+use crate::data_tree::PageType;
+
+// Size of the length prefix in front of a byte-string key
+pub const KEY_LENGTH_SIZE: usize = 2; // 2 bytes for key length
+
+/// A key type that leaf and branch pages can store.
+///
+/// u64 keys are stored as 8 fixed bytes, as they always have been. Byte-string
+/// keys are stored with a length prefix, compare lexicographically and use
+/// page types of their own, so a tree of one kind can't be read as the other.
+pub trait PageKey: Clone + Ord + Debug {
+    /// The form callers pass keys in: the key itself for u64, a slice for
+    /// byte strings
+    type Ref<'a>: Copy;
+
+    /// Page type of the leaves holding this kind of key
+    const LEAF_PAGE_TYPE: PageType;
+    /// Page type of the branches holding this kind of key
+    const BRANCH_PAGE_TYPE: PageType;
+    /// Page type of the slotted leaves holding this kind of key
+    const SLOTTED_LEAF_PAGE_TYPE: PageType;
+    /// Number of bytes `encode` writes when it is the same for every key,
+    /// which lets page views binary search entries in place
+    const ENCODED_SIZE: Option<usize>;
+
+    fn from_ref(key: Self::Ref<'_>) -> Self;
+
+    fn to_ref(&self) -> Self::Ref<'_>;
+
+    /// Compares the key with one passed in by a caller
+    fn cmp_ref(&self, key: Self::Ref<'_>) -> Ordering;
+
+    /// Compares two keys in their borrowed form
+    fn cmp_refs(a: Self::Ref<'_>, b: Self::Ref<'_>) -> Ordering;
+
+    /// The smallest key, which the first page of every level starts at
+    fn min_key() -> Self;
+
+    /// Number of bytes `encode` writes
+    fn encoded_size(&self) -> usize;
+
+    fn encode(&self, bytes: &mut Vec<u8>);
+
+    /// Reads a key from the start of `bytes`, returning it with the number of
+    /// bytes it took. None if `bytes` is too short.
+    fn decode(bytes: &[u8]) -> Option<(Self, usize)>;
+
+    /// Like `decode`, but borrows a byte-string key from `bytes` instead of
+    /// copying it
+    fn decode_ref(bytes: &[u8]) -> Option<(Self::Ref<'_>, usize)>;
+
+    /// A key above `left` and at most `right`, to separate the pages holding
+    /// them in a branch. `left` must be below `right`.
+    fn separator(left: &Self, right: &Self) -> Self;
+
+    /// Writes the key as one component of a composite key, in a form whose
+    /// byte order matches the key order even when more bytes follow it
+    fn encode_ordered(&self, bytes: &mut Vec<u8>);
+
+    /// Reads a component written by `encode_ordered` from the start of
+    /// `bytes`, returning it with the number of bytes it took
+    fn decode_ordered(bytes: &[u8]) -> Option<(Self, usize)>;
+
+    /// The key as a u64, for run-length leaves, which only hold u64 keys.
+    /// None for key types that can't be stored in them.
+    fn run_key(key: Self::Ref<'_>) -> Option<u64>;
+
+    /// The key for a u64 read from a run-length leaf, or None for key types
+    /// that can't be stored in them
+    fn from_run_key(key: u64) -> Option<Self>;
+
+    /// Whether keys of this type can be stored in run-length leaves
+    fn supports_runs() -> bool {
+        Self::from_run_key(0).is_some()
+    }
+}
+
+impl PageKey for u64 {
+    type Ref<'a> = u64;
+
+    const LEAF_PAGE_TYPE: PageType = PageType::LeafPage;
+    const BRANCH_PAGE_TYPE: PageType = PageType::BranchPage;
+    const SLOTTED_LEAF_PAGE_TYPE: PageType = PageType::SlottedLeafPage;
+    const ENCODED_SIZE: Option<usize> = Some(8);
+
+    fn from_ref(key: u64) -> Self {
+        key
+    }
+
+    fn to_ref(&self) -> u64 {
+        *self
+    }
+
+    fn cmp_ref(&self, key: u64) -> Ordering {
+        self.cmp(&key)
+    }
+
+    fn cmp_refs(a: u64, b: u64) -> Ordering {
+        a.cmp(&b)
+    }
+
+    fn min_key() -> Self {
+        0
+    }
+
+    fn encoded_size(&self) -> usize {
+        8
+    }
+
+    fn encode(&self, bytes: &mut Vec<u8>) {
+        bytes.extend_from_slice(&self.to_le_bytes());
+    }
+
+    fn decode(bytes: &[u8]) -> Option<(Self, usize)> {
+        let key_bytes = bytes.get(0..8)?;
+        Some((u64::from_le_bytes(key_bytes.try_into().unwrap()), 8))
+    }
+
+    fn decode_ref(bytes: &[u8]) -> Option<(u64, usize)> {
+        Self::decode(bytes)
+    }
+
+    fn separator(_left: &Self, right: &Self) -> Self {
+        *right
+    }
+
+    // Big-endian, so the bytes compare like the numbers
+    fn encode_ordered(&self, bytes: &mut Vec<u8>) {
+        bytes.extend_from_slice(&self.to_be_bytes());
+    }
+
+    fn decode_ordered(bytes: &[u8]) -> Option<(Self, usize)> {
+        let key_bytes = bytes.get(0..8)?;
+        Some((u64::from_be_bytes(key_bytes.try_into().unwrap()), 8))
+    }
+
+    fn run_key(key: u64) -> Option<u64> {
+        Some(key)
+    }
+
+    fn from_run_key(key: u64) -> Option<Self> {
+        Some(key)
+    }
+}
+
+impl PageKey for Vec<u8> {
+    type Ref<'a> = &'a [u8];
+
+    const LEAF_PAGE_TYPE: PageType = PageType::ByteLeafPage;
+    const BRANCH_PAGE_TYPE: PageType = PageType::ByteBranchPage;
+    const SLOTTED_LEAF_PAGE_TYPE: PageType = PageType::ByteSlottedLeafPage;
+    const ENCODED_SIZE: Option<usize> = None;
+
+    fn from_ref(key: &[u8]) -> Self {
+        key.to_vec()
+    }
+
+    fn to_ref(&self) -> &[u8] {
+        self
+    }
+
+    fn cmp_ref(&self, key: &[u8]) -> Ordering {
+        self.as_slice().cmp(key)
+    }
+
+    fn cmp_refs(a: &[u8], b: &[u8]) -> Ordering {
+        a.cmp(b)
+    }
+
+    fn min_key() -> Self {
+        Vec::new()
+    }
+
+    fn encoded_size(&self) -> usize {
+        KEY_LENGTH_SIZE + self.len()
+    }
+
+    fn encode(&self, bytes: &mut Vec<u8>) {
+        bytes.extend_from_slice(&(self.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(self);
+    }
+
+    fn decode(bytes: &[u8]) -> Option<(Self, usize)> {
+        let (key, size) = Self::decode_ref(bytes)?;
+        Some((key.to_vec(), size))
+    }
+
+    fn decode_ref(bytes: &[u8]) -> Option<(&[u8], usize)> {
+        let length_bytes = bytes.get(0..KEY_LENGTH_SIZE)?;
+        let length = u16::from_le_bytes(length_bytes.try_into().unwrap()) as usize;
+        let key = bytes.get(KEY_LENGTH_SIZE..KEY_LENGTH_SIZE + length)?;
+        Some((key, KEY_LENGTH_SIZE + length))
+    }
+
+    // The shortest prefix of `right` that is still above `left`, which keeps
+    // branch entries small when keys share long prefixes
+    fn separator(left: &Self, right: &Self) -> Self {
+        let common = left.iter().zip(right.iter()).take_while(|(l, r)| l == r).count();
+        right[..(common + 1).min(right.len())].to_vec()
+    }
+
+    // A length prefix would order short keys first, so zero bytes are
+    // escaped as 0x00 0xFF and the key ends with 0x00 0x00, which sorts
+    // below any byte that can follow a prefix
+    fn encode_ordered(&self, bytes: &mut Vec<u8>) {
+        for &b in self {
+            bytes.push(b);
+            if b == 0 {
+                bytes.push(0xFF);
+            }
+        }
+        bytes.extend_from_slice(&[0, 0]);
+    }
+
+    fn decode_ordered(bytes: &[u8]) -> Option<(Self, usize)> {
+        let mut key = Vec::new();
+        let mut offset = 0;
+        loop {
+            let b = *bytes.get(offset)?;
+            if b != 0 {
+                key.push(b);
+                offset += 1;
+                continue;
+            }
+            match *bytes.get(offset + 1)? {
+                0 => return Some((key, offset + 2)),
+                0xFF => key.push(0),
+                _ => return None,
+            }
+            offset += 2;
+        }
+    }
+
+    fn run_key(_key: &[u8]) -> Option<u64> {
+        None
+    }
+
+    fn from_run_key(_key: u64) -> Option<Self> {
+        None
+    }
+}
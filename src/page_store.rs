@@ -1,9 +1,14 @@
 use std::error::Error;
 use std::collections::{HashMap, HashSet};
+use crate::data_tree::PageType;
 use crate::leaf_page::LeafPage;
+use crate::superblock::SUPERBLOCK_PAGE_ID;
 use crc::{Crc, CRC_32_ISCSI};
 
-const DEFAULT_PAGE_SIZE: usize = 4096;
+pub const DEFAULT_PAGE_SIZE: usize = 4096;
+
+// Every stored page carries a 4 byte CRC trailer
+pub const CRC_SIZE: usize = 4;
 
 // CRC-32/ISCSI is a good choice for data integrity checks
 const CRC: Crc<u32> = Crc::<u32>::new(&CRC_32_ISCSI);
@@ -19,6 +24,61 @@ impl std::fmt::Display for PageCorruptionError {
 
 impl Error for PageCorruptionError {}
 
+pub(crate) fn calculate_crc(data: &[u8]) -> u32 {
+    CRC.checksum(data)
+}
+
+pub(crate) fn verify_crc(data: &[u8], expected_crc: u32) -> bool {
+    calculate_crc(data) == expected_crc
+}
+
+pub(crate) fn add_crc(mut bytes: Vec<u8>) -> Vec<u8> {
+    let crc = calculate_crc(&bytes);
+    bytes.extend_from_slice(&crc.to_le_bytes());
+    bytes
+}
+
+pub(crate) fn extract_and_verify_crc(bytes: &[u8]) -> Result<&[u8], Box<dyn Error>> {
+    if bytes.len() < CRC_SIZE {
+        return Err(Box::new(PageCorruptionError));
+    }
+    let (data, crc_bytes) = bytes.split_at(bytes.len() - CRC_SIZE);
+    let expected_crc = u32::from_le_bytes(crc_bytes.try_into().unwrap());
+
+    if !verify_crc(data, expected_crc) {
+        return Err(Box::new(PageCorruptionError));
+    }
+
+    Ok(data)
+}
+
+// Write `page_id` as a FREE page and make it the new head of the free list
+pub(crate) fn push_free_page<S: PageStore + ?Sized>(store: &mut S, page_id: u64) -> Result<(), Box<dyn Error>> {
+    let mut free_page = LeafPage::new_free_page(store.page_size());
+    free_page.set_next_page_id(store.free_list_head());
+    store.put_page_bytes(page_id, &free_page.serialize())?;
+    store.set_free_list_head(page_id);
+    Ok(())
+}
+
+// Unlink the head of the free list. A head that can't be read back as a
+// FREE page is an error, and the list is left as it is so that a failed
+// read doesn't lose the pages behind it.
+pub(crate) fn pop_free_page<S: PageStore + ?Sized>(store: &mut S) -> Result<Option<u64>, Box<dyn Error>> {
+    let page_id = store.free_list_head();
+    if page_id == 0 {
+        return Ok(None);
+    }
+
+    let bytes = store.get_page_bytes(page_id)?;
+    if bytes.first().and_then(|page_type| PageType::from_u8(*page_type)) != Some(PageType::FREE) {
+        return Err(format!("Free list head {} is not a free page", page_id).into());
+    }
+
+    store.set_free_list_head(LeafPage::deserialize(&bytes).next_page_id());
+    Ok(Some(page_id))
+}
+
 // Trait for storing and retrieving pages
 pub trait PageStore {
     fn get_page_bytes(&self, page_id: u64) -> Result<Vec<u8>, Box<dyn Error>>;
@@ -37,6 +97,81 @@ pub trait PageStore {
     fn mark_page_dirty(&mut self, page_id: u64);
     fn dirty_pages(&self) -> &HashSet<u64>;
     fn clear_dirty_pages(&mut self);
+
+    // Methods for the free list of FREE pages, threaded through their next_page_id.
+    // A store without a free list never reuses the pages it frees.
+    fn free_list_head(&self) -> u64 {
+        0
+    }
+
+    fn set_free_list_head(&mut self, _page_id: u64) {}
+}
+
+// A borrowed store is a store too, so several trees can share one
+impl<S: PageStore + ?Sized> PageStore for &mut S {
+    fn get_page_bytes(&self, page_id: u64) -> Result<Vec<u8>, Box<dyn Error>> {
+        (**self).get_page_bytes(page_id)
+    }
+
+    fn put_page_bytes(&mut self, page_id: u64, bytes: &[u8]) -> Result<(), Box<dyn Error>> {
+        (**self).put_page_bytes(page_id, bytes)
+    }
+
+    fn allocate_page(&mut self) -> u64 {
+        (**self).allocate_page()
+    }
+
+    fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        (**self).flush()
+    }
+
+    fn page_size(&self) -> usize {
+        (**self).page_size()
+    }
+
+    fn get_next_page_id(&self, page_id: u64) -> Option<u64> {
+        (**self).get_next_page_id(page_id)
+    }
+
+    fn get_prev_page_id(&self, page_id: u64) -> Option<u64> {
+        (**self).get_prev_page_id(page_id)
+    }
+
+    fn link_pages(&mut self, prev_page_id: u64, next_page_id: u64) -> Result<(), Box<dyn Error>> {
+        (**self).link_pages(prev_page_id, next_page_id)
+    }
+
+    fn page_exists(&self, page_id: u64) -> bool {
+        (**self).page_exists(page_id)
+    }
+
+    fn free_page(&mut self, page_id: u64) -> Result<(), Box<dyn Error>> {
+        (**self).free_page(page_id)
+    }
+
+    fn get_page_count(&self) -> usize {
+        (**self).get_page_count()
+    }
+
+    fn mark_page_dirty(&mut self, page_id: u64) {
+        (**self).mark_page_dirty(page_id)
+    }
+
+    fn dirty_pages(&self) -> &HashSet<u64> {
+        (**self).dirty_pages()
+    }
+
+    fn clear_dirty_pages(&mut self) {
+        (**self).clear_dirty_pages()
+    }
+
+    fn free_list_head(&self) -> u64 {
+        (**self).free_list_head()
+    }
+
+    fn set_free_list_head(&mut self, page_id: u64) {
+        (**self).set_free_list_head(page_id)
+    }
 }
 
 // In-memory implementation of PageStore for testing
@@ -45,6 +180,14 @@ pub struct InMemoryPageStore {
     next_page_id: u64,
     page_size: usize,
     dirty_pages: HashSet<u64>,
+    free_list_head: u64,
+    free_pages: HashSet<u64>,
+}
+
+impl Default for InMemoryPageStore {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl InMemoryPageStore {
@@ -58,6 +201,8 @@ impl InMemoryPageStore {
             next_page_id: 1,
             page_size,
             dirty_pages: HashSet::new(),
+            free_list_head: 0,
+            free_pages: HashSet::new(),
         }
     }
 
@@ -69,48 +214,8 @@ impl InMemoryPageStore {
             }
         }
     }
-
-    fn calculate_crc(data: &[u8]) -> u32 {
-        CRC.checksum(data)
-    }
-
-    fn verify_crc(data: &[u8], expected_crc: u32) -> bool {
-        Self::calculate_crc(data) == expected_crc
-    }
-
-    fn add_crc(mut bytes: Vec<u8>) -> Vec<u8> {
-        let crc = Self::calculate_crc(&bytes);
-        bytes.extend_from_slice(&crc.to_le_bytes());
-        bytes
-    }
-
-    fn extract_and_verify_crc(bytes: &[u8]) -> Result<&[u8], Box<dyn Error>> {
-        if bytes.len() < 4 {
-            return Err(Box::new(PageCorruptionError));
-        }
-        let (data, crc_bytes) = bytes.split_at(bytes.len() - 4);
-        let expected_crc = u32::from_le_bytes(crc_bytes.try_into().unwrap());
-
-        if !Self::verify_crc(data, expected_crc) {
-            return Err(Box::new(PageCorruptionError));
-        }
-
-        Ok(data)
-    }
-
-    pub fn page_exists(&self, page_id: u64) -> bool {
-        self.pages.contains_key(&page_id)
-    }
-
-    pub fn free_page(&mut self, page_id: u64) -> Result<(), Box<dyn Error>> {
-        // Remove the page from the store
-        self.pages.remove(&page_id);
-        Ok(())
-    }
 }
 
-const CRC_SIZE: usize = 4;
-
 impl PageStore for InMemoryPageStore {
     fn get_page_bytes(&self, page_id: u64) -> Result<Vec<u8>, Box<dyn Error>> {
         let bytes = self.pages.get(&page_id)
@@ -121,7 +226,7 @@ impl PageStore for InMemoryPageStore {
             )))?;
 
         // Extract data and verify CRC
-        let data = Self::extract_and_verify_crc(&bytes)?;
+        let data = extract_and_verify_crc(&bytes)?;
         Ok(data.to_vec())
     }
 
@@ -134,9 +239,16 @@ impl PageStore for InMemoryPageStore {
         }
 
         // Add CRC to the page
-        let bytes_with_crc = Self::add_crc(bytes.to_vec());
+        let bytes_with_crc = add_crc(bytes.to_vec());
         self.pages.insert(page_id, bytes_with_crc);
 
+        // Track FREE pages by their type byte
+        if bytes.first() == Some(&PageType::FREE.to_u8()) {
+            self.free_pages.insert(page_id);
+        } else {
+            self.free_pages.remove(&page_id);
+        }
+
         // Mark the page as dirty
         self.mark_page_dirty(page_id);
 
@@ -144,11 +256,19 @@ impl PageStore for InMemoryPageStore {
     }
 
     fn allocate_page(&mut self) -> u64 {
-        let page_id = self.next_page_id;
-        self.next_page_id += 1;
+        // Reuse a freed page before growing the id space. A free list that can't be read
+        // is kept for later, and a new page is used this time.
+        let page_id = match pop_free_page(self) {
+            Ok(Some(page_id)) => page_id,
+            _ => {
+                let page_id = self.next_page_id;
+                self.next_page_id += 1;
+                page_id
+            }
+        };
 
         // Initialize the page with an empty LeafPage
-        let page = LeafPage::new_empty(self.page_size);
+        let page = LeafPage::empty(self.page_size);
         self.put_page_bytes(page_id, &page.serialize()).unwrap();
 
         page_id
@@ -203,17 +323,30 @@ impl PageStore for InMemoryPageStore {
     }
 
     fn page_exists(&self, page_id: u64) -> bool {
-        self.pages.contains_key(&page_id)
+        self.pages.contains_key(&page_id) && !self.free_pages.contains(&page_id)
     }
 
     fn free_page(&mut self, page_id: u64) -> Result<(), Box<dyn Error>> {
-        self.pages.remove(&page_id);
-        self.dirty_pages.remove(&page_id);
-        Ok(())
+        if page_id == SUPERBLOCK_PAGE_ID {
+            return Err("The superblock page cannot be freed".into());
+        }
+        if !self.page_exists(page_id) {
+            return Err(Box::new(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "Page not found",
+            )));
+        }
+
+        push_free_page(self, page_id)
     }
 
     fn get_page_count(&self) -> usize {
-        self.pages.len()
+        // Neither the superblock in page 0 nor FREE pages count as tree pages
+        let mut count = self.pages.len() - self.free_pages.len();
+        if self.pages.contains_key(&SUPERBLOCK_PAGE_ID) {
+            count -= 1;
+        }
+        count
     }
 
     fn mark_page_dirty(&mut self, page_id: u64) {
@@ -227,4 +360,12 @@ impl PageStore for InMemoryPageStore {
     fn clear_dirty_pages(&mut self) {
         self.dirty_pages.clear();
     }
+
+    fn free_list_head(&self) -> u64 {
+        self.free_list_head
+    }
+
+    fn set_free_list_head(&mut self, page_id: u64) {
+        self.free_list_head = page_id;
+    }
 }
\ No newline at end of file
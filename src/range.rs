@@ -0,0 +1,377 @@
+use std::collections::VecDeque;
+use std::error::Error;
+use std::ops::Bound;
+use crate::data_tree::DataTree;
+use crate::leaf::LeafRun;
+use crate::leaf_page::LeafPageItem;
+use crate::key_codec::KeyCodec;
+use crate::page_key::PageKey;
+use crate::page_store::PageStore;
+
+// The runs of one leaf that an end of the iterator has not yet yielded,
+// with the links to continue from once they run out
+struct LeafEntries<K> {
+    prev_page_id: u64,
+    next_page_id: u64,
+    runs: VecDeque<LeafRun<K>>,
+}
+
+impl<K: PageKey> LeafEntries<K> {
+    fn read<S: PageStore, C: KeyCodec<Encoded = K>>(tree: &DataTree<S, C>, page_id: u64) -> Result<Self, Box<dyn Error>> {
+        let leaf_page = tree.read_leaf(page_id)?;
+        Ok(LeafEntries {
+            prev_page_id: leaf_page.prev_page_id(),
+            next_page_id: leaf_page.next_page_id(),
+            runs: leaf_page.runs().into(),
+        })
+    }
+
+    // Take the first entry, leaving the rest of its run in place
+    fn pop_front(&mut self) -> Option<LeafPageItem<K>> {
+        let (item, rest) = self.runs.pop_front()?.pop_first();
+        if let Some(rest) = rest {
+            self.runs.push_front(rest);
+        }
+        Some(item)
+    }
+
+    // Drop the keys below `lower`, so a run reaching into the range isn't
+    // walked key by key up to it
+    fn skip_below(&mut self, lower: &Bound<K>) {
+        while let Some(run) = self.runs.pop_front() {
+            if let Some(rest) = run.within(lower.as_ref(), Bound::Unbounded) {
+                self.runs.push_front(rest);
+                return;
+            }
+        }
+    }
+
+    // Drop the keys above `upper`
+    fn skip_above(&mut self, upper: &Bound<K>) {
+        while let Some(run) = self.runs.pop_back() {
+            if let Some(rest) = run.within(Bound::Unbounded, upper.as_ref()) {
+                self.runs.push_back(rest);
+                return;
+            }
+        }
+    }
+
+    // Take the last entry, leaving the rest of its run in place
+    fn pop_back(&mut self) -> Option<LeafPageItem<K>> {
+        let (item, rest) = self.runs.pop_back()?.pop_last();
+        if let Some(rest) = rest {
+            self.runs.push_back(rest);
+        }
+        Some(item)
+    }
+}
+
+/// Iterator over the entries of a DataTree in key order, created by
+/// `DataTree::range` and `DataTree::iter`.
+///
+/// Each end descends from the root once, to the first leaf it reads, and then
+/// follows the leaves' prev/next links. The two ends stop when they meet. If a
+/// page can't be read the iterator ends early; `take_error` returns the cause.
+pub struct Range<'a, S: PageStore, K: KeyCodec = u64> {
+    tree: &'a DataTree<S, K>,
+    lower: Bound<K::Encoded>,
+    upper: Bound<K::Encoded>,
+    front: Option<LeafEntries<K::Encoded>>,
+    back: Option<LeafEntries<K::Encoded>>,
+    finished: bool,
+    error: Option<Box<dyn Error>>,
+}
+
+impl<'a, S: PageStore, K: KeyCodec> Range<'a, S, K> {
+    pub(crate) fn new(tree: &'a DataTree<S, K>, lower: Bound<K::Encoded>, upper: Bound<K::Encoded>) -> Self {
+        Range {
+            tree,
+            lower,
+            upper,
+            front: None,
+            back: None,
+            finished: false,
+            error: None,
+        }
+    }
+
+    /// Returns the error that ended the iteration early, if any
+    pub fn take_error(&mut self) -> Option<Box<dyn Error>> {
+        self.error.take()
+    }
+
+    fn above_lower(&self, key: &K::Encoded) -> bool {
+        match &self.lower {
+            Bound::Included(lower) => key >= lower,
+            Bound::Excluded(lower) => key > lower,
+            Bound::Unbounded => true,
+        }
+    }
+
+    fn below_upper(&self, key: &K::Encoded) -> bool {
+        match &self.upper {
+            Bound::Included(upper) => key <= upper,
+            Bound::Excluded(upper) => key < upper,
+            Bound::Unbounded => true,
+        }
+    }
+
+    // Read a leaf's entries, ending the iteration if the page can't be read
+    fn load(&mut self, page_id: u64) -> Option<LeafEntries<K::Encoded>> {
+        match LeafEntries::read(self.tree, page_id) {
+            Ok(entries) => Some(entries),
+            Err(e) => {
+                self.fail(e);
+                None
+            }
+        }
+    }
+
+    // Descend to the leaf that owns `key`, or to the last leaf for None
+    fn load_leaf_for(&mut self, key: Option<K::Encoded>) -> Option<LeafEntries<K::Encoded>> {
+        let found = match key {
+            Some(key) => self.tree.find_leaf(key.to_ref()).map(|(_, page_id)| page_id),
+            None => self.tree.find_last_leaf(),
+        };
+        match found {
+            Ok(page_id) => self.load(page_id),
+            Err(e) => {
+                self.fail(e);
+                None
+            }
+        }
+    }
+
+    // The decoded key and value of an entry, with the value read from its
+    // overflow pages if it has them
+    fn value_of(&mut self, item: LeafPageItem<K::Encoded>) -> Option<(K, Vec<u8>)> {
+        let key = K::decode(&item.key);
+        if !item.overflow {
+            return Some((key, item.bytes));
+        }
+        match self.tree.read_overflow_value(&item.bytes) {
+            Ok(value) => Some((key, value)),
+            Err(e) => {
+                self.fail(e);
+                None
+            }
+        }
+    }
+
+    fn fail(&mut self, e: Box<dyn Error>) {
+        self.error = Some(e);
+        self.finished = true;
+    }
+}
+
+impl<S: PageStore, K: KeyCodec> Iterator for Range<'_, S, K> {
+    type Item = (K, Vec<u8>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.finished {
+                return None;
+            }
+
+            let mut front = match self.front.take() {
+                Some(front) => front,
+                None => {
+                    // An excluded start key is skipped below
+                    let start = match &self.lower {
+                        Bound::Included(key) | Bound::Excluded(key) => key.clone(),
+                        Bound::Unbounded => K::Encoded::min_key(),
+                    };
+                    self.load_leaf_for(Some(start))?
+                }
+            };
+
+            front.skip_below(&self.lower);
+            if let Some(item) = front.pop_front() {
+                let key = item.key.clone();
+                self.front = Some(front);
+                if !self.above_lower(&key) {
+                    continue;
+                }
+                if !self.below_upper(&key) {
+                    // Past the end of the range, or already yielded from the back
+                    self.finished = true;
+                    return None;
+                }
+                self.lower = Bound::Excluded(key);
+                return self.value_of(item);
+            }
+
+            // This leaf is used up; move on to the next one
+            if front.next_page_id == 0 {
+                self.finished = true;
+                return None;
+            }
+            self.front = Some(self.load(front.next_page_id)?);
+        }
+    }
+}
+
+impl<S: PageStore, K: KeyCodec> DoubleEndedIterator for Range<'_, S, K> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.finished {
+                return None;
+            }
+
+            let mut back = match self.back.take() {
+                Some(back) => back,
+                None => {
+                    // An excluded end key is skipped below
+                    let end = match &self.upper {
+                        Bound::Included(key) | Bound::Excluded(key) => Some(key.clone()),
+                        Bound::Unbounded => None,
+                    };
+                    self.load_leaf_for(end)?
+                }
+            };
+
+            back.skip_above(&self.upper);
+            if let Some(item) = back.pop_back() {
+                let key = item.key.clone();
+                self.back = Some(back);
+                if !self.below_upper(&key) {
+                    continue;
+                }
+                if !self.above_lower(&key) {
+                    // Before the start of the range, or already yielded from the front
+                    self.finished = true;
+                    return None;
+                }
+                self.upper = Bound::Excluded(key);
+                return self.value_of(item);
+            }
+
+            // This leaf is used up; move back to the previous one
+            if back.prev_page_id == 0 {
+                self.finished = true;
+                return None;
+            }
+            self.back = Some(self.load(back.prev_page_id)?);
+        }
+    }
+}
+
+/// A run as the interval queries return it: its first and last keys, both
+/// included, and the value they hold
+pub type KeyRun<K> = (K, K, Vec<u8>);
+
+/// Iterator over the runs of a DataTree, created by `DataTree::runs`. Each
+/// run is the longest span of consecutive keys holding the same value, as
+/// `(start, end, value)` with both ends included.
+///
+/// Runs are clipped to the range. Runs that meet and hold equal values are
+/// joined, whether they were stored apart in one leaf or in neighbouring
+/// leaves. Keys that can't form runs, such as byte strings, each come as a
+/// run of their own. If a page can't be read the iterator ends early;
+/// `take_error` returns the cause.
+pub struct Runs<'a, S: PageStore, K: KeyCodec = u64> {
+    tree: &'a DataTree<S, K>,
+    lower: Bound<K::Encoded>,
+    upper: Bound<K::Encoded>,
+    leaf: Option<LeafEntries<K::Encoded>>,
+    // The run being extended, yielded once the next run doesn't join it
+    pending: Option<LeafRun<K::Encoded>>,
+    finished: bool,
+    error: Option<Box<dyn Error>>,
+}
+
+impl<'a, S: PageStore, K: KeyCodec> Runs<'a, S, K> {
+    pub(crate) fn new(tree: &'a DataTree<S, K>, lower: Bound<K::Encoded>, upper: Bound<K::Encoded>) -> Self {
+        Runs {
+            tree,
+            lower,
+            upper,
+            leaf: None,
+            pending: None,
+            finished: false,
+            error: None,
+        }
+    }
+
+    /// Returns the error that ended the iteration early, if any
+    pub fn take_error(&mut self) -> Option<Box<dyn Error>> {
+        self.error.take()
+    }
+
+    // The next run within the range as stored, with its value read from its
+    // overflow pages if it has them
+    fn next_stored(&mut self) -> Result<Option<LeafRun<K::Encoded>>, Box<dyn Error>> {
+        loop {
+            if self.finished {
+                return Ok(None);
+            }
+
+            let mut leaf = match self.leaf.take() {
+                Some(leaf) => leaf,
+                None => {
+                    let start = match &self.lower {
+                        Bound::Included(key) | Bound::Excluded(key) => key.clone(),
+                        Bound::Unbounded => K::Encoded::min_key(),
+                    };
+                    let (_, page_id) = self.tree.find_leaf(start.to_ref())?;
+                    LeafEntries::read(self.tree, page_id)?
+                }
+            };
+
+            leaf.skip_below(&self.lower);
+            if let Some(run) = leaf.runs.pop_front() {
+                self.leaf = Some(leaf);
+                return match run.within(Bound::Unbounded, self.upper.as_ref()) {
+                    Some(run) => self.tree.read_run_value(run).map(Some),
+                    None => {
+                        // Past the end of the range
+                        self.finished = true;
+                        Ok(None)
+                    }
+                };
+            }
+
+            // This leaf is used up; move on to the next one
+            if leaf.next_page_id == 0 {
+                self.finished = true;
+                return Ok(None);
+            }
+            self.leaf = Some(LeafEntries::read(self.tree, leaf.next_page_id)?);
+        }
+    }
+}
+
+impl<S: PageStore, K: KeyCodec> Iterator for Runs<'_, S, K> {
+    type Item = KeyRun<K>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let run = match self.next_stored() {
+                Ok(run) => run,
+                Err(e) => {
+                    self.error = Some(e);
+                    self.finished = true;
+                    self.pending = None;
+                    return None;
+                }
+            };
+
+            let pending = match (self.pending.take(), run) {
+                (Some(mut pending), Some(run)) if pending.joins(&run) => {
+                    pending.end = run.end;
+                    self.pending = Some(pending);
+                    continue;
+                }
+                (pending, run) => {
+                    self.pending = run;
+                    pending
+                }
+            };
+            match pending {
+                Some(run) => return Some((K::decode(&run.start), K::decode(&run.end), run.bytes)),
+                None if self.pending.is_none() => return None,
+                None => {}
+            }
+        }
+    }
+}
@@ -1,4 +1,7 @@
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
 use crate::data_tree::PageType;
+use crate::leaf_page::{read_u64, LeafPageItem, OVERFLOW_FLAG};
 
 // Metadata for each run of key-value pairs with identical values
 #[derive(Debug, Clone, Copy)]
@@ -7,6 +10,9 @@ pub struct RLELeafPageEntry {
     pub end_key: u64,       // Last key in the run (inclusive)
     pub value_offset: usize, // Offset of the value in the data array
     pub value_length: usize, // Length of the value
+    // The stored bytes are an overflow pointer record rather than the value.
+    // Such a run always holds a single key, since each key has its own chain.
+    pub overflow: bool,
 }
 
 // Constants for page header sizes
@@ -79,10 +85,11 @@ impl RLELeafPage {
 
         // Write metadata entries
         for meta in &self.metadata {
+            let flag = if meta.overflow { OVERFLOW_FLAG } else { 0 };
             bytes.extend_from_slice(&meta.start_key.to_le_bytes());
             bytes.extend_from_slice(&meta.end_key.to_le_bytes());
             bytes.extend_from_slice(&(meta.value_offset as u64).to_le_bytes());
-            bytes.extend_from_slice(&(meta.value_length as u64).to_le_bytes());
+            bytes.extend_from_slice(&(meta.value_length as u64 | flag).to_le_bytes());
         }
 
         // Write data
@@ -143,14 +150,15 @@ impl RLELeafPage {
             offset += 8;
             let value_offset = u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap()) as usize;
             offset += 8;
-            let value_length = u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap()) as usize;
+            let value_length = u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap());
             offset += 8;
 
             metadata.push(RLELeafPageEntry {
                 start_key,
                 end_key,
                 value_offset,
-                value_length,
+                value_length: (value_length & !OVERFLOW_FLAG) as usize,
+                overflow: value_length & OVERFLOW_FLAG != 0,
             });
         }
 
@@ -204,54 +212,74 @@ impl RLELeafPage {
 
     // Get a value for a specific key
     pub fn get(&self, key: u64) -> Option<&[u8]> {
-        // Find the metadata entry that contains the key
-        for meta in &self.metadata {
-            if key >= meta.start_key && key <= meta.end_key {
-                return Some(&self.data[meta.value_offset..meta.value_offset + meta.value_length]);
-            }
+        let meta = &self.metadata[self.run_index(key).ok()?];
+        Some(&self.data[meta.value_offset..meta.value_offset + meta.value_length])
+    }
+
+    // Binary search of the runs, which are kept sorted and don't overlap: the
+    // index of the run holding `key`, or of the first run above it
+    fn run_index(&self, key: u64) -> Result<usize, usize> {
+        let index = self.metadata.partition_point(|meta| meta.end_key < key);
+        match self.metadata.get(index) {
+            Some(meta) if meta.start_key <= key => Ok(index),
+            _ => Err(index),
         }
-        None
+    }
+
+    /// Whether the run holding `key` stores an overflow pointer record
+    pub fn is_overflow(&self, key: u64) -> bool {
+        self.run_index(key).is_ok_and(|index| self.metadata[index].overflow)
     }
 
     // Insert a key-value pair
     pub fn put(&mut self, key: u64, value: &[u8]) -> bool {
-        // First, check if we need to update an existing run
-        let mut found_run = false;
-        let mut run_index = 0;
-        let mut adjacent_run_before = None;
-        let mut adjacent_run_after = None;
+        self.put_entry(key, value, false)
+    }
 
-        // Check if the key fits into an existing run or is adjacent to one
-        for (i, meta) in self.metadata.iter().enumerate() {
-            // Check if key is within an existing run
-            if key >= meta.start_key && key <= meta.end_key {
-                found_run = true;
-                run_index = i;
-                break;
-            }
+    /// Stores an overflow pointer record for `key` in place of its value
+    pub fn put_overflow(&mut self, key: u64, record: &[u8]) -> bool {
+        self.put_entry(key, record, true)
+    }
 
-            // Check if key is adjacent to the start of a run
-            if key + 1 == meta.start_key {
-                adjacent_run_after = Some(i);
-            }
+    /// Stores an item taken from another page, keeping its overflow flag
+    pub fn put_item(&mut self, item: &LeafPageItem) -> bool {
+        self.put_entry(item.key, &item.bytes, item.overflow)
+    }
 
-            // Check if key is adjacent to the end of a run
-            if key == meta.end_key + 1 {
-                adjacent_run_before = Some(i);
+    fn put_entry(&mut self, key: u64, value: &[u8], overflow: bool) -> bool {
+        // First, check if we need to update an existing run
+        let index = match self.run_index(key) {
+            Ok(run_index) => {
+                // The key is in an existing run, check if the value matches
+                let meta = &self.metadata[run_index];
+                let current_value = &self.data[meta.value_offset..meta.value_offset + meta.value_length];
+
+                if current_value == value && meta.overflow == overflow {
+                    // Value already matches, nothing to do
+                    return true;
+                } else {
+                    // Value doesn't match, need to split the run
+                    return self.split_run_and_insert(run_index, key, value, overflow);
+                }
             }
-        }
-
-        // If the key is in an existing run, check if the value matches
-        if found_run {
-            let meta = &self.metadata[run_index];
-            let current_value = &self.data[meta.value_offset..meta.value_offset + meta.value_length];
+            Err(index) => index,
+        };
 
-            if current_value == value {
-                // Value already matches, nothing to do
-                return true;
-            } else {
-                // Value doesn't match, need to split the run
-                return self.split_run_and_insert(run_index, key, value);
+        // Only the runs either side of the gap can be adjacent to the key.
+        // Overflow records are never shared, so their runs aren't extended.
+        let mut adjacent_run_before = None;
+        let mut adjacent_run_after = None;
+        if !overflow {
+            if index > 0 {
+                let meta = &self.metadata[index - 1];
+                if !meta.overflow && meta.end_key.checked_add(1) == Some(key) {
+                    adjacent_run_before = Some(index - 1);
+                }
+            }
+            if let Some(meta) = self.metadata.get(index) {
+                if !meta.overflow && key.checked_add(1) == Some(meta.start_key) {
+                    adjacent_run_after = Some(index);
+                }
             }
         }
 
@@ -336,19 +364,17 @@ impl RLELeafPage {
             end_key: key,
             value_offset,
             value_length: value.len(),
+            overflow,
         };
 
-        // Add the new metadata
-        self.metadata.push(new_meta);
-
-        // Sort metadata by start_key for easier lookups
-        self.metadata.sort_by_key(|m| m.start_key);
+        // Add the new metadata in the gap it fills
+        self.metadata.insert(index, new_meta);
 
         true
     }
 
     // Helper method to split a run and insert a new value
-    fn split_run_and_insert(&mut self, run_index: usize, key: u64, value: &[u8]) -> bool {
+    fn split_run_and_insert(&mut self, run_index: usize, key: u64, value: &[u8], overflow: bool) -> bool {
         let meta = self.metadata[run_index];
 
         // Check if we need to split into 1 or 2 new runs
@@ -396,10 +422,8 @@ impl RLELeafPage {
             self.data.extend_from_slice(value);
         }
 
-        // Remove the original run
-        self.metadata.remove(run_index);
-
-        // Create new runs
+        // Create new runs to replace the original one, in key order
+        let mut runs = Vec::with_capacity(3);
         if key == meta.start_key {
             // Key is at the start of the run
             // Create a new run for the key
@@ -408,8 +432,11 @@ impl RLELeafPage {
                 end_key: key,
                 value_offset,
                 value_length: value.len(),
+                overflow,
             };
 
+            runs.push(new_meta1);
+
             // Create a run for the rest of the original run
             if meta.end_key > key {
                 let new_meta2 = RLELeafPageEntry {
@@ -417,11 +444,10 @@ impl RLELeafPage {
                     end_key: meta.end_key,
                     value_offset: meta.value_offset,
                     value_length: meta.value_length,
+                    overflow: meta.overflow,
                 };
-                self.metadata.push(new_meta2);
+                runs.push(new_meta2);
             }
-
-            self.metadata.push(new_meta1);
         } else if key == meta.end_key {
             // Key is at the end of the run
             // Create a run for the original run except the last key
@@ -430,6 +456,7 @@ impl RLELeafPage {
                 end_key: key - 1,
                 value_offset: meta.value_offset,
                 value_length: meta.value_length,
+                overflow: meta.overflow,
             };
 
             // Create a new run for the key
@@ -438,10 +465,11 @@ impl RLELeafPage {
                 end_key: key,
                 value_offset,
                 value_length: value.len(),
+                overflow,
             };
 
-            self.metadata.push(new_meta1);
-            self.metadata.push(new_meta2);
+            runs.push(new_meta1);
+            runs.push(new_meta2);
         } else {
             // Key is in the middle of the run
             // Create a run for the part before the key
@@ -450,6 +478,7 @@ impl RLELeafPage {
                 end_key: key - 1,
                 value_offset: meta.value_offset,
                 value_length: meta.value_length,
+                overflow: meta.overflow,
             };
 
             // Create a new run for the key
@@ -458,6 +487,7 @@ impl RLELeafPage {
                 end_key: key,
                 value_offset,
                 value_length: value.len(),
+                overflow,
             };
 
             // Create a run for the part after the key
@@ -466,15 +496,15 @@ impl RLELeafPage {
                 end_key: meta.end_key,
                 value_offset: meta.value_offset,
                 value_length: meta.value_length,
+                overflow: meta.overflow,
             };
 
-            self.metadata.push(new_meta1);
-            self.metadata.push(new_meta2);
-            self.metadata.push(new_meta3);
+            runs.push(new_meta1);
+            runs.push(new_meta2);
+            runs.push(new_meta3);
         }
 
-        // Sort metadata by start_key
-        self.metadata.sort_by_key(|m| m.start_key);
+        self.metadata.splice(run_index..=run_index, runs);
 
         true
     }
@@ -482,20 +512,9 @@ impl RLELeafPage {
     // Delete a key
     pub fn delete(&mut self, key: u64) -> bool {
         // Find the run containing the key
-        let mut found_run = false;
-        let mut run_index = 0;
-
-        for (i, meta) in self.metadata.iter().enumerate() {
-            if key >= meta.start_key && key <= meta.end_key {
-                found_run = true;
-                run_index = i;
-                break;
-            }
-        }
-
-        if !found_run {
+        let Ok(run_index) = self.run_index(key) else {
             return false; // Key not found
-        }
+        };
 
         let meta = self.metadata[run_index];
 
@@ -516,16 +535,14 @@ impl RLELeafPage {
                 end_key: meta.end_key,
                 value_offset: meta.value_offset,
                 value_length: meta.value_length,
+                overflow: meta.overflow,
             };
 
             // Update the original run to end before the key
             self.metadata[run_index].end_key = key - 1;
 
-            // Add the new run
-            self.metadata.push(new_meta);
-
-            // Sort metadata by start_key
-            self.metadata.sort_by_key(|m| m.start_key);
+            // Add the new run after it
+            self.metadata.insert(run_index + 1, new_meta);
         }
 
         // Clean up unused data if needed
@@ -553,15 +570,58 @@ impl RLELeafPage {
         with_buffer > self.page_size
     }
 
+    /// Appends the run `start_key..=end_key`, which must lie above every key
+    /// in the page. It extends the last run instead when that run ends just
+    /// before `start_key` with the same value.
+    pub fn push_run(&mut self, start_key: u64, end_key: u64, value: &[u8], overflow: bool) -> bool {
+        debug_assert!(self.metadata.last().is_none_or(|last| last.end_key < start_key));
+
+        if let Some(last) = self.metadata.last_mut() {
+            let last_value = &self.data[last.value_offset..last.value_offset + last.value_length];
+            if !overflow && !last.overflow && last.end_key.checked_add(1) == Some(start_key) && last_value == value {
+                last.end_key = end_key;
+                return true;
+            }
+        }
+
+        // Share the bytes of an equal value already in the page
+        let existing = self.metadata.iter()
+            .find(|meta| &self.data[meta.value_offset..meta.value_offset + meta.value_length] == value)
+            .map(|meta| meta.value_offset);
+        let required_space = if existing.is_some() { 0 } else { value.len() };
+        if self.used_space() + METADATA_ENTRY_SIZE + required_space > self.page_size {
+            return false;
+        }
+
+        let value_offset = match existing {
+            Some(offset) => offset,
+            None => {
+                self.data.extend_from_slice(value);
+                self.data.len() - value.len()
+            }
+        };
+        self.metadata.push(RLELeafPageEntry {
+            start_key,
+            end_key,
+            value_offset,
+            value_length: value.len(),
+            overflow,
+        });
+        true
+    }
+
+    /// Returns the number of bytes the page's contents take when serialized,
+    /// before padding
+    pub fn used_space(&self) -> usize {
+        HEADER_SIZE + self.metadata.len() * METADATA_ENTRY_SIZE + self.data.len()
+    }
+
     // Split the page into two
     pub fn split(&mut self) -> Option<RLELeafPage> {
         if self.metadata.len() < 2 {
             return None;
         }
 
-        // Sort metadata by key for consistent splitting
-        self.metadata.sort_by(|a, b| a.start_key.cmp(&b.start_key));
-
         // Calculate split point
         let split_point = self.metadata.len() / 2;
 
@@ -569,49 +629,9 @@ impl RLELeafPage {
         let mut new_page = RLELeafPage::new_empty(self.page_size);
         new_page.page_type = PageType::RLELeafPage;
 
-        // Move metadata entries to the new page
-        let entries_to_move = self.metadata.split_off(split_point);
-
-        // Collect all unique values from the moved entries
-        let mut values_to_move = Vec::new();
-        for meta in &entries_to_move {
-            let value = &self.data[meta.value_offset..meta.value_offset + meta.value_length];
-            // Check if this value is already in values_to_move
-            if !values_to_move.iter().any(|(_, v)| v == value) {
-                values_to_move.push((meta.value_offset, value.to_vec()));
-            }
-        }
-
-        // Add values to the new page and update metadata
-        for meta in &mut new_page.metadata {
-            // Find the corresponding value
-            for (old_offset, value) in &values_to_move {
-                if meta.value_offset == *old_offset {
-                    // Update the offset to point to the new location
-                    meta.value_offset = new_page.data.len();
-                    new_page.data.extend_from_slice(value);
-                    break;
-                }
-            }
-        }
-
-        // Set the metadata for the new page
-        new_page.metadata = entries_to_move;
-
-        // Update the offsets in the new page's metadata
-        let mut offset_map = std::collections::HashMap::new();
-
-        for (old_offset, value) in &values_to_move {
-            let new_offset = new_page.data.len();
-            offset_map.insert(*old_offset, new_offset);
-            new_page.data.extend_from_slice(value);
-        }
-
-        for meta in &mut new_page.metadata {
-            if let Some(&new_offset) = offset_map.get(&meta.value_offset) {
-                meta.value_offset = new_offset;
-            }
-        }
+        // Move metadata entries to the new page, with their values
+        new_page.metadata = self.metadata.split_off(split_point);
+        new_page.data = pack_values(&mut new_page.metadata, &self.data);
 
         // Compact the current page's data
         self.compact_data();
@@ -626,53 +646,17 @@ impl RLELeafPage {
             return;
         }
 
-        // Collect all unique values that are still in use
-        let mut used_values = Vec::new();
-        for meta in &self.metadata {
-            let value = &self.data[meta.value_offset..meta.value_offset + meta.value_length];
-            // Check if this value is already in used_values
-            if !used_values.iter().any(|(_, v)| v == value) {
-                used_values.push((meta.value_offset, value.to_vec()));
-            }
-        }
-
-        // Create new data array
-        let mut new_data = Vec::new();
-        let mut offset_map = std::collections::HashMap::new();
-
-        for (old_offset, value) in used_values {
-            let new_offset = new_data.len();
-            offset_map.insert(old_offset, new_offset);
-            new_data.extend_from_slice(&value);
-        }
-
-        // Update metadata with new offsets
-        for meta in &mut self.metadata {
-            if let Some(&new_offset) = offset_map.get(&meta.value_offset) {
-                meta.value_offset = new_offset;
-            }
-        }
-
-        // Replace data with compacted version
-        self.data = new_data;
+        self.data = pack_values(&mut self.metadata, &self.data);
     }
 
     // Only compact data if there's a significant amount of unused data
     fn compact_data_if_needed(&mut self) {
-        // Count how many unique values are still in use
-        let mut used_offsets = std::collections::HashSet::new();
-        for meta in &self.metadata {
-            used_offsets.insert(meta.value_offset);
-        }
-
-        // Calculate total size of used values
-        let mut used_size = 0;
-        for meta in &self.metadata {
-            if used_offsets.contains(&meta.value_offset) {
-                used_size += meta.value_length;
-                used_offsets.remove(&meta.value_offset);
-            }
-        }
+        // Calculate total size of the values still in use. Entries can
+        // share an offset with different lengths, so spans are counted once.
+        let used_spans: HashSet<(usize, usize)> = self.metadata.iter()
+            .map(|meta| (meta.value_offset, meta.value_length))
+            .collect();
+        let used_size: usize = used_spans.iter().map(|(_, length)| length).sum();
 
         // If less than 75% of data is used, compact
         if used_size < self.data.len() * 3 / 4 {
@@ -694,3 +678,101 @@ impl RLELeafPage {
         self.max_value_size()
     }
 }
+
+// Lay out the values `metadata` points at in `data` afresh, each distinct
+// value once, and point the entries at the new layout. Entries are read by
+// their own offset and length: runs can share the start of a longer value,
+// and empty values sit at any offset.
+fn pack_values(metadata: &mut [RLELeafPageEntry], data: &[u8]) -> Vec<u8> {
+    let mut packed = Vec::new();
+    let mut offsets: HashMap<&[u8], usize> = HashMap::new();
+    for meta in metadata {
+        let value = &data[meta.value_offset..meta.value_offset + meta.value_length];
+        meta.value_offset = *offsets.entry(value).or_insert_with(|| {
+            packed.extend_from_slice(value);
+            packed.len() - value.len()
+        });
+    }
+    packed
+}
+
+/// A run-length leaf page read in place from its serialized bytes, the
+/// counterpart of `LeafPageView`. Runs are sorted and fixed-size, so a
+/// lookup is a binary search over them.
+#[derive(Debug, Clone, Copy)]
+pub struct RLELeafPageView<'a> {
+    bytes: &'a [u8],
+    count: usize,
+}
+
+impl<'a> RLELeafPageView<'a> {
+    pub fn new(bytes: &'a [u8]) -> Result<Self, Box<dyn Error>> {
+        if bytes.len() < HEADER_SIZE {
+            return Err(format!("Run-length leaf page of {} bytes is shorter than its header", bytes.len()).into());
+        }
+        if PageType::from_u8(bytes[0]) != Some(PageType::RLELeafPage) {
+            return Err(format!("Page type {} is not a run-length leaf", bytes[0]).into());
+        }
+
+        let count = read_u64(bytes, PAGE_TYPE_SIZE).unwrap() as usize;
+        let entries_end = count.checked_mul(METADATA_ENTRY_SIZE).and_then(|size| size.checked_add(HEADER_SIZE));
+        if entries_end.is_none_or(|end| end > bytes.len()) {
+            return Err(format!("Run-length leaf page runs past its {} bytes", bytes.len()).into());
+        }
+        Ok(RLELeafPageView { bytes, count })
+    }
+
+    /// Number of runs in the page
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn prev_page_id(&self) -> u64 {
+        read_u64(self.bytes, HEADER_SIZE - NEXT_PAGE_ID_SIZE - PREV_PAGE_ID_SIZE).unwrap()
+    }
+
+    pub fn next_page_id(&self) -> u64 {
+        read_u64(self.bytes, HEADER_SIZE - NEXT_PAGE_ID_SIZE).unwrap()
+    }
+
+    pub fn get(&self, key: u64) -> Option<&'a [u8]> {
+        self.get_entry(key).map(|(value, _)| value)
+    }
+
+    /// Whether the run holding `key` stores an overflow pointer record
+    pub fn is_overflow(&self, key: u64) -> bool {
+        self.get_entry(key).is_some_and(|(_, overflow)| overflow)
+    }
+
+    /// The bytes stored for the run holding `key`, with whether they are an
+    /// overflow pointer record rather than the value
+    pub fn get_entry(&self, key: u64) -> Option<(&'a [u8], bool)> {
+        let field = |index: usize, offset: usize| read_u64(self.bytes, HEADER_SIZE + index * METADATA_ENTRY_SIZE + offset).unwrap();
+
+        // The first run that doesn't end below the key
+        let (mut low, mut high) = (0, self.count);
+        while low < high {
+            let middle = low + (high - low) / 2;
+            if field(middle, START_KEY_SIZE) < key {
+                low = middle + 1;
+            } else {
+                high = middle;
+            }
+        }
+        if low == self.count || field(low, 0) > key {
+            return None;
+        }
+
+        let value_offset = field(low, START_KEY_SIZE + END_KEY_SIZE) as usize;
+        let stored_length = field(low, START_KEY_SIZE + END_KEY_SIZE + VALUE_OFFSET_SIZE);
+        let value_length = (stored_length & !OVERFLOW_FLAG) as usize;
+        let data_start = read_u64(self.bytes, PAGE_TYPE_SIZE + COUNT_SIZE)? as usize;
+        let start = data_start.checked_add(value_offset)?;
+        let value = self.bytes.get(start..start.checked_add(value_length)?)?;
+        Some((value, stored_length & OVERFLOW_FLAG != 0))
+    }
+}
@@ -0,0 +1,593 @@
+use std::cmp::Ordering;
+use std::error::Error;
+use std::marker::PhantomData;
+use crate::data_tree::PageType;
+use crate::leaf_page::{read_u64, LeafPageItem, OVERFLOW_FLAG};
+use crate::page_key::PageKey;
+
+// A key's entry in the slot directory, pointing at its value in the data area
+#[derive(Debug, Clone, Copy)]
+pub struct Slot<K = u64> {
+    pub key: K,
+    pub value_offset: usize,
+    pub value_length: usize,
+    // The stored bytes are an overflow pointer record rather than the value
+    pub overflow: bool,
+}
+
+// Constants for page header sizes
+pub const PAGE_TYPE_SIZE: usize = 1; // 1 byte for page type
+pub const COUNT_SIZE: usize = 8;     // 8 bytes for slot count
+pub const DATA_START_SIZE: usize = 8; // 8 bytes for data start offset
+pub const DATA_LENGTH_SIZE: usize = 8; // 8 bytes for data area length, holes included
+pub const PREV_PAGE_ID_SIZE: usize = 8; // 8 bytes for previous page ID
+pub const NEXT_PAGE_ID_SIZE: usize = 8; // 8 bytes for next page ID
+pub const HEADER_SIZE: usize = PAGE_TYPE_SIZE + COUNT_SIZE + DATA_START_SIZE +
+                              DATA_LENGTH_SIZE + PREV_PAGE_ID_SIZE + NEXT_PAGE_ID_SIZE;
+
+// Constants for slot sizes. Byte-string keys take their encoded size in
+// place of KEY_SIZE.
+pub const KEY_SIZE: usize = 8; // 8 bytes for u64 key
+pub const VALUE_OFFSET_SIZE: usize = 8; // 8 bytes for value offset
+pub const VALUE_LENGTH_SIZE: usize = 8; // 8 bytes for value length
+pub const SLOT_SIZE: usize = KEY_SIZE + VALUE_OFFSET_SIZE + VALUE_LENGTH_SIZE;
+
+// Fraction of the page the holes left by updates and deletes may take before
+// the data area is compacted
+pub const COMPACTION_THRESHOLD: f64 = 0.25;
+
+// A leaf of keys of type K whose slots point at values anywhere in its data
+// area. Updates that fit are made in place and deleted values leave holes,
+// tracked in a free-space map and reused by later puts, so the data area is
+// only rebuilt once the holes grow past COMPACTION_THRESHOLD or a put needs
+// their space.
+#[derive(Debug)]
+pub struct KeyedSlottedLeafPage<K: PageKey> {
+    pub page_type: PageType,
+    pub page_size: usize,
+    // Sorted by key
+    slots: Vec<Slot<K>>,
+    data: Vec<u8>,
+    // Holes in `data` as (offset, length), sorted by offset. Neighbouring
+    // holes are joined and none reaches the end of `data`, which is cut
+    // short instead.
+    free: Vec<(usize, usize)>,
+    // Total length of the holes in `free`
+    fragmented: usize,
+    pub prev_page_id: u64,
+    pub next_page_id: u64,
+}
+
+pub type SlottedLeafPage = KeyedSlottedLeafPage<u64>;
+
+impl<K: PageKey> KeyedSlottedLeafPage<K> {
+    pub fn new(bytes: &[u8]) -> Self {
+        Self::deserialize(bytes)
+    }
+
+    pub fn new_empty(page_size: usize) -> Self {
+        KeyedSlottedLeafPage {
+            page_type: K::SLOTTED_LEAF_PAGE_TYPE,
+            page_size,
+            slots: Vec::new(),
+            data: Vec::new(),
+            free: Vec::new(),
+            fragmented: 0,
+            prev_page_id: 0,
+            next_page_id: 0,
+        }
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.page_size);
+
+        // Write page type (1 byte)
+        bytes.push(self.page_type.to_u8());
+
+        // Write slot count (8 bytes)
+        bytes.extend_from_slice(&(self.slots.len() as u64).to_le_bytes());
+
+        // Write data start offset (8 bytes)
+        let data_start = HEADER_SIZE + self.slots_size();
+        bytes.extend_from_slice(&(data_start as u64).to_le_bytes());
+
+        // Write data area length (8 bytes)
+        bytes.extend_from_slice(&(self.data.len() as u64).to_le_bytes());
+
+        // Write prev_page_id (8 bytes)
+        bytes.extend_from_slice(&self.prev_page_id.to_le_bytes());
+
+        // Write next_page_id (8 bytes)
+        bytes.extend_from_slice(&self.next_page_id.to_le_bytes());
+
+        // Write slots
+        for slot in &self.slots {
+            let flag = if slot.overflow { OVERFLOW_FLAG } else { 0 };
+            slot.key.encode(&mut bytes);
+            bytes.extend_from_slice(&(slot.value_offset as u64).to_le_bytes());
+            bytes.extend_from_slice(&(slot.value_length as u64 | flag).to_le_bytes());
+        }
+
+        // Write the data area as it is, holes and all, so the offsets hold
+        bytes.extend_from_slice(&self.data);
+
+        bytes
+    }
+
+    pub fn deserialize(bytes: &[u8]) -> Self {
+        if bytes.len() < HEADER_SIZE {
+            panic!("Cannot deserialize SlottedLeafPage: byte array length {} is less than required header size {}", bytes.len(), HEADER_SIZE);
+        }
+
+        let mut offset = PAGE_TYPE_SIZE;
+
+        // Read slot count (8 bytes)
+        let count = read_u64(bytes, offset).unwrap();
+        offset += COUNT_SIZE;
+
+        // Read data start offset (8 bytes)
+        let data_start = read_u64(bytes, offset).unwrap() as usize;
+        offset += DATA_START_SIZE;
+
+        // Read data area length (8 bytes)
+        let data_length = read_u64(bytes, offset).unwrap() as usize;
+        offset += DATA_LENGTH_SIZE;
+
+        // Read prev_page_id (8 bytes)
+        let prev_page_id = read_u64(bytes, offset).unwrap();
+        offset += PREV_PAGE_ID_SIZE;
+
+        // Read next_page_id (8 bytes)
+        let next_page_id = read_u64(bytes, offset).unwrap();
+        offset += NEXT_PAGE_ID_SIZE;
+
+        // Read slots
+        let mut slots = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let (key, key_size) = match K::decode(&bytes[offset..]) {
+                Some(decoded) => decoded,
+                None => break,
+            };
+            let (value_offset, stored_length) = match (read_u64(bytes, offset + key_size), read_u64(bytes, offset + key_size + VALUE_OFFSET_SIZE)) {
+                (Some(value_offset), Some(stored_length)) => (value_offset as usize, stored_length),
+                _ => break,
+            };
+            offset += key_size + VALUE_OFFSET_SIZE + VALUE_LENGTH_SIZE;
+            // Pages written before empty values were pinned to the start
+            // may point them past the end of the data area
+            let value_length = (stored_length & !OVERFLOW_FLAG) as usize;
+            slots.push(Slot {
+                key,
+                value_offset: if value_length == 0 { 0 } else { value_offset },
+                value_length,
+                overflow: stored_length & OVERFLOW_FLAG != 0,
+            });
+        }
+
+        // Read data
+        let data = if data_start < bytes.len() {
+            let end = std::cmp::min(data_start + data_length, bytes.len());
+            bytes[data_start..end].to_vec()
+        } else {
+            Vec::new()
+        };
+
+        let mut page = KeyedSlottedLeafPage {
+            page_type: K::SLOTTED_LEAF_PAGE_TYPE,
+            page_size: bytes.len(),
+            slots,
+            data,
+            free: Vec::new(),
+            fragmented: 0,
+            prev_page_id,
+            next_page_id,
+        };
+        page.rebuild_free_map();
+        page
+    }
+
+    // The free-space map isn't stored: the holes are the gaps between the
+    // values the slots point at
+    fn rebuild_free_map(&mut self) {
+        let mut extents: Vec<(usize, usize)> = self.slots.iter()
+            .map(|slot| (slot.value_offset, slot.value_length))
+            .filter(|&(_, length)| length > 0)
+            .collect();
+        extents.sort_unstable();
+
+        self.free.clear();
+        self.fragmented = 0;
+        let mut end = 0;
+        for (offset, length) in extents {
+            if offset > end {
+                self.free.push((end, offset - end));
+                self.fragmented += offset - end;
+            }
+            end = end.max(offset + length);
+        }
+        self.data.truncate(end);
+    }
+
+    pub fn page_type(&self) -> PageType {
+        self.page_type
+    }
+
+    pub fn slots(&self) -> &[Slot<K>] {
+        &self.slots
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn prev_page_id(&self) -> u64 {
+        self.prev_page_id
+    }
+
+    pub fn next_page_id(&self) -> u64 {
+        self.next_page_id
+    }
+
+    pub fn set_prev_page_id(&mut self, page_id: u64) {
+        self.prev_page_id = page_id;
+    }
+
+    pub fn set_next_page_id(&mut self, page_id: u64) {
+        self.next_page_id = page_id;
+    }
+
+    // Binary search of the slots: the index of the slot for `key`, or where
+    // it would be inserted
+    fn position(&self, key: K::Ref<'_>) -> Result<usize, usize> {
+        self.slots.binary_search_by(|slot| slot.key.cmp_ref(key))
+    }
+
+    // Takes a u64 key, or a byte slice in a byte-key page
+    pub fn get(&self, key: K::Ref<'_>) -> Option<&[u8]> {
+        let slot = &self.slots[self.position(key).ok()?];
+        Some(&self.data[slot.value_offset..slot.value_offset + slot.value_length])
+    }
+
+    /// Whether the entry for `key` holds an overflow pointer record
+    pub fn is_overflow(&self, key: K::Ref<'_>) -> bool {
+        self.position(key).is_ok_and(|index| self.slots[index].overflow)
+    }
+
+    // Takes a u64 key, or a byte slice in a byte-key page
+    pub fn put(&mut self, key: K::Ref<'_>, value: &[u8]) -> bool {
+        self.put_entry(K::from_ref(key), value, false)
+    }
+
+    /// Stores an overflow pointer record for `key` in place of its value
+    pub fn put_overflow(&mut self, key: K::Ref<'_>, record: &[u8]) -> bool {
+        self.put_entry(K::from_ref(key), record, true)
+    }
+
+    /// Stores an item taken from another page, keeping its overflow flag
+    pub fn put_item(&mut self, item: &LeafPageItem<K>) -> bool {
+        self.put_entry(item.key.clone(), &item.bytes, item.overflow)
+    }
+
+    fn put_entry(&mut self, key: K, value: &[u8], overflow: bool) -> bool {
+        match self.slots.binary_search_by(|slot| slot.key.cmp(&key)) {
+            Ok(index) => {
+                let (old_offset, old_length) = (self.slots[index].value_offset, self.slots[index].value_length);
+                if value.len() <= old_length {
+                    // Overwrite the value where it is, freeing what the new
+                    // one doesn't use
+                    self.data[old_offset..old_offset + value.len()].copy_from_slice(value);
+                    let slot = &mut self.slots[index];
+                    if value.is_empty() {
+                        slot.value_offset = 0;
+                    }
+                    slot.value_length = value.len();
+                    slot.overflow = overflow;
+                    self.release(old_offset + value.len(), old_length - value.len());
+                    self.compact_if_fragmented();
+                    return true;
+                }
+
+                // The old value's bytes are free once it moves. The slot
+                // stays where it is, emptied so a compaction skips it.
+                if value.len() - old_length > self.free_space() {
+                    return false;
+                }
+                let slot = &mut self.slots[index];
+                (slot.value_offset, slot.value_length) = (0, 0);
+                self.release(old_offset, old_length);
+                let value_offset = self.store_value(value, 0);
+                let slot = &mut self.slots[index];
+                (slot.value_offset, slot.value_length, slot.overflow) = (value_offset, value.len(), overflow);
+            }
+            Err(index) => {
+                let slot_size = Self::slot_size(&key);
+                if slot_size + value.len() > self.free_space() {
+                    return false;
+                }
+                let value_offset = self.store_value(value, slot_size);
+                self.slots.insert(index, Slot {
+                    key,
+                    value_offset,
+                    value_length: value.len(),
+                    overflow,
+                });
+            }
+        }
+        true
+    }
+
+    // Copies `value` into the data area and returns its offset, while the
+    // slot directory grows by `slot_growth` bytes. The page must have room
+    // for both.
+    fn store_value(&mut self, value: &[u8], slot_growth: usize) -> usize {
+        // First fit among the holes, else the end of the data area. The
+        // slot directory grows into the room at the end of the page too, so
+        // compact first if what it needs there is all in holes.
+        let mut hole = match value.is_empty() {
+            true => None,
+            false => self.free.iter().position(|&(_, length)| length >= value.len()),
+        };
+        let tail_needed = slot_growth + if hole.is_some() { 0 } else { value.len() };
+        if self.used_space() + tail_needed > self.page_size {
+            self.compact();
+            hole = None;
+        }
+
+        match hole {
+            Some(hole) => {
+                let (offset, length) = self.free[hole];
+                if length == value.len() {
+                    self.free.remove(hole);
+                } else {
+                    self.free[hole] = (offset + value.len(), length - value.len());
+                }
+                self.fragmented -= value.len();
+                self.data[offset..offset + value.len()].copy_from_slice(value);
+                offset
+            }
+            // Empty values point at the start, which the data area never
+            // shrinks past
+            None if value.is_empty() => 0,
+            None => {
+                self.data.extend_from_slice(value);
+                self.data.len() - value.len()
+            }
+        }
+    }
+
+    // Takes a u64 key, or a byte slice in a byte-key page
+    pub fn delete(&mut self, key: K::Ref<'_>) -> bool {
+        match self.position(key) {
+            Ok(index) => {
+                let slot = self.slots.remove(index);
+                self.release(slot.value_offset, slot.value_length);
+                self.compact_if_fragmented();
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    // Adds `length` bytes at `offset` of the data area to the free-space map
+    fn release(&mut self, offset: usize, length: usize) {
+        if length == 0 {
+            return;
+        }
+
+        // Join the holes either side
+        let mut index = self.free.partition_point(|&(start, _)| start < offset);
+        let (mut start, mut end) = (offset, offset + length);
+        if self.free.get(index).is_some_and(|&(next, _)| next == end) {
+            end += self.free.remove(index).1;
+        }
+        if index > 0 && self.free[index - 1].0 + self.free[index - 1].1 == start {
+            start = self.free.remove(index - 1).0;
+            index -= 1;
+        }
+
+        if end == self.data.len() {
+            // The holes joined to the released bytes go with them
+            self.data.truncate(start);
+            self.fragmented -= end - start - length;
+        } else {
+            self.free.insert(index, (start, end - start));
+            self.fragmented += length;
+        }
+    }
+
+    fn compact_if_fragmented(&mut self) {
+        if self.fragmented_bytes() as f64 > self.page_size as f64 * COMPACTION_THRESHOLD {
+            self.compact();
+        }
+    }
+
+    /// Packs the values together in key order, removing every hole
+    pub fn compact(&mut self) {
+        let mut data = Vec::with_capacity(self.data.len() - self.fragmented_bytes());
+        for slot in &mut self.slots {
+            let value = &self.data[slot.value_offset..slot.value_offset + slot.value_length];
+            slot.value_offset = Self::append_value(&mut data, value);
+        }
+        self.data = data;
+        self.free.clear();
+        self.fragmented = 0;
+    }
+
+    // Appends a value to a data area being packed and returns its offset.
+    // Empty values point at the start, as the data area may later be cut
+    // short of its current end.
+    fn append_value(data: &mut Vec<u8>, value: &[u8]) -> usize {
+        if value.is_empty() {
+            return 0;
+        }
+        data.extend_from_slice(value);
+        data.len() - value.len()
+    }
+
+    /// Bytes lost to holes in the data area until it is compacted
+    pub fn fragmented_bytes(&self) -> usize {
+        self.fragmented
+    }
+
+    /// Bytes a put can still use, counting those in holes
+    pub fn free_space(&self) -> usize {
+        self.page_size.saturating_sub(self.used_space()) + self.fragmented_bytes()
+    }
+
+    /// Returns the number of bytes the page takes when serialized, holes
+    /// included
+    pub fn used_space(&self) -> usize {
+        HEADER_SIZE + self.slots_size() + self.data.len()
+    }
+
+    fn slot_size(key: &K) -> usize {
+        key.encoded_size() + VALUE_OFFSET_SIZE + VALUE_LENGTH_SIZE
+    }
+
+    // Bytes taken by the serialized slots
+    fn slots_size(&self) -> usize {
+        self.slots.iter().map(|slot| Self::slot_size(&slot.key)).sum()
+    }
+
+    /// Returns the page's entries in key order
+    pub fn sorted_items(&self) -> Vec<LeafPageItem<K>> {
+        self.slots.iter()
+            .map(|slot| LeafPageItem {
+                key: slot.key.clone(),
+                bytes: self.data[slot.value_offset..slot.value_offset + slot.value_length].to_vec(),
+                overflow: slot.overflow,
+            })
+            .collect()
+    }
+
+    /// Moves the upper half of the slots to a new page, leaving both pages
+    /// compacted. None if there are fewer than two slots.
+    pub fn split(&mut self) -> Option<KeyedSlottedLeafPage<K>> {
+        if self.slots.len() < 2 {
+            return None;
+        }
+
+        let mut new_page = KeyedSlottedLeafPage::new_empty(self.page_size);
+        for mut slot in self.slots.split_off(self.slots.len() / 2) {
+            let value = &self.data[slot.value_offset..slot.value_offset + slot.value_length];
+            slot.value_offset = Self::append_value(&mut new_page.data, value);
+            new_page.slots.push(slot);
+        }
+        self.compact();
+
+        Some(new_page)
+    }
+
+    /// Returns the largest value that fits under `key` in an otherwise
+    /// empty page
+    pub fn max_value_size_for(&self, key: &K) -> usize {
+        self.page_size.saturating_sub(HEADER_SIZE + Self::slot_size(key))
+    }
+}
+
+impl SlottedLeafPage {
+    pub fn max_value_size(&self) -> usize {
+        // A value must fit in an otherwise empty page alongside its slot
+        self.page_size.saturating_sub(HEADER_SIZE + SLOT_SIZE)
+    }
+}
+
+/// A slotted leaf page read in place from its serialized bytes, the
+/// counterpart of `LeafPageView`. Slots hold their values' offsets, so a
+/// lookup of a fixed-size key is a binary search and nothing more.
+#[derive(Debug, Clone, Copy)]
+pub struct SlottedLeafPageView<'a, K: PageKey = u64> {
+    bytes: &'a [u8],
+    count: usize,
+    _key: PhantomData<K>,
+}
+
+impl<'a, K: PageKey> SlottedLeafPageView<'a, K> {
+    pub fn new(bytes: &'a [u8]) -> Result<Self, Box<dyn Error>> {
+        if bytes.len() < HEADER_SIZE {
+            return Err(format!("Slotted leaf page of {} bytes is shorter than its header", bytes.len()).into());
+        }
+        if PageType::from_u8(bytes[0]) != Some(K::SLOTTED_LEAF_PAGE_TYPE) {
+            return Err(format!("Page type {} is not a {:?}", bytes[0], K::SLOTTED_LEAF_PAGE_TYPE).into());
+        }
+
+        let count = read_u64(bytes, PAGE_TYPE_SIZE).unwrap() as usize;
+        if let Some(key_size) = K::ENCODED_SIZE {
+            let slots_end = count.checked_mul(key_size + VALUE_OFFSET_SIZE + VALUE_LENGTH_SIZE).and_then(|size| size.checked_add(HEADER_SIZE));
+            if slots_end.is_none_or(|end| end > bytes.len()) {
+                return Err(format!("Slotted leaf page slots run past its {} bytes", bytes.len()).into());
+            }
+        }
+        Ok(SlottedLeafPageView { bytes, count, _key: PhantomData })
+    }
+
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn prev_page_id(&self) -> u64 {
+        read_u64(self.bytes, HEADER_SIZE - NEXT_PAGE_ID_SIZE - PREV_PAGE_ID_SIZE).unwrap()
+    }
+
+    pub fn next_page_id(&self) -> u64 {
+        read_u64(self.bytes, HEADER_SIZE - NEXT_PAGE_ID_SIZE).unwrap()
+    }
+
+    pub fn get(&self, key: K::Ref<'_>) -> Option<&'a [u8]> {
+        self.get_entry(key).map(|(value, _)| value)
+    }
+
+    /// The bytes stored for `key`, with whether they are an overflow pointer
+    /// record rather than the value
+    pub fn get_entry(&self, key: K::Ref<'_>) -> Option<(&'a [u8], bool)> {
+        let slot_offset = match K::ENCODED_SIZE {
+            Some(key_size) => self.search(key, key_size)?,
+            None => self.scan(key)?,
+        };
+
+        let value_offset = read_u64(self.bytes, slot_offset)? as usize;
+        let stored_length = read_u64(self.bytes, slot_offset + VALUE_OFFSET_SIZE)?;
+        let value_length = (stored_length & !OVERFLOW_FLAG) as usize;
+        let data_start = read_u64(self.bytes, PAGE_TYPE_SIZE + COUNT_SIZE)? as usize;
+        let start = data_start.checked_add(value_offset)?;
+        let value = self.bytes.get(start..start.checked_add(value_length)?)?;
+        Some((value, stored_length & OVERFLOW_FLAG != 0))
+    }
+
+    // Binary search of fixed-size slots. Returns the offset of the found
+    // slot's value offset field.
+    fn search(&self, key: K::Ref<'_>, key_size: usize) -> Option<usize> {
+        let slot_size = key_size + VALUE_OFFSET_SIZE + VALUE_LENGTH_SIZE;
+        let slot_key = |index: usize| K::decode_ref(&self.bytes[HEADER_SIZE + index * slot_size..]).map(|(key, _)| key);
+
+        let (mut low, mut high) = (0, self.count);
+        while low < high {
+            let middle = low + (high - low) / 2;
+            match K::cmp_refs(slot_key(middle)?, key) {
+                Ordering::Less => low = middle + 1,
+                Ordering::Greater => high = middle,
+                Ordering::Equal => return Some(HEADER_SIZE + middle * slot_size + key_size),
+            }
+        }
+        None
+    }
+
+    // Walks slots whose keys vary in size, stopping at the first key above
+    // `key`
+    fn scan(&self, key: K::Ref<'_>) -> Option<usize> {
+        let mut offset = HEADER_SIZE;
+        for _ in 0..self.count {
+            let (slot_key, key_size) = K::decode_ref(self.bytes.get(offset..)?)?;
+            match K::cmp_refs(slot_key, key) {
+                Ordering::Less => offset += key_size + VALUE_OFFSET_SIZE + VALUE_LENGTH_SIZE,
+                Ordering::Equal => return Some(offset + key_size),
+                Ordering::Greater => return None,
+            }
+        }
+        None
+    }
+}
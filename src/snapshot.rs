@@ -0,0 +1,439 @@
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::ops::RangeBounds;
+use std::sync::{Arc, Mutex};
+use crate::data_tree::{DataTree, PageType};
+use crate::key_codec::KeyCodec;
+use crate::leaf_page::LeafPage;
+use crate::page_store::PageStore;
+use crate::range::{Range, Runs};
+use crate::superblock::SUPERBLOCK_PAGE_ID;
+
+// The live snapshots of a tree and the pages kept for them
+//
+// A snapshot reads the tree's pages as they were when it was taken. Before
+// the tree first changes or frees a page a live snapshot can still see, the
+// page's image is copied to a page of its own, which the snapshot reads in
+// its place from then on. A copy is freed once the last snapshot reading it
+// is dropped.
+#[derive(Default)]
+pub(crate) struct Snapshots {
+    next_id: u64,
+    // The pages each live snapshot no longer reads from the tree: the copy
+    // it reads instead, or None for pages allocated after it was taken
+    live: HashMap<u64, HashMap<u64, Option<u64>>>,
+    // The number of live snapshots reading each copy
+    readers: HashMap<u64, usize>,
+    // Copies no snapshot reads any more, and pages a dropped transaction
+    // couldn't free, for the tree to free
+    reclaimable: Vec<u64>,
+}
+
+impl Snapshots {
+    fn register(&mut self) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.live.insert(id, HashMap::new());
+        id
+    }
+
+    fn release(&mut self, id: u64) {
+        let pages = match self.live.remove(&id) {
+            Some(pages) => pages,
+            None => return,
+        };
+        for copy_page_id in pages.into_values().flatten() {
+            let readers = self.readers.get_mut(&copy_page_id).unwrap();
+            *readers -= 1;
+            if *readers == 0 {
+                self.readers.remove(&copy_page_id);
+                self.reclaimable.push(copy_page_id);
+            }
+        }
+    }
+
+    // The page snapshot `id` reads for the tree's page `page_id`
+    fn page_to_read(&self, id: u64, page_id: u64) -> u64 {
+        match self.live.get(&id).and_then(|pages| pages.get(&page_id)) {
+            Some(Some(copy_page_id)) => *copy_page_id,
+            _ => page_id,
+        }
+    }
+
+    pub(crate) fn any_live(&self) -> bool {
+        !self.live.is_empty()
+    }
+
+    /// Whether changes to the tree have pages to keep for live snapshots, or
+    /// copies to free
+    pub(crate) fn preserving_pages(&self) -> bool {
+        !self.live.is_empty() || !self.reclaimable.is_empty()
+    }
+
+    /// Hands over pages nothing uses any more, for the tree's next write or
+    /// flush to free
+    pub(crate) fn free_later(&mut self, pages: Vec<u64>) {
+        self.reclaimable.extend(pages);
+    }
+
+    pub(crate) fn take_reclaimable(&mut self) -> Vec<u64> {
+        std::mem::take(&mut self.reclaimable)
+    }
+
+    /// Returns every copy kept for a live snapshot or waiting to be freed
+    pub(crate) fn copies(&self) -> Vec<u64> {
+        let mut copies: Vec<u64> = self.readers.keys().chain(&self.reclaimable).copied().collect();
+        copies.sort_unstable();
+        copies
+    }
+
+    /// Notes that `page_ids`, free or new, are in none of the live snapshots
+    pub(crate) fn hide(&mut self, page_ids: &[u64]) {
+        for pages in self.live.values_mut() {
+            for page_id in page_ids {
+                pages.entry(*page_id).or_insert(None);
+            }
+        }
+    }
+
+    /// Copies `page_id` before `store` first changes or frees it, for the
+    /// live snapshots that still read it from the tree, and returns the
+    /// copy. The snapshots read the copy from then on, so they never see
+    /// the change, and a failed copy leaves them reading the page as it is.
+    pub(crate) fn keep<S: PageStore>(&mut self, store: &mut S, page_id: u64) -> Result<Option<u64>, Box<dyn Error>> {
+        if page_id == SUPERBLOCK_PAGE_ID || self.live.values().all(|pages| pages.contains_key(&page_id)) {
+            return Ok(None);
+        }
+
+        // Pages that are free or don't exist yet are new to every snapshot
+        let bytes = match store.page_exists(page_id) {
+            true => store.get_page_bytes(page_id)?,
+            false => Vec::new(),
+        };
+        if bytes.first().is_none_or(|&page_type| page_type == PageType::FREE.to_u8()) {
+            self.hide(&[page_id]);
+            return Ok(None);
+        }
+
+        let copy_page_id = store.allocate_page();
+        store.put_page_bytes(copy_page_id, &bytes)?;
+        for pages in self.live.values_mut() {
+            if let Entry::Vacant(entry) = pages.entry(page_id) {
+                entry.insert(Some(copy_page_id));
+                *self.readers.entry(copy_page_id).or_insert(0) += 1;
+            }
+        }
+        Ok(Some(copy_page_id))
+    }
+
+    /// Points the snapshots back at pages a rolled back change had copied,
+    /// which are as they were again. The rollback frees the copies.
+    pub(crate) fn forget(&mut self, copies: &[(u64, u64)]) {
+        for (page_id, copy_page_id) in copies {
+            for pages in self.live.values_mut() {
+                if pages.get(page_id) == Some(&Some(*copy_page_id)) {
+                    pages.remove(page_id);
+                }
+            }
+            self.readers.remove(copy_page_id);
+        }
+    }
+}
+
+// A store that has live snapshots keep each page before it is first changed
+// or freed. Pages it allocates are new to every snapshot.
+pub(crate) struct CopyOnWritePageStore<'a, S: PageStore> {
+    inner: S,
+    snapshots: &'a mut Snapshots,
+    // Each page copied, with its copy
+    copies: Vec<(u64, u64)>,
+}
+
+impl<'a, S: PageStore> CopyOnWritePageStore<'a, S> {
+    pub(crate) fn new(inner: S, snapshots: &'a mut Snapshots) -> Self {
+        CopyOnWritePageStore { inner, snapshots, copies: Vec::new() }
+    }
+
+    /// Returns the inner store and the pages copied, for `Snapshots::forget`
+    /// if the change is rolled back
+    pub(crate) fn into_parts(self) -> (S, Vec<(u64, u64)>) {
+        (self.inner, self.copies)
+    }
+
+    fn keep(&mut self, page_id: u64) -> Result<(), Box<dyn Error>> {
+        if let Some(copy_page_id) = self.snapshots.keep(&mut self.inner, page_id)? {
+            self.copies.push((page_id, copy_page_id));
+        }
+        Ok(())
+    }
+}
+
+impl<S: PageStore> PageStore for CopyOnWritePageStore<'_, S> {
+    fn get_page_bytes(&self, page_id: u64) -> Result<Vec<u8>, Box<dyn Error>> {
+        self.inner.get_page_bytes(page_id)
+    }
+
+    fn put_page_bytes(&mut self, page_id: u64, bytes: &[u8]) -> Result<(), Box<dyn Error>> {
+        self.keep(page_id)?;
+        self.inner.put_page_bytes(page_id, bytes)
+    }
+
+    fn allocate_page(&mut self) -> u64 {
+        let page_id = self.inner.allocate_page();
+        self.snapshots.hide(&[page_id]);
+        page_id
+    }
+
+    fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        self.inner.flush()
+    }
+
+    fn page_size(&self) -> usize {
+        self.inner.page_size()
+    }
+
+    fn get_next_page_id(&self, page_id: u64) -> Option<u64> {
+        self.inner.get_next_page_id(page_id)
+    }
+
+    fn get_prev_page_id(&self, page_id: u64) -> Option<u64> {
+        self.inner.get_prev_page_id(page_id)
+    }
+
+    fn link_pages(&mut self, prev_page_id: u64, next_page_id: u64) -> Result<(), Box<dyn Error>> {
+        self.keep(prev_page_id)?;
+        self.keep(next_page_id)?;
+        self.inner.link_pages(prev_page_id, next_page_id)
+    }
+
+    fn page_exists(&self, page_id: u64) -> bool {
+        self.inner.page_exists(page_id)
+    }
+
+    fn free_page(&mut self, page_id: u64) -> Result<(), Box<dyn Error>> {
+        self.keep(page_id)?;
+        self.inner.free_page(page_id)
+    }
+
+    fn get_page_count(&self) -> usize {
+        self.inner.get_page_count()
+    }
+
+    fn mark_page_dirty(&mut self, page_id: u64) {
+        self.inner.mark_page_dirty(page_id);
+    }
+
+    fn dirty_pages(&self) -> &HashSet<u64> {
+        self.inner.dirty_pages()
+    }
+
+    fn clear_dirty_pages(&mut self) {
+        self.inner.clear_dirty_pages();
+    }
+
+    fn free_list_head(&self) -> u64 {
+        self.inner.free_list_head()
+    }
+
+    fn set_free_list_head(&mut self, page_id: u64) {
+        self.inner.set_free_list_head(page_id);
+    }
+}
+
+/// A read-only view of a DataTree as it was when `DataTree::snapshot` was
+/// called. The tree can go on changing while the snapshot is alive; read
+/// the snapshot through `DataTree::view`, which borrows the tree for as
+/// long as the view is open.
+pub struct Snapshot {
+    id: u64,
+    root_page_id: u64,
+    snapshots: Arc<Mutex<Snapshots>>,
+}
+
+impl Snapshot {
+    pub(crate) fn new(snapshots: &Arc<Mutex<Snapshots>>, root_page_id: u64) -> Self {
+        let id = snapshots.lock().unwrap().register();
+        Snapshot {
+            id,
+            root_page_id,
+            snapshots: Arc::clone(snapshots),
+        }
+    }
+
+    /// Returns the root page ID the snapshot is pinned to
+    pub fn root_page_id(&self) -> u64 {
+        self.root_page_id
+    }
+
+    // Whether the snapshot was taken of the tree with these snapshots
+    pub(crate) fn belongs_to(&self, snapshots: &Arc<Mutex<Snapshots>>) -> bool {
+        Arc::ptr_eq(&self.snapshots, snapshots)
+    }
+}
+
+impl Drop for Snapshot {
+    fn drop(&mut self) {
+        if let Ok(mut snapshots) = self.snapshots.lock() {
+            snapshots.release(self.id);
+        }
+    }
+}
+
+/// The pages of a tree as a snapshot sees them, with the pages changed
+/// since it was taken read from their copies. Every write is an error.
+pub struct SnapshotPageStore<'a, S: PageStore> {
+    store: &'a S,
+    id: u64,
+    snapshots: Arc<Mutex<Snapshots>>,
+}
+
+impl<S: PageStore> SnapshotPageStore<'_, S> {
+    fn read_only() -> Box<dyn Error> {
+        "A snapshot is read-only".into()
+    }
+}
+
+impl<S: PageStore> PageStore for SnapshotPageStore<'_, S> {
+    fn get_page_bytes(&self, page_id: u64) -> Result<Vec<u8>, Box<dyn Error>> {
+        let page_id = self.snapshots.lock().unwrap().page_to_read(self.id, page_id);
+        self.store.get_page_bytes(page_id)
+    }
+
+    fn put_page_bytes(&mut self, _page_id: u64, _bytes: &[u8]) -> Result<(), Box<dyn Error>> {
+        Err(Self::read_only())
+    }
+
+    // Allocation can't fail, so hand out the id that means "no page"; the
+    // write that sets the page up returns the read-only error
+    fn allocate_page(&mut self) -> u64 {
+        0
+    }
+
+    fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+
+    fn page_size(&self) -> usize {
+        self.store.page_size()
+    }
+
+    fn get_next_page_id(&self, page_id: u64) -> Option<u64> {
+        let bytes = self.get_page_bytes(page_id).ok()?;
+        let next_id = LeafPage::new(&bytes).next_page_id();
+        if next_id == 0 {
+            None
+        } else {
+            Some(next_id)
+        }
+    }
+
+    fn get_prev_page_id(&self, page_id: u64) -> Option<u64> {
+        let bytes = self.get_page_bytes(page_id).ok()?;
+        let prev_id = LeafPage::new(&bytes).prev_page_id();
+        if prev_id == 0 {
+            None
+        } else {
+            Some(prev_id)
+        }
+    }
+
+    fn link_pages(&mut self, _prev_page_id: u64, _next_page_id: u64) -> Result<(), Box<dyn Error>> {
+        Err(Self::read_only())
+    }
+
+    fn page_exists(&self, page_id: u64) -> bool {
+        let page_id = self.snapshots.lock().unwrap().page_to_read(self.id, page_id);
+        self.store.page_exists(page_id)
+    }
+
+    fn free_page(&mut self, _page_id: u64) -> Result<(), Box<dyn Error>> {
+        Err(Self::read_only())
+    }
+
+    fn get_page_count(&self) -> usize {
+        self.store.get_page_count()
+    }
+
+    fn mark_page_dirty(&mut self, _page_id: u64) {}
+
+    fn dirty_pages(&self) -> &HashSet<u64> {
+        self.store.dirty_pages()
+    }
+
+    fn clear_dirty_pages(&mut self) {}
+
+    fn free_list_head(&self) -> u64 {
+        self.store.free_list_head()
+    }
+
+    fn set_free_list_head(&mut self, _page_id: u64) {}
+}
+
+/// Reads of a snapshot, created by `DataTree::view`. The view borrows the
+/// tree, so the tree can't change while a view is open, but can between
+/// views of the same snapshot: each sees the tree as it was when the
+/// snapshot was taken.
+pub struct SnapshotView<'a, S: PageStore, K: KeyCodec = u64> {
+    tree: DataTree<SnapshotPageStore<'a, S>, K>,
+}
+
+impl<'a, S: PageStore, K: KeyCodec> SnapshotView<'a, S, K> {
+    pub(crate) fn new(store: &'a S, snapshot: &Snapshot) -> Self {
+        let store = SnapshotPageStore {
+            store,
+            id: snapshot.id,
+            snapshots: Arc::clone(&snapshot.snapshots),
+        };
+        SnapshotView { tree: DataTree::at_root(store, snapshot.root_page_id, false) }
+    }
+
+    pub fn get(&self, key: K::Ref<'_>) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
+        self.tree.get(key)
+    }
+
+    /// Iterates over the entries with keys in `range`, in key order
+    pub fn range<'k, R: RangeBounds<K::Ref<'k>>>(&self, range: R) -> Range<'_, SnapshotPageStore<'a, S>, K> {
+        self.tree.range(range)
+    }
+
+    /// Iterates over the runs of keys in `range` holding the same value
+    pub fn runs<'k, R: RangeBounds<K::Ref<'k>>>(&self, range: R) -> Runs<'_, SnapshotPageStore<'a, S>, K> {
+        self.tree.runs(range)
+    }
+
+    /// Iterates over all entries in key order
+    pub fn iter(&self) -> Range<'_, SnapshotPageStore<'a, S>, K> {
+        self.tree.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::page_store::InMemoryPageStore;
+
+    #[test]
+    fn test_writes_through_a_snapshot_are_errors() {
+        let mut tree = DataTree::new(InMemoryPageStore::with_page_size(256));
+        tree.put(1, b"one").unwrap();
+        let snapshot = tree.snapshot();
+        let mut store = SnapshotPageStore {
+            store: tree.store(),
+            id: snapshot.id,
+            snapshots: Arc::clone(&snapshot.snapshots),
+        };
+
+        let page_id = store.allocate_page();
+        let page = LeafPage::empty(256).serialize();
+        assert_eq!(store.put_page_bytes(page_id, &page).unwrap_err().to_string(), "A snapshot is read-only");
+        assert!(store.free_page(tree.root_page_id()).is_err());
+        assert!(store.link_pages(1, 2).is_err());
+
+        // A tree over the snapshot's pages fails its writes the same way
+        let mut view_tree = DataTree::<_, u64>::at_root(store, snapshot.root_page_id, false);
+        assert!(view_tree.put(2, b"two").is_err());
+        assert!(view_tree.put(3, &[7; 1000]).is_err());
+        assert_eq!(view_tree.get(1).unwrap().unwrap(), b"one");
+    }
+}
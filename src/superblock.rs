@@ -0,0 +1,192 @@
+use std::error::Error;
+use crate::data_tree::PageType;
+use crate::overflow_page::OverflowPointer;
+
+// The superblock always lives in page 0, which is never handed out by
+// `allocate_page` because 0 means "no page" in the sibling links
+pub const SUPERBLOCK_PAGE_ID: u64 = 0;
+
+// "DATATREE" in ASCII
+pub const SUPERBLOCK_MAGIC: u64 = 0x4441_5441_5452_4545;
+// Version 2 added the copy list. Version 1 superblocks end at the free
+// list head and are read with no copy list.
+pub const FORMAT_VERSION: u32 = 2;
+
+// Constants for superblock field sizes
+pub const PAGE_TYPE_SIZE: usize = 1; // 1 byte for page type
+pub const MAGIC_SIZE: usize = 8;     // 8 bytes for magic number
+pub const VERSION_SIZE: usize = 4;   // 4 bytes for format version
+pub const PAGE_SIZE_SIZE: usize = 8; // 8 bytes for page size
+pub const ROOT_PAGE_ID_SIZE: usize = 8; // 8 bytes for root page ID
+pub const PAGE_COUNT_SIZE: usize = 8;   // 8 bytes for page count
+pub const FREE_LIST_HEAD_SIZE: usize = 8; // 8 bytes for free list head
+pub const COPY_LIST_PAGE_ID_SIZE: usize = 8; // 8 bytes for first page of the copy list
+pub const COPY_LIST_LENGTH_SIZE: usize = 8;  // 8 bytes for copy list length
+pub const V1_SUPERBLOCK_SIZE: usize = PAGE_TYPE_SIZE + MAGIC_SIZE + VERSION_SIZE + PAGE_SIZE_SIZE +
+                                      ROOT_PAGE_ID_SIZE + PAGE_COUNT_SIZE + FREE_LIST_HEAD_SIZE;
+pub const SUPERBLOCK_SIZE: usize = V1_SUPERBLOCK_SIZE + COPY_LIST_PAGE_ID_SIZE + COPY_LIST_LENGTH_SIZE;
+
+// Header page that makes a store self-describing
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Superblock {
+    pub version: u32,
+    pub page_size: u64,
+    pub root_page_id: u64,
+    pub page_count: u64,
+    pub free_list_head: u64,
+    // Overflow chain listing the page copies kept for snapshots when the
+    // store was flushed, which no snapshot reads once the store is reopened
+    pub copy_list_page_id: u64,
+    pub copy_list_length: u64,
+}
+
+impl Superblock {
+    pub fn new(page_size: usize, root_page_id: u64) -> Self {
+        Superblock {
+            version: FORMAT_VERSION,
+            page_size: page_size as u64,
+            root_page_id,
+            page_count: 0,
+            free_list_head: 0,
+            copy_list_page_id: 0,
+            copy_list_length: 0,
+        }
+    }
+
+    /// Returns the pointer to the copy list, if there is one
+    pub fn copy_list(&self) -> Option<OverflowPointer> {
+        if self.copy_list_page_id == 0 {
+            return None;
+        }
+        Some(OverflowPointer {
+            first_page_id: self.copy_list_page_id,
+            value_length: self.copy_list_length,
+        })
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(SUPERBLOCK_SIZE);
+
+        // Write page type (1 byte)
+        bytes.push(PageType::Superblock.to_u8());
+
+        // Write magic number (8 bytes)
+        bytes.extend_from_slice(&SUPERBLOCK_MAGIC.to_le_bytes());
+
+        // Write format version (4 bytes)
+        bytes.extend_from_slice(&self.version.to_le_bytes());
+
+        // Write page size (8 bytes)
+        bytes.extend_from_slice(&self.page_size.to_le_bytes());
+
+        // Write root page ID (8 bytes)
+        bytes.extend_from_slice(&self.root_page_id.to_le_bytes());
+
+        // Write page count (8 bytes)
+        bytes.extend_from_slice(&self.page_count.to_le_bytes());
+
+        // Write free list head (8 bytes)
+        bytes.extend_from_slice(&self.free_list_head.to_le_bytes());
+
+        // Write copy list page ID (8 bytes)
+        bytes.extend_from_slice(&self.copy_list_page_id.to_le_bytes());
+
+        // Write copy list length (8 bytes)
+        bytes.extend_from_slice(&self.copy_list_length.to_le_bytes());
+
+        bytes
+    }
+
+    pub fn deserialize(bytes: &[u8]) -> Result<Self, Box<dyn Error>> {
+        if bytes.len() < V1_SUPERBLOCK_SIZE {
+            return Err(format!(
+                "Superblock is {} bytes, expected at least {}",
+                bytes.len(), V1_SUPERBLOCK_SIZE
+            ).into());
+        }
+
+        let mut offset = 0;
+
+        // Read page type (1 byte)
+        if PageType::from_u8(bytes[offset]) != Some(PageType::Superblock) {
+            return Err("Page 0 is not a superblock".into());
+        }
+        offset += 1;
+
+        // Read magic number (8 bytes)
+        let magic = u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap());
+        if magic != SUPERBLOCK_MAGIC {
+            return Err(format!("Bad superblock magic number {:#x}", magic).into());
+        }
+        offset += 8;
+
+        // Read format version (4 bytes)
+        let version = u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+        if version != 1 && version != FORMAT_VERSION {
+            return Err(format!(
+                "Unsupported format version {}, expected {}",
+                version, FORMAT_VERSION
+            ).into());
+        }
+        if version == FORMAT_VERSION && bytes.len() < SUPERBLOCK_SIZE {
+            return Err(format!(
+                "Superblock is {} bytes, expected at least {}",
+                bytes.len(), SUPERBLOCK_SIZE
+            ).into());
+        }
+        offset += 4;
+
+        // Read page size (8 bytes)
+        let page_size = u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap());
+        offset += 8;
+
+        // Read root page ID (8 bytes)
+        let root_page_id = u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap());
+        offset += 8;
+
+        // Read page count (8 bytes)
+        let page_count = u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap());
+        offset += 8;
+
+        // Read free list head (8 bytes)
+        let free_list_head = u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap());
+        offset += 8;
+
+        // Version 1 has no copy list
+        let (copy_list_page_id, copy_list_length) = if version == 1 {
+            (0, 0)
+        } else {
+            // Read copy list page ID (8 bytes)
+            let copy_list_page_id = u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap());
+            offset += 8;
+
+            // Read copy list length (8 bytes)
+            let copy_list_length = u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap());
+            (copy_list_page_id, copy_list_length)
+        };
+
+        Ok(Superblock {
+            version,
+            page_size,
+            root_page_id,
+            page_count,
+            free_list_head,
+            copy_list_page_id,
+            copy_list_length,
+        })
+    }
+
+    /// Checks that the superblock was written for a store with this page size
+    pub fn validate(&self, page_size: usize) -> Result<(), Box<dyn Error>> {
+        if self.page_size != page_size as u64 {
+            return Err(format!(
+                "Superblock page size {} does not match store page size {}",
+                self.page_size, page_size
+            ).into());
+        }
+        if self.root_page_id == SUPERBLOCK_PAGE_ID {
+            return Err("Superblock has no root page".into());
+        }
+        Ok(())
+    }
+}
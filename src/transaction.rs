@@ -0,0 +1,313 @@
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::ops::RangeBounds;
+use std::sync::{Arc, Mutex};
+use crate::data_tree::{DataTree, PageType};
+use crate::key_codec::KeyCodec;
+use crate::leaf_page::LeafPage;
+use crate::page_store::{pop_free_page, push_free_page, PageStore, CRC_SIZE};
+use crate::range::Range;
+use crate::snapshot::{CopyOnWritePageStore, Snapshots};
+use crate::superblock::SUPERBLOCK_PAGE_ID;
+use crate::write_batch::{UndoPageStore, WriteBatch};
+
+/// A store that holds a transaction's page writes in memory over the store
+/// the tree lives in, created by `DataTree::begin`.
+///
+/// Reads see the buffered pages first. Freed pages go onto a free list of
+/// the transaction's own, and new pages are taken from the inner store so
+/// their ids are not handed out twice; they are freed there again if the
+/// transaction doesn't commit.
+pub struct TransactionPageStore<S: PageStore> {
+    inner: S,
+    // Page images written by the transaction, keyed by the dirty set
+    pages: HashMap<u64, Vec<u8>>,
+    dirty_pages: HashSet<u64>,
+    free_list_head: u64,
+    // Pages allocated from the inner store
+    allocated: Vec<u64>,
+}
+
+impl<S: PageStore> TransactionPageStore<S> {
+    pub(crate) fn new(inner: S) -> Self {
+        let free_list_head = inner.free_list_head();
+        TransactionPageStore {
+            inner,
+            pages: HashMap::new(),
+            dirty_pages: HashSet::new(),
+            free_list_head,
+            allocated: Vec::new(),
+        }
+    }
+
+    // Write the buffered pages to the inner store in page order, having live
+    // snapshots keep each page before it is changed, and put back the pages
+    // already written if any write fails
+    fn publish(&mut self, snapshots: &mut Snapshots) -> Result<(), Box<dyn Error>> {
+        let mut page_ids: Vec<u64> = self.dirty_pages.iter().copied().collect();
+        page_ids.sort_unstable();
+
+        // The pages the transaction allocated are new to every snapshot. The
+        // copies are new pages too: the free list the inner store has may
+        // hold pages the transaction has taken.
+        snapshots.hide(&self.allocated);
+        let mut undo = UndoPageStore::new(&mut self.inner);
+        undo.set_free_list_head(0);
+        let mut store = CopyOnWritePageStore::new(undo, snapshots);
+        let published = page_ids.iter()
+            .try_for_each(|page_id| store.put_page_bytes(*page_id, &self.pages[page_id]));
+        let (mut undo, copies) = store.into_parts();
+        if let Err(error) = published {
+            return match undo.roll_back() {
+                Ok(()) => {
+                    snapshots.forget(&copies);
+                    Err(error)
+                }
+                Err(rollback_error) => Err(format!("{}; rolling back the commit also failed: {}", error, rollback_error).into()),
+            };
+        }
+        undo.set_free_list_head(self.free_list_head);
+        self.allocated.clear();
+        Ok(())
+    }
+
+    // Drop the buffered pages and free the pages allocated from the inner
+    // store. A page that fails to be freed is left in `allocated`.
+    fn discard(&mut self) -> Result<(), Box<dyn Error>> {
+        self.pages.clear();
+        self.dirty_pages.clear();
+        while let Some(&page_id) = self.allocated.last() {
+            push_free_page(&mut self.inner, page_id)?;
+            self.allocated.pop();
+        }
+        Ok(())
+    }
+}
+
+impl<S: PageStore> PageStore for TransactionPageStore<S> {
+    fn get_page_bytes(&self, page_id: u64) -> Result<Vec<u8>, Box<dyn Error>> {
+        match self.pages.get(&page_id) {
+            Some(bytes) => Ok(bytes.clone()),
+            None => self.inner.get_page_bytes(page_id),
+        }
+    }
+
+    fn put_page_bytes(&mut self, page_id: u64, bytes: &[u8]) -> Result<(), Box<dyn Error>> {
+        if bytes.len() + CRC_SIZE > self.inner.page_size() {
+            return Err(Box::new(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "Page too large",
+            )));
+        }
+
+        self.pages.insert(page_id, bytes.to_vec());
+        self.mark_page_dirty(page_id);
+        Ok(())
+    }
+
+    fn allocate_page(&mut self) -> u64 {
+        // Reuse a page freed in the transaction or before it
+        if let Ok(Some(page_id)) = pop_free_page(self) {
+            let page = LeafPage::empty(self.page_size());
+            self.put_page_bytes(page_id, &page.serialize()).unwrap();
+            return page_id;
+        }
+
+        // Hide the inner store's free list, whose pages the transaction may
+        // already have taken, so it hands out a new page
+        let free_list_head = self.inner.free_list_head();
+        self.inner.set_free_list_head(0);
+        let page_id = self.inner.allocate_page();
+        self.inner.set_free_list_head(free_list_head);
+        self.allocated.push(page_id);
+        page_id
+    }
+
+    // Pages reach the inner store when the transaction commits
+    fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+
+    fn page_size(&self) -> usize {
+        self.inner.page_size()
+    }
+
+    fn get_next_page_id(&self, page_id: u64) -> Option<u64> {
+        let bytes = self.get_page_bytes(page_id).ok()?;
+        let next_id = LeafPage::new(&bytes).next_page_id();
+        if next_id == 0 {
+            None
+        } else {
+            Some(next_id)
+        }
+    }
+
+    fn get_prev_page_id(&self, page_id: u64) -> Option<u64> {
+        let bytes = self.get_page_bytes(page_id).ok()?;
+        let prev_id = LeafPage::new(&bytes).prev_page_id();
+        if prev_id == 0 {
+            None
+        } else {
+            Some(prev_id)
+        }
+    }
+
+    fn link_pages(&mut self, prev_page_id: u64, next_page_id: u64) -> Result<(), Box<dyn Error>> {
+        // Get and update previous page
+        let prev_bytes = self.get_page_bytes(prev_page_id)?;
+        let mut prev_page = LeafPage::new(&prev_bytes);
+        prev_page.set_next_page_id(next_page_id);
+        self.put_page_bytes(prev_page_id, &prev_page.serialize())?;
+
+        // Get and update next page
+        let next_bytes = self.get_page_bytes(next_page_id)?;
+        let mut next_page = LeafPage::new(&next_bytes);
+        next_page.set_prev_page_id(prev_page_id);
+        self.put_page_bytes(next_page_id, &next_page.serialize())?;
+
+        Ok(())
+    }
+
+    fn page_exists(&self, page_id: u64) -> bool {
+        match self.pages.get(&page_id) {
+            Some(bytes) => bytes.first() != Some(&PageType::FREE.to_u8()),
+            None => self.inner.page_exists(page_id),
+        }
+    }
+
+    fn free_page(&mut self, page_id: u64) -> Result<(), Box<dyn Error>> {
+        if page_id == SUPERBLOCK_PAGE_ID {
+            return Err("The superblock page cannot be freed".into());
+        }
+        if !self.page_exists(page_id) {
+            return Err(Box::new(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "Page not found",
+            )));
+        }
+
+        push_free_page(self, page_id)
+    }
+
+    fn get_page_count(&self) -> usize {
+        self.inner.get_page_count()
+    }
+
+    fn mark_page_dirty(&mut self, page_id: u64) {
+        self.dirty_pages.insert(page_id);
+    }
+
+    fn dirty_pages(&self) -> &HashSet<u64> {
+        &self.dirty_pages
+    }
+
+    // The buffered pages stay dirty until the transaction ends
+    fn clear_dirty_pages(&mut self) {}
+
+    fn free_list_head(&self) -> u64 {
+        self.free_list_head
+    }
+
+    fn set_free_list_head(&mut self, page_id: u64) {
+        self.free_list_head = page_id;
+    }
+}
+
+/// Changes to a DataTree that take effect together when `commit` is
+/// called, created by `DataTree::begin`.
+///
+/// Reads through the transaction see its own writes; the tree itself is
+/// borrowed until the transaction ends and is unchanged until it commits.
+/// Dropping a transaction without committing rolls it back, but can't report
+/// a failure to free the pages it allocated; the tree's next write or flush
+/// frees them instead. Call `rollback` to see the error.
+pub struct Transaction<'a, S: PageStore, K: KeyCodec = u64> {
+    tree: DataTree<TransactionPageStore<&'a mut S>, K>,
+    // The root of the tree the transaction began on, moved by commit
+    root_page_id: &'a mut u64,
+    snapshots: Arc<Mutex<Snapshots>>,
+    finished: bool,
+}
+
+impl<'a, S: PageStore, K: KeyCodec> Transaction<'a, S, K> {
+    pub(crate) fn new(tree: DataTree<TransactionPageStore<&'a mut S>, K>, root_page_id: &'a mut u64, snapshots: Arc<Mutex<Snapshots>>) -> Self {
+        Transaction {
+            tree,
+            root_page_id,
+            snapshots,
+            finished: false,
+        }
+    }
+
+    pub fn get(&self, key: K::Ref<'_>) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
+        self.tree.get(key)
+    }
+
+    pub fn put(&mut self, key: K::Ref<'_>, value: &[u8]) -> Result<(), Box<dyn Error>> {
+        self.tree.put(key, value)
+    }
+
+    pub fn delete(&mut self, key: K::Ref<'_>) -> Result<bool, Box<dyn Error>> {
+        self.tree.delete(key)
+    }
+
+    pub fn put_range<'k, R: RangeBounds<K::Ref<'k>>>(&mut self, range: R, value: &[u8]) -> Result<(), Box<dyn Error>> {
+        self.tree.put_range(range, value)
+    }
+
+    pub fn delete_range<'k, R: RangeBounds<K::Ref<'k>>>(&mut self, range: R) -> Result<u64, Box<dyn Error>> {
+        self.tree.delete_range(range)
+    }
+
+    /// Applies a batch within the transaction, all of it or none
+    pub fn write(&mut self, batch: WriteBatch<K>) -> Result<(), Box<dyn Error>> {
+        self.tree.write(batch)
+    }
+
+    /// Iterates over the entries with keys in `range`, in key order
+    pub fn range<'k, R: RangeBounds<K::Ref<'k>>>(&self, range: R) -> Range<'_, TransactionPageStore<&'a mut S>, K> {
+        self.tree.range(range)
+    }
+
+    /// Iterates over all entries in key order
+    pub fn iter(&self) -> Range<'_, TransactionPageStore<&'a mut S>, K> {
+        self.tree.iter()
+    }
+
+    /// Returns the IDs of the pages the transaction has written
+    pub fn dirty_pages(&self) -> &HashSet<u64> {
+        self.tree.dirty_pages()
+    }
+
+    /// Writes the transaction's pages to the store and moves the tree to
+    /// its new root. If a page write fails, the pages already written are
+    /// put back and the transaction is rolled back.
+    pub fn commit(mut self) -> Result<(), Box<dyn Error>> {
+        self.finished = true;
+        let root_page_id = self.tree.root_page_id();
+        let store = self.tree.store_mut();
+        if let Err(error) = store.publish(&mut self.snapshots.lock().unwrap()) {
+            store.discard()?;
+            return Err(error);
+        }
+        *self.root_page_id = root_page_id;
+        Ok(())
+    }
+
+    /// Discards the transaction's writes and frees the pages it allocated
+    pub fn rollback(mut self) -> Result<(), Box<dyn Error>> {
+        self.finished = true;
+        self.tree.store_mut().discard()
+    }
+}
+
+impl<S: PageStore, K: KeyCodec> Drop for Transaction<'_, S, K> {
+    fn drop(&mut self) {
+        // Nothing can report a failure here, so the pages left allocated go
+        // to the tree to free
+        if !self.finished && self.tree.store_mut().discard().is_err() {
+            let pages = std::mem::take(&mut self.tree.store_mut().allocated);
+            self.snapshots.lock().unwrap().free_later(pages);
+        }
+    }
+}
@@ -0,0 +1,333 @@
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use crate::data_tree::PageType;
+use crate::leaf_page::LeafPage;
+use crate::page_store::{calculate_crc, PageStore};
+use crate::superblock::{Superblock, SUPERBLOCK_PAGE_ID};
+
+// Record tags in the log
+pub const PAGE_RECORD: u8 = 1;
+pub const COMMIT_RECORD: u8 = 2;
+
+// Constants for record field sizes
+pub const TAG_SIZE: usize = 1;       // 1 byte for record tag
+pub const PAGE_ID_SIZE: usize = 8;   // 8 bytes for page ID
+pub const LENGTH_SIZE: usize = 4;    // 4 bytes for page image length
+pub const PAGE_COUNT_SIZE: usize = 4; // 4 bytes for the number of pages in a batch
+pub const RECORD_CRC_SIZE: usize = 4; // 4 bytes for record CRC
+
+// Write-ahead log layered under a DataTree
+//
+// `flush` appends the full image of every dirty page to the log followed by a
+// commit record, syncs the log, and only then applies the pages to the inner
+// store. A crash part way through applying them is repaired on the next
+// `open` by replaying every committed batch; a batch without a valid commit
+// record was never acknowledged and is thrown away.
+pub struct WalPageStore<S: PageStore> {
+    inner: S,
+    log: File,
+    log_path: PathBuf,
+    dirty_pages: HashSet<u64>,
+    auto_checkpoint: bool,
+}
+
+// A batch of page images read back from the log
+struct LogBatch {
+    pages: Vec<(u64, Vec<u8>)>,
+}
+
+impl<S: PageStore> WalPageStore<S> {
+    /// Opens the log at `log_path` over `inner`, replaying any committed batches
+    pub fn open<P: AsRef<Path>>(inner: S, log_path: P) -> Result<Self, Box<dyn Error>> {
+        let log = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(log_path.as_ref())?;
+
+        let mut store = WalPageStore {
+            inner,
+            log,
+            log_path: log_path.as_ref().to_path_buf(),
+            dirty_pages: HashSet::new(),
+            auto_checkpoint: true,
+        };
+
+        store.recover()?;
+        Ok(store)
+    }
+
+    /// Returns the inner store
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// Consumes the log and returns the inner store
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    /// Returns the path of the log file
+    pub fn log_path(&self) -> &Path {
+        &self.log_path
+    }
+
+    /// Controls whether `flush` applies logged pages to the inner store right away.
+    /// When disabled, committed batches stay in the log until `checkpoint` is called.
+    pub fn set_auto_checkpoint(&mut self, auto_checkpoint: bool) {
+        self.auto_checkpoint = auto_checkpoint;
+    }
+
+    /// Appends the dirty pages and a commit record to the log and syncs it.
+    /// Once this returns the batch survives a crash.
+    pub fn commit_to_log(&mut self) -> Result<(), Box<dyn Error>> {
+        if self.dirty_pages.is_empty() {
+            return Ok(());
+        }
+
+        let mut page_ids: Vec<u64> = self.dirty_pages.iter().copied().collect();
+        page_ids.sort_unstable();
+
+        let mut batch = Vec::new();
+        for page_id in &page_ids {
+            let bytes = self.inner.get_page_bytes(*page_id)?;
+            Self::append_page_record(&mut batch, *page_id, &bytes);
+        }
+        Self::append_commit_record(&mut batch, page_ids.len() as u32);
+
+        self.log.seek(SeekFrom::End(0))?;
+        self.log.write_all(&batch)?;
+        self.log.sync_data()?;
+
+        self.dirty_pages.clear();
+        Ok(())
+    }
+
+    /// Applies every logged page to the inner store, flushes it and empties the log
+    pub fn checkpoint(&mut self) -> Result<(), Box<dyn Error>> {
+        self.inner.flush()?;
+        self.log.set_len(0)?;
+        self.log.sync_all()?;
+        Ok(())
+    }
+
+    // Replay committed batches into the inner store and discard the rest
+    fn recover(&mut self) -> Result<(), Box<dyn Error>> {
+        let mut log_bytes = Vec::new();
+        self.log.seek(SeekFrom::Start(0))?;
+        self.log.read_to_end(&mut log_bytes)?;
+
+        // Replaying a page past the end of a short data file frees the slots
+        // it skips, onto a list of their own
+        let free_list_head = self.inner.free_list_head();
+        self.inner.set_free_list_head(0);
+        let batches = Self::read_committed_batches(&log_bytes);
+        for batch in &batches {
+            for (page_id, bytes) in &batch.pages {
+                self.inner.put_page_bytes(*page_id, bytes)?;
+            }
+        }
+        self.keep_skipped_pages(free_list_head)?;
+
+        // Make the replayed pages durable in place before dropping the log
+        if !batches.is_empty() {
+            self.inner.flush()?;
+        }
+        self.log.set_len(0)?;
+        self.log.sync_all()?;
+        Ok(())
+    }
+
+    // A tree takes its free list from the superblock when it opens, so link
+    // the slots the replay freed in ahead of the list the superblock records,
+    // or of `free_list_head` in a store without one
+    fn keep_skipped_pages(&mut self, free_list_head: u64) -> Result<(), Box<dyn Error>> {
+        let mut skipped = Vec::new();
+        let mut page_id = self.inner.free_list_head();
+        while page_id != 0 {
+            let bytes = self.inner.get_page_bytes(page_id)?;
+            if bytes.first() != Some(&PageType::FREE.to_u8()) {
+                break;
+            }
+            skipped.push(page_id);
+            page_id = LeafPage::deserialize(&bytes).next_page_id();
+        }
+        if skipped.is_empty() {
+            self.inner.set_free_list_head(free_list_head);
+            return Ok(());
+        }
+
+        let superblock = self.inner.get_page_bytes(SUPERBLOCK_PAGE_ID).ok()
+            .and_then(|bytes| Superblock::deserialize(&bytes).ok());
+        let mut head = superblock.map_or(free_list_head, |superblock| superblock.free_list_head);
+        for page_id in skipped.iter().rev() {
+            let mut free_page = LeafPage::new_free_page(self.inner.page_size());
+            free_page.set_next_page_id(head);
+            self.inner.put_page_bytes(*page_id, &free_page.serialize())?;
+            head = *page_id;
+        }
+
+        if let Some(mut superblock) = superblock {
+            superblock.free_list_head = head;
+            self.inner.put_page_bytes(SUPERBLOCK_PAGE_ID, &superblock.serialize())?;
+        }
+        self.inner.set_free_list_head(head);
+        Ok(())
+    }
+
+    fn append_page_record(out: &mut Vec<u8>, page_id: u64, bytes: &[u8]) {
+        let start = out.len();
+        out.push(PAGE_RECORD);
+        out.extend_from_slice(&page_id.to_le_bytes());
+        out.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+        out.extend_from_slice(bytes);
+        let crc = calculate_crc(&out[start..]);
+        out.extend_from_slice(&crc.to_le_bytes());
+    }
+
+    fn append_commit_record(out: &mut Vec<u8>, page_count: u32) {
+        let start = out.len();
+        out.push(COMMIT_RECORD);
+        out.extend_from_slice(&page_count.to_le_bytes());
+        let crc = calculate_crc(&out[start..]);
+        out.extend_from_slice(&crc.to_le_bytes());
+    }
+
+    // Check the CRC that follows `bytes[start..end]`
+    fn record_crc_ok(bytes: &[u8], start: usize, end: usize) -> bool {
+        if end + RECORD_CRC_SIZE > bytes.len() {
+            return false;
+        }
+        let expected = u32::from_le_bytes(bytes[end..end + RECORD_CRC_SIZE].try_into().unwrap());
+        calculate_crc(&bytes[start..end]) == expected
+    }
+
+    // Parse the log, keeping only batches closed by a valid commit record.
+    // Parsing stops at the first short or corrupt record: everything after a
+    // torn write is garbage.
+    fn read_committed_batches(bytes: &[u8]) -> Vec<LogBatch> {
+        let mut batches = Vec::new();
+        let mut pending = Vec::new();
+        let mut offset = 0;
+
+        while offset < bytes.len() {
+            let start = offset;
+            match bytes[offset] {
+                PAGE_RECORD => {
+                    let header_end = start + TAG_SIZE + PAGE_ID_SIZE + LENGTH_SIZE;
+                    if header_end > bytes.len() {
+                        break;
+                    }
+                    let page_id = u64::from_le_bytes(bytes[start + TAG_SIZE..start + TAG_SIZE + PAGE_ID_SIZE].try_into().unwrap());
+                    let length = u32::from_le_bytes(bytes[header_end - LENGTH_SIZE..header_end].try_into().unwrap()) as usize;
+                    let end = header_end + length;
+                    if end > bytes.len() || !Self::record_crc_ok(bytes, start, end) {
+                        break;
+                    }
+                    pending.push((page_id, bytes[header_end..end].to_vec()));
+                    offset = end + RECORD_CRC_SIZE;
+                }
+                COMMIT_RECORD => {
+                    let end = start + TAG_SIZE + PAGE_COUNT_SIZE;
+                    if end > bytes.len() || !Self::record_crc_ok(bytes, start, end) {
+                        break;
+                    }
+                    let page_count = u32::from_le_bytes(bytes[start + TAG_SIZE..end].try_into().unwrap()) as usize;
+                    if page_count != pending.len() {
+                        break;
+                    }
+                    batches.push(LogBatch { pages: std::mem::take(&mut pending) });
+                    offset = end + RECORD_CRC_SIZE;
+                }
+                _ => break,
+            }
+        }
+
+        batches
+    }
+}
+
+impl<S: PageStore> PageStore for WalPageStore<S> {
+    fn get_page_bytes(&self, page_id: u64) -> Result<Vec<u8>, Box<dyn Error>> {
+        self.inner.get_page_bytes(page_id)
+    }
+
+    fn put_page_bytes(&mut self, page_id: u64, bytes: &[u8]) -> Result<(), Box<dyn Error>> {
+        self.inner.put_page_bytes(page_id, bytes)?;
+        self.mark_page_dirty(page_id);
+        Ok(())
+    }
+
+    fn allocate_page(&mut self) -> u64 {
+        let page_id = self.inner.allocate_page();
+        self.mark_page_dirty(page_id);
+        page_id
+    }
+
+    fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        self.commit_to_log()?;
+        if self.auto_checkpoint {
+            self.checkpoint()?;
+        }
+        Ok(())
+    }
+
+    fn page_size(&self) -> usize {
+        self.inner.page_size()
+    }
+
+    fn get_next_page_id(&self, page_id: u64) -> Option<u64> {
+        self.inner.get_next_page_id(page_id)
+    }
+
+    fn get_prev_page_id(&self, page_id: u64) -> Option<u64> {
+        self.inner.get_prev_page_id(page_id)
+    }
+
+    fn link_pages(&mut self, prev_page_id: u64, next_page_id: u64) -> Result<(), Box<dyn Error>> {
+        self.inner.link_pages(prev_page_id, next_page_id)?;
+        self.mark_page_dirty(prev_page_id);
+        self.mark_page_dirty(next_page_id);
+        Ok(())
+    }
+
+    fn page_exists(&self, page_id: u64) -> bool {
+        self.inner.page_exists(page_id)
+    }
+
+    fn free_page(&mut self, page_id: u64) -> Result<(), Box<dyn Error>> {
+        self.inner.free_page(page_id)?;
+        self.mark_page_dirty(page_id);
+        Ok(())
+    }
+
+    fn get_page_count(&self) -> usize {
+        self.inner.get_page_count()
+    }
+
+    // Dirty pages are tracked here rather than in the inner store, whose dirty
+    // set keeps everything logged since the last checkpoint
+    fn mark_page_dirty(&mut self, page_id: u64) {
+        self.dirty_pages.insert(page_id);
+    }
+
+    fn dirty_pages(&self) -> &HashSet<u64> {
+        &self.dirty_pages
+    }
+
+    fn clear_dirty_pages(&mut self) {
+        self.dirty_pages.clear();
+    }
+
+    fn free_list_head(&self) -> u64 {
+        self.inner.free_list_head()
+    }
+
+    fn set_free_list_head(&mut self, page_id: u64) {
+        self.inner.set_free_list_head(page_id);
+    }
+}
@@ -0,0 +1,206 @@
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, HashSet};
+use std::error::Error;
+use std::ops::{Bound, RangeBounds};
+use crate::data_tree::DataTree;
+use crate::key_codec::KeyCodec;
+use crate::page_key::PageKey;
+use crate::page_store::{push_free_page, PageStore};
+
+// An operation in a batch, with its keys in stored form
+enum BatchOperation<E> {
+    Put(E, Vec<u8>),
+    Delete(E),
+    PutRange(Bound<E>, Bound<E>, Vec<u8>),
+}
+
+/// Puts, deletes and range puts applied together by `DataTree::write`, in
+/// the order they were added. Either every operation takes effect or none
+/// does.
+pub struct WriteBatch<K: KeyCodec = u64> {
+    operations: Vec<BatchOperation<K::Encoded>>,
+}
+
+impl<K: KeyCodec> Default for WriteBatch<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: KeyCodec> WriteBatch<K> {
+    pub fn new() -> Self {
+        WriteBatch { operations: Vec::new() }
+    }
+
+    pub fn put(&mut self, key: K::Ref<'_>, value: &[u8]) {
+        self.operations.push(BatchOperation::Put(K::encode(key), value.to_vec()));
+    }
+
+    pub fn delete(&mut self, key: K::Ref<'_>) {
+        self.operations.push(BatchOperation::Delete(K::encode(key)));
+    }
+
+    /// Adds a `DataTree::put_range` of `value` over `range`
+    pub fn put_range<'k, R: RangeBounds<K::Ref<'k>>>(&mut self, range: R, value: &[u8]) {
+        let lower = range.start_bound().map(|key| K::encode(*key));
+        let upper = range.end_bound().map(|key| K::encode(*key));
+        self.operations.push(BatchOperation::PutRange(lower, upper, value.to_vec()));
+    }
+
+    /// Returns the number of operations in the batch
+    pub fn len(&self) -> usize {
+        self.operations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.operations.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.operations.clear();
+    }
+
+    // Apply the operations in order, stopping at the first that fails
+    pub(crate) fn apply<S: PageStore>(self, tree: &mut DataTree<S, K>) -> Result<(), Box<dyn Error>> {
+        for operation in self.operations {
+            match operation {
+                BatchOperation::Put(key, value) => tree.put_encoded(key, &value)?,
+                BatchOperation::Delete(key) => {
+                    tree.delete_encoded(key.to_ref())?;
+                }
+                BatchOperation::PutRange(lower, upper, value) => tree.put_range_encoded(lower, upper, &value)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+// A store that keeps the image every page had before it was first dirtied,
+// so that a failed batch can put them all back
+//
+// Writes go straight through to the inner store. A page handed out by
+// `allocate_page` either comes off the free list, and is put back as the
+// FREE page it was, or is new, and is freed again on rollback.
+pub(crate) struct UndoPageStore<S: PageStore> {
+    inner: S,
+    // Prior images of the dirtied pages. None for pages that had none.
+    images: BTreeMap<u64, Option<Vec<u8>>>,
+    // Pages allocated past the end of the store
+    allocated: Vec<u64>,
+    free_list_head: u64,
+}
+
+impl<S: PageStore> UndoPageStore<S> {
+    pub(crate) fn new(inner: S) -> Self {
+        let free_list_head = inner.free_list_head();
+        UndoPageStore {
+            inner,
+            images: BTreeMap::new(),
+            allocated: Vec::new(),
+            free_list_head,
+        }
+    }
+
+    // Keep the image of a page about to be written for the first time
+    fn record(&mut self, page_id: u64) {
+        if let Entry::Vacant(entry) = self.images.entry(page_id) {
+            entry.insert(self.inner.get_page_bytes(page_id).ok());
+        }
+    }
+
+    /// Writes back every dirtied page as it was, restores the free list and
+    /// frees the pages allocated since the store was created
+    pub(crate) fn roll_back(mut self) -> Result<(), Box<dyn Error>> {
+        for (page_id, image) in &self.images {
+            if let Some(bytes) = image {
+                self.inner.put_page_bytes(*page_id, bytes)?;
+            }
+        }
+        self.inner.set_free_list_head(self.free_list_head);
+        for page_id in &self.allocated {
+            push_free_page(&mut self.inner, *page_id)?;
+        }
+        Ok(())
+    }
+}
+
+impl<S: PageStore> PageStore for UndoPageStore<S> {
+    fn get_page_bytes(&self, page_id: u64) -> Result<Vec<u8>, Box<dyn Error>> {
+        self.inner.get_page_bytes(page_id)
+    }
+
+    fn put_page_bytes(&mut self, page_id: u64, bytes: &[u8]) -> Result<(), Box<dyn Error>> {
+        self.record(page_id);
+        self.inner.put_page_bytes(page_id, bytes)
+    }
+
+    fn allocate_page(&mut self) -> u64 {
+        // A page reused from the free list is its head
+        let free_list_head = self.inner.free_list_head();
+        if free_list_head != 0 {
+            self.record(free_list_head);
+        }
+
+        let page_id = self.inner.allocate_page();
+        if let Entry::Vacant(entry) = self.images.entry(page_id) {
+            entry.insert(None);
+            self.allocated.push(page_id);
+        }
+        page_id
+    }
+
+    fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        self.inner.flush()
+    }
+
+    fn page_size(&self) -> usize {
+        self.inner.page_size()
+    }
+
+    fn get_next_page_id(&self, page_id: u64) -> Option<u64> {
+        self.inner.get_next_page_id(page_id)
+    }
+
+    fn get_prev_page_id(&self, page_id: u64) -> Option<u64> {
+        self.inner.get_prev_page_id(page_id)
+    }
+
+    fn link_pages(&mut self, prev_page_id: u64, next_page_id: u64) -> Result<(), Box<dyn Error>> {
+        self.record(prev_page_id);
+        self.record(next_page_id);
+        self.inner.link_pages(prev_page_id, next_page_id)
+    }
+
+    fn page_exists(&self, page_id: u64) -> bool {
+        self.inner.page_exists(page_id)
+    }
+
+    fn free_page(&mut self, page_id: u64) -> Result<(), Box<dyn Error>> {
+        self.record(page_id);
+        self.inner.free_page(page_id)
+    }
+
+    fn get_page_count(&self) -> usize {
+        self.inner.get_page_count()
+    }
+
+    fn mark_page_dirty(&mut self, page_id: u64) {
+        self.inner.mark_page_dirty(page_id);
+    }
+
+    fn dirty_pages(&self) -> &HashSet<u64> {
+        self.inner.dirty_pages()
+    }
+
+    fn clear_dirty_pages(&mut self) {
+        self.inner.clear_dirty_pages();
+    }
+
+    fn free_list_head(&self) -> u64 {
+        self.inner.free_list_head()
+    }
+
+    fn set_free_list_head(&mut self, page_id: u64) {
+        self.inner.set_free_list_head(page_id);
+    }
+}
@@ -0,0 +1,147 @@
+use data_tree::DataTree;
+use data_tree::data_tree::{DataTreeConfig, PageStats};
+use data_tree::leaf::LeafEncoding;
+use data_tree::page_store::{PageStore, InMemoryPageStore};
+
+const PAGE_SIZE: usize = 256;
+
+const ADAPTIVE: DataTreeConfig = DataTreeConfig {
+    min_fill: 0.25,
+    fill_factor: 1.0,
+    leaf_encoding: LeafEncoding::Adaptive,
+};
+
+fn adaptive_tree() -> DataTree<InMemoryPageStore> {
+    let mut tree = DataTree::new(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    tree.set_config(ADAPTIVE).unwrap();
+    tree
+}
+
+// The value of key `i` when keys come in runs of 50 equal values
+fn run_value(i: u64) -> Vec<u8> {
+    format!("run{}", i / 50).into_bytes()
+}
+
+fn distinct_value(i: u64) -> Vec<u8> {
+    format!("value{}", i).into_bytes()
+}
+
+// Every page the tree uses is counted once
+fn assert_counts_all_pages<S: PageStore>(tree: &DataTree<S>, stats: &PageStats) {
+    let total = stats.branch_pages + stats.plain_leaf_pages + stats.run_length_leaf_pages + stats.slotted_leaf_pages + stats.overflow_pages;
+    assert_eq!(total, tree.store().get_page_count());
+}
+
+#[test]
+fn test_runs_are_written_run_length() {
+    let mut tree = adaptive_tree();
+    for i in 0..5000 {
+        tree.put(i, &run_value(i)).unwrap();
+    }
+    assert!(tree.iter().eq((0..5000).map(|i| (i, run_value(i)))));
+
+    let stats = tree.page_stats().unwrap();
+    assert_eq!(stats.plain_leaf_pages, 0);
+    assert!(stats.run_length_leaf_pages > 0);
+    assert!(stats.bytes_saved > 5000 * 16);
+    assert_counts_all_pages(&tree, &stats);
+}
+
+#[test]
+fn test_distinct_values_are_written_plain() {
+    let mut tree = adaptive_tree();
+    for i in 0..2000 {
+        tree.put(i, &distinct_value(i)).unwrap();
+    }
+
+    let stats = tree.page_stats().unwrap();
+    assert_eq!(stats.run_length_leaf_pages, 0);
+    assert_eq!(stats.bytes_saved, 0);
+
+    // The same leaves as a plain tree
+    let mut plain = DataTree::new(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    for i in 0..2000 {
+        plain.put(i, &distinct_value(i)).unwrap();
+    }
+    assert_eq!(plain.page_stats().unwrap(), stats);
+}
+
+#[test]
+fn test_leaves_convert_as_their_runs_change() {
+    let mut tree = adaptive_tree();
+    for i in 0..1000 {
+        tree.put(i, &run_value(i)).unwrap();
+    }
+    assert_eq!(tree.page_stats().unwrap().plain_leaf_pages, 0);
+
+    // Breaking up the runs turns the leaves plain
+    for i in 0..1000 {
+        tree.put(i, &distinct_value(i)).unwrap();
+    }
+    let stats = tree.page_stats().unwrap();
+    assert_eq!(stats.run_length_leaf_pages, 0);
+    assert!(tree.iter().eq((0..1000).map(|i| (i, distinct_value(i)))));
+
+    // Restoring them turns the leaves back
+    for i in 0..1000 {
+        tree.put(i, &run_value(i)).unwrap();
+    }
+    let stats = tree.page_stats().unwrap();
+    assert_eq!(stats.plain_leaf_pages, 0);
+    assert!(stats.bytes_saved > 0);
+    assert!(tree.iter().eq((0..1000).map(|i| (i, run_value(i)))));
+    assert_counts_all_pages(&tree, &stats);
+}
+
+#[test]
+fn test_bulk_load_picks_encoding_per_leaf() {
+    // Runs in the lower half of the keys, distinct values in the upper half
+    let value = |i: u64| if i < 3000 { run_value(i) } else { distinct_value(i) };
+    let store = InMemoryPageStore::with_page_size(PAGE_SIZE);
+    let tree = DataTree::bulk_load_with_config(store, ADAPTIVE, (0..6000).map(|i| (i, value(i)))).unwrap();
+    assert!(tree.iter().eq((0..6000).map(|i| (i, value(i)))));
+
+    let stats = tree.page_stats().unwrap();
+    assert!(stats.plain_leaf_pages > 0);
+    assert!(stats.run_length_leaf_pages > 0);
+    assert!(stats.run_length_leaf_pages < stats.plain_leaf_pages);
+    assert!(stats.bytes_saved > 0);
+    assert_counts_all_pages(&tree, &stats);
+}
+
+#[test]
+fn test_page_stats_count_overflow_pages() {
+    let mut tree = DataTree::new(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    for i in 0..200 {
+        tree.put(i, &distinct_value(i)).unwrap();
+    }
+    tree.put(1000, &vec![1u8; PAGE_SIZE * 4]).unwrap();
+    tree.put(1001, &vec![2u8; PAGE_SIZE * 2]).unwrap();
+
+    let stats = tree.page_stats().unwrap();
+    assert!(stats.branch_pages >= 1);
+    assert!(stats.overflow_pages >= 6);
+    assert_eq!(stats.run_length_leaf_pages, 0);
+    assert_counts_all_pages(&tree, &stats);
+
+    // Run-length leaves that take more bytes than plain ones show a loss
+    tree.set_config(DataTreeConfig { leaf_encoding: LeafEncoding::RunLength, ..Default::default() }).unwrap();
+    for i in 0..200 {
+        tree.put(i, &distinct_value(i)).unwrap();
+    }
+    let stats = tree.page_stats().unwrap();
+    assert!(stats.run_length_leaf_pages > 0);
+    assert!(stats.bytes_saved < 0);
+    assert_counts_all_pages(&tree, &stats);
+}
+
+#[test]
+fn test_adaptive_byte_keys_stay_plain() {
+    let mut tree: DataTree<_, Vec<u8>> = DataTree::new_keyed(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    tree.set_config(ADAPTIVE).unwrap();
+    for i in 0..500u64 {
+        tree.put(&i.to_be_bytes(), b"same").unwrap();
+    }
+    assert_eq!(tree.iter().count(), 500);
+    assert_eq!(tree.page_stats().unwrap().run_length_leaf_pages, 0);
+}
@@ -10,13 +10,11 @@ fn test_page_splitting() {
     let mut tree = DataTree::new(store);
 
     // Insert data that will require multiple pages
-    let keys = vec![1, 2, 3, 4];
-    let values = vec![
-        b"value1".to_vec(),
+    let keys = [1, 2, 3, 4];
+    let values = [b"value1".to_vec(),
         b"value2".to_vec(),
         b"value3".to_vec(),
-        b"value4".to_vec(),
-    ];
+        b"value4".to_vec()];
 
     // Insert all key-value pairs
     for (key, value) in keys.iter().zip(values.iter()) {
@@ -45,12 +43,10 @@ fn test_page_cleanup_after_deletion() {
 
     // Insert data that will require multiple pages
     let keys = vec![10, 11, 12, 13];
-    let values = vec![
-        b"value1".to_vec(),
+    let values = [b"value1".to_vec(),
         b"value2".to_vec(),
         b"value3".to_vec(),
-        b"value4".to_vec(),
-    ];
+        b"value4".to_vec()];
 
     // Insert all key-value pairs
     for (key, value) in keys.iter().zip(values.iter()) {
@@ -102,7 +98,7 @@ fn test_page_type_serialization() {
     assert_eq!(page_type, PageType::BranchPage);
 
     // Create a LeafPage and verify its serialization
-    let leaf_page = LeafPage::new_empty(100);
+    let leaf_page = LeafPage::empty(100);
     let serialized = leaf_page.serialize();
     assert_eq!(serialized[0], PageType::LeafPage.to_u8());
 }
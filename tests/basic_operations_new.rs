@@ -1,23 +1,23 @@
-use data_tree::data_tree2::{DataTree2, ResultFormatter, IdentityFormatter};
-use data_tree::page_store::{PageStore, InMemoryPageStore};
+use data_tree::data_tree2::DataTree2;
+use data_tree::formatter::Value;
+use data_tree::page_store::InMemoryPageStore;
 
 #[test]
 fn test_put_then_get() {
     // Create store with 100 byte pages
-    let mut store = InMemoryPageStore::with_page_size(100);
-    let page_id = store.allocate_page();
-    let formatter = ResultFormatter::new(vec![IdentityFormatter::new(page_id)]);
-    let mut tree = DataTree2::new(store, formatter);
+    let store = InMemoryPageStore::with_page_size(100);
+    let mut db = DataTree2::new(store);
+    db.create_tree("values").unwrap();
 
+    let mut tree = db.open_tree("values").unwrap();
     tree.put(1, b"value1").unwrap();
-    // At this point there should be 1 page - the one we allocated before creating the tree
+    let root_page_id = tree.root_page_id();
 
-    // Verify that we have 1 page in total
-    assert_eq!(tree.get_page_count(), 1, "Expected 1 page after put operation");
-    let dirty = tree.dirty_pages();
-    assert!(dirty.contains(&page_id), "Expected the page to be marked as dirty");
-    assert_eq!(dirty.len(), 1, "Expected 1 page to be dirty");
+    // The catalog and the named tree each have a root branch over one leaf
+    assert_eq!(db.get_page_count(), 4, "Expected 4 pages after put operation");
+    let dirty = db.dirty_pages();
+    assert!(dirty.contains(&root_page_id), "Expected the tree's root to be marked as dirty");
 
-    let actual = tree.get(1).unwrap().unwrap();
-    assert_eq!(actual, b"value1");
-}
\ No newline at end of file
+    let actual = db.open_tree("values").unwrap().get(1).unwrap().unwrap();
+    assert_eq!(actual, Value::from(b"value1"));
+}
@@ -0,0 +1,140 @@
+use data_tree::DataTree;
+use data_tree::branch_page::BranchPage;
+use data_tree::data_tree::{DataTreeConfig, PageType};
+use data_tree::file_page_store::FilePageStore;
+use data_tree::leaf_page::LeafPage;
+use data_tree::page_store::{PageStore, InMemoryPageStore, CRC_SIZE};
+use std::fs;
+use std::path::PathBuf;
+
+const PAGE_SIZE: usize = 256;
+
+// Build a unique path in the temp directory for a test's backing file
+fn temp_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("data_tree_{}_{}.db", name, std::process::id()));
+    let _ = fs::remove_file(&path);
+    path
+}
+
+fn entries(count: u64) -> impl Iterator<Item = (u64, Vec<u8>)> {
+    (0..count).map(|i| (i * 10, format!("value{}", i).into_bytes()))
+}
+
+// Every leaf from left to right, following the sibling links
+fn leaves<S: PageStore>(tree: &DataTree<S>) -> Vec<LeafPage> {
+    let mut page_id = tree.root_page_id();
+    loop {
+        let bytes = tree.store().get_page_bytes(page_id).unwrap();
+        if PageType::from_u8(bytes[0]) != Some(PageType::BranchPage) {
+            break;
+        }
+        page_id = BranchPage::deserialize(&bytes).entries()[0].page_id;
+    }
+
+    let mut leaves = Vec::new();
+    while page_id != 0 {
+        let leaf = LeafPage::deserialize(&tree.store().get_page_bytes(page_id).unwrap());
+        page_id = leaf.next_page_id();
+        leaves.push(leaf);
+    }
+    leaves
+}
+
+#[test]
+fn test_bulk_load_builds_a_searchable_tree() {
+    let store = InMemoryPageStore::with_page_size(PAGE_SIZE);
+    let mut tree = DataTree::bulk_load(store, entries(5000)).unwrap();
+
+    for i in 0..5000 {
+        assert_eq!(tree.get(i * 10).unwrap().unwrap(), format!("value{}", i).into_bytes());
+        assert!(tree.get(i * 10 + 5).unwrap().is_none());
+    }
+    assert!(tree.iter().map(|(key, _)| key).eq((0..5000).map(|i| i * 10)));
+    assert!(tree.iter().rev().map(|(key, _)| key).eq((0..5000).rev().map(|i| i * 10)));
+
+    // The loaded tree takes further updates
+    tree.put(5, b"five").unwrap();
+    tree.put(100_000, b"end").unwrap();
+    assert!(tree.delete(10).unwrap());
+    assert_eq!(tree.get(5).unwrap().unwrap(), b"five");
+    assert_eq!(tree.get(100_000).unwrap().unwrap(), b"end");
+    assert!(tree.get(10).unwrap().is_none());
+}
+
+#[test]
+fn test_leaves_are_packed_to_the_fill_factor() {
+    let usable_size = PAGE_SIZE - CRC_SIZE;
+    for fill_factor in [1.0, 0.6] {
+        let config = DataTreeConfig { fill_factor, ..Default::default() };
+        let store = InMemoryPageStore::with_page_size(PAGE_SIZE);
+        let tree = DataTree::bulk_load_with_config(store, config, entries(2000)).unwrap();
+
+        let leaves = leaves(&tree);
+        let fill_bytes = (usable_size as f64 * fill_factor) as usize;
+        for leaf in &leaves[..leaves.len() - 1] {
+            // Full up to the fill factor: the next entry would not have fit
+            assert!(leaf.used_space() <= fill_bytes);
+            assert!(leaf.used_space() + 16 + 10 > fill_bytes, "leaf uses {} bytes", leaf.used_space());
+        }
+    }
+}
+
+#[test]
+fn test_bulk_load_uses_fewer_pages_than_puts() {
+    let store = InMemoryPageStore::with_page_size(PAGE_SIZE);
+    let loaded = DataTree::bulk_load(store, entries(2000)).unwrap();
+
+    let mut inserted = DataTree::new(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    for (key, value) in entries(2000) {
+        inserted.put(key, &value).unwrap();
+    }
+
+    assert!(loaded.store().get_page_count() < inserted.store().get_page_count());
+}
+
+#[test]
+fn test_unsorted_input_is_rejected() {
+    let store = InMemoryPageStore::with_page_size(PAGE_SIZE);
+    let unsorted = vec![(1, b"a".to_vec()), (3, b"b".to_vec()), (2, b"c".to_vec())];
+    assert!(DataTree::bulk_load(store, unsorted).is_err());
+
+    let store = InMemoryPageStore::with_page_size(PAGE_SIZE);
+    let duplicate = vec![(1, b"a".to_vec()), (1, b"b".to_vec())];
+    assert!(DataTree::bulk_load(store, duplicate).is_err());
+
+    let store = InMemoryPageStore::with_page_size(PAGE_SIZE);
+    let config = DataTreeConfig { fill_factor: 0.0, ..Default::default() };
+    assert!(DataTree::bulk_load_with_config(store, config, entries(10)).is_err());
+}
+
+#[test]
+fn test_empty_and_single_leaf_loads() {
+    let store = InMemoryPageStore::with_page_size(PAGE_SIZE);
+    let mut tree = DataTree::bulk_load(store, Vec::new()).unwrap();
+    assert_eq!(tree.iter().count(), 0);
+    tree.put(1, b"one").unwrap();
+    assert_eq!(tree.get(1).unwrap().unwrap(), b"one");
+
+    let store = InMemoryPageStore::with_page_size(PAGE_SIZE);
+    let tree = DataTree::bulk_load(store, entries(3)).unwrap();
+    let root = BranchPage::deserialize(&tree.store().get_page_bytes(tree.root_page_id()).unwrap());
+    assert_eq!(root.entries().len(), 1);
+    assert_eq!(tree.get(20).unwrap().unwrap(), b"value2");
+}
+
+#[test]
+fn test_bulk_loaded_file_reopens() {
+    let path = temp_path("bulk_load");
+    {
+        let store = FilePageStore::create(&path, PAGE_SIZE).unwrap();
+        let mut tree = DataTree::bulk_load(store, entries(3000)).unwrap();
+        tree.flush().unwrap();
+    }
+
+    let store = FilePageStore::open(&path, PAGE_SIZE).unwrap();
+    let tree = DataTree::open(store).unwrap();
+    assert_eq!(tree.keys().count(), 3000);
+    assert_eq!(tree.get(29_990).unwrap().unwrap(), b"value2999");
+
+    fs::remove_file(&path).unwrap();
+}
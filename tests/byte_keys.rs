@@ -0,0 +1,207 @@
+use data_tree::DataTree;
+use data_tree::branch_page::KeyedBranchPage;
+use data_tree::data_tree::{DataTreeConfig, PageType};
+use data_tree::file_page_store::FilePageStore;
+use data_tree::page_store::{PageStore, InMemoryPageStore};
+use rand::prelude::*;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+const PAGE_SIZE: usize = 256;
+
+type ByteTree<S> = DataTree<S, Vec<u8>>;
+
+// Build a unique path in the temp directory for a test's backing file
+fn temp_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("data_tree_{}_{}.db", name, std::process::id()));
+    let _ = fs::remove_file(&path);
+    path
+}
+
+fn key(i: usize) -> Vec<u8> {
+    format!("user/{:05}/profile", i).into_bytes()
+}
+
+// Number of branch levels above the leaves
+fn height<S: PageStore>(tree: &ByteTree<S>) -> usize {
+    let mut height = 0;
+    let mut page_id = tree.root_page_id();
+    loop {
+        let bytes = tree.store().get_page_bytes(page_id).unwrap();
+        if PageType::from_u8(bytes[0]) != Some(PageType::ByteBranchPage) {
+            return height;
+        }
+        height += 1;
+        page_id = KeyedBranchPage::<Vec<u8>>::deserialize(&bytes).entries()[0].page_id;
+    }
+}
+
+#[test]
+fn test_keys_sharing_a_prefix_do_not_collide() {
+    let mut tree: ByteTree<_> = DataTree::new_keyed(InMemoryPageStore::with_page_size(PAGE_SIZE));
+
+    // All three would truncate to the same u64
+    tree.put(b"abcdefgh", b"eight").unwrap();
+    tree.put(b"abcdefgh-one", b"one").unwrap();
+    tree.put(b"abcdefgh-two", b"two").unwrap();
+    tree.put(b"", b"empty").unwrap();
+
+    assert_eq!(tree.get(b"abcdefgh").unwrap().unwrap(), b"eight");
+    assert_eq!(tree.get(b"abcdefgh-one").unwrap().unwrap(), b"one");
+    assert_eq!(tree.get(b"abcdefgh-two").unwrap().unwrap(), b"two");
+    assert_eq!(tree.get(b"").unwrap().unwrap(), b"empty");
+    assert!(tree.get(b"abcdefg").unwrap().is_none());
+
+    assert!(tree.delete(b"abcdefgh-one").unwrap());
+    assert!(!tree.delete(b"abcdefgh-one").unwrap());
+    assert!(tree.get(b"abcdefgh-one").unwrap().is_none());
+    assert_eq!(tree.get(b"abcdefgh-two").unwrap().unwrap(), b"two");
+}
+
+#[test]
+fn test_keys_are_ordered_lexicographically() {
+    let mut tree: ByteTree<_> = DataTree::new_keyed(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    for word in ["pear", "apple", "b", "banana", "a", "apricot", "ba"] {
+        tree.put(word.as_bytes(), word.as_bytes()).unwrap();
+    }
+
+    let keys: Vec<Vec<u8>> = tree.keys().collect();
+    let expected: Vec<&[u8]> = vec![b"a", b"apple", b"apricot", b"b", b"ba", b"banana", b"pear"];
+    assert_eq!(keys, expected);
+
+    let in_range: Vec<Vec<u8>> = tree.range(&b"apple"[..]..&b"banana"[..]).map(|(key, _)| key).collect();
+    let expected: Vec<&[u8]> = vec![b"apple", b"apricot", b"b", b"ba"];
+    assert_eq!(in_range, expected);
+
+    let backwards: Vec<Vec<u8>> = tree.range(&b"b"[..]..).rev().map(|(key, _)| key).collect();
+    let expected: Vec<&[u8]> = vec![b"pear", b"banana", b"ba", b"b"];
+    assert_eq!(backwards, expected);
+}
+
+#[test]
+fn test_many_keys_split_into_a_multi_level_tree() {
+    let mut tree: ByteTree<_> = DataTree::new_keyed(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    for i in 0..3000 {
+        tree.put(&key(i), format!("value{}", i).as_bytes()).unwrap();
+    }
+
+    assert!(height(&tree) >= 2);
+    for i in 0..3000 {
+        assert_eq!(tree.get(&key(i)).unwrap().unwrap(), format!("value{}", i).into_bytes());
+    }
+    assert!(tree.keys().eq((0..3000).map(key)));
+    assert!(tree.keys().rev().eq((0..3000).rev().map(key)));
+
+    // Branch entries hold separators no longer than the keys they divide
+    let root = KeyedBranchPage::<Vec<u8>>::deserialize(&tree.store().get_page_bytes(tree.root_page_id()).unwrap());
+    assert!(root.entries()[0].first_key.is_empty());
+    assert!(root.entries()[1..].iter().all(|entry| entry.first_key.len() < key(0).len()));
+}
+
+#[test]
+fn test_random_operations_against_a_map() {
+    let mut tree: ByteTree<_> = DataTree::new_keyed(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    tree.set_config(DataTreeConfig { min_fill: 0.5, ..Default::default() }).unwrap();
+    let mut expected = BTreeMap::new();
+    let mut rng = StdRng::seed_from_u64(12);
+
+    // Keys of varied lengths from a fixed pool, so deletes often hit
+    let pool: Vec<Vec<u8>> = (0..1500)
+        .map(|_| (0..rng.gen_range(0..24)).map(|_| rng.gen_range(b'a'..b'e')).collect())
+        .collect();
+    for _ in 0..8000 {
+        let key = pool.choose(&mut rng).unwrap().clone();
+        if rng.gen_bool(0.55) {
+            let value = vec![rng.gen(); rng.gen_range(0..40)];
+            tree.put(&key, &value).unwrap();
+            expected.insert(key, value);
+        } else {
+            assert_eq!(tree.delete(&key).unwrap(), expected.remove(&key).is_some());
+        }
+    }
+
+    for (key, value) in &expected {
+        assert_eq!(tree.get(key).unwrap().as_ref(), Some(value));
+    }
+    assert!(tree.iter().eq(expected.clone()));
+}
+
+#[test]
+fn test_oversized_keys_are_rejected() {
+    let mut tree: ByteTree<_> = DataTree::new_keyed(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    let max_key_length = tree.max_key_size() - 2;
+
+    tree.put(&vec![b'k'; max_key_length], b"fits").unwrap();
+    assert!(tree.put(&vec![b'k'; max_key_length + 1], b"too long").is_err());
+    assert_eq!(tree.get(&vec![b'k'; max_key_length]).unwrap().unwrap(), b"fits");
+}
+
+#[test]
+fn test_key_length_fits_its_prefix_on_large_pages() {
+    let mut tree: ByteTree<_> = DataTree::new_keyed(InMemoryPageStore::with_page_size(512 * 1024));
+    let max_key_length = tree.max_key_size() - 2;
+    assert!(max_key_length < u16::MAX as usize);
+
+    tree.put(&vec![b'k'; max_key_length], b"fits").unwrap();
+    assert!(tree.put(&vec![b'k'; u16::MAX as usize + 1], b"too long").is_err());
+    assert_eq!(tree.get(&vec![b'k'; max_key_length]).unwrap().unwrap(), b"fits");
+    assert_eq!(tree.iter().next().unwrap().0.len(), max_key_length);
+}
+
+#[test]
+fn test_large_values_and_cursor() {
+    let mut tree: ByteTree<_> = DataTree::new_keyed(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    let large_value = vec![7u8; 2000];
+    tree.put(b"large", &large_value).unwrap();
+    tree.put(b"small", b"value").unwrap();
+    tree.put(b"medium", b"value").unwrap();
+    assert_eq!(tree.get(b"large").unwrap().unwrap(), large_value);
+
+    let mut cursor = tree.cursor();
+    assert!(cursor.seek(b"l").unwrap());
+    assert_eq!(cursor.key(), Some(b"large".to_vec()));
+    assert_eq!(cursor.value().unwrap().unwrap(), large_value);
+    cursor.delete_current().unwrap();
+    assert_eq!(cursor.key(), Some(b"medium".to_vec()));
+    assert!(cursor.seek_for_prev(b"z").unwrap());
+    assert_eq!(cursor.key(), Some(b"small".to_vec()));
+}
+
+#[test]
+fn test_bulk_load_byte_keys() {
+    let store = InMemoryPageStore::with_page_size(PAGE_SIZE);
+    let entries = (0..2000).map(|i| (key(i), format!("value{}", i).into_bytes()));
+    let tree: ByteTree<_> = DataTree::bulk_load_keyed(store, DataTreeConfig::default(), entries).unwrap();
+
+    assert!(tree.keys().eq((0..2000).map(key)));
+    assert_eq!(tree.get(&key(1234)).unwrap().unwrap(), b"value1234");
+
+    let store = InMemoryPageStore::with_page_size(PAGE_SIZE);
+    let unsorted = vec![(b"b".to_vec(), Vec::new()), (b"a".to_vec(), Vec::new())];
+    assert!(ByteTree::bulk_load_keyed(store, DataTreeConfig::default(), unsorted).is_err());
+}
+
+#[test]
+fn test_byte_tree_reopens_and_is_not_a_u64_tree() {
+    let path = temp_path("byte_keys");
+    {
+        let store = FilePageStore::create(&path, PAGE_SIZE).unwrap();
+        let mut tree: ByteTree<_> = DataTree::new_keyed(store);
+        for i in 0..500 {
+            tree.put(&key(i), b"value").unwrap();
+        }
+        tree.flush().unwrap();
+    }
+
+    let store = FilePageStore::open(&path, PAGE_SIZE).unwrap();
+    let tree: ByteTree<_> = DataTree::open_keyed(store).unwrap();
+    assert_eq!(tree.keys().count(), 500);
+    assert_eq!(tree.get(&key(499)).unwrap().unwrap(), b"value");
+    drop(tree);
+
+    let store = FilePageStore::open(&path, PAGE_SIZE).unwrap();
+    assert!(DataTree::open(store).is_err());
+
+    fs::remove_file(&path).unwrap();
+}
@@ -174,7 +174,7 @@ fn test_branch_page_crc_verification_on_updates() {
     assert_eq!(page_type, PageType::BranchPage, "Expected a branch page to be created");
 
     // Verify we can read the branch page
-    assert!(root_page_bytes.len() > 0);
+    assert!(!root_page_bytes.is_empty());
 
     // Release the store reference
     let _ = store;
@@ -187,5 +187,5 @@ fn test_branch_page_crc_verification_on_updates() {
 
     // Verify we can still read the branch page (root page)
     let root_page_bytes = store.get_page_bytes(root_page_id).unwrap();
-    assert!(root_page_bytes.len() > 0);
+    assert!(!root_page_bytes.is_empty());
 }
\ No newline at end of file
@@ -0,0 +1,146 @@
+use data_tree::DataTree;
+use data_tree::page_store::InMemoryPageStore;
+use std::collections::BTreeMap;
+
+const PAGE_SIZE: usize = 256;
+
+// A multi-level tree holding the even keys below 2000
+fn build_tree() -> DataTree<InMemoryPageStore> {
+    let mut tree = DataTree::new(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    for i in 0..1000 {
+        tree.put(i * 2, format!("value{}", i * 2).as_bytes()).unwrap();
+    }
+    tree
+}
+
+#[test]
+fn test_seek_lands_on_first_key_at_or_after_target() {
+    let mut tree = build_tree();
+    let mut cursor = tree.cursor();
+    assert!(!cursor.is_valid());
+
+    assert!(cursor.seek(10).unwrap());
+    assert_eq!(cursor.key(), Some(10));
+    assert_eq!(cursor.value().unwrap(), Some(b"value10".to_vec()));
+
+    assert!(cursor.seek(11).unwrap());
+    assert_eq!(cursor.key(), Some(12));
+
+    assert!(!cursor.seek(1999).unwrap());
+    assert!(!cursor.is_valid());
+    assert_eq!(cursor.key(), None);
+    assert_eq!(cursor.value().unwrap(), None);
+}
+
+#[test]
+fn test_seek_for_prev_lands_on_last_key_at_or_before_target() {
+    let mut tree = build_tree();
+    let mut cursor = tree.cursor();
+
+    assert!(cursor.seek_for_prev(11).unwrap());
+    assert_eq!(cursor.key(), Some(10));
+
+    assert!(cursor.seek_for_prev(u64::MAX).unwrap());
+    assert_eq!(cursor.key(), Some(1998));
+
+    assert!(cursor.seek_for_prev(0).unwrap());
+    assert_eq!(cursor.key(), Some(0));
+
+    assert!(!cursor.prev().unwrap());
+    assert!(!cursor.is_valid());
+}
+
+#[test]
+fn test_next_and_prev_walk_every_key() {
+    let mut tree = build_tree();
+    let mut cursor = tree.cursor();
+
+    let mut keys = Vec::new();
+    let mut valid = cursor.seek(0).unwrap();
+    while valid {
+        keys.push(cursor.key().unwrap());
+        valid = cursor.next().unwrap();
+    }
+    assert_eq!(keys, (0..1000).map(|i| i * 2).collect::<Vec<u64>>());
+
+    let mut keys = Vec::new();
+    let mut valid = cursor.seek_for_prev(u64::MAX).unwrap();
+    while valid {
+        keys.push(cursor.key().unwrap());
+        valid = cursor.prev().unwrap();
+    }
+    assert_eq!(keys, (0..1000).rev().map(|i| i * 2).collect::<Vec<u64>>());
+}
+
+#[test]
+fn test_put_current_updates_in_place() {
+    let mut tree = build_tree();
+    {
+        let mut cursor = tree.cursor();
+        cursor.seek(500).unwrap();
+        for _ in 0..10 {
+            let key = cursor.key().unwrap();
+            cursor.put_current(format!("new{}", key).as_bytes()).unwrap();
+            assert_eq!(cursor.value().unwrap().unwrap(), format!("new{}", key).into_bytes());
+            cursor.next().unwrap();
+        }
+
+        // A value too big for the leaf splits it and the cursor follows the key
+        cursor.seek(700).unwrap();
+        cursor.put_current(&[7u8; 150]).unwrap();
+        assert_eq!(cursor.key(), Some(700));
+        assert_eq!(cursor.value().unwrap().unwrap(), vec![7u8; 150]);
+        assert!(cursor.next().unwrap());
+        assert_eq!(cursor.key(), Some(702));
+    }
+
+    for key in (500..520).step_by(2) {
+        assert_eq!(tree.get(key).unwrap().unwrap(), format!("new{}", key).into_bytes());
+    }
+    assert_eq!(tree.get(520).unwrap().unwrap(), b"value520");
+    assert_eq!(tree.get(700).unwrap().unwrap(), vec![7u8; 150]);
+}
+
+#[test]
+fn test_delete_current_moves_to_next_entry() {
+    let mut tree = build_tree();
+    let mut expected: BTreeMap<u64, Vec<u8>> = (0..1000)
+        .map(|i| (i * 2, format!("value{}", i * 2).into_bytes()))
+        .collect();
+
+    {
+        // Delete every key in 200..1200 that is a multiple of 4
+        let mut cursor = tree.cursor();
+        cursor.seek(200).unwrap();
+        while cursor.key().is_some_and(|key| key < 1200) {
+            let key = cursor.key().unwrap();
+            if key.is_multiple_of(4) {
+                cursor.delete_current().unwrap();
+                expected.remove(&key);
+                assert!(cursor.key().is_some_and(|next| next > key));
+            } else {
+                cursor.next().unwrap();
+            }
+        }
+
+        // Deleting the last key leaves the cursor invalid
+        cursor.seek_for_prev(u64::MAX).unwrap();
+        cursor.delete_current().unwrap();
+        expected.remove(&1998);
+        assert!(!cursor.is_valid());
+        assert!(cursor.delete_current().is_err());
+    }
+
+    let entries: Vec<(u64, Vec<u8>)> = tree.iter().collect();
+    assert_eq!(entries, expected.into_iter().collect::<Vec<_>>());
+}
+
+#[test]
+fn test_cursor_on_empty_tree() {
+    let mut tree = DataTree::new(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    let mut cursor = tree.cursor();
+    assert!(!cursor.seek(0).unwrap());
+    assert!(!cursor.seek_for_prev(u64::MAX).unwrap());
+    assert!(!cursor.next().unwrap());
+    assert!(cursor.put_current(b"x").is_err());
+}
@@ -0,0 +1,209 @@
+use data_tree::DataTree;
+use data_tree::file_page_store::FilePageStore;
+use data_tree::leaf_page::LeafPage;
+use data_tree::page_store::{PageStore, PageCorruptionError};
+use std::fs;
+use std::path::PathBuf;
+
+// Build a unique path in the temp directory for a test's backing file
+fn temp_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("data_tree_{}_{}.db", name, std::process::id()));
+    let _ = fs::remove_file(&path);
+    path
+}
+
+#[test]
+fn test_put_and_get_page_bytes() {
+    let path = temp_path("put_and_get");
+    let mut store = FilePageStore::create(&path, 256).unwrap();
+
+    let page_id = store.allocate_page();
+    let mut page = LeafPage::empty(256);
+    page.put(1, b"value1");
+    store.put_page_bytes(page_id, &page.serialize()).unwrap();
+
+    // Readable from the write buffer before the flush
+    let bytes = store.get_page_bytes(page_id).unwrap();
+    assert_eq!(LeafPage::deserialize(&bytes).get(1).unwrap(), b"value1");
+
+    // And from the file after it
+    store.flush().unwrap();
+    let bytes = store.get_page_bytes(page_id).unwrap();
+    assert_eq!(LeafPage::deserialize(&bytes).get(1).unwrap(), b"value1");
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_pages_are_written_at_fixed_offsets() {
+    let path = temp_path("fixed_offsets");
+    let page_size = 128;
+    let mut store = FilePageStore::create(&path, page_size).unwrap();
+
+    let first = store.allocate_page();
+    let second = store.allocate_page();
+    let mut page = LeafPage::empty(page_size);
+    page.put(7, b"seven");
+    store.put_page_bytes(second, &page.serialize()).unwrap();
+    store.flush().unwrap();
+
+    // The file holds one slot per page id, including the reserved page 0
+    let file_bytes = fs::read(&path).unwrap();
+    assert_eq!(file_bytes.len(), (second as usize + 1) * page_size);
+
+    // The second page's slot starts with its serialized bytes
+    let serialized = page.serialize();
+    let offset = second as usize * page_size;
+    assert_eq!(&file_bytes[offset..offset + serialized.len()], &serialized[..]);
+    assert_ne!(first, second);
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_flush_writes_dirty_pages() {
+    let path = temp_path("flush_dirty");
+    let mut store = FilePageStore::create(&path, 256).unwrap();
+
+    let page_id = store.allocate_page();
+    assert!(store.dirty_pages().contains(&page_id));
+
+    store.flush().unwrap();
+    assert!(store.dirty_pages().is_empty());
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_reopen_data_tree() {
+    let path = temp_path("reopen_tree");
+
+    let root_page_id = {
+        let store = FilePageStore::create(&path, 512).unwrap();
+        let mut tree = DataTree::new(store);
+        for i in 0..20 {
+            tree.put(i, format!("value{}", i).as_bytes()).unwrap();
+        }
+        tree.flush().unwrap();
+        tree.root_page_id()
+    };
+
+    // Reopen the file and keep going
+    let store = FilePageStore::open(&path, 512).unwrap();
+    let mut tree = DataTree::from_existing(store, root_page_id);
+    for i in 0..20 {
+        assert_eq!(tree.get(i).unwrap().unwrap(), format!("value{}", i).into_bytes());
+    }
+
+    tree.put(100, b"after reopen").unwrap();
+    tree.delete(3).unwrap();
+    tree.flush().unwrap();
+
+    let store = FilePageStore::open(&path, 512).unwrap();
+    let tree = DataTree::from_existing(store, root_page_id);
+    assert_eq!(tree.get(100).unwrap().unwrap(), b"after reopen");
+    assert!(tree.get(3).unwrap().is_none());
+    assert_eq!(tree.get(4).unwrap().unwrap(), b"value4");
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_unflushed_pages_are_lost_on_reopen() {
+    let path = temp_path("unflushed");
+    let mut store = FilePageStore::create(&path, 256).unwrap();
+    let page_id = store.allocate_page();
+    store.flush().unwrap();
+
+    let mut page = LeafPage::empty(256);
+    page.put(1, b"not flushed");
+    store.put_page_bytes(page_id, &page.serialize()).unwrap();
+    drop(store);
+
+    let store = FilePageStore::open(&path, 256).unwrap();
+    let bytes = store.get_page_bytes(page_id).unwrap();
+    assert!(LeafPage::deserialize(&bytes).get(1).is_none());
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_corrupted_file_is_detected() {
+    let path = temp_path("corrupted");
+    let page_size = 256;
+    let mut store = FilePageStore::create(&path, page_size).unwrap();
+    let page_id = store.allocate_page();
+    let mut page = LeafPage::empty(page_size);
+    page.put(1, b"value1");
+    store.put_page_bytes(page_id, &page.serialize()).unwrap();
+    store.flush().unwrap();
+
+    // Flip a byte inside the page's slot
+    let mut file_bytes = fs::read(&path).unwrap();
+    file_bytes[page_id as usize * page_size + 10] ^= 0xFF;
+    fs::write(&path, &file_bytes).unwrap();
+
+    let result = store.get_page_bytes(page_id);
+    assert!(result.unwrap_err().downcast_ref::<PageCorruptionError>().is_some());
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_freed_pages_stay_free_after_reopen() {
+    let path = temp_path("freed");
+    let mut store = FilePageStore::create(&path, 256).unwrap();
+    let first = store.allocate_page();
+    let second = store.allocate_page();
+    store.free_page(first).unwrap();
+    store.flush().unwrap();
+    assert_eq!(store.get_page_count(), 1);
+    drop(store);
+
+    let store = FilePageStore::open(&path, 256).unwrap();
+    assert!(!store.page_exists(first));
+    assert!(store.page_exists(second));
+    assert_eq!(store.get_page_count(), 1);
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_open_rejects_mismatched_page_size() {
+    let path = temp_path("mismatched");
+    let mut tree = DataTree::new(FilePageStore::create(&path, 256).unwrap());
+    tree.put(1, b"one").unwrap();
+    tree.flush().unwrap();
+    drop(tree);
+
+    // The superblock records the page size the file was written with
+    assert!(DataTree::open(FilePageStore::open(&path, 384).unwrap()).is_err());
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_open_ignores_a_torn_trailing_slot() {
+    let path = temp_path("torn_slot");
+    let mut store = FilePageStore::create(&path, 256).unwrap();
+    let first = store.allocate_page();
+    let second = store.allocate_page();
+    store.flush().unwrap();
+    drop(store);
+
+    // Cut the file part way through the second page's slot
+    let bytes = fs::read(&path).unwrap();
+    fs::write(&path, &bytes[..bytes.len() - 100]).unwrap();
+
+    let mut store = FilePageStore::open(&path, 256).unwrap();
+    assert!(store.page_exists(first));
+    assert!(!store.page_exists(second));
+    assert_eq!(store.get_page_count(), 1);
+
+    // The slot is written in full when the page is allocated again
+    assert_eq!(store.allocate_page(), second);
+    store.flush().unwrap();
+    assert_eq!(fs::metadata(&path).unwrap().len(), 3 * 256);
+
+    fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,229 @@
+use data_tree::DataTree;
+use data_tree::data_tree2::{CatalogEntry, DataTree2};
+use data_tree::file_page_store::FilePageStore;
+use data_tree::formatter::*;
+use data_tree::page_store::InMemoryPageStore;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use serde_json::json;
+use std::error::Error;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+
+const PAGE_SIZE: usize = 1024;
+
+// Build a unique path in the temp directory for a test's backing file
+fn temp_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("data_tree_{}_{}.db", name, std::process::id()));
+    let _ = fs::remove_file(&path);
+    path
+}
+
+fn round_trip(formatter: &dyn Formatter, value: Value) {
+    let bytes = formatter.format(&value).unwrap();
+    assert_eq!(formatter.parse(&bytes).unwrap(), value);
+}
+
+fn compressed(inner: impl Formatter + 'static) -> CompressedFormatter {
+    CompressedFormatter::new(Box::new(inner)).unwrap()
+}
+
+#[test]
+fn test_formatters_round_trip_their_values() {
+    round_trip(&IdentityFormatter, Value::from(b"\x00\xffbytes"));
+    round_trip(&Utf8Formatter, Value::from("grüße"));
+    round_trip(&RecordFormatter, Value::Record(vec![b"id".to_vec(), Vec::new(), vec![9; 300]]));
+    round_trip(&RecordFormatter, Value::Record(Vec::new()));
+    round_trip(&JsonFormatter, Value::from(json!({"name": "pear", "sizes": [1, 2.5, null]})));
+    for width in [1, 2, 4, 8] {
+        let formatter = IntegerFormatter::new(width).unwrap();
+        let max = i64::MAX >> (64 - width * 8);
+        for integer in [0, 1, -1, max, -max - 1] {
+            round_trip(&formatter, Value::Integer(integer));
+        }
+        assert_eq!(formatter.format(&Value::Integer(-1)).unwrap().len(), width);
+    }
+
+    // Each kind of value has its own formatter
+    assert!(IdentityFormatter.format(&Value::from("text")).is_err());
+    assert!(Utf8Formatter.format(&Value::Integer(1)).is_err());
+    assert!(JsonFormatter.format(&Value::from(b"{}")).is_err());
+}
+
+#[test]
+fn test_formatters_reject_bad_input() {
+    let byte = IntegerFormatter::new(1).unwrap();
+    assert!(byte.format(&Value::Integer(128)).is_err());
+    assert!(byte.format(&Value::Integer(-129)).is_err());
+    assert!(byte.parse(&[1, 2]).is_err());
+    assert!(IntegerFormatter::new(3).is_err());
+
+    assert!(Utf8Formatter.parse(&[0xff, 0xfe]).is_err());
+    assert!(JsonFormatter.parse(b"{\"open\": ").is_err());
+
+    // A field length cut short, and a field running past the end
+    assert!(RecordFormatter.parse(&[1, 0]).is_err());
+    assert!(RecordFormatter.parse(&[5, 0, 0, 0, b'a']).is_err());
+
+    assert!(CompressedFormatter::new(Box::new(compressed(Utf8Formatter))).is_err());
+    assert!(compressed(Utf8Formatter).parse(b"not deflated").is_err());
+}
+
+#[test]
+fn test_formatters_are_found_by_id() {
+    let formatters: Vec<Box<dyn Formatter>> = vec![
+        Box::new(IdentityFormatter),
+        Box::new(IntegerFormatter::new(2).unwrap()),
+        Box::new(Utf8Formatter),
+        Box::new(RecordFormatter),
+        Box::new(JsonFormatter),
+        Box::new(compressed(JsonFormatter)),
+        Box::new(compressed(IntegerFormatter::new(8).unwrap())),
+    ];
+    for formatter in &formatters {
+        assert_eq!(formatter_for(formatter.id()).unwrap().id(), formatter.id());
+    }
+    assert!(formatter_for(0x13).is_none());
+    assert!(formatter_for(0x7f).is_none());
+
+    // The compressed formatter writes what the one it wraps would, deflated
+    let value = Value::from("abc".repeat(500));
+    let bytes = compressed(Utf8Formatter).format(&value).unwrap();
+    assert!(bytes.len() < 100);
+    assert_eq!(formatter_for(UTF8_FORMATTER_ID | COMPRESSED_FORMATTER_FLAG).unwrap().parse(&bytes).unwrap(), value);
+}
+
+#[test]
+fn test_compressed_values_inflate_up_to_a_limit() {
+    let formatter = compressed(IdentityFormatter);
+    let largest = Value::Bytes(vec![0u8; MAX_INFLATED_SIZE]);
+    assert_eq!(formatter.parse(&formatter.format(&largest).unwrap()).unwrap(), largest);
+
+    // A value past the limit isn't written, and a small stream that would
+    // inflate past it is turned away
+    assert!(formatter.format(&Value::Bytes(vec![0u8; MAX_INFLATED_SIZE + 1])).is_err());
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&vec![0u8; MAX_INFLATED_SIZE + 1]).unwrap();
+    let bytes = encoder.finish().unwrap();
+    assert!(bytes.len() < 100_000);
+    assert!(formatter.parse(&bytes).is_err());
+}
+
+#[test]
+fn test_compressed_tree_refuses_values_past_the_limit() {
+    let mut db = DataTree2::new(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    db.create_tree_with_formatter("blobs", IDENTITY_FORMATTER_ID | COMPRESSED_FORMATTER_FLAG).unwrap();
+    let mut tree = db.open_tree("blobs").unwrap();
+
+    let largest = Value::Bytes(vec![0u8; MAX_INFLATED_SIZE]);
+    tree.put(1, largest.clone()).unwrap();
+    assert!(tree.put(2, Value::Bytes(vec![0u8; MAX_INFLATED_SIZE + 1])).is_err());
+    assert_eq!(tree.get(1).unwrap(), Some(largest));
+    assert_eq!(tree.get(2).unwrap(), None);
+}
+
+#[test]
+fn test_reopened_store_decodes_each_tree() {
+    let path = temp_path("formatters");
+    let document = |i: i64| json!({"id": i, "tags": ["a", "b"], "text": "x".repeat(200)});
+    {
+        let mut db = DataTree2::new(FilePageStore::create(&path, PAGE_SIZE).unwrap());
+        db.create_tree_with_formatter("counts", IntegerFormatter::new(4).unwrap().id()).unwrap();
+        db.create_tree_with_formatter("names", UTF8_FORMATTER_ID).unwrap();
+        db.create_tree_with_formatter("rows", RecordFormatter.id()).unwrap();
+        db.create_tree_with_formatter("documents", JSON_FORMATTER_ID | COMPRESSED_FORMATTER_FLAG).unwrap();
+
+        for i in 0..300i64 {
+            db.open_tree("counts").unwrap().put(i as u64, -i * 1000).unwrap();
+            db.open_tree("names").unwrap().put(i as u64, format!("name {}", i)).unwrap();
+            db.open_tree("rows").unwrap().put(i as u64, Value::Record(vec![i.to_le_bytes().to_vec(), b"row".to_vec()])).unwrap();
+            db.open_tree("documents").unwrap().put(i as u64, document(i)).unwrap();
+        }
+
+        // Values of the wrong kind are turned away before anything is written
+        let mut names = db.open_tree("names").unwrap();
+        assert!(names.put(1, 5).is_err());
+        assert_eq!(names.get(1).unwrap().unwrap(), Value::from("name 1"));
+        db.flush().unwrap();
+    }
+
+    let mut db = DataTree2::open(FilePageStore::open(&path, PAGE_SIZE).unwrap()).unwrap();
+    let counts = db.open_tree("counts").unwrap();
+    assert_eq!(counts.formatter().id(), INTEGER_FORMATTER_ID + 4);
+    assert_eq!(counts.get(299).unwrap().unwrap(), Value::Integer(-299_000));
+    assert!(counts.iter().map(|(_, value)| value).eq((0..300).map(|i| Value::Integer(-i * 1000))));
+
+    let names = db.open_tree("names").unwrap();
+    assert_eq!(names.range(..3).next_back().unwrap(), (2, Value::from("name 2")));
+
+    let rows = db.open_tree("rows").unwrap();
+    assert_eq!(rows.get(7).unwrap().unwrap(), Value::Record(vec![7i64.to_le_bytes().to_vec(), b"row".to_vec()]));
+
+    let documents = db.open_tree("documents").unwrap();
+    assert_eq!(documents.get(42).unwrap().unwrap(), Value::Json(document(42)));
+    assert_eq!(documents.iter().count(), 300);
+
+    // The catalog records each tree's formatter id
+    let catalog: DataTree<_, Vec<u8>> = DataTree::open_keyed(db.into_store()).unwrap();
+    let entry = CatalogEntry::deserialize(&catalog.get(b"documents").unwrap().unwrap()).unwrap();
+    assert_eq!(entry.formatter_id, JSON_FORMATTER_ID | COMPRESSED_FORMATTER_FLAG);
+    drop(catalog);
+    fs::remove_file(&path).unwrap();
+}
+
+// A formatter of the caller's own, which a reopened store couldn't find
+struct ReversedFormatter;
+
+impl Formatter for ReversedFormatter {
+    fn id(&self) -> u8 {
+        0x7f
+    }
+
+    fn format(&self, value: &Value) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut bytes = IdentityFormatter.format(value)?;
+        bytes.reverse();
+        Ok(bytes)
+    }
+
+    fn parse(&self, bytes: &[u8]) -> Result<Value, Box<dyn Error>> {
+        Ok(Value::Bytes(bytes.iter().rev().copied().collect()))
+    }
+}
+
+#[test]
+fn test_trees_need_a_built_in_formatter() {
+    let mut db = DataTree2::new(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    let page_count = db.get_page_count();
+    assert!(db.create_tree_with_formatter("reversed", ReversedFormatter.id()).is_err());
+    assert_eq!(db.get_page_count(), page_count);
+    assert!(db.list_trees().is_empty());
+}
+
+#[test]
+fn test_values_that_fail_to_parse_end_iteration() {
+    let mut db = DataTree2::new(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    db.create_tree("text").unwrap();
+    let mut tree = db.open_tree("text").unwrap();
+    tree.put(1, b"one").unwrap();
+    tree.put(2, b"\xff\xfe").unwrap();
+    tree.put(3, b"three").unwrap();
+
+    // Point the catalog entry at the UTF-8 formatter behind DataTree2's back
+    let mut catalog: DataTree<_, Vec<u8>> = DataTree::open_keyed(db.into_store()).unwrap();
+    let mut entry = CatalogEntry::deserialize(&catalog.get(b"text").unwrap().unwrap()).unwrap();
+    entry.formatter_id = UTF8_FORMATTER_ID;
+    catalog.put(b"text", &entry.serialize()).unwrap();
+
+    let mut db = DataTree2::open(catalog.into_store()).unwrap();
+    let tree = db.open_tree("text").unwrap();
+    assert_eq!(tree.get(1).unwrap().unwrap(), Value::from("one"));
+    assert!(tree.get(2).is_err());
+
+    let mut entries = tree.iter();
+    assert_eq!(entries.next(), Some((1, Value::from("one"))));
+    assert_eq!(entries.next(), None);
+    assert!(entries.take_error().is_some());
+    assert_eq!(entries.next(), None);
+    assert_eq!(tree.iter().next_back(), Some((3, Value::from("three"))));
+}
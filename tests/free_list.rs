@@ -0,0 +1,178 @@
+use data_tree::DataTree;
+use data_tree::data_tree::PageType;
+use data_tree::file_page_store::FilePageStore;
+use data_tree::leaf_page::LeafPage;
+use data_tree::page_store::{PageStore, InMemoryPageStore};
+use std::fs;
+use std::path::PathBuf;
+
+// Build a unique path in the temp directory for a test's backing file
+fn temp_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("data_tree_{}_{}.db", name, std::process::id()));
+    let _ = fs::remove_file(&path);
+    path
+}
+
+#[test]
+fn test_freed_page_is_written_as_free_page() {
+    let mut store = InMemoryPageStore::with_page_size(256);
+    let page_id = store.allocate_page();
+    store.free_page(page_id).unwrap();
+
+    let bytes = store.get_page_bytes(page_id).unwrap();
+    assert_eq!(PageType::from_u8(bytes[0]), Some(PageType::FREE));
+    assert_eq!(store.free_list_head(), page_id);
+    assert!(!store.page_exists(page_id));
+    assert_eq!(store.get_page_count(), 0);
+}
+
+#[test]
+fn test_allocate_reuses_freed_pages() {
+    let mut store = InMemoryPageStore::with_page_size(256);
+    let first = store.allocate_page();
+    let second = store.allocate_page();
+    let third = store.allocate_page();
+
+    store.free_page(first).unwrap();
+    store.free_page(third).unwrap();
+
+    // The free list is LIFO, so the most recently freed page comes back first
+    assert_eq!(store.allocate_page(), third);
+    assert_eq!(store.allocate_page(), first);
+    assert_eq!(store.free_list_head(), 0);
+
+    // Once the list is empty the id space grows again
+    let fourth = store.allocate_page();
+    assert!(fourth > second && fourth > third);
+
+    // Reused pages come back as empty leaf pages
+    let bytes = store.get_page_bytes(first).unwrap();
+    let page = LeafPage::deserialize(&bytes);
+    assert_eq!(page.page_type(), PageType::LeafPage);
+    assert!(page.metadata().is_empty());
+}
+
+#[test]
+fn test_free_list_is_threaded_through_free_pages() {
+    let mut store = InMemoryPageStore::with_page_size(256);
+    let ids: Vec<u64> = (0..4).map(|_| store.allocate_page()).collect();
+    for id in &ids {
+        store.free_page(*id).unwrap();
+    }
+
+    // Walk the list from the head through the next_page_id links
+    let mut walked = Vec::new();
+    let mut current = store.free_list_head();
+    while current != 0 {
+        walked.push(current);
+        let page = LeafPage::deserialize(&store.get_page_bytes(current).unwrap());
+        assert_eq!(page.page_type(), PageType::FREE);
+        current = page.next_page_id();
+    }
+
+    let mut expected = ids.clone();
+    expected.reverse();
+    assert_eq!(walked, expected);
+}
+
+#[test]
+fn test_unreadable_free_list_head_keeps_the_list() {
+    let mut store = InMemoryPageStore::with_page_size(256);
+    let ids: Vec<u64> = (0..3).map(|_| store.allocate_page()).collect();
+    for id in &ids {
+        store.free_page(*id).unwrap();
+    }
+
+    // A failed read of the head allocates a new page and leaves the list alone
+    store.corrupt_page_for_testing(ids[2]);
+    let fresh = store.allocate_page();
+    assert!(!ids.contains(&fresh));
+    assert_eq!(store.free_list_head(), ids[2]);
+
+    // Once the head reads again, every freed page is reused
+    store.corrupt_page_for_testing(ids[2]);
+    assert_eq!(store.allocate_page(), ids[2]);
+    assert_eq!(store.allocate_page(), ids[1]);
+    assert_eq!(store.allocate_page(), ids[0]);
+    assert_eq!(store.free_list_head(), 0);
+}
+
+#[test]
+fn test_freeing_superblock_is_rejected() {
+    let store = InMemoryPageStore::with_page_size(256);
+    let mut tree = DataTree::new(store);
+    assert!(tree.store_mut().free_page(0).is_err());
+}
+
+#[test]
+fn test_file_does_not_grow_when_pages_are_reused() {
+    let path = temp_path("free_list_reuse");
+    let page_size = 256;
+    let mut store = FilePageStore::create(&path, page_size).unwrap();
+
+    let ids: Vec<u64> = (0..8).map(|_| store.allocate_page()).collect();
+    store.flush().unwrap();
+    let file_len = fs::metadata(&path).unwrap().len();
+
+    for id in &ids {
+        store.free_page(*id).unwrap();
+    }
+    for _ in 0..8 {
+        store.allocate_page();
+    }
+    store.flush().unwrap();
+
+    assert_eq!(fs::metadata(&path).unwrap().len(), file_len);
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_free_list_persists_across_flushes() {
+    let path = temp_path("free_list_persist");
+    let page_size = 256;
+
+    let (freed, head) = {
+        let store = FilePageStore::create(&path, page_size).unwrap();
+        let mut tree = DataTree::new(store);
+        let a = tree.store_mut().allocate_page();
+        let b = tree.store_mut().allocate_page();
+        tree.store_mut().free_page(a).unwrap();
+        tree.store_mut().free_page(b).unwrap();
+        tree.flush().unwrap();
+        (vec![a, b], tree.store().free_list_head())
+    };
+
+    // The superblock carries the head of the list across a reopen
+    let store = FilePageStore::open(&path, page_size).unwrap();
+    let mut tree = DataTree::open(store).unwrap();
+    assert_eq!(tree.superblock().unwrap().free_list_head, head);
+    assert_eq!(tree.store().free_list_head(), head);
+
+    let first = tree.store_mut().allocate_page();
+    let second = tree.store_mut().allocate_page();
+    let mut reused = vec![first, second];
+    reused.sort();
+    assert_eq!(reused, freed);
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_tree_reuses_pages_after_delete() {
+    let store = InMemoryPageStore::with_page_size(128);
+    let mut tree = DataTree::new(store);
+
+    for round in 0..5 {
+        for i in 0..20 {
+            tree.put(i, format!("value{}", round).as_bytes()).unwrap();
+        }
+        for i in 0..20 {
+            tree.delete(i).unwrap();
+        }
+    }
+
+    // Without reuse every round would allocate fresh page ids
+    let next_page_id = tree.store_mut().allocate_page();
+    assert!(next_page_id < 40, "page ids were not reused: {}", next_page_id);
+}
@@ -8,7 +8,7 @@ fn test_free_page_creation() {
     let free_page = LeafPage::new_free_page(page_size);
 
     // Verify it has the correct page type
-    assert_eq!(free_page.page_type(), PageType::LeafPage);
+    assert_eq!(free_page.page_type(), PageType::FREE);
 
     // Verify it has no metadata or data
     assert!(free_page.metadata.is_empty());
@@ -25,7 +25,7 @@ fn test_free_page_serialization() {
     let bytes = free_page.serialize();
 
     // Verify the serialized bytes have the correct page type
-    assert_eq!(bytes[0], PageType::LeafPage.to_u8());
+    assert_eq!(bytes[0], PageType::FREE.to_u8());
 }
 
 #[test]
@@ -41,18 +41,30 @@ fn test_free_page_deserialization() {
     let deserialized_page = LeafPage::deserialize(&bytes);
 
     // Verify it has the correct page type
-    assert_eq!(deserialized_page.page_type(), PageType::LeafPage);
+    assert_eq!(deserialized_page.page_type(), PageType::FREE);
 
     // Verify it has no metadata or data
     assert!(deserialized_page.metadata.is_empty());
     assert!(deserialized_page.data.is_empty());
 }
 
+#[test]
+fn test_free_page_keeps_free_list_link() {
+    // Create a FREE page that points at the next free page
+    let mut free_page = LeafPage::new_free_page(1024);
+    free_page.set_next_page_id(42);
+
+    // The link survives a round trip
+    let deserialized_page = LeafPage::deserialize(&free_page.serialize());
+    assert_eq!(deserialized_page.page_type(), PageType::FREE);
+    assert_eq!(deserialized_page.next_page_id(), 42);
+}
+
 #[test]
 fn test_convert_to_free_page() {
     // Create a regular leaf page
     let page_size = 1024;
-    let mut leaf_page = LeafPage::new_with_size(page_size);
+    let mut leaf_page = LeafPage::empty(page_size);
 
     // Add some data to it
     leaf_page.put(1, b"value1");
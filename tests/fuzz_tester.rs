@@ -1,5 +1,7 @@
 use data_tree::DataTree;
 use data_tree::branch_page::BranchPage;
+use data_tree::leaf_page::LeafPage;
+use data_tree::overflow_page::{OverflowPage, OverflowPointer};
 use data_tree::page_store::{PageStore, InMemoryPageStore};
 use std::collections::{HashMap, HashSet};
 use std::error::Error;
@@ -178,9 +180,7 @@ impl<S: PageStore + 'static> FuzzTest<S> {
             let op = self.random_operation();
             self.operations.push(op.clone());
 
-            if let Err(e) = self.execute_operation(&op) {
-                return Err(e);
-            }
+            self.execute_operation(&op)?;
 
             operation_count += 1;
 
@@ -235,7 +235,7 @@ impl<S: PageStore + 'static> FuzzTest<S> {
         writeln!(file, "# Page size: {}", self.tree.store().page_size())?;
         writeln!(file, "# Number of operations: {}", self.operations.len())?;
         writeln!(file, "# Timestamp: {}", SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs())?;
-        writeln!(file, "")?;
+        writeln!(file)?;
 
         for (i, op) in self.operations.iter().enumerate() {
             match op {
@@ -492,8 +492,8 @@ fn test_fuzz_data_tree_with_custom_store() {
 
 // Thread local storage for the current fuzz test
 thread_local! {
-    static CURRENT_FUZZ_TEST: std::cell::RefCell<Option<FuzzTest<InMemoryPageStore>>> = std::cell::RefCell::new(None);
-    static CURRENT_CUSTOM_FUZZ_TEST: std::cell::RefCell<Option<FuzzTest<CustomPageStore>>> = std::cell::RefCell::new(None);
+    static CURRENT_FUZZ_TEST: std::cell::RefCell<Option<FuzzTest<InMemoryPageStore>>> = const { std::cell::RefCell::new(None) };
+    static CURRENT_CUSTOM_FUZZ_TEST: std::cell::RefCell<Option<FuzzTest<CustomPageStore>>> = const { std::cell::RefCell::new(None) };
 }
 
 // Parse a duration string like "30s", "20m", or "100ms"
@@ -535,6 +535,40 @@ fn parse_duration(duration_str: &str) -> Result<Duration, String> {
     }
 }
 
+// Collect every page reachable from the root: all branch levels, the leaves
+// and the overflow pages their large values are kept in
+fn collect_tree_page_ids<S: PageStore>(store: &S, root_page_id: u64) -> Result<Vec<u64>, String> {
+    let mut page_ids = Vec::new();
+    let mut pending = vec![root_page_id];
+
+    while let Some(page_id) = pending.pop() {
+        let bytes = store.get_page_bytes(page_id)
+            .map_err(|e| format!("Failed to get page bytes: {}", e))?;
+        page_ids.push(page_id);
+
+        if !bytes.is_empty() && bytes[0] == 2 { // 2 is PageType::BranchPage
+            let branch_page = BranchPage::deserialize(&bytes);
+            for entry in branch_page.entries() {
+                pending.push(entry.page_id);
+            }
+        } else if !bytes.is_empty() && bytes[0] == 1 { // 1 is PageType::LeafPage
+            // Large values live in overflow pages outside the tree structure
+            let leaf_page = LeafPage::deserialize(&bytes);
+            for item in leaf_page.sorted_items().into_iter().filter(|item| item.overflow) {
+                let pointer = OverflowPointer::deserialize(&item.bytes).map_err(|e| e.to_string())?;
+                pending.push(pointer.first_page_id);
+            }
+        } else if !bytes.is_empty() && bytes[0] == 5 { // 5 is PageType::OverflowPage
+            let overflow_page = OverflowPage::deserialize(&bytes).map_err(|e| e.to_string())?;
+            if overflow_page.next_page_id != 0 {
+                pending.push(overflow_page.next_page_id);
+            }
+        }
+    }
+
+    Ok(page_ids)
+}
+
 // Implement reload_tree_from_serialized_pages for FuzzTest with InMemoryPageStore
 impl FuzzTest<InMemoryPageStore> {
     fn reload_tree_from_serialized_pages(&mut self) -> Result<(), String> {
@@ -550,33 +584,7 @@ impl FuzzTest<InMemoryPageStore> {
         let mut new_store = InMemoryPageStore::with_page_size(page_size);
 
         // Get all page IDs from the original store
-        let mut page_ids = Vec::new();
-        let current_id = root_page_id;
-        page_ids.push(current_id);
-
-        // Collect all page IDs by traversing the tree
-        // First, get all pages linked from the root page
-        if let Ok(root_bytes) = store.get_page_bytes(root_page_id) {
-            // Check if it's a branch page
-            if root_bytes.len() > 0 && root_bytes[0] == 2 { // 2 is PageType::BranchPage
-                // It's a branch page, get all leaf pages it points to
-                let branch_page = BranchPage::deserialize(&root_bytes);
-                for entry in branch_page.entries() {
-                    page_ids.push(entry.page_id);
-
-                    // For each leaf page, follow its chain
-                    let mut leaf_id = entry.page_id;
-                    while let Some(next_id) = store.get_next_page_id(leaf_id) {
-                        if !page_ids.contains(&next_id) {
-                            page_ids.push(next_id);
-                        }
-                        leaf_id = next_id;
-                    }
-                }
-            }
-        } else {
-            return Err("Failed to get root page bytes".to_string());
-        }
+        let page_ids = collect_tree_page_ids(store, root_page_id)?;
 
         // Copy all pages to the new store
         for page_id in &page_ids {
@@ -640,33 +648,7 @@ impl FuzzTest<CustomPageStore> {
         let mut new_store = CustomPageStore::new(page_size);
 
         // Get all page IDs from the original store
-        let mut page_ids = Vec::new();
-        let current_id = root_page_id;
-        page_ids.push(current_id);
-
-        // Collect all page IDs by traversing the tree
-        // First, get all pages linked from the root page
-        if let Ok(root_bytes) = store.get_page_bytes(root_page_id) {
-            // Check if it's a branch page
-            if root_bytes.len() > 0 && root_bytes[0] == 2 { // 2 is PageType::BranchPage
-                // It's a branch page, get all leaf pages it points to
-                let branch_page = BranchPage::deserialize(&root_bytes);
-                for entry in branch_page.entries() {
-                    page_ids.push(entry.page_id);
-
-                    // For each leaf page, follow its chain
-                    let mut leaf_id = entry.page_id;
-                    while let Some(next_id) = store.get_next_page_id(leaf_id) {
-                        if !page_ids.contains(&next_id) {
-                            page_ids.push(next_id);
-                        }
-                        leaf_id = next_id;
-                    }
-                }
-            }
-        } else {
-            return Err("Failed to get root page bytes".to_string());
-        }
+        let page_ids = collect_tree_page_ids(store, root_page_id)?;
 
         // Copy all pages to the new store
         for page_id in &page_ids {
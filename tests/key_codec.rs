@@ -0,0 +1,178 @@
+use data_tree::{DataTree, KeyCodec};
+use data_tree::file_page_store::FilePageStore;
+use data_tree::page_store::InMemoryPageStore;
+use rand::prelude::*;
+use std::fs;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+const PAGE_SIZE: usize = 256;
+
+// Build a unique path in the temp directory for a test's backing file
+fn temp_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("data_tree_{}_{}.db", name, std::process::id()));
+    let _ = fs::remove_file(&path);
+    path
+}
+
+// Every pair of keys must compare the same way as their encodings
+fn assert_order_preserved<K: KeyCodec + PartialOrd + std::fmt::Debug>(keys: &[K]) {
+    for a in keys {
+        for b in keys {
+            let encoded = K::encode(a.to_ref()).cmp(&K::encode(b.to_ref()));
+            assert_eq!(a.partial_cmp(b), Some(encoded), "{:?} vs {:?}", a, b);
+        }
+    }
+}
+
+#[test]
+fn test_encodings_preserve_order_and_round_trip() {
+    assert_order_preserved(&[0u32, 1, 255, 256, u32::MAX]);
+    assert_order_preserved(&[i64::MIN, -256, -1, 0, 1, 255, i64::MAX]);
+    assert_order_preserved(&[f64::NEG_INFINITY, -1e300, -1.5, -0.0001, 0.0, 0.0001, 1.5, 1e300, f64::INFINITY]);
+    assert_order_preserved(&[0u128, 1, 1 << 64, (1 << 64) + 1, u128::MAX]);
+    assert_order_preserved(&["".to_string(), "a".to_string(), "a\0".to_string(), "ab".to_string(), "é".to_string()]);
+
+    // Tuples compare component by component, even when an earlier
+    // component is a prefix of another or holds zero bytes
+    assert_order_preserved(&[
+        ("a".to_string(), 5i64),
+        ("a".to_string(), 6i64),
+        ("a\0".to_string(), -1i64),
+        ("ab".to_string(), -7i64),
+        ("b".to_string(), i64::MIN),
+    ]);
+    assert_order_preserved(&[(1u32, vec![0u8], 0u64), (1, vec![0, 0], 0), (1, vec![0, 1], 0), (2, vec![], 0)]);
+
+    // -0.0 sorts just below 0.0
+    assert!(f64::encode(-0.0) < f64::encode(0.0));
+
+    for key in [i64::MIN, -1, 0, i64::MAX] {
+        assert_eq!(i64::decode(&i64::encode(key)), key);
+    }
+    for key in [f64::NEG_INFINITY, -2.5, -0.0, 0.0, 3.25, f64::MAX] {
+        assert_eq!(f64::decode(&f64::encode(key)).to_bits(), key.to_bits());
+    }
+    let key = ("x\0y".to_string(), u128::MAX, vec![0u8, 0xFF, 0]);
+    let encoded = <(String, u128, Vec<u8>)>::encode((&key.0, key.1, &key.2));
+    assert_eq!(<(String, u128, Vec<u8>)>::decode(&encoded), key);
+}
+
+#[test]
+fn test_signed_keys_sort_in_tree() {
+    let mut tree: DataTree<_, i64> = DataTree::new_keyed(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    let mut keys: Vec<i64> = (-500..500).map(|i| i * 7919).collect();
+    keys.shuffle(&mut StdRng::seed_from_u64(13));
+    for key in &keys {
+        tree.put(*key, &key.to_le_bytes()).unwrap();
+    }
+
+    keys.sort();
+    assert!(tree.keys().eq(keys.iter().copied()));
+    assert_eq!(tree.get(-7919).unwrap().unwrap(), (-7919i64).to_le_bytes());
+
+    let in_range: Vec<i64> = tree.range(-3 * 7919..=2 * 7919).map(|(key, _)| key).collect();
+    assert_eq!(in_range, vec![-3 * 7919, -2 * 7919, -7919, 0, 7919, 2 * 7919]);
+
+    assert!(tree.delete(0).unwrap());
+    let mut cursor = tree.cursor();
+    assert!(cursor.seek(-1).unwrap());
+    assert_eq!(cursor.key(), Some(7919));
+    assert!(cursor.seek_for_prev(-1).unwrap());
+    assert_eq!(cursor.key(), Some(-7919));
+}
+
+#[test]
+fn test_float_keys_sort_in_tree() {
+    let mut tree: DataTree<_, f64> = DataTree::new_keyed(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    for i in -300..300 {
+        let key = i as f64 / 4.0;
+        tree.put(key, format!("{}", key).as_bytes()).unwrap();
+    }
+
+    let keys: Vec<f64> = tree.keys().collect();
+    assert_eq!(keys.len(), 600);
+    assert!(keys.windows(2).all(|pair| pair[0] < pair[1]));
+    assert_eq!(tree.get(-0.25).unwrap().unwrap(), b"-0.25");
+
+    let below_zero: Vec<f64> = tree.range(-1.0..0.0).map(|(key, _)| key).collect();
+    assert_eq!(below_zero, vec![-1.0, -0.75, -0.5, -0.25]);
+}
+
+#[test]
+fn test_string_and_u128_keys() {
+    let mut tree: DataTree<_, String> = DataTree::new_keyed(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    for word in ["pear", "apple", "b", "banana", "a"] {
+        tree.put(word, word.to_uppercase().as_bytes()).unwrap();
+    }
+    assert_eq!(tree.keys().collect::<Vec<_>>(), vec!["a", "apple", "b", "banana", "pear"]);
+    assert_eq!(tree.get("banana").unwrap().unwrap(), b"BANANA");
+    let from_b: Vec<String> = tree.range("b"..).map(|(key, _)| key).collect();
+    assert_eq!(from_b, vec!["b", "banana", "pear"]);
+
+    let mut tree: DataTree<_, u128> = DataTree::new_keyed(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    for i in (0..1000u128).rev() {
+        tree.put(i << 70, b"value").unwrap();
+    }
+    assert!(tree.keys().eq((0..1000u128).map(|i| i << 70)));
+    assert!(tree.get(1 << 70).unwrap().is_some());
+    assert!(tree.get(1).unwrap().is_none());
+}
+
+#[test]
+fn test_uuid_and_tuple_keys() {
+    let mut rng = StdRng::seed_from_u64(14);
+    let mut ids: Vec<Uuid> = (0..300).map(|_| Uuid::from_u128(rng.gen())).collect();
+    let mut tree: DataTree<_, Uuid> = DataTree::new_keyed(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    for id in &ids {
+        tree.put(*id, id.as_bytes()).unwrap();
+    }
+    ids.sort();
+    assert!(tree.keys().eq(ids.iter().copied()));
+    assert_eq!(tree.get(ids[42]).unwrap().unwrap(), ids[42].as_bytes());
+
+    // (user, timestamp) keys: all of one user's entries are contiguous and
+    // ordered by time, including negative times
+    let mut tree: DataTree<_, (String, i64)> = DataTree::new_keyed(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    for user in ["bob", "al", "alice"] {
+        for time in [10, -5, 0, 3] {
+            tree.put((user, time), format!("{}@{}", user, time).as_bytes()).unwrap();
+        }
+    }
+    let alice: Vec<(String, i64)> = tree.range(("alice", i64::MIN)..=("alice", i64::MAX)).map(|(key, _)| key).collect();
+    let times: Vec<i64> = alice.iter().map(|(_, time)| *time).collect();
+    assert!(alice.iter().all(|(user, _)| user == "alice"));
+    assert_eq!(times, vec![-5, 0, 3, 10]);
+    assert_eq!(tree.keys().next().unwrap(), ("al".to_string(), -5));
+    assert_eq!(tree.get(("bob", 3)).unwrap().unwrap(), b"bob@3");
+}
+
+#[test]
+fn test_codec_tree_bulk_loads_and_reopens() {
+    let path = temp_path("key_codec");
+    {
+        let store = FilePageStore::create(&path, PAGE_SIZE).unwrap();
+        let entries = (-1000..1000i64).map(|i| (i, i.to_string().into_bytes()));
+        let mut tree: DataTree<_, i64> = DataTree::bulk_load_keyed(store, Default::default(), entries).unwrap();
+        tree.flush().unwrap();
+    }
+
+    let store = FilePageStore::open(&path, PAGE_SIZE).unwrap();
+    let tree: DataTree<_, i64> = DataTree::open_keyed(store).unwrap();
+    assert!(tree.keys().eq(-1000..1000));
+    assert_eq!(tree.get(-999).unwrap().unwrap(), b"-999");
+
+    fs::remove_file(&path).unwrap();
+}
+
+type U64Tree = DataTree<InMemoryPageStore>;
+
+#[test]
+fn test_u64_byte_helpers_stay_little_endian() {
+    // Values written through the helpers before typed keys must decode the same
+    assert_eq!(U64Tree::u64_to_bytes(0x0102), [2, 1, 0, 0, 0, 0, 0, 0]);
+    assert_eq!(U64Tree::bytes_to_u64(b"ab"), 0x6261);
+    for key in [0u64, 1, 255, 256, 1 << 32, u64::MAX] {
+        assert_eq!(U64Tree::bytes_to_u64(&U64Tree::u64_to_bytes(key)), key);
+    }
+}
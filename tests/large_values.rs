@@ -92,9 +92,9 @@ fn test_mixed_size_values() {
 
     // Insert a mix of small and large values
     tree.put(1501, b"value1").unwrap();
-    tree.put(1502, &vec![1u8; 20]).unwrap();
+    tree.put(1502, &[1u8; 20]).unwrap();
     tree.put(1503, b"value2").unwrap();
-    tree.put(1504, &vec![2u8; 20]).unwrap();
+    tree.put(1504, &[2u8; 20]).unwrap();
 
     // Verify all values can be retrieved
     assert_eq!(tree.get(1501).unwrap().unwrap(), b"value1");
@@ -0,0 +1,150 @@
+use data_tree::DataTree;
+use data_tree::branch_page::BranchPage;
+use data_tree::leaf_page::LeafPage;
+use data_tree::page_store::{PageStore, InMemoryPageStore, CRC_SIZE};
+
+const PAGE_SIZE: usize = 1024;
+
+fn root_branch(tree: &DataTree<InMemoryPageStore>) -> BranchPage {
+    BranchPage::deserialize(&tree.store().get_page_bytes(tree.root_page_id()).unwrap())
+}
+
+fn leaf(tree: &DataTree<InMemoryPageStore>, page_id: u64) -> LeafPage {
+    LeafPage::deserialize(&tree.store().get_page_bytes(page_id).unwrap())
+}
+
+#[test]
+fn test_full_leaf_is_split_into_the_branch() {
+    let store = InMemoryPageStore::with_page_size(PAGE_SIZE);
+    let mut tree = DataTree::new(store);
+
+    for i in 0..100 {
+        tree.put(i, format!("value{}", i).as_bytes()).unwrap();
+    }
+
+    // Every leaf is registered in the root and there are no overflow chains
+    let branch = root_branch(&tree);
+    assert!(branch.entries().len() > 1);
+    for (i, entry) in branch.entries().iter().enumerate() {
+        let page = leaf(&tree, entry.page_id);
+        let next = branch.entries().get(i + 1).map_or(0, |next| next.page_id);
+        assert_eq!(page.next_page_id(), next);
+
+        // Each leaf only holds keys routed to it
+        for meta in page.metadata() {
+            assert_eq!(branch.find_page_id(meta.key), Some(entry.page_id));
+        }
+    }
+
+    for i in 0..100 {
+        assert_eq!(tree.get(i).unwrap().unwrap(), format!("value{}", i).into_bytes());
+    }
+}
+
+#[test]
+fn test_split_with_reverse_and_interleaved_keys() {
+    let store = InMemoryPageStore::with_page_size(PAGE_SIZE);
+    let mut tree = DataTree::new(store);
+
+    for i in (0..60).rev() {
+        tree.put(i * 2, format!("even{}", i).as_bytes()).unwrap();
+    }
+    for i in 0..60 {
+        tree.put(i * 2 + 1, format!("odd{}", i).as_bytes()).unwrap();
+    }
+
+    for i in 0..60 {
+        assert_eq!(tree.get(i * 2).unwrap().unwrap(), format!("even{}", i).into_bytes());
+        assert_eq!(tree.get(i * 2 + 1).unwrap().unwrap(), format!("odd{}", i).into_bytes());
+    }
+    assert!(tree.get(500).unwrap().is_none());
+}
+
+#[test]
+fn test_value_filling_a_page_gets_its_own_leaf() {
+    let store = InMemoryPageStore::with_page_size(PAGE_SIZE);
+    let mut tree = DataTree::new(store);
+    let max_value_size = LeafPage::empty(PAGE_SIZE - CRC_SIZE).max_value_size();
+
+    tree.put(10, &vec![b'a'; max_value_size]).unwrap();
+    tree.put(5, &vec![b'b'; max_value_size]).unwrap();
+    tree.put(20, &vec![b'c'; max_value_size]).unwrap();
+
+    assert_eq!(root_branch(&tree).entries().len(), 3);
+    assert_eq!(tree.get(5).unwrap().unwrap(), vec![b'b'; max_value_size]);
+    assert_eq!(tree.get(10).unwrap().unwrap(), vec![b'a'; max_value_size]);
+    assert_eq!(tree.get(20).unwrap().unwrap(), vec![b'c'; max_value_size]);
+}
+
+#[test]
+fn test_delete_removes_empty_leaves_from_the_branch() {
+    let store = InMemoryPageStore::with_page_size(PAGE_SIZE);
+    let mut tree = DataTree::new(store);
+
+    for i in 0..100 {
+        tree.put(i, format!("value{}", i).as_bytes()).unwrap();
+    }
+    let leaf_count = root_branch(&tree).entries().len();
+
+    for i in 0..90 {
+        assert!(tree.delete(i).unwrap());
+    }
+    assert!(!tree.delete(0).unwrap());
+
+    assert!(root_branch(&tree).entries().len() < leaf_count);
+    for i in 90..100 {
+        assert_eq!(tree.get(i).unwrap().unwrap(), format!("value{}", i).into_bytes());
+    }
+
+    // The root always keeps one leaf, even when the tree is empty
+    for i in 90..100 {
+        assert!(tree.delete(i).unwrap());
+    }
+    assert_eq!(root_branch(&tree).entries().len(), 1);
+    tree.put(7, b"again").unwrap();
+    assert_eq!(tree.get(7).unwrap().unwrap(), b"again");
+}
+
+#[test]
+fn test_migrate_overflow_chains() {
+    // Build a store the way older versions left it: one leaf registered in the
+    // root with a chain of unregistered overflow pages behind it
+    let mut store = InMemoryPageStore::with_page_size(PAGE_SIZE);
+    let usable_size = PAGE_SIZE - CRC_SIZE;
+    let page_ids: Vec<u64> = (0..4).map(|_| store.allocate_page()).collect();
+    let root_page_id = page_ids[0];
+    let chain = &page_ids[1..];
+
+    for (i, page_id) in chain.iter().enumerate() {
+        let mut page = LeafPage::empty(usable_size);
+        for j in 0..20 {
+            // Keys are spread across the whole chain, out of order
+            let key = (j * 3 + i) as u64;
+            page.put(key, format!("chain{}", key).as_bytes());
+        }
+        page.set_prev_page_id(if i > 0 { chain[i - 1] } else { 0 });
+        page.set_next_page_id(chain.get(i + 1).copied().unwrap_or(0));
+        store.put_page_bytes(*page_id, &page.serialize()).unwrap();
+    }
+
+    let mut branch = BranchPage::new_empty(usable_size);
+    branch.insert(chain[0], 0);
+    store.put_page_bytes(root_page_id, &branch.serialize()).unwrap();
+
+    let mut tree = DataTree::from_existing(store, root_page_id);
+    assert_eq!(tree.migrate_overflow_chains().unwrap(), 2);
+
+    // Every key is reachable through the branch alone
+    for key in 0..60 {
+        assert_eq!(tree.get(key).unwrap(), Some(format!("chain{}", key).into_bytes()), "key {}", key);
+    }
+    let branch = root_branch(&tree);
+    for entry in branch.entries() {
+        for meta in leaf(&tree, entry.page_id).metadata() {
+            assert_eq!(branch.find_page_id(meta.key), Some(entry.page_id));
+        }
+    }
+
+    // Running it again finds nothing left to do
+    assert_eq!(tree.migrate_overflow_chains().unwrap(), 0);
+}
@@ -0,0 +1,159 @@
+use data_tree::DataTree;
+use data_tree::branch_page::BranchPage;
+use data_tree::data_tree::{DataTreeConfig, PageType};
+use data_tree::leaf_page::LeafPage;
+use data_tree::page_store::{PageStore, InMemoryPageStore, CRC_SIZE};
+use rand::prelude::*;
+use std::collections::BTreeMap;
+
+const PAGE_SIZE: usize = 256;
+
+// Number of branch levels above the leaves
+fn height(tree: &DataTree<InMemoryPageStore>) -> usize {
+    let mut height = 0;
+    let mut page_id = tree.root_page_id();
+    loop {
+        let bytes = tree.store().get_page_bytes(page_id).unwrap();
+        if PageType::from_u8(bytes[0]) != Some(PageType::BranchPage) {
+            return height;
+        }
+        height += 1;
+        page_id = BranchPage::deserialize(&bytes).entries()[0].page_id;
+    }
+}
+
+// Every leaf from left to right, following the sibling links
+fn leaves(tree: &DataTree<InMemoryPageStore>) -> Vec<LeafPage> {
+    let mut page_id = tree.root_page_id();
+    loop {
+        let bytes = tree.store().get_page_bytes(page_id).unwrap();
+        if PageType::from_u8(bytes[0]) != Some(PageType::BranchPage) {
+            break;
+        }
+        page_id = BranchPage::deserialize(&bytes).entries()[0].page_id;
+    }
+
+    let mut leaves = Vec::new();
+    let mut prev_page_id = 0;
+    while page_id != 0 {
+        let leaf = LeafPage::deserialize(&tree.store().get_page_bytes(page_id).unwrap());
+        assert_eq!(leaf.prev_page_id(), prev_page_id);
+        prev_page_id = page_id;
+        page_id = leaf.next_page_id();
+        leaves.push(leaf);
+    }
+    leaves
+}
+
+fn fill(tree: &mut DataTree<InMemoryPageStore>, count: u64) {
+    for i in 0..count {
+        tree.put(i, format!("value{}", i).as_bytes()).unwrap();
+    }
+}
+
+#[test]
+fn test_underfull_leaves_are_rebalanced() {
+    let mut tree = DataTree::new(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    fill(&mut tree, 1000);
+
+    // Delete four keys out of every five, spread over every leaf
+    for i in 0..1000 {
+        if i % 5 != 0 {
+            assert!(tree.delete(i).unwrap());
+        }
+    }
+
+    let min_fill_bytes = ((PAGE_SIZE - CRC_SIZE) as f64 * tree.config().min_fill) as usize;
+    let leaves = leaves(&tree);
+    assert!(leaves.len() > 1);
+    for leaf in &leaves {
+        assert!(leaf.used_space() >= min_fill_bytes, "leaf uses {} bytes", leaf.used_space());
+    }
+
+    let keys: Vec<u64> = leaves.iter()
+        .flat_map(|leaf| leaf.metadata().iter().map(|meta| meta.key).collect::<Vec<_>>())
+        .collect();
+    assert_eq!(keys.len(), 200);
+    for i in (0..1000).step_by(5) {
+        assert_eq!(tree.get(i).unwrap().unwrap(), format!("value{}", i).into_bytes());
+    }
+}
+
+#[test]
+fn test_higher_min_fill_uses_fewer_pages() {
+    let mut page_counts = Vec::new();
+    for min_fill in [0.0, 0.5] {
+        let mut tree = DataTree::new(InMemoryPageStore::with_page_size(PAGE_SIZE));
+        tree.set_config(DataTreeConfig { min_fill, ..Default::default() }).unwrap();
+        fill(&mut tree, 1000);
+        for i in 0..1000 {
+            if i % 10 != 0 {
+                tree.delete(i).unwrap();
+            }
+        }
+        page_counts.push(tree.store().get_page_count());
+    }
+
+    assert!(page_counts[1] < page_counts[0], "page counts {:?}", page_counts);
+}
+
+#[test]
+fn test_tree_shrinks_when_keys_are_deleted() {
+    let mut tree = DataTree::new(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    fill(&mut tree, 2000);
+    let full_height = height(&tree);
+    assert!(full_height >= 3);
+
+    for i in 0..1990 {
+        tree.delete(i).unwrap();
+    }
+
+    assert!(height(&tree) < full_height);
+    assert_eq!(tree.superblock().unwrap().root_page_id, tree.root_page_id());
+    for i in 1990..2000 {
+        assert_eq!(tree.get(i).unwrap().unwrap(), format!("value{}", i).into_bytes());
+    }
+
+    // Emptying the tree leaves a root with a single leaf
+    for i in 1990..2000 {
+        tree.delete(i).unwrap();
+    }
+    assert_eq!(height(&tree), 1);
+    assert_eq!(leaves(&tree).len(), 1);
+    assert_eq!(tree.store().get_page_count(), 2);
+}
+
+#[test]
+fn test_random_deletes_against_a_map() {
+    let mut tree = DataTree::new(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    tree.set_config(DataTreeConfig { min_fill: 0.5, ..Default::default() }).unwrap();
+    let mut expected = BTreeMap::new();
+    let mut rng = StdRng::seed_from_u64(7);
+
+    for round in 0..4 {
+        for _ in 0..1500 {
+            let key = rng.gen_range(0..2000u64);
+            let value = vec![round as u8; rng.gen_range(1..40)];
+            tree.put(key, &value).unwrap();
+            expected.insert(key, value);
+        }
+        for _ in 0..1500 {
+            let key = rng.gen_range(0..2000u64);
+            assert_eq!(tree.delete(key).unwrap(), expected.remove(&key).is_some());
+        }
+
+        for key in 0..2000 {
+            assert_eq!(tree.get(key).unwrap(), expected.get(&key).cloned(), "key {}", key);
+        }
+        let leaf_key_count: usize = leaves(&tree).iter().map(|leaf| leaf.metadata().len()).sum();
+        assert_eq!(leaf_key_count, expected.len());
+    }
+}
+
+#[test]
+fn test_min_fill_must_be_at_most_half() {
+    let mut tree = DataTree::new(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    assert!(tree.set_config(DataTreeConfig { min_fill: 0.75, ..Default::default() }).is_err());
+    assert!(tree.set_config(DataTreeConfig { min_fill: -0.1, ..Default::default() }).is_err());
+    assert_eq!(tree.config(), DataTreeConfig::default());
+}
@@ -0,0 +1,98 @@
+use data_tree::DataTree;
+use data_tree::data_tree::DataTreeConfig;
+use data_tree::leaf::LeafEncoding;
+use data_tree::page_store::{PageStore, InMemoryPageStore};
+use rand::prelude::*;
+use std::collections::BTreeMap;
+
+const SEEDS: u64 = 60;
+const OPERATIONS: usize = 300;
+const KEY_SPACE: u64 = 300;
+
+// A value that is usually short and often repeated, so neighbouring keys
+// form runs, but is sometimes empty or too large for a leaf
+fn random_value(rng: &mut StdRng, page_size: usize) -> Vec<u8> {
+    match rng.gen_range(0..10) {
+        0 => Vec::new(),
+        1 => vec![rng.gen(); rng.gen_range(page_size / 2..page_size * 3)],
+        _ => vec![rng.gen_range(0..3); rng.gen_range(0..20)],
+    }
+}
+
+// Apply random puts and deletes to a tree and to a BTreeMap, checking that
+// the tree agrees with the map after every operation. With `ranges`, some
+// operations put or delete a range of keys instead.
+fn check_against_model(encoding: LeafEncoding, page_size: usize, seed: u64, ranges: bool) {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut tree = DataTree::new(InMemoryPageStore::with_page_size(page_size));
+    tree.set_config(DataTreeConfig { leaf_encoding: encoding, ..DataTreeConfig::default() }).unwrap();
+    let base_page_count = tree.store().get_page_count();
+    let mut model = BTreeMap::new();
+    let context = format!("{:?} tree, {} byte pages, seed {}", encoding, page_size, seed);
+
+    for step in 0..OPERATIONS {
+        let key = rng.gen_range(0..KEY_SPACE);
+        if ranges && rng.gen_bool(0.2) {
+            let end = key + rng.gen_range(0..40);
+            if rng.gen_bool(0.5) {
+                let value = vec![rng.gen_range(0..3); rng.gen_range(0..16)];
+                tree.put_range(key..=end, &value).unwrap();
+                for key in key..=end {
+                    model.insert(key, value.clone());
+                }
+            } else {
+                let keys: Vec<u64> = model.range(key..=end).map(|(key, _)| *key).collect();
+                assert_eq!(tree.delete_range(key..=end).unwrap(), keys.len() as u64, "{}, step {}", context, step);
+                for key in keys {
+                    model.remove(&key);
+                }
+            }
+        } else if rng.gen_bool(0.6) {
+            let value = random_value(&mut rng, page_size);
+            tree.put(key, &value).unwrap();
+            model.insert(key, value);
+        } else {
+            assert_eq!(tree.delete(key).unwrap(), model.remove(&key).is_some(), "{}, step {}", context, step);
+        }
+        assert_eq!(tree.get(key).unwrap(), model.get(&key).cloned(), "{}, step {}", context, step);
+
+        if step % 50 == 49 {
+            assert!(tree.iter().eq(model.clone()), "{}, step {}", context, step);
+        }
+    }
+    assert!(tree.iter().eq(model), "{}", context);
+
+    // Every overflow chain is freed with the values that used it
+    tree.delete_range(..).unwrap();
+    assert_eq!(tree.store().get_page_count(), base_page_count, "{}", context);
+}
+
+fn check_encoding(encoding: LeafEncoding, ranges: bool) {
+    for page_size in [128, 512, 4096] {
+        for seed in 0..SEEDS {
+            check_against_model(encoding, page_size, seed, ranges);
+        }
+    }
+}
+
+#[test]
+fn test_run_length_tree_matches_model() {
+    check_encoding(LeafEncoding::RunLength, false);
+}
+
+#[test]
+fn test_adaptive_tree_matches_model() {
+    check_encoding(LeafEncoding::Adaptive, false);
+}
+
+#[test]
+fn test_range_updates_with_large_values_match_model() {
+    for encoding in [LeafEncoding::Plain, LeafEncoding::RunLength, LeafEncoding::Adaptive, LeafEncoding::Slotted] {
+        check_encoding(encoding, true);
+    }
+}
+
+#[test]
+fn test_slotted_tree_matches_model() {
+    check_encoding(LeafEncoding::Slotted, false);
+}
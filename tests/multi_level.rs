@@ -0,0 +1,130 @@
+use data_tree::DataTree;
+use data_tree::branch_page::BranchPage;
+use data_tree::data_tree::PageType;
+use data_tree::file_page_store::FilePageStore;
+use data_tree::page_store::{PageStore, InMemoryPageStore};
+use rand::prelude::*;
+use std::fs;
+use std::path::PathBuf;
+
+// Small pages hold only a few branch entries, so the tree grows quickly
+const PAGE_SIZE: usize = 128;
+
+// Build a unique path in the temp directory for a test's backing file
+fn temp_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("data_tree_{}_{}.db", name, std::process::id()));
+    let _ = fs::remove_file(&path);
+    path
+}
+
+// Number of branch levels above the leaves
+fn height<S: PageStore>(tree: &DataTree<S>) -> usize {
+    let mut height = 0;
+    let mut page_id = tree.root_page_id();
+    loop {
+        let bytes = tree.store().get_page_bytes(page_id).unwrap();
+        if PageType::from_u8(bytes[0]) != Some(PageType::BranchPage) {
+            return height;
+        }
+        height += 1;
+        page_id = BranchPage::deserialize(&bytes).entries()[0].page_id;
+    }
+}
+
+#[test]
+fn test_branch_insert_respects_page_size() {
+    // Header plus two 16 byte entries
+    let mut branch = BranchPage::new_empty(25 + 2 * 16);
+    assert!(branch.insert(1, 10));
+    assert!(branch.insert(2, 20));
+    assert!(!branch.has_room_for_entry());
+    assert!(!branch.insert(3, 30));
+    assert_eq!(branch.entries().len(), 2);
+    assert!(branch.serialize().len() <= 25 + 2 * 16);
+}
+
+#[test]
+fn test_branch_split_moves_upper_half() {
+    let mut branch = BranchPage::new_empty(1024);
+    for i in 0..5 {
+        branch.insert(i + 1, i * 10);
+    }
+
+    let right = branch.split().unwrap();
+    let left_keys: Vec<u64> = branch.entries().iter().map(|e| e.first_key).collect();
+    let right_keys: Vec<u64> = right.entries().iter().map(|e| e.first_key).collect();
+    assert_eq!(left_keys, vec![0, 10]);
+    assert_eq!(right_keys, vec![20, 30, 40]);
+
+    let mut single = BranchPage::new_empty(1024);
+    single.insert(1, 0);
+    assert!(single.split().is_none());
+}
+
+#[test]
+fn test_root_grows_with_sequential_keys() {
+    let store = InMemoryPageStore::with_page_size(PAGE_SIZE);
+    let mut tree = DataTree::new(store);
+    let first_root = tree.root_page_id();
+
+    for i in 0..2000 {
+        tree.put(i, format!("v{}", i).as_bytes()).unwrap();
+    }
+
+    assert_ne!(tree.root_page_id(), first_root);
+    assert!(height(&tree) >= 3, "height {}", height(&tree));
+    assert_eq!(tree.superblock().unwrap().root_page_id, tree.root_page_id());
+
+    for i in 0..2000 {
+        assert_eq!(tree.get(i).unwrap().unwrap(), format!("v{}", i).into_bytes());
+    }
+    assert!(tree.get(2000).unwrap().is_none());
+}
+
+#[test]
+fn test_random_operations_against_a_map() {
+    let store = InMemoryPageStore::with_page_size(PAGE_SIZE);
+    let mut tree = DataTree::new(store);
+    let mut expected = std::collections::BTreeMap::new();
+    let mut rng = StdRng::seed_from_u64(6);
+
+    for _ in 0..5000 {
+        let key = rng.gen_range(0..1500u64);
+        if rng.gen_bool(0.7) {
+            let value = vec![rng.gen::<u8>(); rng.gen_range(1..20)];
+            tree.put(key, &value).unwrap();
+            expected.insert(key, value);
+        } else {
+            assert_eq!(tree.delete(key).unwrap(), expected.remove(&key).is_some());
+        }
+    }
+
+    assert!(height(&tree) >= 2);
+    for key in 0..1500 {
+        assert_eq!(tree.get(key).unwrap(), expected.get(&key).cloned(), "key {}", key);
+    }
+}
+
+#[test]
+fn test_multi_level_tree_survives_reopen() {
+    let path = temp_path("multi_level");
+
+    {
+        let store = FilePageStore::create(&path, PAGE_SIZE).unwrap();
+        let mut tree = DataTree::new(store);
+        for i in (0..1000).rev() {
+            tree.put(i * 7, format!("v{}", i).as_bytes()).unwrap();
+        }
+        tree.flush().unwrap();
+    }
+
+    let store = FilePageStore::open(&path, PAGE_SIZE).unwrap();
+    let tree = DataTree::open(store).unwrap();
+    assert!(height(&tree) >= 3);
+    for i in 0..1000 {
+        assert_eq!(tree.get(i * 7).unwrap().unwrap(), format!("v{}", i).into_bytes());
+        assert!(tree.get(i * 7 + 1).unwrap().is_none());
+    }
+
+    fs::remove_file(&path).unwrap();
+}
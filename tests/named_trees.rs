@@ -0,0 +1,147 @@
+use data_tree::DataTree;
+use data_tree::data_tree2::{CatalogEntry, DataTree2};
+use data_tree::file_page_store::FilePageStore;
+use data_tree::formatter::Value;
+use data_tree::page_store::{PageStore, InMemoryPageStore};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+const PAGE_SIZE: usize = 512;
+
+// Build a unique path in the temp directory for a test's backing file
+fn temp_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("data_tree_{}_{}.db", name, std::process::id()));
+    let _ = fs::remove_file(&path);
+    path
+}
+
+#[test]
+fn test_trees_are_kept_apart() {
+    let mut db = DataTree2::new(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    for name in ["users", "orders", "events"] {
+        db.create_tree(name).unwrap();
+    }
+    assert_eq!(db.list_trees(), vec!["events", "orders", "users"]);
+
+    // Enough entries that every tree splits its root a few times
+    for (i, name) in ["users", "orders", "events"].iter().enumerate() {
+        let mut tree = db.open_tree(name).unwrap();
+        for key in 0..500 {
+            tree.put(key, format!("{}{}", name, key * i as u64).as_bytes()).unwrap();
+        }
+    }
+
+    for (i, name) in ["users", "orders", "events"].iter().enumerate() {
+        let tree = db.open_tree(name).unwrap();
+        assert_eq!(tree.name(), *name);
+        assert_eq!(tree.get(250).unwrap().unwrap(), Value::from(format!("{}{}", name, 250 * i as u64).as_bytes()));
+        assert_eq!(tree.iter().count(), 500);
+        assert!(tree.range(10..20).map(|(key, _)| key).eq(10..20));
+    }
+}
+
+#[test]
+fn test_create_rejects_taken_names() {
+    let mut db = DataTree2::new(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    db.create_tree("users").unwrap();
+    let page_count = db.get_page_count();
+
+    assert!(db.create_tree("users").is_err());
+    assert!(db.open_tree("missing").is_err());
+
+    // A name too long for the catalog leaves nothing behind
+    assert!(db.create_tree(&"x".repeat(PAGE_SIZE)).is_err());
+    assert_eq!(db.get_page_count(), page_count);
+    assert_eq!(db.list_trees(), vec!["users"]);
+}
+
+#[test]
+fn test_drop_frees_a_trees_pages() {
+    let mut db = DataTree2::new(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    db.create_tree("kept").unwrap();
+    let page_count = db.get_page_count();
+
+    db.create_tree("dropped").unwrap();
+    let mut tree = db.open_tree("dropped").unwrap();
+    for key in 0..300 {
+        tree.put(key, b"value").unwrap();
+    }
+    // A value large enough for overflow pages
+    tree.put(1000, vec![7u8; PAGE_SIZE * 3]).unwrap();
+    assert!(db.get_page_count() > page_count + 10);
+
+    assert!(db.drop_tree("dropped").unwrap());
+    assert!(!db.drop_tree("dropped").unwrap());
+    assert_eq!(db.get_page_count(), page_count);
+    assert_eq!(db.list_trees(), vec!["kept"]);
+    assert!(db.open_tree("dropped").is_err());
+
+    // The freed pages are reused by the next tree
+    let free_list_head = db.store().free_list_head();
+    db.create_tree("dropped").unwrap();
+    assert_ne!(db.store().free_list_head(), free_list_head);
+    assert_eq!(db.open_tree("dropped").unwrap().get(0).unwrap(), None);
+}
+
+#[test]
+fn test_many_trees_grow_the_catalog() {
+    let mut db = DataTree2::new(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    let mut model = BTreeMap::new();
+    for i in 0..200u64 {
+        let name = format!("tree{:03}", i);
+        db.create_tree(&name).unwrap();
+        db.open_tree(&name).unwrap().put(i, name.as_bytes()).unwrap();
+        model.insert(name, i);
+    }
+    for i in (0..200).step_by(3) {
+        let name = format!("tree{:03}", i);
+        assert!(db.drop_tree(&name).unwrap());
+        model.remove(&name);
+    }
+
+    assert!(db.list_trees().into_iter().eq(model.keys().map(String::as_str)));
+    let db = DataTree2::open(db.into_store()).unwrap();
+    assert!(db.list_trees().into_iter().eq(model.keys().map(String::as_str)));
+}
+
+#[test]
+fn test_reopened_store_finds_its_trees() {
+    let path = temp_path("named_trees");
+    let mut roots = Vec::new();
+    {
+        let mut db = DataTree2::new(FilePageStore::create(&path, PAGE_SIZE).unwrap());
+        for name in ["a", "b"] {
+            db.create_tree(name).unwrap();
+            let mut tree = db.open_tree(name).unwrap();
+            for key in 0..400 {
+                tree.put(key, format!("{}{}", name, key).as_bytes()).unwrap();
+            }
+            roots.push(tree.root_page_id());
+        }
+        db.drop_tree("a").unwrap();
+        db.flush().unwrap();
+    }
+
+    let mut db = DataTree2::open(FilePageStore::open(&path, PAGE_SIZE).unwrap()).unwrap();
+    assert_eq!(db.list_trees(), vec!["b"]);
+    let tree = db.open_tree("b").unwrap();
+    assert_eq!(tree.root_page_id(), roots[1]);
+    assert_eq!(tree.get(399).unwrap().unwrap(), Value::from(b"b399"));
+    assert_eq!(tree.iter().count(), 400);
+
+    // The catalog is a tree of byte-string keys recorded in the superblock
+    let store = db.into_store();
+    let catalog: DataTree<_, Vec<u8>> = DataTree::open_keyed(store).unwrap();
+    let entry = CatalogEntry::deserialize(&catalog.get(b"b").unwrap().unwrap()).unwrap();
+    assert_eq!(entry.root_page_id, roots[1]);
+    drop(catalog);
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_open_rejects_a_plain_tree_store() {
+    let mut tree = DataTree::new(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    tree.put(1, b"value").unwrap();
+    assert!(DataTree2::open(tree.into_store()).is_err());
+}
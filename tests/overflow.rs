@@ -0,0 +1,177 @@
+use data_tree::DataTree;
+use data_tree::branch_page::BranchPage;
+use data_tree::data_tree::PageType;
+use data_tree::file_page_store::FilePageStore;
+use data_tree::leaf_page::LeafPage;
+use data_tree::overflow_page::{OverflowPage, OverflowPointer, HEADER_SIZE};
+use data_tree::page_store::{PageStore, InMemoryPageStore, CRC_SIZE};
+use std::fs;
+use std::path::PathBuf;
+
+const PAGE_SIZE: usize = 256;
+
+// Build a unique path in the temp directory for a test's backing file
+fn temp_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("data_tree_{}_{}.db", name, std::process::id()));
+    let _ = fs::remove_file(&path);
+    path
+}
+
+// A value whose bytes differ by position, so misordered chunks are caught
+fn large_value(length: usize, seed: u8) -> Vec<u8> {
+    (0..length).map(|i| (i as u8).wrapping_mul(31).wrapping_add(seed)).collect()
+}
+
+fn chunk_size() -> usize {
+    LeafPage::empty(PAGE_SIZE - CRC_SIZE).get_value_chunk_size()
+}
+
+#[test]
+fn test_overflow_page_round_trip() {
+    let page = OverflowPage::new(b"chunk", 42);
+    let bytes = page.serialize();
+    assert_eq!(PageType::from_u8(bytes[0]), Some(PageType::OverflowPage));
+
+    let page = OverflowPage::deserialize(&bytes).unwrap();
+    assert_eq!(page.data, b"chunk");
+    assert_eq!(page.next_page_id, 42);
+
+    let pointer = OverflowPointer { first_page_id: 7, value_length: 1000 };
+    assert_eq!(OverflowPointer::deserialize(&pointer.serialize()).unwrap(), pointer);
+
+    assert!(OverflowPage::deserialize(&LeafPage::empty(64).serialize()).is_err());
+    assert_eq!(chunk_size(), PAGE_SIZE - CRC_SIZE - HEADER_SIZE);
+}
+
+#[test]
+fn test_large_values_round_trip() {
+    let mut tree = DataTree::new(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    let max_value_size = LeafPage::empty(PAGE_SIZE - CRC_SIZE).max_value_size();
+
+    // Just past the inline limit, exact multiples of a chunk and a large blob
+    let lengths = [max_value_size + 1, chunk_size() * 2, chunk_size() * 3 + 1, 10_000];
+    for (i, length) in lengths.iter().enumerate() {
+        tree.put(i as u64, &large_value(*length, i as u8)).unwrap();
+    }
+    tree.put(100, b"small").unwrap();
+
+    for (i, length) in lengths.iter().enumerate() {
+        assert_eq!(tree.get(i as u64).unwrap().unwrap(), large_value(*length, i as u8));
+    }
+    assert_eq!(tree.get(100).unwrap().unwrap(), b"small");
+}
+
+#[test]
+fn test_delete_and_update_free_the_chain() {
+    let mut tree = DataTree::new(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    tree.put(1, b"small").unwrap();
+    let base_page_count = tree.store().get_page_count();
+
+    // Each chunk takes one page
+    tree.put(2, &large_value(5000, 2)).unwrap();
+    let chain_length = 5000usize.div_ceil(chunk_size());
+    assert_eq!(tree.store().get_page_count(), base_page_count + chain_length);
+
+    // Replacing the value with a smaller large one frees the old chain
+    tree.put(2, &large_value(1000, 3)).unwrap();
+    assert_eq!(tree.store().get_page_count(), base_page_count + 1000usize.div_ceil(chunk_size()));
+    assert_eq!(tree.get(2).unwrap().unwrap(), large_value(1000, 3));
+
+    // Replacing it with an inline value frees the rest
+    tree.put(2, b"inline").unwrap();
+    assert_eq!(tree.store().get_page_count(), base_page_count);
+    assert_eq!(tree.get(2).unwrap().unwrap(), b"inline");
+
+    tree.put(2, &large_value(3000, 4)).unwrap();
+    assert!(tree.delete(2).unwrap());
+    assert_eq!(tree.store().get_page_count(), base_page_count);
+    assert!(tree.get(2).unwrap().is_none());
+}
+
+#[test]
+fn test_large_values_survive_splits_and_merges() {
+    let mut tree = DataTree::new(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    for i in 0..300u64 {
+        if i % 3 == 0 {
+            tree.put(i, &large_value(600 + i as usize, i as u8)).unwrap();
+        } else {
+            tree.put(i, format!("value{}", i).as_bytes()).unwrap();
+        }
+    }
+    for i in (0..300).filter(|i| i % 2 == 0) {
+        tree.delete(i).unwrap();
+    }
+
+    for (key, value) in tree.iter() {
+        assert_eq!(key % 2, 1);
+        if key % 3 == 0 {
+            assert_eq!(value, large_value(600 + key as usize, key as u8));
+        } else {
+            assert_eq!(value, format!("value{}", key).into_bytes());
+        }
+    }
+    assert_eq!(tree.keys().count(), 150);
+
+    let mut cursor = tree.cursor();
+    cursor.seek(3).unwrap();
+    assert_eq!(cursor.value().unwrap().unwrap(), large_value(603, 3));
+    cursor.put_current(&large_value(2000, 9)).unwrap();
+    assert_eq!(cursor.value().unwrap().unwrap(), large_value(2000, 9));
+    cursor.delete_current().unwrap();
+    assert_eq!(cursor.key(), Some(5));
+    assert!(tree.get(3).unwrap().is_none());
+}
+
+#[test]
+fn test_bulk_load_with_large_values() {
+    let entries = (0..50u64).map(|i| (i, large_value(if i % 5 == 0 { 1000 } else { 10 }, i as u8)));
+    let tree = DataTree::bulk_load(InMemoryPageStore::with_page_size(PAGE_SIZE), entries).unwrap();
+
+    for i in 0..50u64 {
+        let length = if i % 5 == 0 { 1000 } else { 10 };
+        assert_eq!(tree.get(i).unwrap().unwrap(), large_value(length, i as u8));
+    }
+}
+
+#[test]
+fn test_large_values_survive_reopen() {
+    let path = temp_path("overflow");
+    {
+        let store = FilePageStore::create(&path, PAGE_SIZE).unwrap();
+        let mut tree = DataTree::new(store);
+        tree.put(1, &large_value(20_000, 1)).unwrap();
+        tree.put(2, b"small").unwrap();
+        tree.flush().unwrap();
+    }
+
+    let store = FilePageStore::open(&path, PAGE_SIZE).unwrap();
+    let tree = DataTree::open(store).unwrap();
+    assert_eq!(tree.get(1).unwrap().unwrap(), large_value(20_000, 1));
+    assert_eq!(tree.get(2).unwrap().unwrap(), b"small");
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_damaged_pointer_length_is_an_error() {
+    let mut tree = DataTree::new(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    tree.put(1, &large_value(5000, 1)).unwrap();
+
+    // Find the leaf holding the pointer record and claim a huge value length
+    let mut page_id = tree.root_page_id();
+    let mut bytes = tree.store().get_page_bytes(page_id).unwrap();
+    if PageType::from_u8(bytes[0]) == Some(PageType::BranchPage) {
+        page_id = BranchPage::deserialize(&bytes).entries()[0].page_id;
+        bytes = tree.store().get_page_bytes(page_id).unwrap();
+    }
+    let length = 5000u64.to_le_bytes();
+    let at = bytes.windows(length.len()).position(|window| window == length).unwrap();
+    bytes[at..at + length.len()].copy_from_slice(&(u64::MAX / 2).to_le_bytes());
+    tree.store_mut().put_page_bytes(page_id, &bytes).unwrap();
+    assert!(tree.get(1).is_err());
+
+    // A length shorter than the chain is caught too
+    bytes[at..at + length.len()].copy_from_slice(&100u64.to_le_bytes());
+    tree.store_mut().put_page_bytes(page_id, &bytes).unwrap();
+    assert!(tree.get(1).is_err());
+}
@@ -0,0 +1,121 @@
+use data_tree::branch_page::{BranchPage, BranchPageView, KeyedBranchPage};
+use data_tree::leaf::{LeafEncoding, LeafView};
+use data_tree::leaf_page::{KeyedLeafPage, LeafPage, LeafPageView};
+use data_tree::rle_leaf_page::{RLELeafPage, RLELeafPageView};
+use rand::prelude::*;
+
+const PAGE_SIZE: usize = 4096;
+
+// Serialized bytes padded to the page size, as a store holds them
+fn padded(mut bytes: Vec<u8>) -> Vec<u8> {
+    bytes.resize(PAGE_SIZE, 0);
+    bytes
+}
+
+#[test]
+fn test_leaf_page_view_matches_page() {
+    let mut rng = StdRng::seed_from_u64(19);
+    let mut page = LeafPage::empty(PAGE_SIZE);
+    for _ in 0..150 {
+        let key = rng.gen_range(0..1000u64);
+        page.put(key, &vec![key as u8; rng.gen_range(0..10)]);
+    }
+    assert!(page.put_overflow(2000, b"record"));
+    page.set_prev_page_id(7);
+    page.set_next_page_id(9);
+
+    let bytes = padded(page.serialize());
+    let view = LeafPageView::<u64>::new(&bytes).unwrap();
+    assert_eq!(view.len(), page.metadata().len());
+    assert_eq!((view.prev_page_id(), view.next_page_id()), (7, 9));
+    for key in 0..1000 {
+        assert_eq!(view.get(key), page.get(key));
+    }
+    assert_eq!(view.get_entry(2000), Some((&b"record"[..], true)));
+    assert!(view.is_overflow(2000));
+    assert!(!view.is_overflow(page.metadata()[0].key));
+    assert_eq!(view.get(3000), None);
+}
+
+#[test]
+fn test_byte_key_views_match_pages() {
+    let mut leaf = KeyedLeafPage::<Vec<u8>>::empty(PAGE_SIZE);
+    let mut branch = KeyedBranchPage::<Vec<u8>>::new_empty(PAGE_SIZE);
+    for (i, word) in ["pear", "apple", "fig", "banana", "cherry", "", "date"].iter().enumerate() {
+        assert!(leaf.put(word.as_bytes(), word.to_uppercase().as_bytes()));
+        assert!(branch.insert(i as u64 + 1, word.as_bytes().to_vec()));
+    }
+
+    let leaf_bytes = padded(leaf.serialize());
+    let branch_bytes = padded(branch.serialize());
+    let leaf_view = LeafPageView::<Vec<u8>>::new(&leaf_bytes).unwrap();
+    let branch_view = BranchPageView::<Vec<u8>>::new(&branch_bytes).unwrap();
+    for key in ["", "a", "apple", "blueberry", "cherry", "date", "fig", "grape", "pear", "zucchini"] {
+        assert_eq!(leaf_view.get(key.as_bytes()), leaf.get(key.as_bytes()), "{}", key);
+        assert_eq!(branch_view.find_page_id(key.as_bytes()), branch.find_page_id(key.as_bytes()), "{}", key);
+    }
+}
+
+#[test]
+fn test_branch_page_view_matches_page() {
+    let mut page = BranchPage::new_empty(PAGE_SIZE);
+    for i in 0..200u64 {
+        assert!(page.insert(i + 100, i * 7 + 5));
+    }
+    page.set_next_page_id(3);
+
+    let bytes = padded(page.serialize());
+    let view = BranchPageView::<u64>::new(&bytes).unwrap();
+    assert_eq!(view.len(), 200);
+    assert_eq!((view.prev_page_id(), view.next_page_id()), (0, 3));
+    for key in 0..1500 {
+        assert_eq!(view.find_page_id(key), page.find_page_id(key));
+    }
+
+    let empty = padded(BranchPage::new_empty(PAGE_SIZE).serialize());
+    assert_eq!(BranchPageView::<u64>::new(&empty).unwrap().find_page_id(5), None);
+}
+
+#[test]
+fn test_rle_leaf_page_view_matches_page() {
+    let mut page = RLELeafPage::new_empty(PAGE_SIZE);
+    for key in 0..1000u64 {
+        page.put(key, format!("run{}", key / 37).as_bytes());
+    }
+    for key in (5..1000).step_by(50) {
+        page.delete(key);
+    }
+    assert!(page.put_overflow(2000, b"record"));
+
+    let bytes = padded(page.serialize());
+    let view = RLELeafPageView::new(&bytes).unwrap();
+    assert_eq!(view.len(), page.metadata().len());
+    for key in 0..2100 {
+        assert_eq!(view.get(key), page.get(key), "{}", key);
+    }
+    assert!(view.is_overflow(2000));
+    assert!(!view.is_overflow(0));
+
+    let leaf = LeafView::<u64>::new(&bytes).unwrap();
+    assert_eq!(leaf.encoding(), LeafEncoding::RunLength);
+    assert_eq!(leaf.get(36), Some(&b"run0"[..]));
+}
+
+#[test]
+fn test_views_reject_other_bytes() {
+    let leaf_bytes = padded(LeafPage::empty(PAGE_SIZE).serialize());
+    let branch_bytes = padded(BranchPage::new_empty(PAGE_SIZE).serialize());
+
+    assert!(LeafPageView::<u64>::new(&leaf_bytes[..10]).is_err());
+    assert!(LeafPageView::<u64>::new(&branch_bytes).is_err());
+    assert!(LeafPageView::<Vec<u8>>::new(&leaf_bytes).is_err());
+    assert!(BranchPageView::<u64>::new(&leaf_bytes).is_err());
+    assert!(RLELeafPageView::new(&leaf_bytes).is_err());
+    assert!(LeafView::<u64>::new(&branch_bytes).is_err());
+    assert!(LeafView::<Vec<u8>>::new(&padded(RLELeafPage::new_empty(PAGE_SIZE).serialize())).is_err());
+
+    // A count claiming more entries than the page holds
+    let mut bytes = leaf_bytes.clone();
+    bytes[1..9].copy_from_slice(&u64::MAX.to_le_bytes());
+    assert!(LeafPageView::<u64>::new(&bytes).is_err());
+}
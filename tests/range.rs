@@ -0,0 +1,107 @@
+use data_tree::DataTree;
+use data_tree::page_store::{PageStore, InMemoryPageStore};
+use std::collections::BTreeMap;
+use std::ops::Bound;
+
+const PAGE_SIZE: usize = 256;
+
+// A multi-level tree holding every third key below 3000, alongside the same
+// entries in a BTreeMap to compare against
+fn build_tree() -> (DataTree<InMemoryPageStore>, BTreeMap<u64, Vec<u8>>) {
+    let mut tree = DataTree::new(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    let mut expected = BTreeMap::new();
+    for i in (0..1000).rev() {
+        let value = format!("value{}", i).into_bytes();
+        tree.put(i * 3, &value).unwrap();
+        expected.insert(i * 3, value);
+    }
+    (tree, expected)
+}
+
+#[test]
+fn test_iter_returns_all_entries_in_order() {
+    let (tree, expected) = build_tree();
+
+    let entries: Vec<(u64, Vec<u8>)> = tree.iter().collect();
+    let expected_entries: Vec<(u64, Vec<u8>)> = expected.into_iter().collect();
+    assert_eq!(entries, expected_entries);
+}
+
+#[test]
+fn test_keys_and_values() {
+    let (tree, expected) = build_tree();
+
+    assert!(tree.keys().eq(expected.keys().copied()));
+    assert!(tree.values().eq(expected.values().cloned()));
+    assert!(tree.keys().rev().eq(expected.keys().rev().copied()));
+}
+
+#[test]
+fn test_range_bounds_match_btree_map() {
+    let (tree, expected) = build_tree();
+
+    let bounds = [
+        (Bound::Included(0), Bound::Excluded(30)),
+        (Bound::Included(1), Bound::Included(299)),
+        (Bound::Excluded(300), Bound::Included(1500)),
+        (Bound::Excluded(2997), Bound::Unbounded),
+        (Bound::Unbounded, Bound::Excluded(3)),
+        (Bound::Included(1000), Bound::Excluded(1001)),
+        (Bound::Included(5000), Bound::Unbounded),
+        (Bound::Unbounded, Bound::Unbounded),
+    ];
+    for (lower, upper) in bounds {
+        let keys: Vec<u64> = tree.range((lower, upper)).map(|(key, _)| key).collect();
+        let expected_keys: Vec<u64> = expected.range((lower, upper)).map(|(key, _)| *key).collect();
+        assert_eq!(keys, expected_keys, "range {:?}..{:?}", lower, upper);
+
+        let keys: Vec<u64> = tree.range((lower, upper)).rev().map(|(key, _)| key).collect();
+        let expected_keys: Vec<u64> = expected.range((lower, upper)).rev().map(|(key, _)| *key).collect();
+        assert_eq!(keys, expected_keys, "reverse range {:?}..{:?}", lower, upper);
+    }
+
+    assert_eq!(tree.range(9..=9).next(), Some((9, b"value3".to_vec())));
+    assert_eq!(tree.range(10..12).next(), None);
+}
+
+#[test]
+fn test_both_ends_meet_without_repeating() {
+    let (tree, expected) = build_tree();
+
+    // Alternate between the ends; every key is yielded exactly once
+    let mut range = tree.range(100..2000);
+    let mut keys = Vec::new();
+    loop {
+        let front = range.next();
+        let back = range.next_back();
+        if front.is_none() && back.is_none() {
+            break;
+        }
+        keys.extend(front.map(|(key, _)| key));
+        keys.extend(back.map(|(key, _)| key));
+    }
+    keys.sort();
+
+    let expected_keys: Vec<u64> = expected.range(100..2000).map(|(key, _)| *key).collect();
+    assert_eq!(keys, expected_keys);
+}
+
+#[test]
+fn test_empty_tree() {
+    let tree = DataTree::new(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    assert_eq!(tree.iter().count(), 0);
+    assert_eq!(tree.iter().next_back(), None);
+    assert_eq!(tree.keys().count(), 0);
+}
+
+#[test]
+fn test_unreadable_page_ends_iteration_with_error() {
+    let (mut tree, _) = build_tree();
+    let root_page_id = tree.root_page_id();
+    tree.store_mut().corrupt_page_for_testing(root_page_id);
+
+    let mut range = tree.iter();
+    assert_eq!(range.next(), None);
+    assert!(range.take_error().is_some());
+    assert!(tree.store().get_page_bytes(root_page_id).is_err());
+}
@@ -0,0 +1,190 @@
+use data_tree::DataTree;
+use data_tree::data_tree::{DataTreeConfig, PageType};
+use data_tree::leaf::{Leaf, LeafEncoding};
+use data_tree::branch_page::BranchPage;
+use data_tree::page_store::{PageStore, InMemoryPageStore};
+use rand::prelude::*;
+use std::collections::BTreeMap;
+use std::ops::Bound;
+
+const PAGE_SIZE: usize = 256;
+
+// The number of runs in every leaf, from left to right
+fn leaf_runs<S: PageStore>(tree: &DataTree<S>) -> Vec<usize> {
+    let mut page_id = tree.root_page_id();
+    loop {
+        let bytes = tree.store().get_page_bytes(page_id).unwrap();
+        if PageType::from_u8(bytes[0]) != Some(PageType::BranchPage) {
+            break;
+        }
+        page_id = BranchPage::deserialize(&bytes).entries()[0].page_id;
+    }
+
+    let mut runs = Vec::new();
+    while page_id != 0 {
+        let leaf: Leaf = Leaf::deserialize(&tree.store().get_page_bytes(page_id).unwrap()).unwrap();
+        page_id = leaf.next_page_id();
+        runs.push(leaf.runs().len());
+    }
+    runs
+}
+
+#[test]
+fn test_put_range_stores_a_run() {
+    let mut tree = DataTree::new(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    tree.put_range(1000..=1_000_000_000, b"allocated").unwrap();
+
+    assert!(tree.get(999).unwrap().is_none());
+    assert_eq!(tree.get(1000).unwrap().unwrap(), b"allocated");
+    assert_eq!(tree.get(123_456_789).unwrap().unwrap(), b"allocated");
+    assert_eq!(tree.get(1_000_000_000).unwrap().unwrap(), b"allocated");
+    assert!(tree.get(1_000_000_001).unwrap().is_none());
+    assert_eq!(leaf_runs(&tree), vec![1]);
+
+    // Adjacent ranges of the same value join
+    tree.put_range(1_000_000_001..1_000_000_100, b"allocated").unwrap();
+    tree.put_range(..1000, b"allocated").unwrap();
+    assert_eq!(leaf_runs(&tree), vec![1]);
+    assert!(tree.range(1_000_000_090..).map(|(key, _)| key).eq(1_000_000_090..1_000_000_100));
+
+    // Empty ranges change nothing
+    tree.put_range(50..50, b"empty").unwrap();
+    tree.put_range((Bound::Excluded(u64::MAX), Bound::Unbounded), b"empty").unwrap();
+    assert_eq!(tree.get(50).unwrap().unwrap(), b"allocated");
+}
+
+#[test]
+fn test_put_range_clips_and_splits_runs() {
+    let mut tree = DataTree::new(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    tree.put_range(0..=99, b"a").unwrap();
+    tree.put_range(50..=59, b"b").unwrap();
+    assert_eq!(leaf_runs(&tree), vec![3]);
+    for (key, value) in [(49, b"a"), (50, b"b"), (59, b"b"), (60, b"a"), (99, b"a")] {
+        assert_eq!(tree.get(key).unwrap().unwrap(), value);
+    }
+
+    // Overlapping both ends of existing runs
+    tree.put_range(40..=69, b"c").unwrap();
+    assert_eq!(leaf_runs(&tree), vec![3]);
+    assert_eq!(tree.get(39).unwrap().unwrap(), b"a");
+    assert_eq!(tree.get(55).unwrap().unwrap(), b"c");
+    assert_eq!(tree.get(70).unwrap().unwrap(), b"a");
+
+    // Single keys inside a range replace part of it
+    tree.put(10, b"single").unwrap();
+    assert_eq!(tree.get(9).unwrap().unwrap(), b"a");
+    assert_eq!(tree.get(10).unwrap().unwrap(), b"single");
+    assert_eq!(tree.get(11).unwrap().unwrap(), b"a");
+    assert_eq!(tree.iter().count(), 100);
+}
+
+#[test]
+fn test_delete_range_clips_runs_and_entries() {
+    let mut tree = DataTree::new(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    for i in 0..500 {
+        tree.put(i, format!("value{}", i).as_bytes()).unwrap();
+    }
+    tree.put_range(1000..=1_000_000, b"run").unwrap();
+
+    assert_eq!(tree.delete_range(100..200).unwrap(), 100);
+    assert_eq!(tree.delete_range(400..=2000).unwrap(), 100 + 1001);
+    assert_eq!(tree.delete_range(500_000..500_010).unwrap(), 10);
+    assert_eq!(tree.delete_range(100..200).unwrap(), 0);
+
+    assert!(tree.get(99).unwrap().is_some());
+    assert!(tree.get(100).unwrap().is_none());
+    assert!(tree.get(2000).unwrap().is_none());
+    assert_eq!(tree.get(2001).unwrap().unwrap(), b"run");
+    assert_eq!(tree.get(500_009 + 1).unwrap().unwrap(), b"run");
+    assert!(tree.get(500_005).unwrap().is_none());
+
+    // Deleting everything leaves a single empty leaf
+    let remaining = 300 + (1_000_000 - 2000) - 10;
+    assert_eq!(tree.delete_range(..).unwrap(), remaining);
+    assert_eq!(tree.iter().count(), 0);
+    assert_eq!(leaf_runs(&tree), vec![0]);
+}
+
+#[test]
+fn test_range_updates_free_overflow_pages() {
+    let mut tree = DataTree::new(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    tree.put_range(0..=1000, b"base").unwrap();
+    let page_count = tree.store().get_page_count();
+
+    for i in 0..5 {
+        tree.put(i * 100, &vec![i as u8; PAGE_SIZE * 3]).unwrap();
+    }
+    assert!(tree.store().get_page_count() > page_count);
+
+    tree.put_range(0..=1000, b"base").unwrap();
+    assert_eq!(tree.store().get_page_count(), page_count);
+    assert_eq!(tree.get(300).unwrap().unwrap(), b"base");
+
+    // A value too large for a run is rejected rather than stored apart
+    assert!(tree.put_range(0..=10, &vec![0u8; PAGE_SIZE]).is_err());
+    assert_eq!(tree.get(5).unwrap().unwrap(), b"base");
+}
+
+#[test]
+fn test_range_updates_match_a_model() {
+    let mut rng = StdRng::seed_from_u64(16);
+    for encoding in [LeafEncoding::Plain, LeafEncoding::RunLength, LeafEncoding::Adaptive] {
+        let mut tree = DataTree::new(InMemoryPageStore::with_page_size(PAGE_SIZE));
+        tree.set_config(DataTreeConfig { leaf_encoding: encoding, ..Default::default() }).unwrap();
+        let mut model = BTreeMap::new();
+
+        for step in 0..400 {
+            let start = rng.gen_range(0..3000u64);
+            let end = start + rng.gen_range(0..300);
+            let value = format!("v{}", rng.gen_range(0..4)).into_bytes();
+            match rng.gen_range(0..4) {
+                0 => {
+                    tree.put_range(start..=end, &value).unwrap();
+                    for key in start..=end {
+                        model.insert(key, value.clone());
+                    }
+                }
+                1 => {
+                    let deleted = tree.delete_range(start..end).unwrap();
+                    let keys: Vec<u64> = model.range(start..end).map(|(key, _)| *key).collect();
+                    assert_eq!(deleted, keys.len() as u64);
+                    for key in keys {
+                        model.remove(&key);
+                    }
+                }
+                2 => {
+                    tree.put(start, &value).unwrap();
+                    model.insert(start, value);
+                }
+                _ => {
+                    assert_eq!(tree.delete(start).unwrap(), model.remove(&start).is_some());
+                }
+            }
+            if step % 50 == 0 {
+                assert!(tree.iter().eq(model.iter().map(|(key, value)| (*key, value.clone()))));
+            }
+        }
+        assert!(tree.iter().eq(model.iter().map(|(key, value)| (*key, value.clone()))));
+        assert!(tree.iter().rev().eq(model.iter().rev().map(|(key, value)| (*key, value.clone()))));
+    }
+}
+
+#[test]
+fn test_range_updates_with_other_key_types() {
+    let mut tree: DataTree<_, i64> = DataTree::new_keyed(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    tree.put_range(-100..=100, b"around zero").unwrap();
+    assert_eq!(tree.get(-100).unwrap().unwrap(), b"around zero");
+    assert!(tree.get(-101).unwrap().is_none());
+    assert_eq!(tree.delete_range(-10..10).unwrap(), 20);
+    assert!(tree.range(-12..12).map(|(key, _)| key).eq([-12, -11, 10, 11]));
+
+    // Byte-string keys can't form runs, but their ranges can be deleted
+    let mut tree: DataTree<_, Vec<u8>> = DataTree::new_keyed(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    assert!(tree.put_range(&b"a"[..]..=&b"z"[..], b"value").is_err());
+    for word in ["apple", "banana", "cherry", "date", "elderberry"] {
+        tree.put(word.as_bytes(), b"fruit").unwrap();
+    }
+    assert_eq!(tree.delete_range(&b"b"[..]..&b"d"[..]).unwrap(), 2);
+    assert_eq!(tree.delete_range((Bound::Excluded(&b"date"[..]), Bound::Unbounded)).unwrap(), 1);
+    assert_eq!(tree.keys().collect::<Vec<_>>(), vec![b"apple".to_vec(), b"date".to_vec()]);
+}
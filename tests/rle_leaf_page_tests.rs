@@ -49,7 +49,7 @@ fn test_rle_leaf_page_with_single_key() {
 
     // Check that the deserialized page has the expected properties
     assert_eq!(deserialized.metadata.len(), 1);
-    assert!(deserialized.data.len() > 0);
+    assert!(!deserialized.data.is_empty());
 
     // Check that the key-value pair can be retrieved
     let retrieved_value = deserialized.get(key).unwrap();
@@ -394,8 +394,8 @@ fn test_rle_leaf_page_incremental_fill_and_split() {
 
     // 2. Check that the metadata is split roughly in half
     let total_runs = all_keys.len(); // In this case, each key is its own run
-    assert!(leaf_page.metadata.len() > 0, "Original page has no metadata after split");
-    assert!(new_page.metadata.len() > 0, "New page has no metadata after split");
+    assert!(!leaf_page.metadata.is_empty(), "Original page has no metadata after split");
+    assert!(!new_page.metadata.is_empty(), "New page has no metadata after split");
 
     // The split should be roughly even, but we'll allow some flexibility
     let min_expected = 1; // At least 1 entry in each page
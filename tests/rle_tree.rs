@@ -0,0 +1,257 @@
+use data_tree::DataTree;
+use data_tree::branch_page::BranchPage;
+use data_tree::data_tree::{DataTreeConfig, PageType};
+use data_tree::file_page_store::FilePageStore;
+use data_tree::leaf::{Leaf, LeafEncoding};
+use data_tree::page_store::{PageStore, InMemoryPageStore};
+use std::fs;
+use std::path::PathBuf;
+
+const PAGE_SIZE: usize = 256;
+
+const RUN_LENGTH: DataTreeConfig = DataTreeConfig {
+    min_fill: 0.25,
+    fill_factor: 1.0,
+    leaf_encoding: LeafEncoding::RunLength,
+};
+
+// Build a unique path in the temp directory for a test's backing file
+fn temp_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("data_tree_{}_{}.db", name, std::process::id()));
+    let _ = fs::remove_file(&path);
+    path
+}
+
+fn run_length_tree() -> DataTree<InMemoryPageStore> {
+    let mut tree = DataTree::new(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    tree.set_config(RUN_LENGTH).unwrap();
+    tree
+}
+
+// The value of key `i` when keys come in runs of 100 equal values
+fn run_value(i: u64) -> Vec<u8> {
+    format!("run{}", i / 100).into_bytes()
+}
+
+// Every leaf from left to right, following the sibling links
+fn leaves<S: PageStore>(tree: &DataTree<S>) -> Vec<Leaf> {
+    let mut page_id = tree.root_page_id();
+    loop {
+        let bytes = tree.store().get_page_bytes(page_id).unwrap();
+        if PageType::from_u8(bytes[0]) != Some(PageType::BranchPage) {
+            break;
+        }
+        page_id = BranchPage::deserialize(&bytes).entries()[0].page_id;
+    }
+
+    let mut leaves = Vec::new();
+    while page_id != 0 {
+        let leaf = Leaf::deserialize(&tree.store().get_page_bytes(page_id).unwrap()).unwrap();
+        page_id = leaf.next_page_id();
+        leaves.push(leaf);
+    }
+    leaves
+}
+
+fn count_encoding<S: PageStore>(tree: &DataTree<S>, encoding: LeafEncoding) -> usize {
+    leaves(tree).iter().filter(|leaf| leaf.encoding() == encoding).count()
+}
+
+#[test]
+fn test_runs_take_fewer_leaves() {
+    let mut plain = DataTree::new(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    let mut rle = run_length_tree();
+    for i in 0..5000 {
+        plain.put(i, &run_value(i)).unwrap();
+        rle.put(i, &run_value(i)).unwrap();
+    }
+
+    for i in (0..5000).step_by(37) {
+        assert_eq!(rle.get(i).unwrap().unwrap(), run_value(i));
+    }
+    assert!(rle.get(5000).unwrap().is_none());
+    assert!(rle.iter().eq(plain.iter()));
+
+    let rle_leaves = leaves(&rle);
+    assert!(rle_leaves.iter().all(|leaf| leaf.encoding() == LeafEncoding::RunLength));
+    assert!(rle_leaves.len() * 20 < leaves(&plain).len());
+}
+
+#[test]
+fn test_overwrites_and_deletes_split_runs() {
+    let mut tree = run_length_tree();
+    let mut expected = Vec::new();
+    for i in 0..3000 {
+        tree.put(i, &run_value(i)).unwrap();
+        expected.push((i, run_value(i)));
+    }
+
+    // Distinct values punched into the middle of runs split them, until
+    // leaves overflow and split in turn
+    for i in (5..3000).step_by(10) {
+        let value = format!("single{}", i).into_bytes();
+        tree.put(i, &value).unwrap();
+        expected[i as usize].1 = value;
+    }
+    assert!(tree.iter().eq(expected.iter().cloned()));
+
+    // Deleting from the middle of runs splits them too
+    for i in (7..3000).step_by(10) {
+        assert!(tree.delete(i).unwrap());
+    }
+    expected.retain(|(key, _)| key % 10 != 7);
+    assert!(tree.iter().eq(expected.iter().cloned()));
+    assert!(!tree.delete(7).unwrap());
+
+    // Emptying most of the tree merges leaves back together
+    let leaves_before = leaves(&tree).len();
+    for (key, _) in expected.drain(100..) {
+        assert!(tree.delete(key).unwrap());
+    }
+    assert!(tree.iter().eq(expected.iter().cloned()));
+    assert!(leaves(&tree).len() < leaves_before);
+}
+
+#[test]
+fn test_switching_encoding_converts_leaves_as_they_are_written() {
+    let mut tree = DataTree::new(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    for i in 0..2000 {
+        tree.put(i, &run_value(i)).unwrap();
+    }
+    assert_eq!(count_encoding(&tree, LeafEncoding::RunLength), 0);
+
+    // Only the leaves written after the switch change encoding; the rest are
+    // read as they are
+    tree.set_config(RUN_LENGTH).unwrap();
+    for i in 0..100 {
+        tree.put(i, &run_value(i)).unwrap();
+    }
+    assert!(count_encoding(&tree, LeafEncoding::RunLength) > 0);
+    assert!(count_encoding(&tree, LeafEncoding::Plain) > 0);
+    assert!(tree.iter().eq((0..2000).map(|i| (i, run_value(i)))));
+
+    // And back again. A run-length leaf stays as it is while its keys don't
+    // fit a plain leaf, and converts once distinct values break up its runs.
+    tree.set_config(DataTreeConfig::default()).unwrap();
+    tree.put(0, b"zero").unwrap();
+    assert!(count_encoding(&tree, LeafEncoding::RunLength) > 0);
+    for i in 0..2000 {
+        tree.put(i, i.to_string().as_bytes()).unwrap();
+    }
+    assert_eq!(count_encoding(&tree, LeafEncoding::RunLength), 0);
+    assert!(tree.iter().eq((0..2000u64).map(|i| (i, i.to_string().into_bytes()))));
+}
+
+#[test]
+fn test_large_values_in_run_length_leaves() {
+    let mut tree = run_length_tree();
+    let large = vec![7u8; PAGE_SIZE * 3];
+    for i in 0..300 {
+        tree.put(i, &run_value(i)).unwrap();
+    }
+
+    // Equal large values are stored apart, never merged into a run
+    tree.put(150, &large).unwrap();
+    tree.put(151, &large).unwrap();
+    assert_eq!(tree.get(150).unwrap().unwrap(), large);
+    assert_eq!(tree.get(151).unwrap().unwrap(), large);
+    assert_eq!(tree.get(152).unwrap().unwrap(), run_value(152));
+
+    tree.put(150, &run_value(150)).unwrap();
+    assert!(tree.delete(151).unwrap());
+    assert_eq!(tree.get(150).unwrap().unwrap(), run_value(150));
+    assert!(tree.get(151).unwrap().is_none());
+}
+
+#[test]
+fn test_range_and_cursor_step_through_runs() {
+    let mut tree = run_length_tree();
+    for i in 0..1000 {
+        tree.put(i * 2, &run_value(i)).unwrap();
+    }
+    for i in 0..1000 {
+        tree.put(i * 2 + 1, &run_value(i)).unwrap();
+    }
+
+    assert!(tree.range(95..=205).map(|(key, _)| key).eq(95..=205));
+    assert!(tree.range(..300).rev().map(|(key, _)| key).eq((0..300).rev()));
+
+    let mut cursor = tree.cursor();
+    assert!(cursor.seek(150).unwrap());
+    assert_eq!(cursor.key(), Some(150));
+    assert!(cursor.next().unwrap());
+    assert_eq!(cursor.key(), Some(151));
+    assert!(cursor.prev().unwrap());
+    assert!(cursor.prev().unwrap());
+    assert_eq!(cursor.key(), Some(149));
+    assert_eq!(cursor.value().unwrap().unwrap(), run_value(74));
+
+    // Updating through the cursor splits the run it is on
+    cursor.put_current(b"changed").unwrap();
+    assert_eq!(cursor.key(), Some(149));
+    assert!(cursor.next().unwrap());
+    assert_eq!(cursor.key(), Some(150));
+    cursor.delete_current().unwrap();
+    assert_eq!(cursor.key(), Some(151));
+
+    assert!(cursor.seek_for_prev(150).unwrap());
+    assert_eq!(cursor.key(), Some(149));
+    assert_eq!(cursor.value().unwrap().unwrap(), b"changed");
+
+    let mut count = 1;
+    while cursor.next().unwrap() {
+        count += 1;
+    }
+    assert_eq!(count, 2000 - 149 - 1);
+}
+
+#[test]
+fn test_bulk_load_and_reopen_run_length_tree() {
+    let path = temp_path("rle_tree");
+    {
+        let store = FilePageStore::create(&path, PAGE_SIZE).unwrap();
+        let entries = (0..10_000).map(|i| (i, run_value(i)));
+        let mut tree = DataTree::bulk_load_with_config(store, RUN_LENGTH, entries).unwrap();
+        assert_eq!(count_encoding(&tree, LeafEncoding::Plain), 0);
+        tree.flush().unwrap();
+    }
+
+    // The encoding setting isn't stored: the reopened tree reads the leaves
+    // and writes plain ones
+    let store = FilePageStore::open(&path, PAGE_SIZE).unwrap();
+    let mut tree = DataTree::open(store).unwrap();
+    assert!(tree.iter().eq((0..10_000).map(|i| (i, run_value(i)))));
+    tree.put(10_000, b"end").unwrap();
+    assert_eq!(tree.get(10_000).unwrap().unwrap(), b"end");
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_run_length_needs_u64_keys() {
+    let mut tree: DataTree<_, Vec<u8>> = DataTree::new_keyed(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    assert!(tree.set_config(RUN_LENGTH).is_err());
+    assert_eq!(tree.config().leaf_encoding, LeafEncoding::Plain);
+
+    let entries = vec![(b"a".to_vec(), b"1".to_vec())];
+    let store = InMemoryPageStore::with_page_size(PAGE_SIZE);
+    assert!(DataTree::<_, Vec<u8>>::bulk_load_keyed(store, RUN_LENGTH, entries).is_err());
+}
+
+#[test]
+fn test_split_keeps_values_that_share_an_offset() {
+    // Empty values and runs sharing the start of a longer value point at the
+    // same offset with different lengths
+    let mut tree = DataTree::new(InMemoryPageStore::with_page_size(128));
+    tree.set_config(RUN_LENGTH).unwrap();
+    tree.put(200, &[2; 16]).unwrap();
+    tree.put(21, &[1; 10]).unwrap();
+    tree.put(201, &[]).unwrap();
+    tree.put(145, &[]).unwrap();
+    tree.delete(200).unwrap();
+    tree.put(200, &[1; 19]).unwrap();
+    tree.put(201, &[0; 5]).unwrap();
+
+    let expected = vec![(21, vec![1; 10]), (145, vec![]), (200, vec![1; 19]), (201, vec![0; 5])];
+    assert_eq!(tree.iter().collect::<Vec<_>>(), expected);
+}
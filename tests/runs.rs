@@ -0,0 +1,113 @@
+use data_tree::DataTree;
+use data_tree::data_tree::DataTreeConfig;
+use data_tree::leaf::LeafEncoding;
+use data_tree::page_store::InMemoryPageStore;
+
+const PAGE_SIZE: usize = 256;
+
+fn run(start: u64, end: u64, value: &[u8]) -> (u64, u64, Vec<u8>) {
+    (start, end, value.to_vec())
+}
+
+#[test]
+fn test_runs_yield_each_span_once() {
+    let mut tree = DataTree::new(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    tree.put_range(0..=99, b"a").unwrap();
+    tree.put_range(100..=199, b"b").unwrap();
+    tree.put_range(300..=1_000_000, b"a").unwrap();
+    tree.put(150, b"c").unwrap();
+
+    let runs: Vec<_> = tree.runs(..).collect();
+    assert_eq!(runs, vec![
+        run(0, 99, b"a"),
+        run(100, 149, b"b"),
+        run(150, 150, b"c"),
+        run(151, 199, b"b"),
+        run(300, 1_000_000, b"a"),
+    ]);
+
+    // Runs are clipped to the range
+    let runs: Vec<_> = tree.runs(50..=120).collect();
+    assert_eq!(runs, vec![run(50, 99, b"a"), run(100, 120, b"b")]);
+    let runs: Vec<_> = tree.runs(999_990..).collect();
+    assert_eq!(runs, vec![run(999_990, 1_000_000, b"a")]);
+    assert_eq!(tree.runs(200..300).count(), 0);
+}
+
+#[test]
+fn test_runs_join_across_leaves() {
+    // One put per key in a plain tree: the run is spread over many leaves,
+    // a key per entry
+    let mut tree = DataTree::new(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    for i in 0..2000 {
+        let value: &[u8] = if i < 1500 { b"low" } else { b"high" };
+        tree.put(i, value).unwrap();
+    }
+    assert!(tree.page_stats().unwrap().plain_leaf_pages > 10);
+
+    let runs: Vec<_> = tree.runs(..).collect();
+    assert_eq!(runs, vec![run(0, 1499, b"low"), run(1500, 1999, b"high")]);
+    assert_eq!(tree.run_containing(700).unwrap(), Some(run(0, 1499, b"low")));
+    assert_eq!(tree.run_containing(1999).unwrap(), Some(run(1500, 1999, b"high")));
+
+    // A gap breaks a run
+    assert!(tree.delete(1000).unwrap());
+    assert_eq!(tree.run_containing(700).unwrap(), Some(run(0, 999, b"low")));
+    assert_eq!(tree.run_containing(1001).unwrap(), Some(run(1001, 1499, b"low")));
+    assert_eq!(tree.run_containing(1000).unwrap(), None);
+    assert_eq!(tree.run_containing(5000).unwrap(), None);
+}
+
+#[test]
+fn test_run_containing_joins_run_length_leaves() {
+    let mut tree = DataTree::new(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    tree.set_config(DataTreeConfig { leaf_encoding: LeafEncoding::RunLength, ..Default::default() }).unwrap();
+
+    // Distinct values between two pieces of a run split it over leaves;
+    // deleting them lets the pieces meet again
+    tree.put_range(0..=10_000, b"run").unwrap();
+    for i in 0..50 {
+        tree.put(5000 + i, format!("single{}", i).as_bytes()).unwrap();
+    }
+    assert!(tree.page_stats().unwrap().run_length_leaf_pages > 1);
+    assert_eq!(tree.run_containing(0).unwrap(), Some(run(0, 4999, b"run")));
+    assert_eq!(tree.run_containing(5010).unwrap(), Some(run(5010, 5010, b"single10")));
+
+    for i in 0..50 {
+        tree.put(5000 + i, b"run").unwrap();
+    }
+    assert_eq!(tree.run_containing(7).unwrap(), Some(run(0, 10_000, b"run")));
+    assert_eq!(tree.runs(..).collect::<Vec<_>>(), vec![run(0, 10_000, b"run")]);
+}
+
+#[test]
+fn test_runs_compare_large_values() {
+    let mut tree = DataTree::new(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    let large = vec![9u8; PAGE_SIZE * 2];
+    tree.put_range(0..=9, b"small").unwrap();
+    tree.put(10, &large).unwrap();
+    tree.put(11, &large).unwrap();
+    tree.put(12, b"small").unwrap();
+
+    let runs: Vec<_> = tree.runs(..).collect();
+    assert_eq!(runs, vec![run(0, 9, b"small"), run(10, 11, &large), run(12, 12, b"small")]);
+    assert_eq!(tree.run_containing(11).unwrap(), Some(run(10, 11, &large)));
+}
+
+#[test]
+fn test_runs_of_other_key_types() {
+    let mut tree: DataTree<_, i64> = DataTree::new_keyed(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    tree.put_range(-50..=-1, b"negative").unwrap();
+    tree.put_range(0..=50, b"positive").unwrap();
+    let runs: Vec<_> = tree.runs(-10..10).collect();
+    assert_eq!(runs, vec![(-10, -1, b"negative".to_vec()), (0, 9, b"positive".to_vec())]);
+    assert_eq!(tree.run_containing(-20).unwrap(), Some((-50, -1, b"negative".to_vec())));
+
+    // Byte-string keys never form runs
+    let mut tree: DataTree<_, Vec<u8>> = DataTree::new_keyed(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    for key in [&b"a"[..], b"b", b"c"] {
+        tree.put(key, b"same").unwrap();
+    }
+    assert_eq!(tree.runs(..).count(), 3);
+    assert_eq!(tree.run_containing(b"b").unwrap(), Some((b"b".to_vec(), b"b".to_vec(), b"same".to_vec())));
+}
@@ -0,0 +1,294 @@
+use data_tree::DataTree;
+use data_tree::data_tree::{DataTreeConfig, PageType};
+use data_tree::leaf::{Leaf, LeafEncoding, LeafView};
+use data_tree::page_store::{PageStore, InMemoryPageStore};
+use data_tree::slotted_leaf_page::{KeyedSlottedLeafPage, SlottedLeafPage, SlottedLeafPageView, HEADER_SIZE, SLOT_SIZE};
+use rand::prelude::*;
+use std::collections::BTreeMap;
+
+const PAGE_SIZE: usize = 1024;
+
+const SLOTTED: DataTreeConfig = DataTreeConfig {
+    min_fill: 0.25,
+    fill_factor: 1.0,
+    leaf_encoding: LeafEncoding::Slotted,
+};
+
+// Serializes and reads back a page, padded to its size as a store would
+fn round_trip(page: &SlottedLeafPage) -> SlottedLeafPage {
+    let mut bytes = page.serialize();
+    bytes.resize(PAGE_SIZE, 0);
+    SlottedLeafPage::deserialize(&bytes)
+}
+
+fn offset_of(page: &SlottedLeafPage, key: u64) -> usize {
+    page.slots().iter().find(|slot| slot.key == key).unwrap().value_offset
+}
+
+#[test]
+fn test_updates_are_made_in_place() {
+    let mut page = SlottedLeafPage::new_empty(PAGE_SIZE);
+    for key in 0..10 {
+        assert!(page.put(key, format!("value{}", key).as_bytes()));
+    }
+    let data_length = page.data().len();
+    let offset = offset_of(&page, 4);
+
+    // A value of the same size or smaller takes the old one's place
+    assert!(page.put(4, b"VALUE4"));
+    assert_eq!(offset_of(&page, 4), offset);
+    assert!(page.put(4, b"v4"));
+    assert_eq!(offset_of(&page, 4), offset);
+    assert_eq!(page.get(4).unwrap(), b"v4");
+    assert_eq!(page.data().len(), data_length);
+    assert_eq!(page.fragmented_bytes(), 4);
+
+    // The hole it left is reused by a value that fits it
+    assert!(page.put(20, b"abcd"));
+    assert_eq!(offset_of(&page, 20), offset + 2);
+    assert_eq!(page.fragmented_bytes(), 0);
+    assert_eq!(page.data().len(), data_length);
+
+    // A larger value moves, leaving its old bytes free
+    assert!(page.put(5, b"a much longer value"));
+    assert_eq!(page.fragmented_bytes(), 6);
+    assert!(page.slots().is_sorted_by(|a, b| a.key < b.key));
+    for key in (0..10).filter(|key| *key != 4 && *key != 5) {
+        assert_eq!(page.get(key).unwrap(), format!("value{}", key).as_bytes());
+    }
+}
+
+#[test]
+fn test_deletes_leave_holes_until_compaction() {
+    let mut page = SlottedLeafPage::new_empty(PAGE_SIZE);
+    let value = [7u8; 40];
+    let mut key = 0;
+    while page.put(key, &value) {
+        key += 1;
+    }
+    let data_length = page.data().len();
+
+    // Deleting the last value shortens the data instead of leaving a hole
+    assert!(page.delete(key - 1));
+    assert_eq!(page.data().len(), data_length - 40);
+    assert_eq!(page.fragmented_bytes(), 0);
+
+    // Deleting every other value leaves holes, joined where they meet, until
+    // they pass the threshold and the data is packed again
+    let mut compacted = false;
+    for key in (0..key - 1).step_by(2) {
+        let holes_before = page.fragmented_bytes();
+        assert!(page.delete(key));
+        if page.fragmented_bytes() < holes_before {
+            compacted = true;
+            assert_eq!(page.fragmented_bytes(), 0);
+        } else {
+            assert!(page.fragmented_bytes() as f64 <= PAGE_SIZE as f64 * 0.25);
+        }
+    }
+    assert!(compacted);
+    assert!(page.slots().iter().all(|slot| slot.key % 2 == 1));
+    assert!(page.slots().iter().all(|slot| page.get(slot.key).unwrap() == value));
+}
+
+#[test]
+fn test_puts_compact_when_only_holes_have_room() {
+    let mut page = SlottedLeafPage::new_empty(PAGE_SIZE);
+    for key in 0..20 {
+        assert!(page.put(key, &[key as u8; 16]));
+    }
+    // Small holes spread through the data, none large enough on its own
+    for key in (1..19).step_by(3) {
+        assert!(page.delete(key));
+    }
+    let holes = page.fragmented_bytes();
+    assert!(holes > 0);
+
+    let tail_room = PAGE_SIZE - page.used_space();
+    let large = vec![9u8; tail_room + holes - SLOT_SIZE];
+    assert!(page.put(100, &large));
+    assert_eq!(page.fragmented_bytes(), 0);
+    assert_eq!(page.used_space(), PAGE_SIZE);
+    assert_eq!(page.get(100).unwrap(), large);
+    assert_eq!(page.get(18).unwrap(), [18u8; 16]);
+    assert!(!page.put(101, b""));
+}
+
+#[test]
+fn test_page_matches_a_model_across_round_trips() {
+    let mut rng = StdRng::seed_from_u64(20);
+    let mut page = SlottedLeafPage::new_empty(PAGE_SIZE);
+    let mut model = BTreeMap::new();
+
+    for step in 0..5000 {
+        let key = rng.gen_range(0..60u64);
+        if rng.gen_bool(0.3) {
+            assert_eq!(page.delete(key), model.remove(&key).is_some());
+        } else {
+            let value = vec![step as u8; rng.gen_range(0..40)];
+            let overflow = rng.gen_bool(0.1);
+            let put = if overflow { page.put_overflow(key, &value) } else { page.put(key, &value) };
+            if put {
+                model.insert(key, (value, overflow));
+            }
+        }
+        assert!(page.used_space() <= PAGE_SIZE);
+
+        if step % 100 == 0 {
+            // Holes survive serialization, rebuilt from the slots
+            let fragmented = page.fragmented_bytes();
+            page = round_trip(&page);
+            assert_eq!(page.fragmented_bytes(), fragmented);
+        }
+    }
+
+    let bytes = page.serialize();
+    let view = SlottedLeafPageView::<u64>::new(&bytes).unwrap();
+    assert_eq!(view.len(), model.len());
+    for key in 0..60 {
+        let expected = model.get(&key).map(|(value, overflow)| (value.as_slice(), *overflow));
+        assert_eq!(page.get(key), expected.map(|(value, _)| value));
+        assert_eq!(page.is_overflow(key), expected.is_some_and(|(_, overflow)| overflow));
+        assert_eq!(view.get_entry(key), expected);
+    }
+}
+
+#[test]
+fn test_split_moves_upper_slots() {
+    let mut page = SlottedLeafPage::new_empty(PAGE_SIZE);
+    for key in 0..20 {
+        assert!(page.put(key, &[key as u8; 10]));
+    }
+    page.delete(3);
+    page.put(5, b"short");
+
+    let new_page = page.split().unwrap();
+    assert_eq!(page.slots().len(), 9);
+    assert_eq!(new_page.slots().first().unwrap().key, 10);
+    assert_eq!(page.fragmented_bytes() + new_page.fragmented_bytes(), 0);
+    assert_eq!(page.get(5).unwrap(), b"short");
+    assert_eq!(new_page.get(19).unwrap(), [19u8; 10]);
+    assert_eq!(page.used_space() + new_page.used_space(), HEADER_SIZE * 2 + 19 * SLOT_SIZE + 18 * 10 + 5);
+}
+
+#[test]
+fn test_byte_keys_in_slotted_pages() {
+    let mut page = KeyedSlottedLeafPage::<Vec<u8>>::new_empty(PAGE_SIZE);
+    for word in ["pear", "apple", "fig", "banana"] {
+        assert!(page.put(word.as_bytes(), word.to_uppercase().as_bytes()));
+    }
+    assert!(page.delete(b"fig"));
+    assert_eq!(page.page_type(), PageType::ByteSlottedLeafPage);
+
+    let bytes = page.serialize();
+    let view = SlottedLeafPageView::<Vec<u8>>::new(&bytes).unwrap();
+    assert_eq!(view.get(b"apple"), Some(&b"APPLE"[..]));
+    assert_eq!(view.get(b"fig"), None);
+    assert_eq!(view.get(b"pear"), Some(&b"PEAR"[..]));
+    assert!(SlottedLeafPageView::<u64>::new(&bytes).is_err());
+    assert!(matches!(LeafView::<Vec<u8>>::new(&bytes).unwrap(), LeafView::Slotted(_)));
+}
+
+#[test]
+fn test_slotted_tree_matches_a_model() {
+    let mut rng = StdRng::seed_from_u64(20);
+    let mut tree = DataTree::new(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    tree.set_config(SLOTTED).unwrap();
+    let mut model = BTreeMap::new();
+
+    for _ in 0..4000 {
+        let key = rng.gen_range(0..1500u64);
+        if rng.gen_bool(0.3) {
+            assert_eq!(tree.delete(key).unwrap(), model.remove(&key).is_some());
+        } else {
+            let value = vec![key as u8; rng.gen_range(1..60)];
+            tree.put(key, &value).unwrap();
+            model.insert(key, value);
+        }
+    }
+    // A value too large for a leaf, held in overflow pages
+    tree.put(5000, &vec![3u8; PAGE_SIZE * 2]).unwrap();
+    model.insert(5000, vec![3u8; PAGE_SIZE * 2]);
+
+    assert!(tree.iter().eq(model.iter().map(|(key, value)| (*key, value.clone()))));
+    for key in (0..1500).step_by(7) {
+        assert_eq!(tree.get(key).unwrap().as_ref(), model.get(&key));
+    }
+    assert_eq!(tree.get(5000).unwrap().unwrap(), vec![3u8; PAGE_SIZE * 2]);
+
+    let stats = tree.page_stats().unwrap();
+    assert!(stats.slotted_leaf_pages > 1);
+    assert_eq!(stats.plain_leaf_pages + stats.run_length_leaf_pages, 0);
+}
+
+#[test]
+fn test_slotted_and_plain_leaves_side_by_side() {
+    let mut tree = DataTree::new(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    for i in 0..2000 {
+        tree.put(i, format!("value{}", i).as_bytes()).unwrap();
+    }
+
+    // Leaves written after the switch are slotted; the rest stay plain and
+    // are read by their page type
+    tree.set_config(SLOTTED).unwrap();
+    for i in 0..300 {
+        tree.put(i, format!("slotted{}", i).as_bytes()).unwrap();
+    }
+    let stats = tree.page_stats().unwrap();
+    assert!(stats.slotted_leaf_pages > 0);
+    assert!(stats.plain_leaf_pages > 0);
+
+    let expected = |i: u64| if i < 300 { format!("slotted{}", i) } else { format!("value{}", i) }.into_bytes();
+    assert!(tree.iter().eq((0..2000).map(|i| (i, expected(i)))));
+    assert!(tree.range(250..350).rev().map(|(key, _)| key).eq((250..350).rev()));
+    assert_eq!(tree.get(299).unwrap().unwrap(), expected(299));
+
+    // And back: rewritten leaves turn plain again
+    tree.set_config(DataTreeConfig::default()).unwrap();
+    for i in 0..300 {
+        assert!(tree.delete(i).unwrap());
+    }
+    assert!(tree.iter().eq((300..2000).map(|i| (i, expected(i)))));
+
+    let mut page_id = tree.root_page_id();
+    while PageType::from_u8(tree.store().get_page_bytes(page_id).unwrap()[0]) == Some(PageType::BranchPage) {
+        page_id = data_tree::branch_page::BranchPage::deserialize(&tree.store().get_page_bytes(page_id).unwrap()).entries()[0].page_id;
+    }
+    let first_leaf: Leaf = Leaf::deserialize(&tree.store().get_page_bytes(page_id).unwrap()).unwrap();
+    assert_eq!(first_leaf.encoding(), LeafEncoding::Plain);
+}
+
+#[test]
+fn test_slotted_byte_key_tree() {
+    let mut tree: DataTree<_, Vec<u8>> = DataTree::new_keyed(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    tree.set_config(SLOTTED).unwrap();
+    for i in 0..1000u32 {
+        tree.put(format!("key{:05}", i).as_bytes(), &i.to_le_bytes()).unwrap();
+    }
+    for i in (0..1000u32).step_by(3) {
+        tree.put(format!("key{:05}", i).as_bytes(), b"updated").unwrap();
+    }
+
+    assert_eq!(tree.get(b"key00003").unwrap().unwrap(), b"updated");
+    assert_eq!(tree.get(b"key00004").unwrap().unwrap(), 4u32.to_le_bytes());
+    assert!(tree.get(b"key01000").unwrap().is_none());
+    assert_eq!(tree.iter().count(), 1000);
+    assert!(tree.page_stats().unwrap().slotted_leaf_pages > 1);
+}
+
+#[test]
+fn test_delete_keeps_empty_values_beside_overflow_records() {
+    let mut tree = DataTree::new(InMemoryPageStore::with_page_size(128));
+    tree.set_config(SLOTTED).unwrap();
+    tree.put(25, &[2; 3]).unwrap();
+    tree.put(21, &[2; 4]).unwrap();
+    tree.put(27, &[]).unwrap();
+    tree.put(24, &[2; 131]).unwrap();
+    assert!(tree.delete(25).unwrap());
+
+    assert_eq!(tree.get(21).unwrap().unwrap(), vec![2; 4]);
+    assert_eq!(tree.get(24).unwrap().unwrap(), vec![2; 131]);
+    assert_eq!(tree.get(25).unwrap(), None);
+    assert_eq!(tree.get(27).unwrap().unwrap(), Vec::<u8>::new());
+    assert!(tree.keys().eq([21, 24, 27]));
+}
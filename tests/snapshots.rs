@@ -0,0 +1,389 @@
+use data_tree::DataTree;
+use data_tree::file_page_store::FilePageStore;
+use data_tree::page_store::{PageStore, InMemoryPageStore};
+use data_tree::write_batch::WriteBatch;
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+const PAGE_SIZE: usize = 512;
+
+// Build a unique path in the temp directory for a test's backing file
+fn temp_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("data_tree_{}_{}.db", name, std::process::id()));
+    let _ = fs::remove_file(&path);
+    path
+}
+
+// A store whose page writes fail once, after a set number succeed
+struct FailingPageStore<S: PageStore> {
+    inner: S,
+    writes_before_failure: Option<usize>,
+}
+
+impl<S: PageStore> FailingPageStore<S> {
+    fn new(inner: S) -> Self {
+        FailingPageStore { inner, writes_before_failure: None }
+    }
+
+    fn fail_after(&mut self, writes: Option<usize>) {
+        self.writes_before_failure = writes;
+    }
+}
+
+impl<S: PageStore> PageStore for FailingPageStore<S> {
+    fn get_page_bytes(&self, page_id: u64) -> Result<Vec<u8>, Box<dyn Error>> {
+        self.inner.get_page_bytes(page_id)
+    }
+
+    fn put_page_bytes(&mut self, page_id: u64, bytes: &[u8]) -> Result<(), Box<dyn Error>> {
+        match self.writes_before_failure {
+            Some(0) => {
+                self.writes_before_failure = None;
+                Err("Injected write failure".into())
+            }
+            Some(writes) => {
+                self.writes_before_failure = Some(writes - 1);
+                self.inner.put_page_bytes(page_id, bytes)
+            }
+            None => self.inner.put_page_bytes(page_id, bytes),
+        }
+    }
+
+    fn allocate_page(&mut self) -> u64 {
+        self.inner.allocate_page()
+    }
+
+    fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        self.inner.flush()
+    }
+
+    fn page_size(&self) -> usize {
+        self.inner.page_size()
+    }
+
+    fn get_next_page_id(&self, page_id: u64) -> Option<u64> {
+        self.inner.get_next_page_id(page_id)
+    }
+
+    fn get_prev_page_id(&self, page_id: u64) -> Option<u64> {
+        self.inner.get_prev_page_id(page_id)
+    }
+
+    fn link_pages(&mut self, prev_page_id: u64, next_page_id: u64) -> Result<(), Box<dyn Error>> {
+        self.inner.link_pages(prev_page_id, next_page_id)
+    }
+
+    fn page_exists(&self, page_id: u64) -> bool {
+        self.inner.page_exists(page_id)
+    }
+
+    fn free_page(&mut self, page_id: u64) -> Result<(), Box<dyn Error>> {
+        self.inner.free_page(page_id)
+    }
+
+    fn get_page_count(&self) -> usize {
+        self.inner.get_page_count()
+    }
+
+    fn mark_page_dirty(&mut self, page_id: u64) {
+        self.inner.mark_page_dirty(page_id);
+    }
+
+    fn dirty_pages(&self) -> &HashSet<u64> {
+        self.inner.dirty_pages()
+    }
+
+    fn clear_dirty_pages(&mut self) {
+        self.inner.clear_dirty_pages();
+    }
+
+    fn free_list_head(&self) -> u64 {
+        self.inner.free_list_head()
+    }
+
+    fn set_free_list_head(&mut self, page_id: u64) {
+        self.inner.set_free_list_head(page_id);
+    }
+}
+
+fn entries<S: PageStore>(tree: &DataTree<S>) -> Vec<(u64, Vec<u8>)> {
+    tree.iter().collect()
+}
+
+// A tree over several leaves, with a value in overflow pages
+fn populated_tree<S: PageStore>(store: S) -> DataTree<S> {
+    let mut tree = DataTree::new(store);
+    for key in 0..200 {
+        tree.put(key * 3, b"before").unwrap();
+    }
+    tree.put(11, &vec![2u8; PAGE_SIZE * 2]).unwrap();
+    tree
+}
+
+// Splits leaves and the root, deletes enough to merge leaves, and replaces
+// the overflow value
+fn change<S: PageStore>(tree: &mut DataTree<S>) {
+    for key in 0..300 {
+        tree.put(key * 2, format!("during{}", key).as_bytes()).unwrap();
+    }
+    assert_eq!(tree.delete_range(300..600).unwrap(), 200);
+    tree.put(11, b"inline").unwrap();
+    tree.put_range(1000..2000, b"run").unwrap();
+}
+
+#[test]
+fn test_snapshot_keeps_the_tree_as_it_was() {
+    let mut tree = populated_tree(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    let expected = entries(&tree);
+    let root_page_id = tree.root_page_id();
+
+    let snapshot = tree.snapshot();
+    change(&mut tree);
+    assert_eq!(tree.get(11).unwrap().unwrap(), b"inline");
+    assert_eq!(tree.iter().count(), 200 + 1 + 1000);
+
+    let view = tree.view(&snapshot).unwrap();
+    assert_eq!(snapshot.root_page_id(), root_page_id);
+    assert_eq!(view.iter().collect::<Vec<_>>(), expected);
+    assert_eq!(view.get(11).unwrap().unwrap(), vec![2u8; PAGE_SIZE * 2]);
+    assert_eq!(view.get(2).unwrap(), None);
+    assert!(view.range(..=9).rev().map(|(key, _)| key).eq([9, 6, 3, 0]));
+    assert_eq!(view.runs(1000..2000).count(), 0);
+}
+
+#[test]
+fn test_scan_resumed_across_writes_sees_one_state() {
+    let mut tree = populated_tree(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    let expected = entries(&tree);
+    let snapshot = tree.snapshot();
+
+    // A view borrows the tree, so the scan reads a chunk, lets the writer
+    // split and merge leaves, then resumes after its last key in a new view
+    // of the same snapshot
+    let mut scanned: Vec<(u64, Vec<u8>)> = Vec::new();
+    let mut round = 0;
+    loop {
+        let next = scanned.last().map_or(0, |(key, _)| key + 1);
+        let chunk: Vec<_> = tree.view(&snapshot).unwrap().range(next..).take(20).collect();
+        if chunk.is_empty() {
+            break;
+        }
+        scanned.extend(chunk);
+
+        for key in 0..40 {
+            tree.put(round * 40 + key, format!("round{}", round).as_bytes()).unwrap();
+        }
+        tree.delete_range(round * 30..round * 30 + 15).unwrap();
+        round += 1;
+    }
+    assert_eq!(scanned, expected);
+    assert_eq!(tree.view(&snapshot).unwrap().iter().collect::<Vec<_>>(), expected);
+}
+
+#[test]
+fn test_snapshots_taken_at_different_times() {
+    let mut tree = DataTree::new(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    let mut snapshots = Vec::new();
+    let mut states = Vec::new();
+    for round in 0..5u64 {
+        snapshots.push(tree.snapshot());
+        states.push(entries(&tree));
+        for key in 0..100 {
+            tree.put(key * 5 + round, format!("round{}", round).as_bytes()).unwrap();
+        }
+        tree.delete_range(round * 100..round * 100 + 50).unwrap();
+    }
+
+    // Dropping a snapshot leaves the others as they were
+    snapshots.remove(2);
+    states.remove(2);
+    tree.put(1, b"after").unwrap();
+    for (snapshot, state) in snapshots.iter().zip(&states) {
+        assert_eq!(&tree.view(snapshot).unwrap().iter().collect::<Vec<_>>(), state);
+    }
+    assert!(states[0].is_empty());
+}
+
+#[test]
+fn test_copies_are_freed_after_the_last_snapshot() {
+    let mut tree = populated_tree(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    let mut reference = populated_tree(InMemoryPageStore::with_page_size(PAGE_SIZE));
+
+    let first = tree.snapshot();
+    let second = tree.snapshot();
+    change(&mut tree);
+    change(&mut reference);
+    assert!(tree.store().get_page_count() > reference.store().get_page_count());
+
+    // The copies stay while a snapshot still reads them
+    drop(first);
+    tree.flush().unwrap();
+    assert!(tree.store().get_page_count() > reference.store().get_page_count());
+    assert_eq!(tree.view(&second).unwrap().iter().count(), 201);
+
+    // The next write or flush after the last snapshot frees them
+    drop(second);
+    tree.put(5000, b"after").unwrap();
+    reference.put(5000, b"after").unwrap();
+    assert_eq!(tree.store().get_page_count(), reference.store().get_page_count());
+    assert_eq!(entries(&tree), entries(&reference));
+}
+
+#[test]
+fn test_batches_transactions_and_cursors_keep_snapshots() {
+    let mut tree = populated_tree(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    let expected = entries(&tree);
+    let snapshot = tree.snapshot();
+
+    let mut batch = WriteBatch::new();
+    for key in 0..300 {
+        batch.put(key * 2, b"batch");
+    }
+    tree.write(batch).unwrap();
+
+    // A failed batch leaves neither changes nor copies behind
+    let page_count = tree.store().get_page_count();
+    let mut batch = WriteBatch::new();
+    batch.delete(0);
+    batch.put(1, &vec![1u8; PAGE_SIZE * 2]);
+    batch.put_range(10..=20, &vec![0u8; PAGE_SIZE]);
+    assert!(tree.write(batch).is_err());
+    assert_eq!(tree.store().get_page_count(), page_count);
+
+    let mut transaction = tree.begin();
+    for key in 0..300 {
+        transaction.put(key * 2 + 1, b"transaction").unwrap();
+    }
+    transaction.delete_range(0..100).unwrap();
+    transaction.commit().unwrap();
+
+    let mut cursor = tree.cursor();
+    assert!(cursor.seek(201).unwrap());
+    cursor.put_current(b"cursor").unwrap();
+    assert_eq!(cursor.key(), Some(201));
+    cursor.delete_current().unwrap();
+    assert_eq!(cursor.key(), Some(202));
+
+    assert_eq!(tree.get(100).unwrap().unwrap(), b"batch");
+    assert_eq!(tree.get(99).unwrap(), None);
+    assert_eq!(tree.get(201).unwrap(), None);
+    assert_eq!(tree.view(&snapshot).unwrap().iter().collect::<Vec<_>>(), expected);
+}
+
+#[test]
+fn test_failed_writes_never_reach_a_snapshot() {
+    let batch = || {
+        let mut batch = WriteBatch::new();
+        for key in 0..150 {
+            batch.put(key * 2, b"batch");
+        }
+        batch.delete(3);
+        batch.put(11, b"inline");
+        batch
+    };
+
+    // Fail a batch and a commit at each page write in turn, copies included
+    for commit in [false, true] {
+        let mut writes = 0;
+        loop {
+            let mut tree = populated_tree(FailingPageStore::new(InMemoryPageStore::with_page_size(PAGE_SIZE)));
+            let expected = entries(&tree);
+            let snapshot = tree.snapshot();
+
+            tree.store_mut().fail_after(Some(writes));
+            let failed = if commit {
+                let mut transaction = tree.begin();
+                transaction.write(batch()).unwrap();
+                transaction.commit().is_err()
+            } else {
+                tree.write(batch()).is_err()
+            };
+            tree.store_mut().fail_after(None);
+            assert_eq!(tree.view(&snapshot).unwrap().iter().collect::<Vec<_>>(), expected, "failed after {} writes", writes);
+            if !failed {
+                break;
+            }
+            assert_eq!(entries(&tree), expected);
+
+            // The pages the failed write copied are copied again
+            tree.write(batch()).unwrap();
+            assert_eq!(tree.view(&snapshot).unwrap().iter().collect::<Vec<_>>(), expected, "failed after {} writes", writes);
+            writes += 1;
+        }
+        assert!(writes > 10);
+    }
+}
+
+#[test]
+fn test_view_rejects_another_trees_snapshot() {
+    let tree = populated_tree(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    let other = DataTree::new(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    let snapshot = other.snapshot();
+    assert!(tree.view(&snapshot).is_err());
+    assert!(other.view(&snapshot).unwrap().iter().next().is_none());
+}
+
+#[test]
+fn test_snapshot_reads_copies_after_flush() {
+    let path = temp_path("snapshots");
+    let mut tree = populated_tree(FilePageStore::create(&path, PAGE_SIZE).unwrap());
+    tree.flush().unwrap();
+    let expected = entries(&tree);
+
+    let snapshot = tree.snapshot();
+    change(&mut tree);
+    tree.flush().unwrap();
+    assert_eq!(tree.view(&snapshot).unwrap().iter().collect::<Vec<_>>(), expected);
+
+    // Overflow chains can't be migrated under a live snapshot
+    assert!(tree.migrate_overflow_chains().is_err());
+    drop(snapshot);
+    tree.flush().unwrap();
+    drop(tree);
+
+    let tree = DataTree::open(FilePageStore::open(&path, PAGE_SIZE).unwrap()).unwrap();
+    assert_eq!(tree.get(11).unwrap().unwrap(), b"inline");
+    assert_eq!(tree.iter().count(), 200 + 1 + 1000);
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_reopening_frees_copies_of_live_snapshots() {
+    let path = temp_path("snapshots_reopen");
+    let reference_path = temp_path("snapshots_reopen_reference");
+    let mut tree = populated_tree(FilePageStore::create(&path, PAGE_SIZE).unwrap());
+    let mut reference = populated_tree(FilePageStore::create(&reference_path, PAGE_SIZE).unwrap());
+    tree.flush().unwrap();
+    reference.flush().unwrap();
+
+    // Close the tree with its snapshot still live, as a crash would
+    let snapshot = tree.snapshot();
+    change(&mut tree);
+    change(&mut reference);
+    tree.flush().unwrap();
+    reference.flush().unwrap();
+    assert!(tree.store().get_page_count() > reference.store().get_page_count());
+    drop(tree);
+    drop(snapshot);
+
+    let mut tree = DataTree::open(FilePageStore::open(&path, PAGE_SIZE).unwrap()).unwrap();
+    assert_eq!(tree.store().get_page_count(), reference.store().get_page_count());
+    assert_eq!(entries(&tree), entries(&reference));
+
+    // The freed copies are reused, and the next open has nothing to free
+    for key in 0..100 {
+        tree.put(key * 3, b"reused").unwrap();
+        reference.put(key * 3, b"reused").unwrap();
+    }
+    tree.flush().unwrap();
+    reference.flush().unwrap();
+    drop(tree);
+    let tree = DataTree::open(FilePageStore::open(&path, PAGE_SIZE).unwrap()).unwrap();
+    assert_eq!(tree.store().get_page_count(), reference.store().get_page_count());
+    assert_eq!(entries(&tree), entries(&reference));
+
+    fs::remove_file(&path).unwrap();
+    fs::remove_file(&reference_path).unwrap();
+}
@@ -0,0 +1,112 @@
+use data_tree::branch_page::BranchPage;
+use data_tree::leaf_page::{LeafPage, LeafPageEntry};
+use data_tree::rle_leaf_page::RLELeafPage;
+use rand::prelude::*;
+use std::collections::BTreeMap;
+
+const PAGE_SIZE: usize = 4096;
+
+// Serializes and reads back a page, padded to its size as a store would
+fn round_trip(page: &LeafPage) -> LeafPage {
+    let mut bytes = page.serialize();
+    bytes.resize(PAGE_SIZE, 0);
+    LeafPage::deserialize(&bytes)
+}
+
+#[test]
+fn test_leaf_page_keeps_metadata_sorted() {
+    let mut rng = StdRng::seed_from_u64(18);
+    let mut page = LeafPage::empty(PAGE_SIZE);
+    let mut model = BTreeMap::new();
+
+    for _ in 0..2000 {
+        let key = rng.gen_range(0..200u64);
+        if rng.gen_bool(0.3) {
+            assert_eq!(page.delete(key), model.remove(&key).is_some());
+        } else {
+            // Values of varying length, so replacing one moves the data
+            let value = vec![key as u8; rng.gen_range(1..12)];
+            if page.put(key, &value) {
+                model.insert(key, value);
+            }
+        }
+        assert!(page.metadata().is_sorted_by(|a, b| a.key < b.key));
+    }
+
+    for key in 0..200 {
+        assert_eq!(page.get(key), model.get(&key).map(Vec::as_slice));
+    }
+
+    // The page reads back the same after a round trip
+    let page = round_trip(&page);
+    let keys: Vec<u64> = page.metadata().iter().map(|meta| meta.key).collect();
+    assert!(keys.iter().eq(model.keys()));
+    for (key, value) in &model {
+        assert_eq!(page.get(*key).unwrap(), value);
+    }
+}
+
+#[test]
+fn test_leaf_page_sorts_unsorted_metadata_on_read() {
+    // Entries in insertion order, as leaf pages used to be written
+    let mut page = LeafPage::empty(PAGE_SIZE);
+    page.data = b"thirtytenforty".to_vec();
+    let entry = |key, value_offset, value_length| LeafPageEntry { key, value_offset, value_length, overflow: false };
+    page.metadata = vec![entry(30, 0, 6), entry(10, 6, 3), entry(40, 9, 5)];
+
+    let mut page = round_trip(&page);
+    let keys: Vec<u64> = page.metadata().iter().map(|meta| meta.key).collect();
+    assert_eq!(keys, vec![10, 30, 40]);
+    assert_eq!(page.get(10).unwrap(), b"ten");
+    assert_eq!(page.get(30).unwrap(), b"thirty");
+    assert_eq!(page.get(40).unwrap(), b"forty");
+
+    assert!(page.put(20, b"twenty"));
+    assert!(page.delete(30));
+    let page = round_trip(&page);
+    assert_eq!(page.sorted_items().iter().map(|item| item.key).collect::<Vec<_>>(), vec![10, 20, 40]);
+    assert_eq!(page.get(20).unwrap(), b"twenty");
+}
+
+#[test]
+fn test_rle_leaf_page_lookups_match_a_model() {
+    let mut rng = StdRng::seed_from_u64(18);
+    let mut page = RLELeafPage::new_empty(PAGE_SIZE);
+    let mut model = BTreeMap::new();
+
+    for _ in 0..3000 {
+        let key = rng.gen_range(0..500u64);
+        if rng.gen_bool(0.2) {
+            assert_eq!(page.delete(key), model.remove(&key).is_some());
+        } else {
+            // Few distinct values, so puts extend, join and split runs
+            let value = vec![rng.gen_range(0..3u8)];
+            if page.put(key, &value) {
+                model.insert(key, value);
+            }
+        }
+        assert!(page.metadata().windows(2).all(|pair| pair[0].end_key < pair[1].start_key));
+    }
+
+    for key in 0..500 {
+        assert_eq!(page.get(key), model.get(&key).map(Vec::as_slice));
+    }
+}
+
+#[test]
+fn test_branch_page_finds_pages_by_binary_search() {
+    let mut page = BranchPage::new_empty(PAGE_SIZE);
+    let mut first_keys: Vec<u64> = (1..100).map(|i| i * 10).collect();
+    first_keys.shuffle(&mut StdRng::seed_from_u64(18));
+    for &key in &first_keys {
+        assert!(page.insert(key + 1000, key));
+    }
+
+    for key in 0..1100 {
+        // The page whose first key is the greatest at or below the key, or the
+        // first page for keys below them all
+        let expected = (key / 10).clamp(1, 99) * 10 + 1000;
+        assert_eq!(page.find_page_id(key), Some(expected));
+    }
+    assert_eq!(BranchPage::new_empty(PAGE_SIZE).find_page_id(5), None);
+}
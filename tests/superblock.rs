@@ -0,0 +1,177 @@
+use data_tree::DataTree;
+use data_tree::file_page_store::FilePageStore;
+use data_tree::page_store::{PageStore, InMemoryPageStore};
+use data_tree::superblock::{Superblock, SUPERBLOCK_PAGE_ID, FORMAT_VERSION, V1_SUPERBLOCK_SIZE};
+use std::fs;
+use std::path::PathBuf;
+
+// Build a unique path in the temp directory for a test's backing file
+fn temp_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("data_tree_{}_{}.db", name, std::process::id()));
+    let _ = fs::remove_file(&path);
+    path
+}
+
+#[test]
+fn test_new_writes_superblock() {
+    let store = InMemoryPageStore::with_page_size(1024);
+    let tree = DataTree::new(store);
+
+    let superblock = tree.superblock().unwrap();
+    assert_eq!(superblock.version, FORMAT_VERSION);
+    assert_eq!(superblock.page_size, 1024);
+    assert_eq!(superblock.root_page_id, tree.root_page_id());
+    assert_eq!(superblock.free_list_head, 0);
+}
+
+#[test]
+fn test_superblock_serialization() {
+    let mut superblock = Superblock::new(4096, 7);
+    superblock.page_count = 12;
+    superblock.free_list_head = 3;
+
+    let deserialized = Superblock::deserialize(&superblock.serialize()).unwrap();
+    assert_eq!(deserialized, superblock);
+}
+
+#[test]
+fn test_open_finds_root_page() {
+    let store = InMemoryPageStore::with_page_size(1024);
+    let mut tree = DataTree::new(store);
+    tree.put(1, b"value1").unwrap();
+    tree.put(2, b"value2").unwrap();
+    let root_page_id = tree.root_page_id();
+
+    let tree = DataTree::open(tree.into_store()).unwrap();
+    assert_eq!(tree.root_page_id(), root_page_id);
+    assert_eq!(tree.get(1).unwrap().unwrap(), b"value1");
+    assert_eq!(tree.get(2).unwrap().unwrap(), b"value2");
+}
+
+#[test]
+fn test_flush_records_page_count() {
+    let store = InMemoryPageStore::with_page_size(1024);
+    let mut tree = DataTree::new(store);
+    for i in 0..10 {
+        tree.put(i, b"value").unwrap();
+    }
+    tree.flush().unwrap();
+
+    let superblock = tree.superblock().unwrap();
+    assert_eq!(superblock.page_count, tree.store().get_page_count() as u64);
+}
+
+#[test]
+fn test_open_rejects_mismatched_page_size() {
+    let mut store = InMemoryPageStore::with_page_size(1024);
+    let superblock = Superblock::new(2048, 2);
+    store.put_page_bytes(SUPERBLOCK_PAGE_ID, &superblock.serialize()).unwrap();
+
+    let result = DataTree::open(store);
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_open_rejects_bad_magic() {
+    let mut store = InMemoryPageStore::with_page_size(1024);
+    let mut bytes = Superblock::new(1024, 2).serialize();
+    bytes[1] ^= 0xFF;
+    store.put_page_bytes(SUPERBLOCK_PAGE_ID, &bytes).unwrap();
+
+    assert!(DataTree::open(store).is_err());
+}
+
+// The superblock bytes a version 1 store has, which end at the free list head
+fn version_1_superblock(superblock: &Superblock) -> Vec<u8> {
+    let mut bytes = superblock.serialize();
+    bytes[9..13].copy_from_slice(&1u32.to_le_bytes());
+    bytes.truncate(V1_SUPERBLOCK_SIZE);
+    bytes
+}
+
+#[test]
+fn test_open_reads_version_1_superblock() {
+    let path = temp_path("superblock_version_1");
+
+    {
+        let mut tree = DataTree::new(FilePageStore::create(&path, 512).unwrap());
+        for i in 0..100 {
+            tree.put(i, format!("value{}", i).as_bytes()).unwrap();
+        }
+        tree.delete_range(20..40).unwrap();
+        tree.flush().unwrap();
+
+        // Write the superblock back as version 1, with random bytes where
+        // the copy list would be
+        let superblock = tree.superblock().unwrap();
+        let mut bytes = version_1_superblock(&superblock);
+        bytes.extend_from_slice(&[0xA5; 16]);
+        tree.store_mut().put_page_bytes(SUPERBLOCK_PAGE_ID, &bytes).unwrap();
+        tree.store_mut().flush().unwrap();
+    }
+
+    let mut tree = DataTree::open(FilePageStore::open(&path, 512).unwrap()).unwrap();
+    let superblock = tree.superblock().unwrap();
+    assert_eq!(superblock.version, 1);
+    assert_eq!(superblock.copy_list(), None);
+    assert_eq!(tree.iter().count(), 80);
+
+    // The next flush writes the current version
+    tree.put(500, b"after").unwrap();
+    tree.flush().unwrap();
+    assert_eq!(tree.superblock().unwrap().version, FORMAT_VERSION);
+    drop(tree);
+
+    let tree = DataTree::open(FilePageStore::open(&path, 512).unwrap()).unwrap();
+    assert_eq!(tree.iter().count(), 81);
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_open_rejects_unknown_version() {
+    let mut store = InMemoryPageStore::with_page_size(1024);
+    let mut bytes = Superblock::new(1024, 2).serialize();
+    bytes[9..13].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+    store.put_page_bytes(SUPERBLOCK_PAGE_ID, &bytes).unwrap();
+    assert!(DataTree::open(store).is_err());
+
+    // A current superblock cut to the version 1 size is too short
+    let mut store = InMemoryPageStore::with_page_size(1024);
+    let mut bytes = Superblock::new(1024, 2).serialize();
+    bytes.truncate(V1_SUPERBLOCK_SIZE);
+    store.put_page_bytes(SUPERBLOCK_PAGE_ID, &bytes).unwrap();
+    assert!(DataTree::open(store).is_err());
+}
+
+#[test]
+fn test_open_without_superblock_fails() {
+    let store = InMemoryPageStore::with_page_size(1024);
+    assert!(DataTree::open(store).is_err());
+}
+
+#[test]
+fn test_open_file_store_without_root_page_id() {
+    let path = temp_path("superblock_reopen");
+
+    {
+        let store = FilePageStore::create(&path, 512).unwrap();
+        let mut tree = DataTree::new(store);
+        for i in 0..20 {
+            tree.put(i, format!("value{}", i).as_bytes()).unwrap();
+        }
+        tree.flush().unwrap();
+    }
+
+    let store = FilePageStore::open(&path, 512).unwrap();
+    let tree = DataTree::open(store).unwrap();
+    for i in 0..20 {
+        assert_eq!(tree.get(i).unwrap().unwrap(), format!("value{}", i).into_bytes());
+    }
+
+    // Opening with the wrong page size fails, either on the slot CRCs or the superblock check
+    let reopened = FilePageStore::open(&path, 256);
+    assert!(reopened.map_or(true, |store| DataTree::open(store).is_err()));
+
+    fs::remove_file(&path).unwrap();
+}
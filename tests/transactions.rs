@@ -0,0 +1,276 @@
+use data_tree::DataTree;
+use data_tree::file_page_store::FilePageStore;
+use data_tree::page_store::{PageStore, InMemoryPageStore};
+use data_tree::write_batch::WriteBatch;
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+const PAGE_SIZE: usize = 512;
+
+// Build a unique path in the temp directory for a test's backing file
+fn temp_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("data_tree_{}_{}.db", name, std::process::id()));
+    let _ = fs::remove_file(&path);
+    path
+}
+
+// A store whose page writes fail once, after a set number succeed
+struct FailingPageStore<S: PageStore> {
+    inner: S,
+    writes_before_failure: Option<usize>,
+}
+
+impl<S: PageStore> FailingPageStore<S> {
+    fn new(inner: S) -> Self {
+        FailingPageStore { inner, writes_before_failure: None }
+    }
+
+    fn fail_after(&mut self, writes: usize) {
+        self.writes_before_failure = Some(writes);
+    }
+}
+
+impl<S: PageStore> PageStore for FailingPageStore<S> {
+    fn get_page_bytes(&self, page_id: u64) -> Result<Vec<u8>, Box<dyn Error>> {
+        self.inner.get_page_bytes(page_id)
+    }
+
+    fn put_page_bytes(&mut self, page_id: u64, bytes: &[u8]) -> Result<(), Box<dyn Error>> {
+        match self.writes_before_failure {
+            Some(0) => {
+                self.writes_before_failure = None;
+                Err("Injected write failure".into())
+            }
+            Some(writes) => {
+                self.writes_before_failure = Some(writes - 1);
+                self.inner.put_page_bytes(page_id, bytes)
+            }
+            None => self.inner.put_page_bytes(page_id, bytes),
+        }
+    }
+
+    fn allocate_page(&mut self) -> u64 {
+        self.inner.allocate_page()
+    }
+
+    fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        self.inner.flush()
+    }
+
+    fn page_size(&self) -> usize {
+        self.inner.page_size()
+    }
+
+    fn get_next_page_id(&self, page_id: u64) -> Option<u64> {
+        self.inner.get_next_page_id(page_id)
+    }
+
+    fn get_prev_page_id(&self, page_id: u64) -> Option<u64> {
+        self.inner.get_prev_page_id(page_id)
+    }
+
+    fn link_pages(&mut self, prev_page_id: u64, next_page_id: u64) -> Result<(), Box<dyn Error>> {
+        self.inner.link_pages(prev_page_id, next_page_id)
+    }
+
+    fn page_exists(&self, page_id: u64) -> bool {
+        self.inner.page_exists(page_id)
+    }
+
+    fn free_page(&mut self, page_id: u64) -> Result<(), Box<dyn Error>> {
+        self.inner.free_page(page_id)
+    }
+
+    fn get_page_count(&self) -> usize {
+        self.inner.get_page_count()
+    }
+
+    fn mark_page_dirty(&mut self, page_id: u64) {
+        self.inner.mark_page_dirty(page_id);
+    }
+
+    fn dirty_pages(&self) -> &HashSet<u64> {
+        self.inner.dirty_pages()
+    }
+
+    fn clear_dirty_pages(&mut self) {
+        self.inner.clear_dirty_pages();
+    }
+
+    fn free_list_head(&self) -> u64 {
+        self.inner.free_list_head()
+    }
+
+    fn set_free_list_head(&mut self, page_id: u64) {
+        self.inner.set_free_list_head(page_id);
+    }
+}
+
+fn entries<S: PageStore>(tree: &DataTree<S>) -> Vec<(u64, Vec<u8>)> {
+    tree.iter().collect()
+}
+
+// A tree over several leaves, with a value in overflow pages
+fn populated_tree<S: PageStore>(store: S) -> DataTree<S> {
+    let mut tree = DataTree::new(store);
+    for key in 0..200 {
+        tree.put(key * 3, b"before").unwrap();
+    }
+    tree.put(11, &vec![2u8; PAGE_SIZE * 2]).unwrap();
+    tree
+}
+
+// Splits leaves and the root, deletes enough to merge leaves, and replaces
+// the overflow value
+fn change<S: PageStore>(transaction: &mut data_tree::transaction::Transaction<'_, S>) {
+    for key in 0..300 {
+        transaction.put(key * 2, format!("during{}", key).as_bytes()).unwrap();
+    }
+    assert_eq!(transaction.delete_range(300..600).unwrap(), 200);
+    transaction.put(11, b"inline").unwrap();
+    transaction.put_range(1000..2000, b"run").unwrap();
+}
+
+#[test]
+fn test_transaction_reads_its_own_writes() {
+    let mut tree = populated_tree(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    let mut transaction = tree.begin();
+    change(&mut transaction);
+    assert_eq!(transaction.get(2).unwrap().unwrap(), b"during1");
+    assert_eq!(transaction.get(3).unwrap().unwrap(), b"before");
+    assert_eq!(transaction.get(300).unwrap(), None);
+    assert_eq!(transaction.get(11).unwrap().unwrap(), b"inline");
+    assert_eq!(transaction.range(1998..).count(), 2);
+    assert!(transaction.iter().map(|(key, _)| key).take(4).eq([0, 2, 3, 4]));
+    assert!(transaction.dirty_pages().len() > 10);
+    transaction.commit().unwrap();
+
+    assert_eq!(tree.get(2).unwrap().unwrap(), b"during1");
+    assert_eq!(tree.get(300).unwrap(), None);
+    assert_eq!(tree.get(11).unwrap().unwrap(), b"inline");
+    assert_eq!(tree.get(1500).unwrap().unwrap(), b"run");
+    assert_eq!(tree.iter().count(), 200 + 1 + 1000);
+}
+
+#[test]
+fn test_rollback_leaves_the_tree_as_it_was() {
+    let mut tree = populated_tree(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    let expected = entries(&tree);
+    let root_page_id = tree.root_page_id();
+    let page_count = tree.store().get_page_count();
+
+    let mut transaction = tree.begin();
+    change(&mut transaction);
+    transaction.rollback().unwrap();
+    assert_eq!(tree.root_page_id(), root_page_id);
+    assert_eq!(tree.store().get_page_count(), page_count);
+    assert_eq!(entries(&tree), expected);
+
+    // Dropping a transaction rolls it back too
+    let mut transaction = tree.begin();
+    change(&mut transaction);
+    drop(transaction);
+    assert_eq!(tree.store().get_page_count(), page_count);
+    assert_eq!(entries(&tree), expected);
+
+    // The pages the transactions allocated are reused
+    let free_list_head = tree.store().free_list_head();
+    assert_ne!(free_list_head, 0);
+    let mut transaction = tree.begin();
+    change(&mut transaction);
+    transaction.commit().unwrap();
+    assert_ne!(tree.store().free_list_head(), free_list_head);
+}
+
+#[test]
+fn test_batches_within_a_transaction() {
+    let mut tree = DataTree::new(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    let mut transaction = tree.begin();
+    transaction.put(1, b"one").unwrap();
+
+    let mut batch = WriteBatch::new();
+    batch.put(2, b"two");
+    batch.put_range(10..=20, &vec![0u8; PAGE_SIZE]);
+    assert!(transaction.write(batch).is_err());
+
+    let mut batch = WriteBatch::new();
+    batch.put(3, b"three");
+    batch.delete(1);
+    transaction.write(batch).unwrap();
+    assert_eq!(transaction.get(2).unwrap(), None);
+    transaction.commit().unwrap();
+
+    assert!(tree.iter().eq([(3, b"three".to_vec())]));
+}
+
+#[test]
+fn test_failed_commit_rolls_back() {
+    let mut tree = populated_tree(FailingPageStore::new(InMemoryPageStore::with_page_size(PAGE_SIZE)));
+    let expected = entries(&tree);
+    let page_count = tree.store().get_page_count();
+
+    // Nothing is written to the store before commit, so each failure
+    // lands part way through publishing the transaction's pages
+    for failures in [0, 5, 30] {
+        tree.store_mut().fail_after(failures);
+        let mut transaction = tree.begin();
+        change(&mut transaction);
+        assert!(transaction.commit().is_err());
+        assert_eq!(tree.store().get_page_count(), page_count);
+        assert_eq!(entries(&tree), expected);
+    }
+
+    let mut transaction = tree.begin();
+    change(&mut transaction);
+    transaction.commit().unwrap();
+    assert_eq!(tree.get(2).unwrap().unwrap(), b"during1");
+}
+
+#[test]
+fn test_pages_a_dropped_transaction_fails_to_free_are_freed_later() {
+    let mut tree = DataTree::new(FailingPageStore::new(InMemoryPageStore::with_page_size(PAGE_SIZE)));
+    for key in 0..50 {
+        tree.put(key, b"before").unwrap();
+    }
+    let page_count = tree.store().get_page_count();
+
+    // The first page freed when the transaction is dropped fails
+    tree.store_mut().fail_after(0);
+    {
+        let mut transaction = tree.begin();
+        for key in 0..200 {
+            transaction.put(key, b"in the transaction").unwrap();
+        }
+    }
+    assert!(tree.store().get_page_count() > page_count);
+
+    tree.flush().unwrap();
+    assert_eq!(tree.store().get_page_count(), page_count);
+    assert!(tree.iter().all(|(_, value)| value == b"before"));
+}
+
+#[test]
+fn test_committed_transaction_survives_reopening() {
+    let path = temp_path("transactions");
+    {
+        let mut tree = populated_tree(FilePageStore::create(&path, PAGE_SIZE).unwrap());
+        tree.flush().unwrap();
+
+        let mut transaction = tree.begin();
+        change(&mut transaction);
+        transaction.commit().unwrap();
+
+        let mut transaction = tree.begin();
+        transaction.put(2, b"rolled back").unwrap();
+        transaction.rollback().unwrap();
+        tree.flush().unwrap();
+    }
+
+    let tree = DataTree::open(FilePageStore::open(&path, PAGE_SIZE).unwrap()).unwrap();
+    assert_eq!(tree.get(2).unwrap().unwrap(), b"during1");
+    assert_eq!(tree.get(11).unwrap().unwrap(), b"inline");
+    assert_eq!(tree.iter().count(), 200 + 1 + 1000);
+    fs::remove_file(&path).unwrap();
+}
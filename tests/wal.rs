@@ -0,0 +1,308 @@
+use data_tree::DataTree;
+use data_tree::file_page_store::FilePageStore;
+use data_tree::leaf_page::LeafPage;
+use data_tree::page_store::PageStore;
+use data_tree::wal::WalPageStore;
+use std::fs;
+use std::path::PathBuf;
+
+const PAGE_SIZE: usize = 256;
+const KEY_COUNT: u64 = 12;
+
+// Build a unique path in the temp directory for a test's backing file
+fn temp_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("data_tree_{}_{}", name, std::process::id()));
+    let _ = fs::remove_file(&path);
+    path
+}
+
+fn open_tree(data_path: &PathBuf, log_path: &PathBuf) -> DataTree<WalPageStore<FilePageStore>> {
+    let store = FilePageStore::open(data_path, PAGE_SIZE).unwrap();
+    let wal = WalPageStore::open(store, log_path).unwrap();
+    DataTree::open(wal).unwrap()
+}
+
+fn put_generation(tree: &mut DataTree<WalPageStore<FilePageStore>>, generation: &str) {
+    for key in 0..KEY_COUNT {
+        tree.put(key, format!("{}{}", generation, key).as_bytes()).unwrap();
+    }
+}
+
+// Which generation of values the tree holds; every key must agree
+fn generation_of(tree: &DataTree<WalPageStore<FilePageStore>>) -> String {
+    let first = String::from_utf8(tree.get(0).unwrap().unwrap()).unwrap();
+    let generation = first.trim_end_matches('0').to_string();
+    for key in 0..KEY_COUNT {
+        let value = tree.get(key).unwrap().unwrap();
+        assert_eq!(value, format!("{}{}", generation, key).into_bytes(), "key {} is from another batch", key);
+    }
+    generation
+}
+
+#[test]
+fn test_flush_applies_pages_and_empties_log() {
+    let data_path = temp_path("wal_flush.db");
+    let log_path = temp_path("wal_flush.log");
+
+    {
+        let store = FilePageStore::create(&data_path, PAGE_SIZE).unwrap();
+        let wal = WalPageStore::open(store, &log_path).unwrap();
+        let mut tree = DataTree::new(wal);
+        put_generation(&mut tree, "a");
+        tree.flush().unwrap();
+        assert!(tree.dirty_pages().is_empty());
+    }
+
+    assert_eq!(fs::metadata(&log_path).unwrap().len(), 0);
+    let tree = open_tree(&data_path, &log_path);
+    assert_eq!(generation_of(&tree), "a");
+
+    fs::remove_file(&data_path).unwrap();
+    fs::remove_file(&log_path).unwrap();
+}
+
+#[test]
+fn test_committed_batch_is_replayed_after_crash() {
+    let data_path = temp_path("wal_replay.db");
+    let log_path = temp_path("wal_replay.log");
+
+    {
+        let store = FilePageStore::create(&data_path, PAGE_SIZE).unwrap();
+        let wal = WalPageStore::open(store, &log_path).unwrap();
+        let mut tree = DataTree::new(wal);
+        put_generation(&mut tree, "a");
+        tree.flush().unwrap();
+
+        // Log the next batch but crash before it reaches the data file
+        tree.store_mut().set_auto_checkpoint(false);
+        put_generation(&mut tree, "b");
+        tree.flush().unwrap();
+    }
+
+    assert!(fs::metadata(&log_path).unwrap().len() > 0);
+    let tree = open_tree(&data_path, &log_path);
+    assert_eq!(generation_of(&tree), "b");
+
+    // Recovery applied the batch in place and emptied the log
+    assert_eq!(fs::metadata(&log_path).unwrap().len(), 0);
+
+    fs::remove_file(&data_path).unwrap();
+    fs::remove_file(&log_path).unwrap();
+}
+
+#[test]
+fn test_log_cut_at_every_byte_offset() {
+    let data_path = temp_path("wal_cut.db");
+    let log_path = temp_path("wal_cut.log");
+
+    // Generation "a" is checkpointed; "b" and "c" are only in the log
+    let (first_batch_end, second_batch_end) = {
+        let store = FilePageStore::create(&data_path, PAGE_SIZE).unwrap();
+        let wal = WalPageStore::open(store, &log_path).unwrap();
+        let mut tree = DataTree::new(wal);
+        put_generation(&mut tree, "a");
+        tree.flush().unwrap();
+
+        tree.store_mut().set_auto_checkpoint(false);
+        put_generation(&mut tree, "b");
+        tree.flush().unwrap();
+        let first_batch_end = fs::metadata(&log_path).unwrap().len() as usize;
+
+        put_generation(&mut tree, "c");
+        tree.flush().unwrap();
+        let second_batch_end = fs::metadata(&log_path).unwrap().len() as usize;
+        (first_batch_end, second_batch_end)
+    };
+
+    let data_bytes = fs::read(&data_path).unwrap();
+    let log_bytes = fs::read(&log_path).unwrap();
+    assert_eq!(log_bytes.len(), second_batch_end);
+
+    for cut in 0..=log_bytes.len() {
+        fs::write(&data_path, &data_bytes).unwrap();
+        fs::write(&log_path, &log_bytes[..cut]).unwrap();
+
+        let tree = open_tree(&data_path, &log_path);
+        let expected = if cut >= second_batch_end {
+            "c"
+        } else if cut >= first_batch_end {
+            "b"
+        } else {
+            "a"
+        };
+        assert_eq!(generation_of(&tree), expected, "log cut at byte {}", cut);
+    }
+
+    fs::remove_file(&data_path).unwrap();
+    fs::remove_file(&log_path).unwrap();
+}
+
+#[test]
+fn test_torn_apply_is_repaired_from_log() {
+    let data_path = temp_path("wal_torn_apply.db");
+    let log_path = temp_path("wal_torn_apply.log");
+
+    let (before, log_bytes) = {
+        let store = FilePageStore::create(&data_path, PAGE_SIZE).unwrap();
+        let wal = WalPageStore::open(store, &log_path).unwrap();
+        let mut tree = DataTree::new(wal);
+        put_generation(&mut tree, "a");
+        tree.flush().unwrap();
+
+        tree.store_mut().set_auto_checkpoint(false);
+        put_generation(&mut tree, "b");
+        tree.flush().unwrap();
+        let before = fs::read(&data_path).unwrap();
+        let log_bytes = fs::read(&log_path).unwrap();
+
+        tree.store_mut().checkpoint().unwrap();
+        (before, log_bytes)
+    };
+    let after = fs::read(&data_path).unwrap();
+
+    // Simulate a crash half way through applying the batch: only some of the
+    // page slots made it to the data file
+    for applied_slots in 0..=after.len() / PAGE_SIZE {
+        let mut torn = before.clone();
+        torn.resize(after.len(), 0);
+        let applied = applied_slots * PAGE_SIZE;
+        torn[..applied].copy_from_slice(&after[..applied]);
+        fs::write(&data_path, &torn).unwrap();
+        fs::write(&log_path, &log_bytes).unwrap();
+
+        let tree = open_tree(&data_path, &log_path);
+        assert_eq!(generation_of(&tree), "b", "{} slots applied", applied_slots);
+    }
+
+    fs::remove_file(&data_path).unwrap();
+    fs::remove_file(&log_path).unwrap();
+}
+
+#[test]
+fn test_torn_append_is_repaired_from_log() {
+    let data_path = temp_path("wal_torn_append.db");
+    let log_path = temp_path("wal_torn_append.log");
+
+    let (before, log_bytes) = {
+        let store = FilePageStore::create(&data_path, PAGE_SIZE).unwrap();
+        let wal = WalPageStore::open(store, &log_path).unwrap();
+        let mut tree = DataTree::new(wal);
+        put_generation(&mut tree, "a");
+        tree.flush().unwrap();
+
+        // The second batch adds keys enough to grow the data file
+        tree.store_mut().set_auto_checkpoint(false);
+        put_generation(&mut tree, "b");
+        for key in KEY_COUNT..KEY_COUNT + 40 {
+            tree.put(key, b"grown").unwrap();
+        }
+        tree.flush().unwrap();
+        let before = fs::read(&data_path).unwrap();
+        let log_bytes = fs::read(&log_path).unwrap();
+
+        tree.store_mut().checkpoint().unwrap();
+        (before, log_bytes)
+    };
+    let after = fs::read(&data_path).unwrap();
+    assert!(after.len() > before.len() + PAGE_SIZE);
+
+    // Simulate a crash part way through appending a page slot
+    for cut in (before.len() + 1..after.len()).step_by(37) {
+        fs::write(&data_path, &after[..cut]).unwrap();
+        fs::write(&log_path, &log_bytes).unwrap();
+
+        let tree = open_tree(&data_path, &log_path);
+        assert_eq!(generation_of(&tree), "b", "data file cut at byte {}", cut);
+        for key in KEY_COUNT..KEY_COUNT + 40 {
+            assert_eq!(tree.get(key).unwrap().unwrap(), b"grown", "data file cut at byte {}", cut);
+        }
+        assert_eq!(fs::metadata(&data_path).unwrap().len() % PAGE_SIZE as u64, 0);
+    }
+
+    fs::remove_file(&data_path).unwrap();
+    fs::remove_file(&log_path).unwrap();
+}
+
+#[test]
+fn test_slots_skipped_by_replay_are_reused() {
+    let data_path = temp_path("wal_skipped.db");
+    let log_path = temp_path("wal_skipped.log");
+
+    {
+        let store = FilePageStore::create(&data_path, PAGE_SIZE).unwrap();
+        let wal = WalPageStore::open(store, &log_path).unwrap();
+        let mut tree = DataTree::new(wal);
+        put_generation(&mut tree, "a");
+        tree.flush().unwrap();
+    }
+
+    // Log a page well past the end of the data file, which never gets there
+    let far_page_id = {
+        let store = FilePageStore::open(&data_path, PAGE_SIZE).unwrap();
+        let mut wal = WalPageStore::open(store, &log_path).unwrap();
+        let far_page_id = wal.inner().get_page_count() as u64 + 6;
+        wal.put_page_bytes(far_page_id, &LeafPage::empty(PAGE_SIZE).serialize()).unwrap();
+        wal.commit_to_log().unwrap();
+        far_page_id
+    };
+
+    // The replay frees the slots it skips, and the tree hands them out
+    let mut tree = open_tree(&data_path, &log_path);
+    assert_eq!(generation_of(&tree), "a");
+    let reused: Vec<u64> = (0..5).map(|_| tree.store_mut().allocate_page()).collect();
+    assert!(reused.iter().all(|page_id| *page_id < far_page_id), "{:?}", reused);
+    assert!(tree.store_mut().allocate_page() > far_page_id);
+
+    fs::remove_file(&data_path).unwrap();
+    fs::remove_file(&log_path).unwrap();
+}
+
+#[test]
+fn test_corrupt_commit_record_discards_batch() {
+    let data_path = temp_path("wal_corrupt.db");
+    let log_path = temp_path("wal_corrupt.log");
+
+    {
+        let store = FilePageStore::create(&data_path, PAGE_SIZE).unwrap();
+        let wal = WalPageStore::open(store, &log_path).unwrap();
+        let mut tree = DataTree::new(wal);
+        put_generation(&mut tree, "a");
+        tree.flush().unwrap();
+
+        tree.store_mut().set_auto_checkpoint(false);
+        put_generation(&mut tree, "b");
+        tree.flush().unwrap();
+    }
+
+    // Flip a bit in the commit record's CRC at the end of the log
+    let mut log_bytes = fs::read(&log_path).unwrap();
+    let last = log_bytes.len() - 1;
+    log_bytes[last] ^= 0x01;
+    fs::write(&log_path, &log_bytes).unwrap();
+
+    let tree = open_tree(&data_path, &log_path);
+    assert_eq!(generation_of(&tree), "a");
+
+    fs::remove_file(&data_path).unwrap();
+    fs::remove_file(&log_path).unwrap();
+}
+
+#[test]
+fn test_dirty_pages_include_allocated_and_freed_pages() {
+    let data_path = temp_path("wal_dirty.db");
+    let log_path = temp_path("wal_dirty.log");
+
+    let store = FilePageStore::create(&data_path, PAGE_SIZE).unwrap();
+    let mut wal = WalPageStore::open(store, &log_path).unwrap();
+    let page_id = wal.allocate_page();
+    assert!(wal.dirty_pages().contains(&page_id));
+
+    wal.flush().unwrap();
+    assert!(wal.dirty_pages().is_empty());
+
+    wal.free_page(page_id).unwrap();
+    assert!(wal.dirty_pages().contains(&page_id));
+
+    fs::remove_file(&data_path).unwrap();
+    fs::remove_file(&log_path).unwrap();
+}
@@ -0,0 +1,246 @@
+use data_tree::DataTree;
+use data_tree::file_page_store::FilePageStore;
+use data_tree::page_store::{PageStore, InMemoryPageStore};
+use data_tree::write_batch::WriteBatch;
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+const PAGE_SIZE: usize = 512;
+
+// Build a unique path in the temp directory for a test's backing file
+fn temp_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("data_tree_{}_{}.db", name, std::process::id()));
+    let _ = fs::remove_file(&path);
+    path
+}
+
+// A store whose page writes fail once, after a set number succeed
+struct FailingPageStore<S: PageStore> {
+    inner: S,
+    writes_before_failure: Option<usize>,
+}
+
+impl<S: PageStore> FailingPageStore<S> {
+    fn new(inner: S) -> Self {
+        FailingPageStore { inner, writes_before_failure: None }
+    }
+
+    fn fail_after(&mut self, writes: usize) {
+        self.writes_before_failure = Some(writes);
+    }
+}
+
+impl<S: PageStore> PageStore for FailingPageStore<S> {
+    fn get_page_bytes(&self, page_id: u64) -> Result<Vec<u8>, Box<dyn Error>> {
+        self.inner.get_page_bytes(page_id)
+    }
+
+    fn put_page_bytes(&mut self, page_id: u64, bytes: &[u8]) -> Result<(), Box<dyn Error>> {
+        match self.writes_before_failure {
+            Some(0) => {
+                self.writes_before_failure = None;
+                Err("Injected write failure".into())
+            }
+            Some(writes) => {
+                self.writes_before_failure = Some(writes - 1);
+                self.inner.put_page_bytes(page_id, bytes)
+            }
+            None => self.inner.put_page_bytes(page_id, bytes),
+        }
+    }
+
+    fn allocate_page(&mut self) -> u64 {
+        self.inner.allocate_page()
+    }
+
+    fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        self.inner.flush()
+    }
+
+    fn page_size(&self) -> usize {
+        self.inner.page_size()
+    }
+
+    fn get_next_page_id(&self, page_id: u64) -> Option<u64> {
+        self.inner.get_next_page_id(page_id)
+    }
+
+    fn get_prev_page_id(&self, page_id: u64) -> Option<u64> {
+        self.inner.get_prev_page_id(page_id)
+    }
+
+    fn link_pages(&mut self, prev_page_id: u64, next_page_id: u64) -> Result<(), Box<dyn Error>> {
+        self.inner.link_pages(prev_page_id, next_page_id)
+    }
+
+    fn page_exists(&self, page_id: u64) -> bool {
+        self.inner.page_exists(page_id)
+    }
+
+    fn free_page(&mut self, page_id: u64) -> Result<(), Box<dyn Error>> {
+        self.inner.free_page(page_id)
+    }
+
+    fn get_page_count(&self) -> usize {
+        self.inner.get_page_count()
+    }
+
+    fn mark_page_dirty(&mut self, page_id: u64) {
+        self.inner.mark_page_dirty(page_id);
+    }
+
+    fn dirty_pages(&self) -> &HashSet<u64> {
+        self.inner.dirty_pages()
+    }
+
+    fn clear_dirty_pages(&mut self) {
+        self.inner.clear_dirty_pages();
+    }
+
+    fn free_list_head(&self) -> u64 {
+        self.inner.free_list_head()
+    }
+
+    fn set_free_list_head(&mut self, page_id: u64) {
+        self.inner.set_free_list_head(page_id);
+    }
+}
+
+fn entries<S: PageStore>(tree: &DataTree<S>) -> Vec<(u64, Vec<u8>)> {
+    tree.iter().collect()
+}
+
+// A batch that splits leaves and the root, frees an overflow chain, deletes
+// and writes a run
+fn large_batch() -> WriteBatch {
+    let mut batch = WriteBatch::new();
+    for key in 0..300 {
+        batch.put(key * 2, format!("batch{}", key).as_bytes());
+    }
+    batch.put(5, &vec![1u8; PAGE_SIZE * 2]);
+    batch.delete(10);
+    batch.delete(11);
+    batch.put_range(1000..2000, b"run");
+    batch
+}
+
+#[test]
+fn test_batch_applies_every_operation() {
+    let mut tree = DataTree::new(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    tree.put(10, b"old").unwrap();
+    tree.put(11, &vec![2u8; PAGE_SIZE * 2]).unwrap();
+
+    let batch = large_batch();
+    assert_eq!(batch.len(), 304);
+    tree.write(batch).unwrap();
+
+    assert_eq!(tree.get(10).unwrap(), None);
+    assert_eq!(tree.get(11).unwrap(), None);
+    assert_eq!(tree.get(5).unwrap().unwrap(), vec![1u8; PAGE_SIZE * 2]);
+    assert_eq!(tree.get(598).unwrap().unwrap(), b"batch299");
+    assert_eq!(tree.get(1999).unwrap().unwrap(), b"run");
+    assert_eq!(tree.iter().count(), 299 + 1 + 1000);
+
+    // An empty batch changes nothing
+    let root_page_id = tree.root_page_id();
+    tree.write(WriteBatch::new()).unwrap();
+    assert_eq!(tree.root_page_id(), root_page_id);
+}
+
+#[test]
+fn test_later_operations_see_earlier_ones() {
+    let mut tree = DataTree::new(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    let mut batch = WriteBatch::new();
+    batch.put(1, b"first");
+    batch.put(1, b"second");
+    batch.put(2, b"deleted");
+    batch.delete(2);
+    batch.put_range(10..=20, b"run");
+    batch.put(15, b"inside");
+    tree.write(batch).unwrap();
+
+    assert_eq!(tree.get(1).unwrap().unwrap(), b"second");
+    assert_eq!(tree.get(2).unwrap(), None);
+    assert_eq!(tree.get(14).unwrap().unwrap(), b"run");
+    assert_eq!(tree.get(15).unwrap().unwrap(), b"inside");
+}
+
+#[test]
+fn test_failed_write_rolls_back_the_batch() {
+    let mut tree = DataTree::new(FailingPageStore::new(InMemoryPageStore::with_page_size(PAGE_SIZE)));
+    for key in 0..200 {
+        tree.put(key * 3, b"before").unwrap();
+    }
+    tree.put(11, &vec![2u8; PAGE_SIZE * 2]).unwrap();
+
+    // Fail each page write of the batch in turn
+    let expected = entries(&tree);
+    let mut failures = 0;
+    loop {
+        let root_page_id = tree.root_page_id();
+        let page_count = tree.store().get_page_count();
+        tree.store_mut().fail_after(failures);
+        if tree.write(large_batch()).is_ok() {
+            break;
+        }
+        failures += 1;
+
+        assert_eq!(tree.root_page_id(), root_page_id);
+        assert_eq!(tree.store().get_page_count(), page_count);
+        assert_eq!(entries(&tree), expected);
+        assert_eq!(tree.get(11).unwrap().unwrap(), vec![2u8; PAGE_SIZE * 2]);
+    }
+    assert!(failures > 20);
+    assert_eq!(tree.get(598).unwrap().unwrap(), b"batch299");
+    assert_eq!(tree.get(11).unwrap(), None);
+}
+
+#[test]
+fn test_failed_operation_rolls_back_the_batch() {
+    let mut tree: DataTree<_, Vec<u8>> = DataTree::new_keyed(InMemoryPageStore::with_page_size(PAGE_SIZE));
+    tree.put(b"kept", b"before").unwrap();
+    let page_count = tree.store().get_page_count();
+
+    let mut batch = WriteBatch::<Vec<u8>>::new();
+    for i in 0..100 {
+        batch.put(format!("key{:03}", i).as_bytes(), b"value");
+    }
+    batch.delete(b"kept");
+
+    // Byte-string keys can't hold runs
+    batch.put_range(&b"a"[..]..&b"b"[..], b"run");
+    assert!(tree.write(batch).is_err());
+    assert_eq!(tree.store().get_page_count(), page_count);
+    assert_eq!(tree.iter().collect::<Vec<_>>(), vec![(b"kept".to_vec(), b"before".to_vec())]);
+}
+
+#[test]
+fn test_rolled_back_store_reopens_as_before() {
+    let path = temp_path("write_batch");
+    {
+        let store = FailingPageStore::new(FilePageStore::create(&path, PAGE_SIZE).unwrap());
+        let mut tree = DataTree::new(store);
+        for key in 0..50 {
+            tree.put(key, b"before").unwrap();
+        }
+        tree.flush().unwrap();
+
+        // Fail part way through the batch, once it has split several leaves
+        tree.store_mut().fail_after(60);
+        assert!(tree.write(large_batch()).is_err());
+        tree.flush().unwrap();
+    }
+
+    let tree = DataTree::open(FilePageStore::open(&path, PAGE_SIZE).unwrap()).unwrap();
+    assert!(tree.iter().eq((0..50).map(|key| (key, b"before".to_vec()))));
+
+    // The pages the batch allocated are free for the next writes
+    let mut tree = tree;
+    let page_count = tree.store().get_page_count();
+    assert_ne!(tree.store().free_list_head(), 0);
+    tree.write(large_batch()).unwrap();
+    assert!(tree.store().get_page_count() > page_count);
+    fs::remove_file(&path).unwrap();
+}